@@ -1,25 +1,118 @@
 use std::{
-    io::{BufRead, BufReader},
-    path::PathBuf,
+    collections::BTreeMap,
+    fs,
+    io::{self, BufRead, BufReader},
+    path::{Path, PathBuf},
     process::Child,
+    slice,
+    time::Instant,
 };
 
-use log::debug;
+use chrono::Local;
+use log::{debug, info, warn};
 use reqwest::Url;
 use serde::{Deserialize, Serialize};
 use tauri::{AppHandle, Manager, State, Wry};
 
 use crate::{
     consts::{CLIENT_ID, MICROSOFT_LOGIN_URL},
+    data_dir::{self, DataDirInfo, DataDirState},
+    deep_link::{ImportRequest, PendingImportState},
+    events::{
+        estimate_remaining, AccountRefreshed, AccountStatusChanged, DownloadKind, EventBus,
+        JavaFallback, LauncherEvent,
+    },
+    export::{
+        archive::{self, ArchiveComponents},
+        launch_script::{self, ScriptKind},
+        mrpack::{self, MrpackExportReport},
+        multimc,
+    },
+    first_run::{self, SetupReport},
+    i18n::{self, LocaleStatus},
+    import::{
+        self,
+        official_launcher::{self, OfficialProfile},
+        version_bundle::{self, VersionBundleReport, VersionDirs},
+        ExternalInstance, ExternalLauncher, ImportOptions,
+    },
+    instance::{
+        advancements::{read_advancements, PlayerAdvancements},
+        backups::{
+            create_backup, delete_backup, is_backup_due, list_backups, prune_backups,
+            restore_backup, BackupEntry, BackupReason,
+        },
+        config_files::{
+            list_config_files, read_config_file, restore_config_file, write_config_file,
+            ConfigFileEntry, CONFIG_BACKUPS_DIR,
+        },
+        content::{
+            inspect_local_file, install_api, install_local_content, missing_api, suggest_api,
+            ContentKind, InstalledContent, LocalInstallReport, ModLoader,
+        },
+        crash_reports::{list_crash_reports, read_crash_report, CrashReport, CrashReportEntry},
+        launch_metrics::{LaunchMetrics, LaunchPhase, LaunchStats, LaunchTimer},
+        launch_preview::{LaunchCommand, LaunchPreview},
+        lockfile::{lock_mods, restore_files, Lockfile, LockfileRestoreReport},
+        logs::{list_logs, read_log, LogFileEntry, LogFilter, LogLine},
+        mod_metadata::{ModMetadata, PinnedMod},
+        mod_search::{self, ModSearch, ModSource, SearchPage},
+        mod_versions::{self, ModProject, ModVersion, ModVersionFilter, ReleaseChannel},
+        modpack::{Modpack, ModpackState, ModpackUpdatePreview},
+        mods_library::ModsLibrary,
+        resolve_file_in,
+        screenshots::{
+            delete_screenshot, get_screenshot_path, get_thumbnail, list_screenshots,
+            ScreenshotEntry,
+        },
+        servers::{add_server, list_servers, move_server, remove_server, ServerEntry},
+        statistics::{aggregate_statistics, InstanceStatistics},
+        sync::{sync_instance, SyncDirection, SyncReport, SyncSettings},
+        trash::{list_trash, purge_trash, TrashEntry, TrashKind},
+        worlds::{list_worlds, read_world_details, resolve_world_dir, WorldDetails, WorldEntry},
+    },
+    launcher_behavior::LauncherBehavior,
+    notifications::notify_task_finished,
     state::{
-        account_manager::AccountState,
-        instance_manager::InstanceState,
-        resource_manager::{ManifestResult, ResourceState},
+        account_manager::{Account, AccountEntry, AccountState, AccountStatus},
+        instance_lock::InstanceOperation,
+        instance_manager::{
+            check_java, launch_auth_server, open_in_file_manager, reveal_in_file_manager,
+            InstanceEntry, InstanceError, InstanceFolder, InstanceGroup, InstanceResult,
+            InstanceSort, InstanceState,
+        },
+        resource_manager::{
+            ManifestError, ManifestResult, PathContext, ResourceManager, ResourceState,
+        },
+        settings_manager::{
+            BackupSettings, LaunchSettings, SettingsState, StorageSettings, VerificationSettings,
+        },
+        task_manager::{TaskHandle, TaskInfo, TaskKind, TaskResult, TaskState, TaskStatus},
     },
+    storage::{storage_report, InstanceUsage, StorageReport, StoreDirs},
+    system::MemoryRecommendation,
     web_services::{
-        authentication::AuthResult, manifest::vanilla::VanillaManifestVersion,
-        resources::create_instance,
+        authentication::{validate_account, AuthResult, AuthenticationError},
+        curseforge::{CurseForgeClient, CurseForgeResult, CurseForgeSettings, CurseForgeStatus},
+        downloader::{run_blocking, Verification},
+        hash_cache,
+        manifest::vanilla::VanillaManifestVersion,
+        mirrors::{self, MirrorSettings},
+        mod_details::{self, ModDetails},
+        modrinth::version_from_hash,
+        network::{self, NetworkResult, NetworkSettings, NetworkStatus},
+        profile::{self, NameAvailability, NameChangeEligibility},
+        resources::{
+            change_instance_version, create_instance, download_version_java, locked_version_files,
+            verify_instance, verify_launch_files, verify_natives,
+        },
+        skins::{
+            self, get_avatar, AppliedSkin, AvatarKind, SkinError, SkinModel, SkinPreview,
+            SkinResult,
+        },
+        yggdrasil::{authenticate_yggdrasil, download_authlib_injector, AuthlibInjector},
     },
+    window_state::UiState,
 };
 
 #[tauri::command(async)]
@@ -87,7 +180,7 @@ pub async fn obtain_manifests(
     let resource_state: State<ResourceState> = app_handle
         .try_state()
         .expect("`ResourceState` should already be managed.");
-    let resource_manager = resource_state.0.lock().await;
+    let resource_manager = &resource_state.0;
 
     let versions = resource_manager.get_vanilla_version_list(&filters);
     Ok(versions)
@@ -99,8 +192,490 @@ pub async fn obtain_version(
     instance_name: String,
     app_handle: AppHandle<Wry>,
 ) -> ManifestResult<()> {
-    create_instance(selected, instance_name, &app_handle).await?;
-    Ok(())
+    let task = register_task(&app_handle, TaskKind::Install, &instance_name).await?;
+    let result = create_instance(selected, instance_name, &app_handle, &task).await;
+    finish_task(&app_handle, &task, &result).await;
+    result
+}
+
+/// Writes an instance to `destination` as a zip that MultiMC and Prism can import.
+#[tauri::command(async)]
+pub async fn export_instance_multimc(
+    instance_name: String,
+    destination: PathBuf,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<()> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let instance_manager = instance_state.0.lock().await;
+
+    // The game could be writing to its saves while they are being archived.
+    instance_manager.ensure_instance_idle(&instance_name)?;
+    let config = instance_manager.get_instance_configuration(&instance_name)?;
+    let instance_dir = instance_manager.instances_dir().join(&instance_name);
+    multimc::export_instance(config, &instance_dir, &destination)
+}
+
+#[tauri::command(async)]
+pub async fn export_instance_archive(
+    instance_name: String,
+    destination: PathBuf,
+    components: ArchiveComponents,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<()> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let instance_manager = instance_state.0.lock().await;
+
+    // The game could be writing to its saves while they are being archived.
+    instance_manager.ensure_instance_idle(&instance_name)?;
+    let config = instance_manager.get_instance_configuration(&instance_name)?;
+    let instance_dir = instance_manager.instances_dir().join(&instance_name);
+    archive::export_instance(config, &instance_dir, &components, &destination)
+}
+
+/// Writes an instance to `destination` as a Modrinth pack. Files that can be downloaded from Modrinth
+/// are only referenced, everything else is put into the pack's overrides.
+#[tauri::command(async)]
+pub async fn export_instance_mrpack(
+    instance_name: String,
+    destination: PathBuf,
+    name: Option<String>,
+    version: Option<String>,
+    components: ArchiveComponents,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<MrpackExportReport> {
+    let (config, instance_dir, _lock) = {
+        let instance_state: State<InstanceState> = app_handle
+            .try_state()
+            .expect("`InstanceState` should already be managed.");
+        let instance_manager = instance_state.0.lock().await;
+
+        // The game could be writing to its saves while they are being archived.
+        instance_manager.ensure_instance_idle(&instance_name)?;
+        let lock = instance_manager.lock_instance(&instance_name, InstanceOperation::Export)?;
+        let config = instance_manager
+            .get_instance_configuration(&instance_name)?
+            .clone();
+        let instance_dir = instance_manager.instances_dir().join(&instance_name);
+        (config, instance_dir, lock)
+    };
+
+    let name = name.unwrap_or_else(|| instance_name.clone());
+    let version = version.unwrap_or_else(|| "1.0.0".into());
+    mrpack::export_instance(
+        &config,
+        &instance_dir,
+        &components,
+        &name,
+        &version,
+        &destination,
+    )
+    .await
+}
+
+/// The official launcher's `.minecraft` directory, if it is installed.
+#[tauri::command(async)]
+pub async fn detect_official_launcher() -> Option<PathBuf> {
+    official_launcher::detect_minecraft_dir()
+}
+
+#[tauri::command(async)]
+pub async fn get_official_launcher_profiles(
+    minecraft_dir: PathBuf,
+    app_handle: AppHandle<Wry>,
+) -> ManifestResult<Vec<OfficialProfile>> {
+    let resource_state: State<ResourceState> = app_handle
+        .try_state()
+        .expect("`ResourceState` should already be managed.");
+    let resource_manager = &resource_state.0;
+
+    official_launcher::list_profiles(&minecraft_dir, resource_manager)
+}
+
+#[tauri::command(async)]
+pub async fn import_official_launcher_profile(
+    minecraft_dir: PathBuf,
+    profile_id: String,
+    instance_name: String,
+    options: ImportOptions,
+    app_handle: AppHandle<Wry>,
+) -> ManifestResult<()> {
+    let task = register_task(&app_handle, TaskKind::Import, &instance_name).await?;
+    let result = official_launcher::import_profile(
+        &minecraft_dir,
+        &profile_id,
+        instance_name,
+        options,
+        &app_handle,
+        &task,
+    )
+    .await;
+    finish_task(&app_handle, &task, &result).await;
+    result
+}
+
+/// Imports the vanilla versions of a game directory or a zip of one, e.g. copied from another machine,
+/// so instances of them can be created without internet access.
+#[tauri::command(async)]
+pub async fn import_version_bundle(
+    path: PathBuf,
+    app_handle: AppHandle<Wry>,
+) -> ManifestResult<VersionBundleReport> {
+    let resource_state: State<ResourceState> = app_handle
+        .try_state()
+        .expect("`ResourceState` should already be managed.");
+    let resource_manager = &resource_state.0;
+    let dirs = VersionDirs {
+        versions: resource_manager.version_dir(),
+        libraries: resource_manager.libraries_dir(),
+        assets: resource_manager.assets_dir(),
+    };
+    let staging_dir = resource_manager.cache_dir().join("version-import");
+
+    let (report, versions) = tauri::async_runtime::spawn_blocking(move || {
+        version_bundle::import_version_bundle(&path, &dirs, &staging_dir)
+    })
+    .await
+    .map_err(|error| ManifestError::ImportError(error.to_string()))??;
+
+    resource_manager.register_imported_versions(versions)?;
+    Ok(report)
+}
+
+/// The data directory of another launcher, if it is installed.
+#[tauri::command(async)]
+pub async fn detect_external_launcher(launcher: ExternalLauncher) -> Option<PathBuf> {
+    launcher.detect_dir()
+}
+
+#[tauri::command(async)]
+pub async fn get_external_instances(
+    launcher: ExternalLauncher,
+    launcher_dir: PathBuf,
+    app_handle: AppHandle<Wry>,
+) -> ManifestResult<Vec<ExternalInstance>> {
+    let resource_state: State<ResourceState> = app_handle
+        .try_state()
+        .expect("`ResourceState` should already be managed.");
+    let resource_manager = &resource_state.0;
+
+    launcher.list_instances(&launcher_dir, resource_manager)
+}
+
+#[tauri::command(async)]
+pub async fn import_external_instance(
+    launcher: ExternalLauncher,
+    instance_dir: PathBuf,
+    instance_name: String,
+    app_handle: AppHandle<Wry>,
+) -> ManifestResult<()> {
+    let task = register_task(&app_handle, TaskKind::Import, &instance_name).await?;
+    let result = import::import_external_instance(
+        launcher,
+        &instance_dir,
+        instance_name,
+        &app_handle,
+        &task,
+    )
+    .await;
+    finish_task(&app_handle, &task, &result).await;
+    result
+}
+
+#[tauri::command(async)]
+pub async fn get_data_dir(app_handle: AppHandle<Wry>) -> DataDirInfo {
+    let data_dir_state: State<DataDirState> = app_handle
+        .try_state()
+        .expect("`DataDirState` should already be managed.");
+    data_dir_state.0.clone()
+}
+
+/// Lets the frontend validate a directory before asking the user to confirm the move.
+#[tauri::command(async)]
+pub async fn check_new_data_dir(
+    new_dir: PathBuf,
+    app_handle: AppHandle<Wry>,
+) -> ManifestResult<()> {
+    let data_dir_state: State<DataDirState> = app_handle
+        .try_state()
+        .expect("`DataDirState` should already be managed.");
+    let DataDirInfo { current, default } = &data_dir_state.0;
+    data_dir::check_data_dir(default, current, &new_dir)
+}
+
+/// Moves the launcher's data to `new_dir` and restarts the launcher to use it.
+#[tauri::command(async)]
+pub async fn move_data_dir(new_dir: PathBuf, app_handle: AppHandle<Wry>) -> ManifestResult<()> {
+    let data_dir_state: State<DataDirState> = app_handle
+        .try_state()
+        .expect("`DataDirState` should already be managed.");
+    let DataDirInfo { current, default } = data_dir_state.0.clone();
+
+    let task = {
+        let task_state: State<TaskState> = app_handle
+            .try_state()
+            .expect("`TaskState` should already be managed.");
+        let mut task_manager = task_state.0.lock().await;
+        if task_manager.has_running_tasks() {
+            return Err(ManifestError::DataDirError(
+                "Wait for running tasks to finish before moving the data directory".into(),
+            ));
+        }
+        task_manager.register(TaskKind::MoveDataDir, "data directory")?
+    };
+
+    // Hold every manager's lock so nothing writes to the data directory while it is moved. Installs
+    // don't need them to download, the running task keeps new ones from starting.
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let instance_manager = instance_state.0.lock().await;
+    let account_state: State<AccountState> = app_handle
+        .try_state()
+        .expect("`AccountState` should already be managed.");
+    let account_manager = account_state.0.lock().await;
+    let settings_state: State<SettingsState> = app_handle
+        .try_state()
+        .expect("`SettingsState` should already be managed.");
+    let settings_manager = settings_state.0.lock().await;
+
+    let result = if instance_manager.has_running_instances() {
+        Err(ManifestError::DataDirError(
+            "Close running instances before moving the data directory".into(),
+        ))
+    } else {
+        let task = task.clone();
+        tauri::async_runtime::spawn_blocking(move || {
+            data_dir::move_data_dir(&default, &current, &new_dir, &task)
+        })
+        .await
+        .unwrap_or_else(|error| Err(ManifestError::DataDirError(error.to_string())))
+    };
+    drop((instance_manager, account_manager, settings_manager));
+
+    finish_task(&app_handle, &task, &result).await;
+    if result.is_ok() {
+        app_handle.restart();
+    }
+    result
+}
+
+/// Whether the onboarding wizard should be shown for the current data directory.
+#[tauri::command(async)]
+pub async fn is_first_run(app_handle: AppHandle<Wry>) -> bool {
+    let data_dir_state: State<DataDirState> = app_handle
+        .try_state()
+        .expect("`DataDirState` should already be managed.");
+    first_run::is_first_run(&data_dir_state.0.current)
+}
+
+/// Prepares the data directory and reports the result of every check to the onboarding wizard.
+#[tauri::command(async)]
+pub async fn run_first_run_checks(app_handle: AppHandle<Wry>) -> SetupReport {
+    let resource_state: State<ResourceState> = app_handle
+        .try_state()
+        .expect("`ResourceState` should already be managed.");
+    let resource_manager = &resource_state.0;
+
+    first_run::run_setup_checks(resource_manager).await
+}
+
+#[tauri::command(async)]
+pub async fn complete_first_run(app_handle: AppHandle<Wry>) -> ManifestResult<()> {
+    let data_dir_state: State<DataDirState> = app_handle
+        .try_state()
+        .expect("`DataDirState` should already be managed.");
+    let app_dir = &data_dir_state.0.current;
+    first_run::complete_first_run(app_dir).with_path(app_dir)
+}
+
+/// Silently refreshes every stored account, emitting an `account-status` event for each of them.
+/// Returns the status of the active account, if there is one.
+pub async fn refresh_all_accounts(app_handle: &AppHandle<Wry>) -> Option<AccountStatus> {
+    let account_state: State<AccountState> = app_handle
+        .try_state()
+        .expect("`AccountState` should already be managed.");
+    // Don't hold the lock while waiting on the network.
+    let accounts: Vec<Account> = account_state.0.lock().await.accounts().cloned().collect();
+    let results = futures::future::join_all(accounts.iter().map(validate_account)).await;
+
+    let event_bus = EventBus::new(app_handle.clone());
+    let mut account_manager = account_state.0.lock().await;
+    for (account, result) in accounts.into_iter().zip(results) {
+        let status = match result {
+            Ok(refreshed) => {
+                account_manager.add_account(refreshed);
+                AccountStatus::Ok
+            }
+            Err(error) if error.needs_login() => {
+                warn!(
+                    "Account {} needs to log in again: {:?}",
+                    account.name, error
+                );
+                AccountStatus::NeedsLogin
+            }
+            Err(error) => {
+                warn!("Could not refresh account {}: {:?}", account.name, error);
+                AccountStatus::NetworkError
+            }
+        };
+        account_manager.set_account_status(&account.uuid, status);
+        event_bus.emit(LauncherEvent::AccountStatusChanged(AccountStatusChanged {
+            uuid: account.uuid,
+            name: account.name,
+            status,
+        }));
+    }
+    if let Err(error) = account_manager.serialize_accounts() {
+        warn!("Could not save the refreshed accounts: {}", error);
+    }
+
+    account_manager
+        .get_active_account()
+        .map(|account| account_manager.get_account_status(&account.uuid))
+}
+
+/// Every stored account along with whether it's still logged in.
+#[tauri::command(async)]
+pub async fn get_accounts(app_handle: AppHandle<Wry>) -> Vec<AccountEntry> {
+    let account_state: State<AccountState> = app_handle
+        .try_state()
+        .expect("`AccountState` should already be managed.");
+    let account_manager = account_state.0.lock().await;
+    account_manager.get_account_entries()
+}
+
+/// Returns the active account with fresh tokens, saving them if they had to be refreshed.
+async fn refreshed_active_account(app_handle: &AppHandle<Wry>) -> AuthResult<Account> {
+    let account_state: State<AccountState> = app_handle
+        .try_state()
+        .expect("`AccountState` should already be managed.");
+    let mut account_manager = account_state.0.lock().await;
+
+    let account = account_manager.get_active_account().ok_or_else(|| {
+        AuthenticationError::MinecraftProfileError {
+            error: "NoAccount".into(),
+            error_message: "There is no active account".into(),
+        }
+    })?;
+    let refreshed = validate_account(account).await?;
+    account_manager.add_account(refreshed.clone());
+    if let Err(error) = account_manager.serialize_accounts() {
+        warn!("Could not save the refreshed account: {}", error);
+    }
+    Ok(refreshed)
+}
+
+#[tauri::command(async)]
+pub async fn get_name_change_eligibility(
+    app_handle: AppHandle<Wry>,
+) -> AuthResult<NameChangeEligibility> {
+    let account = refreshed_active_account(&app_handle).await?;
+    profile::get_name_change_eligibility(&account).await
+}
+
+#[tauri::command(async)]
+pub async fn check_name_availability(
+    name: String,
+    app_handle: AppHandle<Wry>,
+) -> AuthResult<NameAvailability> {
+    let account = refreshed_active_account(&app_handle).await?;
+    profile::check_name_availability(&account, &name).await
+}
+
+/// Changes the name of the active account's profile and returns the new name.
+#[tauri::command(async)]
+pub async fn change_account_name(name: String, app_handle: AppHandle<Wry>) -> AuthResult<String> {
+    let mut account = refreshed_active_account(&app_handle).await?;
+    account.name = profile::change_name(&account, &name).await?;
+
+    let account_state: State<AccountState> = app_handle
+        .try_state()
+        .expect("`AccountState` should already be managed.");
+    let mut account_manager = account_state.0.lock().await;
+    account_manager.add_account(account.clone());
+    if let Err(error) = account_manager.serialize_accounts() {
+        warn!("Could not save the new account name: {}", error);
+    }
+    EventBus::new(app_handle.clone()).emit(LauncherEvent::AccountRefreshed(AccountRefreshed {
+        uuid: account.uuid,
+        name: account.name.clone(),
+    }));
+    Ok(account.name)
+}
+
+async fn register_task(
+    app_handle: &AppHandle<Wry>,
+    kind: TaskKind,
+    target: &str,
+) -> ManifestResult<TaskHandle> {
+    let task_state: State<TaskState> = app_handle
+        .try_state()
+        .expect("`TaskState` should already be managed.");
+    let mut task_manager = task_state.0.lock().await;
+
+    Ok(task_manager.register(kind, target)?)
+}
+
+async fn finish_task(app_handle: &AppHandle<Wry>, task: &TaskHandle, result: &ManifestResult<()>) {
+    let status = match result {
+        Ok(_) => TaskStatus::Completed,
+        Err(ManifestError::Cancelled) => TaskStatus::Cancelled,
+        Err(error) => TaskStatus::Failed(error.to_string()),
+    };
+    let task_state: State<TaskState> = app_handle
+        .try_state()
+        .expect("`TaskState` should already be managed.");
+    let mut task_manager = task_state.0.lock().await;
+
+    if let Some(finished) = task_manager.finish(task, status) {
+        notify_task_finished(app_handle, &finished);
+    }
+}
+
+#[tauri::command(async)]
+pub async fn get_tasks(app_handle: AppHandle<Wry>) -> Vec<TaskInfo> {
+    let task_state: State<TaskState> = app_handle
+        .try_state()
+        .expect("`TaskState` should already be managed.");
+    let task_manager = task_state.0.lock().await;
+
+    task_manager.get_tasks()
+}
+
+/// Asks a task to stop, it ends as cancelled once it reaches its next step.
+#[tauri::command(async)]
+pub async fn cancel_task(id: u64, app_handle: AppHandle<Wry>) -> TaskResult<()> {
+    let task_state: State<TaskState> = app_handle
+        .try_state()
+        .expect("`TaskState` should already be managed.");
+    let mut task_manager = task_state.0.lock().await;
+
+    task_manager.cancel(id)
+}
+
+#[tauri::command(async)]
+pub async fn clear_finished_tasks(app_handle: AppHandle<Wry>) {
+    let task_state: State<TaskState> = app_handle
+        .try_state()
+        .expect("`TaskState` should already be managed.");
+    let mut task_manager = task_state.0.lock().await;
+
+    task_manager.clear_finished();
+}
+
+/// Returns the deep link or modpack file the launcher was started with, only once.
+#[tauri::command(async)]
+pub async fn take_pending_import(app_handle: AppHandle<Wry>) -> Option<ImportRequest> {
+    let pending_state: State<PendingImportState> = app_handle
+        .try_state()
+        .expect("`PendingImportState` should already be managed.");
+    let mut pending_import = pending_state.0.lock().await;
+
+    pending_import.take()
 }
 
 #[tauri::command(async)]
@@ -108,7 +683,7 @@ pub async fn get_instance_path(app_handle: AppHandle<Wry>) -> PathBuf {
     let resource_state: State<ResourceState> = app_handle
         .try_state()
         .expect("`ResourceState` should already be managed.");
-    let resource_manager = resource_state.0.lock().await;
+    let resource_manager = &resource_state.0;
 
     resource_manager.instances_dir()
 }
@@ -126,6 +701,144 @@ pub async fn get_account_skin(app_handle: AppHandle<Wry>) -> String {
     account.skin_url.clone()
 }
 
+/// Renders the head or the whole front of the active account's skin and returns the path of the png.
+#[tauri::command(async)]
+pub async fn get_account_avatar(
+    kind: AvatarKind,
+    scale: Option<u32>,
+    app_handle: AppHandle<Wry>,
+) -> SkinResult<PathBuf> {
+    let resource_state: State<ResourceState> = app_handle
+        .try_state()
+        .expect("`ResourceState` should already be managed.");
+    let cache_dir = resource_state.0.cache_dir();
+
+    let account_state: State<AccountState> = app_handle
+        .try_state()
+        .expect("`AccountState` should already be managed.");
+    let (skin_url, model) = {
+        let account_manager = account_state.0.lock().await;
+        let account = account_manager
+            .get_active_account()
+            .ok_or(SkinError::NoSkin)?;
+        (
+            account.skin_url.clone(),
+            SkinModel::from_variant(&account.skin_variant),
+        )
+    };
+
+    get_avatar(
+        &cache_dir.join("textures"),
+        &cache_dir.join("avatars"),
+        &skin_url,
+        model,
+        kind,
+        scale.unwrap_or(8),
+    )
+    .await
+}
+
+/// Returns the cached skin and cape textures of the active account along with its player model, for
+/// the 3D skin preview.
+#[tauri::command(async)]
+pub async fn get_skin_preview(app_handle: AppHandle<Wry>) -> SkinResult<SkinPreview> {
+    let resource_state: State<ResourceState> = app_handle
+        .try_state()
+        .expect("`ResourceState` should already be managed.");
+    let cache_dir = resource_state.0.cache_dir();
+
+    let account_state: State<AccountState> = app_handle
+        .try_state()
+        .expect("`AccountState` should already be managed.");
+    let account = account_state
+        .0
+        .lock()
+        .await
+        .get_active_account()
+        .cloned()
+        .ok_or(SkinError::NoSkin)?;
+
+    skins::get_skin_preview(
+        &cache_dir.join("textures"),
+        &account.skin_url,
+        SkinModel::from_variant(&account.skin_variant),
+        account.cape_url.as_deref(),
+    )
+    .await
+}
+
+/// Guesses whether a skin file is made for the classic or slim model, so the user can confirm the
+/// model before uploading it.
+#[tauri::command(async)]
+pub async fn detect_skin_model(path: PathBuf) -> SkinResult<SkinModel> {
+    let skin = skins::validate_skin(&fs::read(path)?)?;
+    Ok(skins::detect_model(&skin))
+}
+
+/// Uploads a png as the active account's skin and returns the new skin for the 3D preview.
+#[tauri::command(async)]
+pub async fn upload_skin(
+    path: PathBuf,
+    model: Option<SkinModel>,
+    app_handle: AppHandle<Wry>,
+) -> SkinResult<SkinPreview> {
+    let bytes = fs::read(path)?;
+    // Don't bother refreshing the account for a skin that would be rejected.
+    skins::validate_skin(&bytes)?;
+    let account = refreshed_active_account(&app_handle).await?;
+    let applied = skins::upload_skin(&account, bytes, model).await?;
+    save_applied_skin(&app_handle, account, applied).await
+}
+
+/// Changes the active account's skin to a texture already hosted at `url`.
+#[tauri::command(async)]
+pub async fn apply_skin_url(
+    url: String,
+    model: Option<SkinModel>,
+    app_handle: AppHandle<Wry>,
+) -> SkinResult<SkinPreview> {
+    let resource_state: State<ResourceState> = app_handle
+        .try_state()
+        .expect("`ResourceState` should already be managed.");
+    let textures_dir = resource_state.0.cache_dir().join("textures");
+
+    let account = refreshed_active_account(&app_handle).await?;
+    let applied = skins::apply_skin_url(&account, &textures_dir, &url, model).await?;
+    save_applied_skin(&app_handle, account, applied).await
+}
+
+/// Stores the changed skin with the account and returns it for the 3D preview.
+async fn save_applied_skin(
+    app_handle: &AppHandle<Wry>,
+    mut account: Account,
+    applied: AppliedSkin,
+) -> SkinResult<SkinPreview> {
+    account.skin_url = applied.url;
+    account.skin_variant = applied.variant;
+
+    let account_state: State<AccountState> = app_handle
+        .try_state()
+        .expect("`AccountState` should already be managed.");
+    {
+        let mut account_manager = account_state.0.lock().await;
+        account_manager.add_account(account.clone());
+        if let Err(error) = account_manager.serialize_accounts() {
+            warn!("Could not save the new skin: {}", error);
+        }
+    }
+
+    let resource_state: State<ResourceState> = app_handle
+        .try_state()
+        .expect("`ResourceState` should already be managed.");
+    skins::get_skin_preview(
+        &resource_state.0.cache_dir().join("textures"),
+        &account.skin_url,
+        SkinModel::from_variant(&account.skin_variant),
+        account.cape_url.as_deref(),
+    )
+    .await
+}
+
 #[tauri::command(async)]
 pub async fn load_instances(app_handle: AppHandle<Wry>) -> Vec<String> {
     let instance_state: State<InstanceState> = app_handle
@@ -134,26 +847,2138 @@ pub async fn load_instances(app_handle: AppHandle<Wry>) -> Vec<String> {
     let mut instance_manager = instance_state.0.lock().await;
 
     instance_manager.deserialize_instances();
+    if let Err(error) = instance_manager.deserialize_groups() {
+        debug!("No instance groups loaded: {}", error);
+    }
     instance_manager.get_instance_names()
 }
 
 #[tauri::command(async)]
-pub async fn launch_instance(instance_name: String, app_handle: AppHandle<Wry>) {
+pub async fn launch_instance(
+    instance_name: String,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<()> {
+    let mut timer = LaunchTimer::start();
+    let resource_state: State<ResourceState> = app_handle
+        .try_state()
+        .expect("`ResourceState` should already be managed.");
+    let resource_manager = &resource_state.0;
+
     let instance_state: State<InstanceState> = app_handle
         .try_state()
         .expect("`InstanceState` should already be managed.");
-    let mut instance_manager = instance_state.0.lock().await;
-
     let account_state: State<AccountState> = app_handle
         .try_state()
         .expect("`AccountState` should already be managed.");
+    let settings_state: State<SettingsState> = app_handle
+        .try_state()
+        .expect("`SettingsState` should already be managed.");
 
-    let account_manager = account_state.0.lock().await;
+    // The managers are only locked to read what the launch needs, verifying, downloading and
+    // syncing only hold the instance lock.
+    let (
+        _lock,
+        settings,
+        account,
+        version_id,
+        running,
+        sync,
+        required_java_version,
+        selected_java,
+        behavior,
+        verification,
+    ) = {
+        let instance_manager = instance_state.0.lock().await;
+        let account_manager = account_state.0.lock().await;
+        let settings_manager = settings_state.0.lock().await;
 
-    // Assumed there is an active account.
-    instance_manager.launch_instance(
-        &instance_name,
-        account_manager.get_active_account().unwrap(),
-    );
-    instance_manager.emit_logs_for_running_instance(app_handle.clone());
+        let lock = instance_manager.lock_instance(&instance_name, InstanceOperation::Launch)?;
+        let modded = instance_manager.is_instance_modded(&instance_name);
+        let instance_settings = instance_manager.get_instance_settings(&instance_name)?;
+        let settings = settings_manager.resolve_launch_settings(instance_settings, modded);
+        if settings_manager
+            .memory_recommendation(instance_settings, modded)
+            .exceeds_system_memory
+        {
+            warn!(
+                "Max memory of {}MB for {} is more than the system memory",
+                settings.max_memory.unwrap_or_default(),
+                instance_name
+            );
+        }
+        let account = account_manager
+            .get_active_account()
+            .ok_or(InstanceError::NoActiveAccount)?
+            .clone();
+        // Instances migrated from before the version id was saved can't be checked.
+        let version_id = instance_manager
+            .get_instance_version_id(&instance_name)?
+            .to_string();
+        let required_java_version = if version_id.is_empty() {
+            None
+        } else {
+            resource_manager.get_required_java_version(&version_id)
+        };
+        let selected_java =
+            instance_manager.select_java(&instance_name, &settings, required_java_version);
+        (
+            lock,
+            settings,
+            account,
+            version_id,
+            instance_manager.is_instance_running(&instance_name),
+            instance_manager.get_instance_sync(&instance_name)?,
+            required_java_version,
+            selected_java,
+            settings_manager.get_launcher_behavior(),
+            settings_manager.get_verification_settings().launch(),
+        )
+    };
+
+    let instance_dir = resource_manager.instances_dir().join(&instance_name);
+    // Natives of a running instance are in use, they were verified when it was launched.
+    if !version_id.is_empty() && !running {
+        if settings.verify_on_launch.unwrap_or_default() {
+            // Hashes are only computed again for files that changed since they were last hashed.
+            let verification = match verification {
+                Verification::Fast => Verification::Incremental,
+                verification => verification,
+            };
+            verify_launch_files(resource_manager, &instance_dir, &version_id, verification)
+                .await
+                .map_err(|error| InstanceError::GameFilesUnavailable(error.to_string()))?;
+        } else {
+            verify_natives(resource_manager, &instance_dir, &version_id, verification)
+                .await
+                .map_err(|error| InstanceError::NativesUnavailable(error.to_string()))?;
+        }
+    }
+    let (java, fallback_reason) = match selected_java {
+        Err(error) if !version_id.is_empty() && error.java_problem().is_some() => {
+            let reason = error.java_problem().unwrap_or_default();
+            warn!(
+                "Can't launch {} with the selected java, using the runtime of {}: {}",
+                instance_name, version_id, reason
+            );
+            let jvm_path = download_version_java(resource_manager, &version_id, verification)
+                .await
+                .map_err(|error| InstanceError::JavaUnavailable(error.to_string()))?;
+            (check_java(jvm_path, required_java_version)?, Some(reason))
+        }
+        result => (result?, None),
+    };
+    timer.end_phase(LaunchPhase::Verification);
+
+    if let Some(sync) = sync {
+        let sync_dir = instance_dir.clone();
+        let report = tauri::async_runtime::spawn_blocking(move || {
+            sync_instance(&sync_dir, &sync, SyncDirection::Pull, false)
+        })
+        .await
+        .unwrap_or_else(|error| Err(io::Error::new(io::ErrorKind::Other, error.to_string())))?;
+        if !report.conflicts.is_empty() {
+            return Err(InstanceError::SyncConflicts(report.conflicts));
+        }
+    }
+    let authlib_injector = resolve_authlib_injector(resource_manager, &settings, &account).await?;
+
+    let mut instance_manager = instance_state.0.lock().await;
+    if let Some(reason) = fallback_reason {
+        instance_manager.set_instance_jvm_path(&instance_name, java.path.clone())?;
+        EventBus::new(app_handle.clone()).emit(LauncherEvent::JavaFallback(JavaFallback {
+            instance_name: instance_name.clone(),
+            reason,
+            java_path: java.path.clone(),
+        }));
+    }
+    instance_manager.launch_instance(
+        &instance_name,
+        &account,
+        &settings,
+        &java,
+        authlib_injector.as_ref(),
+        timer,
+    )?;
+    instance_manager.emit_logs_for_running_instance(app_handle.clone(), behavior);
+    Ok(())
+}
+
+/// Kills the game of an instance together with every process it started. The game exits like a
+/// crash, but isn't reported as one.
+#[tauri::command(async)]
+pub async fn kill_instance(
+    instance_name: String,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<()> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let instance_manager = instance_state.0.lock().await;
+
+    instance_manager.kill_instance(&instance_name)
+}
+
+/// Assembles the command launching an instance would run, with the access token masked, without
+/// verifying any files or spawning the game.
+#[tauri::command(async)]
+pub async fn preview_launch(
+    instance_name: String,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<LaunchPreview> {
+    let (command, _) = assemble_launch_command(&app_handle, &instance_name).await?;
+    Ok(LaunchPreview::new(&command))
+}
+
+/// Writes a shell script or batch file that launches an instance without the launcher. The access
+/// token is read from an environment variable instead of being written into the script.
+#[tauri::command(async)]
+pub async fn export_launch_script(
+    instance_name: String,
+    destination: PathBuf,
+    kind: Option<ScriptKind>,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<()> {
+    let (command, account) = assemble_launch_command(&app_handle, &instance_name).await?;
+    launch_script::export_launch_script(
+        &command,
+        &account,
+        &instance_name,
+        kind.unwrap_or_default(),
+        &destination,
+    )
+}
+
+/// The command and account a launch of the instance would use right now, for previews and scripts.
+async fn assemble_launch_command(
+    app_handle: &AppHandle<Wry>,
+    instance_name: &str,
+) -> InstanceResult<(LaunchCommand, Account)> {
+    let resource_state: State<ResourceState> = app_handle
+        .try_state()
+        .expect("`ResourceState` should already be managed.");
+    let resource_manager = &resource_state.0;
+
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let instance_manager = instance_state.0.lock().await;
+
+    let account_state: State<AccountState> = app_handle
+        .try_state()
+        .expect("`AccountState` should already be managed.");
+    let account_manager = account_state.0.lock().await;
+
+    let settings_state: State<SettingsState> = app_handle
+        .try_state()
+        .expect("`SettingsState` should already be managed.");
+    let settings_manager = settings_state.0.lock().await;
+
+    let modded = instance_manager.is_instance_modded(instance_name);
+    let instance_settings = instance_manager.get_instance_settings(instance_name)?;
+    let settings = settings_manager.resolve_launch_settings(instance_settings, modded);
+    let version_id = instance_manager.get_instance_version_id(instance_name)?;
+    let required_java_version = if version_id.is_empty() {
+        None
+    } else {
+        resource_manager.get_required_java_version(version_id)
+    };
+    let java = instance_manager.select_java(instance_name, &settings, required_java_version)?;
+
+    let account = account_manager
+        .get_active_account()
+        .ok_or(InstanceError::NoActiveAccount)?;
+    let authlib_injector = resolve_authlib_injector(resource_manager, &settings, account).await?;
+    let command = instance_manager.launch_command(
+        instance_name,
+        account,
+        &settings,
+        &java,
+        authlib_injector.as_ref(),
+    )?;
+    Ok((command, account.clone()))
+}
+
+/// Downloads authlib-injector when the instance is launched through a Yggdrasil server.
+async fn resolve_authlib_injector(
+    resource_manager: &ResourceManager,
+    settings: &LaunchSettings,
+    account: &Account,
+) -> InstanceResult<Option<AuthlibInjector>> {
+    match launch_auth_server(settings, account)? {
+        Some(api_root) => {
+            let jar_path = download_authlib_injector(&resource_manager.authlib_injector_dir())
+                .await
+                .map_err(|error| InstanceError::AuthlibInjectorUnavailable(error.to_string()))?;
+            Ok(Some(AuthlibInjector {
+                jar_path,
+                api_root: api_root.into(),
+            }))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Returns how long the recent launches of an instance took, split into the phases of a launch.
+#[tauri::command(async)]
+pub async fn get_launch_stats(
+    instance_name: String,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<LaunchStats> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let instance_manager = instance_state.0.lock().await;
+
+    let instance_dir =
+        instance_manager.get_instance_folder(&instance_name, InstanceFolder::Root)?;
+    Ok(LaunchMetrics::load(&instance_dir)?.stats())
+}
+
+#[tauri::command(async)]
+pub async fn add_offline_account(name: String, app_handle: AppHandle<Wry>) -> ManifestResult<()> {
+    let account_state: State<AccountState> = app_handle
+        .try_state()
+        .expect("`AccountState` should already be managed.");
+    let mut account_manager = account_state.0.lock().await;
+
+    account_manager.add_and_activate_account(Account::offline(&name));
+    account_manager.serialize_accounts()?;
+    Ok(())
+}
+
+/// Logs into a Yggdrasil server like ely.by and activates the account.
+#[tauri::command(async)]
+pub async fn add_yggdrasil_account(
+    server: String,
+    username: String,
+    password: String,
+    app_handle: AppHandle<Wry>,
+) -> AuthResult<()> {
+    let account = authenticate_yggdrasil(&server, &username, &password).await?;
+    let account_state: State<AccountState> = app_handle
+        .try_state()
+        .expect("`AccountState` should already be managed.");
+    let mut account_manager = account_state.0.lock().await;
+
+    account_manager.add_and_activate_account(account);
+    if let Err(error) = account_manager.serialize_accounts() {
+        warn!(
+            "Could not properly serialize account information: {}",
+            error
+        );
+    }
+    Ok(())
+}
+
+#[tauri::command(async)]
+pub async fn is_offline(app_handle: AppHandle<Wry>) -> bool {
+    let resource_state: State<ResourceState> = app_handle
+        .try_state()
+        .expect("`ResourceState` should already be managed.");
+    let resource_manager = &resource_state.0;
+
+    resource_manager.is_offline()
+}
+
+#[tauri::command(async)]
+pub async fn rename_instance(
+    instance_name: String,
+    new_instance_name: String,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<()> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let mut instance_manager = instance_state.0.lock().await;
+
+    let _lock = instance_manager.lock_instance(&instance_name, InstanceOperation::Rename)?;
+    instance_manager.rename_instance(&instance_name, &new_instance_name)
+}
+
+/// Moves an instance to the trash, it can be restored with `restore_trash_entry` until it expires.
+#[tauri::command(async)]
+pub async fn delete_instance(
+    instance_name: String,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<TrashEntry> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let mut instance_manager = instance_state.0.lock().await;
+
+    let trash_entry = {
+        let _lock = instance_manager.lock_instance(&instance_name, InstanceOperation::Delete)?;
+        instance_manager.delete_instance(&instance_name)?
+    };
+    EventBus::new(app_handle.clone()).emit(LauncherEvent::InstanceDeleted(instance_name));
+    Ok(trash_entry)
+}
+
+/// Installs local jar or zip files, e.g. ones dropped onto the instance, into the mods, resource
+/// packs or shader packs folder depending on their contents. Nothing is installed if any of the
+/// files isn't recognized. When the new mods need Fabric API or its Quilt counterpart and the
+/// instance doesn't have it, a matching version is suggested for `install_loader_api`.
+#[tauri::command(async)]
+pub async fn install_local_files(
+    instance_name: String,
+    paths: Vec<PathBuf>,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<LocalInstallReport> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let (installed, missing, game_version) = {
+        let instance_manager = instance_state.0.lock().await;
+
+        let contents = paths
+            .iter()
+            .map(|path| inspect_local_file(path))
+            .collect::<InstanceResult<Vec<_>>>()?;
+        // Mods are only loaded on start, and jars in use can't be overwritten on Windows.
+        if contents
+            .iter()
+            .any(|content| content.kind == ContentKind::Mod)
+        {
+            instance_manager.ensure_instance_idle(&instance_name)?;
+        }
+        let _lock =
+            instance_manager.lock_instance(&instance_name, InstanceOperation::InstallContent)?;
+        let instance_dir =
+            instance_manager.get_instance_folder(&instance_name, InstanceFolder::Root)?;
+        let library = mods_library(&app_handle, instance_manager.mods_library_dir()).await;
+        let installed = install_local_content(&instance_dir, contents, library.as_ref())?;
+        let missing = missing_api(&instance_dir.join(ContentKind::Mod.folder()), &installed);
+        let game_version = instance_manager.get_instance_version_id(&instance_name)?;
+        (installed, missing, game_version.to_string())
+    };
+
+    // Only an offer, the files are installed either way.
+    let api_suggestion = match missing {
+        Some(loader) => match suggest_api(loader, &game_version).await {
+            Ok(suggestion) => Some(suggestion),
+            Err(error) => {
+                warn!(
+                    "Could not find the {:?} api for {}: {:?}",
+                    loader, game_version, error
+                );
+                None
+            }
+        },
+        None => None,
+    };
+    Ok(LocalInstallReport {
+        installed,
+        api_suggestion,
+    })
+}
+
+/// Installs the newest Fabric API or Quilt Standard Libraries for the instance's minecraft version,
+/// once the user accepted the suggestion from `install_local_files`.
+#[tauri::command(async)]
+pub async fn install_loader_api(
+    instance_name: String,
+    loader: ModLoader,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<InstalledContent> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let (_lock, mods_dir, game_version, library_dir) = {
+        let instance_manager = instance_state.0.lock().await;
+        instance_manager.ensure_instance_idle(&instance_name)?;
+        let lock =
+            instance_manager.lock_instance(&instance_name, InstanceOperation::InstallContent)?;
+        (
+            lock,
+            instance_manager.get_instance_folder(&instance_name, InstanceFolder::Mods)?,
+            instance_manager
+                .get_instance_version_id(&instance_name)?
+                .to_string(),
+            instance_manager.mods_library_dir(),
+        )
+    };
+    let library = mods_library(&app_handle, library_dir).await;
+    install_api(&mods_dir, loader, &game_version, library.as_ref()).await
+}
+
+/// Shows what updating an instance installed from a Modrinth pack to the `.mrpack` at `mrpack_path`
+/// would change.
+#[tauri::command(async)]
+pub async fn preview_modpack_update(
+    instance_name: String,
+    mrpack_path: PathBuf,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<ModpackUpdatePreview> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let instance_manager = instance_state.0.lock().await;
+
+    let instance_dir =
+        instance_manager.get_instance_folder(&instance_name, InstanceFolder::Root)?;
+    let installed = ModpackState::load(&instance_dir)?;
+    let mod_metadata = ModMetadata::load(&instance_dir)?;
+    let modpack = run_blocking(move || Modpack::read(&mrpack_path)).await??;
+    Ok(modpack.diff(&instance_dir, &installed, &mod_metadata))
+}
+
+/// Updates an instance to the `.mrpack` at `mrpack_path`, keeping files the user added or changed.
+/// Returns the changes that were made.
+#[tauri::command(async)]
+pub async fn apply_modpack_update(
+    instance_name: String,
+    mrpack_path: PathBuf,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<ModpackUpdatePreview> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    // Held until the update is done, downloads don't block the instance manager in the meantime.
+    let (_lock, instance_dir, backups_dir, library_dir) = {
+        let instance_manager = instance_state.0.lock().await;
+        instance_manager.ensure_instance_idle(&instance_name)?;
+        let lock =
+            instance_manager.lock_instance(&instance_name, InstanceOperation::ModpackUpdate)?;
+        (
+            lock,
+            instance_manager.get_instance_folder(&instance_name, InstanceFolder::Root)?,
+            instance_manager.backups_dir(&instance_name),
+            instance_manager.mods_library_dir(),
+        )
+    };
+    let installed = ModpackState::load(&instance_dir)?;
+    let mod_metadata = ModMetadata::load(&instance_dir)?;
+    let modpack = run_blocking(move || Modpack::read(&mrpack_path)).await??;
+    let preview = modpack.diff(&instance_dir, &installed, &mod_metadata);
+    if !preview.dependency_changes.is_empty() {
+        return Err(InstanceError::ModpackDependenciesChanged);
+    }
+
+    let settings_state: State<SettingsState> = app_handle
+        .try_state()
+        .expect("`SettingsState` should already be managed.");
+    let backup_settings = settings_state.0.lock().await.get_backup_settings().clone();
+    if backup_settings.before_version_change {
+        create_backup(
+            &instance_dir,
+            &backups_dir,
+            BackupReason::BeforeModpackUpdate,
+        )?;
+        prune_backups(&backups_dir, backup_settings.max_backups as usize)?;
+    }
+    let library = mods_library(&app_handle, library_dir).await;
+    let events = EventBus::new(app_handle.clone());
+    let start = Instant::now();
+    let on_progress = |completed, total| {
+        events.download_progress(
+            &instance_name,
+            DownloadKind::ModpackFiles,
+            completed,
+            total,
+            estimate_remaining(start.elapsed(), completed, total),
+        )
+    };
+    modpack
+        .apply(&instance_dir, &preview, library.as_ref(), on_progress)
+        .await?;
+    Ok(preview)
+}
+
+/// Writes a lockfile with the exact game jar, libraries and mods an instance uses now, replacing the
+/// previous one.
+#[tauri::command(async)]
+pub async fn generate_instance_lockfile(
+    instance_name: String,
+    app_handle: AppHandle<Wry>,
+) -> ManifestResult<Lockfile> {
+    let resource_state: State<ResourceState> = app_handle
+        .try_state()
+        .expect("`ResourceState` should already be managed.");
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let (instance_dir, version_id) = {
+        let instance_manager = instance_state.0.lock().await;
+        (
+            instance_manager.get_instance_folder(&instance_name, InstanceFolder::Root)?,
+            instance_manager
+                .get_instance_version_id(&instance_name)?
+                .to_string(),
+        )
+    };
+    let (game_jar, libraries) = locked_version_files(&resource_state.0, &version_id).await?;
+
+    let loaders = match ModpackState::load(&instance_dir) {
+        Ok(modpack) => modpack
+            .dependencies
+            .into_iter()
+            .filter(|(dependency, _)| dependency != "minecraft")
+            .collect(),
+        Err(_) => BTreeMap::new(),
+    };
+    let mods = lock_mods(&instance_dir).await?;
+    let lockfile = Lockfile::new(version_id, loaders, game_jar, libraries, mods);
+    lockfile.save(&instance_dir)?;
+    Ok(lockfile)
+}
+
+/// Puts the game jar, libraries and mods of an instance back to the state of its lockfile. Files that
+/// changed are downloaded again and mods that aren't in the lockfile are moved to the trash.
+#[tauri::command(async)]
+pub async fn restore_instance_from_lockfile(
+    instance_name: String,
+    app_handle: AppHandle<Wry>,
+) -> ManifestResult<LockfileRestoreReport> {
+    let resource_state: State<ResourceState> = app_handle
+        .try_state()
+        .expect("`ResourceState` should already be managed.");
+    let versions_dir = resource_state.0.version_dir();
+    let libraries_dir = resource_state.0.libraries_dir();
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    // Held until the files are restored, downloads don't block the instance manager in the meantime.
+    let (_lock, instance_dir, version_id, library_dir) = {
+        let instance_manager = instance_state.0.lock().await;
+        instance_manager.ensure_instance_idle(&instance_name)?;
+        let lock = instance_manager.lock_instance(&instance_name, InstanceOperation::Repair)?;
+        (
+            lock,
+            instance_manager.get_instance_folder(&instance_name, InstanceFolder::Root)?,
+            instance_manager
+                .get_instance_version_id(&instance_name)?
+                .to_string(),
+            instance_manager.mods_library_dir(),
+        )
+    };
+
+    let lockfile = Lockfile::load(&instance_dir)?;
+    if lockfile.version_id != version_id {
+        return Err(InstanceError::LockfileVersionMismatch {
+            locked: lockfile.version_id,
+            installed: version_id,
+        }
+        .into());
+    }
+    let mut report = LockfileRestoreReport::default();
+    restore_files(
+        &versions_dir,
+        slice::from_ref(&lockfile.game_jar),
+        None,
+        &mut report,
+    )
+    .await?;
+    restore_files(&libraries_dir, &lockfile.libraries, None, &mut report).await?;
+    let library = mods_library(&app_handle, library_dir).await;
+    restore_files(&instance_dir, &lockfile.mods, library.as_ref(), &mut report).await?;
+    hash_cache::save();
+
+    let unlocked_mods = lockfile.unlocked_mods(&instance_dir)?;
+    if !unlocked_mods.is_empty() {
+        let instance_manager = instance_state.0.lock().await;
+        for file_name in unlocked_mods {
+            instance_manager.trash_instance_file(
+                &instance_name,
+                InstanceFolder::Mods,
+                TrashKind::Mod,
+                &file_name,
+            )?;
+            report.removed.push(file_name);
+        }
+    }
+    Ok(report)
+}
+
+/// Returns the launcher's metadata about the mods of an instance, including which are pinned.
+#[tauri::command(async)]
+pub async fn get_mod_metadata(
+    instance_name: String,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<ModMetadata> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let instance_manager = instance_state.0.lock().await;
+
+    let instance_dir =
+        instance_manager.get_instance_folder(&instance_name, InstanceFolder::Root)?;
+    Ok(ModMetadata::load(&instance_dir)?)
+}
+
+/// Pins or unpins a mod jar of an instance. Modpack updates leave pinned mods at their version,
+/// including newer versions of the same Modrinth project.
+#[tauri::command(async)]
+pub async fn set_mod_pinned(
+    instance_name: String,
+    file_name: String,
+    pinned: bool,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<ModMetadata> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let (instance_dir, mods_dir) = {
+        let instance_manager = instance_state.0.lock().await;
+        (
+            instance_manager.get_instance_folder(&instance_name, InstanceFolder::Root)?,
+            instance_manager.get_instance_folder(&instance_name, InstanceFolder::Mods)?,
+        )
+    };
+
+    let pin = if pinned {
+        let path = resolve_file_in(&mods_dir, &file_name)
+            .ok_or_else(|| InstanceError::UnknownFile(file_name.clone()))?;
+        let sha1 = hash_cache::file_sha1(&path)?;
+        // Mods that aren't on Modrinth, or a failed lookup, are still pinned by their file name.
+        let project_id = match version_from_hash(&sha1).await {
+            Ok(version) => version.map(|version| version.project_id),
+            Err(error) => {
+                warn!("Could not look up {} on Modrinth: {}", file_name, error);
+                None
+            }
+        };
+        Some(PinnedMod {
+            project_id,
+            pinned_at: Local::now().timestamp(),
+        })
+    } else {
+        None
+    };
+
+    let mut mod_metadata = ModMetadata::load(&instance_dir)?;
+    match pin {
+        Some(pin) => mod_metadata.pinned.insert(file_name, pin),
+        None => mod_metadata.pinned.remove(&file_name),
+    };
+    mod_metadata.save(&instance_dir)?;
+    Ok(mod_metadata)
+}
+
+/// Moves a mod jar of an instance to the trash.
+#[tauri::command(async)]
+pub async fn delete_instance_mod(
+    instance_name: String,
+    file_name: String,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<TrashEntry> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let instance_manager = instance_state.0.lock().await;
+
+    let _lock = instance_manager.lock_instance(&instance_name, InstanceOperation::Delete)?;
+    instance_manager.trash_instance_file(
+        &instance_name,
+        InstanceFolder::Mods,
+        TrashKind::Mod,
+        &file_name,
+    )
+}
+
+/// Moves a world of an instance to the trash, `folder_name` is the one listed by `get_instance_worlds`.
+#[tauri::command(async)]
+pub async fn delete_instance_world(
+    instance_name: String,
+    folder_name: String,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<TrashEntry> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let instance_manager = instance_state.0.lock().await;
+
+    let _lock = instance_manager.lock_instance(&instance_name, InstanceOperation::Delete)?;
+    instance_manager.trash_instance_file(
+        &instance_name,
+        InstanceFolder::Saves,
+        TrashKind::World,
+        &folder_name,
+    )
+}
+
+/// Lists what can still be restored from the trash, expired entries are deleted first.
+#[tauri::command(async)]
+pub async fn get_trash(app_handle: AppHandle<Wry>) -> InstanceResult<Vec<TrashEntry>> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let instance_manager = instance_state.0.lock().await;
+
+    let trash_dir = instance_manager.trash_dir();
+    purge_trash(&trash_dir, false)?;
+    Ok(list_trash(&trash_dir)?)
+}
+
+#[tauri::command(async)]
+pub async fn restore_trash_entry(
+    id: String,
+    app_handle: AppHandle<Wry>,
+) -> ManifestResult<TrashEntry> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let mut instance_manager = instance_state.0.lock().await;
+
+    instance_manager.restore_trash_entry(&id)
+}
+
+/// Deletes everything in the trash for good.
+#[tauri::command(async)]
+pub async fn empty_trash(app_handle: AppHandle<Wry>) -> InstanceResult<()> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let instance_manager = instance_state.0.lock().await;
+
+    Ok(purge_trash(&instance_manager.trash_dir(), true)?)
+}
+
+#[tauri::command(async)]
+pub async fn clone_instance(
+    instance_name: String,
+    new_instance_name: String,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<()> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let mut instance_manager = instance_state.0.lock().await;
+
+    instance_manager.clone_instance(&instance_name, &new_instance_name)
+}
+
+#[tauri::command(async)]
+pub async fn get_instance_groups(app_handle: AppHandle<Wry>) -> Vec<InstanceGroup> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let instance_manager = instance_state.0.lock().await;
+
+    instance_manager.get_groups().to_vec()
+}
+
+#[tauri::command(async)]
+pub async fn create_instance_group(
+    group_name: String,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<()> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let mut instance_manager = instance_state.0.lock().await;
+
+    instance_manager.create_group(&group_name)
+}
+
+#[tauri::command(async)]
+pub async fn delete_instance_group(
+    group_name: String,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<()> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let mut instance_manager = instance_state.0.lock().await;
+
+    instance_manager.delete_group(&group_name)
+}
+
+#[tauri::command(async)]
+pub async fn assign_instance_group(
+    instance_name: String,
+    group_name: Option<String>,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<()> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let mut instance_manager = instance_state.0.lock().await;
+
+    instance_manager.assign_instance_to_group(&instance_name, group_name.as_deref())
+}
+
+#[tauri::command(async)]
+pub async fn move_instance_group(
+    group_name: String,
+    index: usize,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<()> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let mut instance_manager = instance_state.0.lock().await;
+
+    instance_manager.move_group(&group_name, index)
+}
+
+#[tauri::command(async)]
+pub async fn move_instance_in_group(
+    instance_name: String,
+    index: usize,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<()> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let mut instance_manager = instance_state.0.lock().await;
+
+    instance_manager.move_instance_in_group(&instance_name, index)
+}
+
+#[tauri::command(async)]
+pub async fn get_instances_sorted(
+    sort_by: InstanceSort,
+    app_handle: AppHandle<Wry>,
+) -> Vec<InstanceEntry> {
+    let resource_state: State<ResourceState> = app_handle
+        .try_state()
+        .expect("`ResourceState` should already be managed.");
+    let resource_manager = &resource_state.0;
+
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let instance_manager = instance_state.0.lock().await;
+
+    instance_manager.get_instance_entries(sort_by, |version_id| {
+        resource_manager.get_vanilla_version_index(version_id)
+    })
+}
+
+#[tauri::command(async)]
+pub async fn change_version(
+    instance_name: String,
+    version_id: String,
+    app_handle: AppHandle<Wry>,
+) -> ManifestResult<()> {
+    let task = register_task(&app_handle, TaskKind::ChangeVersion, &instance_name).await?;
+    let result = change_instance_version(instance_name, version_id, &app_handle, &task).await;
+    finish_task(&app_handle, &task, &result).await;
+    result
+}
+
+/// Hashes every file of an instance and downloads the ones that are missing or corrupted, like the
+/// install does.
+#[tauri::command(async)]
+pub async fn verify_instance_files(
+    instance_name: String,
+    app_handle: AppHandle<Wry>,
+) -> ManifestResult<()> {
+    let task = register_task(&app_handle, TaskKind::Verify, &instance_name).await?;
+    let result = verify_instance(instance_name, &app_handle, &task).await;
+    finish_task(&app_handle, &task, &result).await;
+    result
+}
+
+#[tauri::command(async)]
+pub async fn get_instance_sync(
+    instance_name: String,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<Option<SyncSettings>> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let instance_manager = instance_state.0.lock().await;
+
+    instance_manager.get_instance_sync(&instance_name)
+}
+
+/// Turns syncing an instance with a folder on or off, `sync` is None to turn it off.
+#[tauri::command(async)]
+pub async fn set_instance_sync(
+    instance_name: String,
+    sync: Option<SyncSettings>,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<()> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let mut instance_manager = instance_state.0.lock().await;
+
+    instance_manager.set_instance_sync(&instance_name, sync)
+}
+
+/// Syncs an instance outside of a launch. With `overwrite` the conflicts are settled by copying the
+/// source side of `direction` over the other one.
+#[tauri::command(async)]
+pub async fn sync_instance_now(
+    instance_name: String,
+    direction: SyncDirection,
+    overwrite: bool,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<SyncReport> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let instance_manager = instance_state.0.lock().await;
+
+    instance_manager.ensure_instance_idle(&instance_name)?;
+    let _lock = instance_manager.lock_instance(&instance_name, InstanceOperation::Sync)?;
+    let sync = instance_manager
+        .get_instance_sync(&instance_name)?
+        .ok_or_else(|| {
+            InstanceError::FilesystemError(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} is not synced with a folder", instance_name),
+            ))
+        })?;
+    let instance_dir =
+        instance_manager.get_instance_folder(&instance_name, InstanceFolder::Root)?;
+    Ok(sync_instance(&instance_dir, &sync, direction, overwrite)?)
+}
+
+/// Backs up an instance in the background, the instance manager is only locked to mark the
+/// instance as busy.
+async fn backup_instance(
+    app_handle: &AppHandle<Wry>,
+    instance_name: &str,
+    reason: BackupReason,
+) -> InstanceResult<BackupEntry> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let (lock, instance_dir, backups_dir) = {
+        let instance_manager = instance_state.0.lock().await;
+        instance_manager.ensure_instance_idle(instance_name)?;
+        let lock = instance_manager.lock_instance(instance_name, InstanceOperation::Backup)?;
+        (
+            lock,
+            instance_manager.get_instance_folder(instance_name, InstanceFolder::Root)?,
+            instance_manager.backups_dir(instance_name),
+        )
+    };
+    let settings_state: State<SettingsState> = app_handle
+        .try_state()
+        .expect("`SettingsState` should already be managed.");
+    let max_backups = settings_state
+        .0
+        .lock()
+        .await
+        .get_backup_settings()
+        .max_backups;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let _lock = lock;
+        let backup = create_backup(&instance_dir, &backups_dir, reason)?;
+        prune_backups(&backups_dir, max_backups as usize)?;
+        Ok(backup)
+    })
+    .await
+    .unwrap_or_else(|error| {
+        Err(InstanceError::FilesystemError(io::Error::new(
+            io::ErrorKind::Other,
+            error.to_string(),
+        )))
+    })
+}
+
+/// Backs up every idle instance whose newest backup is older than the configured interval. Called
+/// periodically from a background thread.
+pub async fn run_scheduled_backups(app_handle: &AppHandle<Wry>) {
+    let settings_state: State<SettingsState> = app_handle
+        .try_state()
+        .expect("`SettingsState` should already be managed.");
+    let interval_hours = match settings_state
+        .0
+        .lock()
+        .await
+        .get_backup_settings()
+        .interval_hours
+    {
+        Some(interval_hours) => interval_hours,
+        None => return,
+    };
+
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let due: Vec<String> = {
+        let instance_manager = instance_state.0.lock().await;
+        instance_manager
+            .get_instance_names()
+            .into_iter()
+            .filter(|instance_name| !instance_manager.is_instance_running(instance_name))
+            .filter(|instance_name| {
+                is_backup_due(&instance_manager.backups_dir(instance_name), interval_hours)
+            })
+            .collect()
+    };
+    for instance_name in due {
+        // Busy instances are picked up again on the next run.
+        if let Err(error) =
+            backup_instance(app_handle, &instance_name, BackupReason::Scheduled).await
+        {
+            warn!("Scheduled backup of {} failed: {:?}", instance_name, error);
+        }
+    }
+}
+
+#[tauri::command(async)]
+pub async fn create_instance_backup(
+    instance_name: String,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<BackupEntry> {
+    backup_instance(&app_handle, &instance_name, BackupReason::Manual).await
+}
+
+/// Lists the restore points of an instance, newest first.
+#[tauri::command(async)]
+pub async fn get_instance_backups(
+    instance_name: String,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<Vec<BackupEntry>> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let instance_manager = instance_state.0.lock().await;
+
+    Ok(list_backups(&instance_manager.backups_dir(&instance_name))?)
+}
+
+#[tauri::command(async)]
+pub async fn delete_instance_backup(
+    instance_name: String,
+    file_name: String,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<()> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let instance_manager = instance_state.0.lock().await;
+
+    delete_backup(&instance_manager.backups_dir(&instance_name), &file_name)
+}
+
+/// Replaces an instance with one of its backups, everything changed since the backup is lost.
+#[tauri::command(async)]
+pub async fn restore_instance_backup(
+    instance_name: String,
+    file_name: String,
+    app_handle: AppHandle<Wry>,
+) -> ManifestResult<()> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let mut instance_manager = instance_state.0.lock().await;
+
+    instance_manager.ensure_instance_idle(&instance_name)?;
+    let _lock = instance_manager.lock_instance(&instance_name, InstanceOperation::Restore)?;
+    let instance_dir =
+        instance_manager.get_instance_folder(&instance_name, InstanceFolder::Root)?;
+    let backups_dir = instance_manager.backups_dir(&instance_name);
+    restore_backup(&instance_dir, &backups_dir, &file_name)?;
+    instance_manager.reload_instance(&instance_name)
+}
+
+/// Searches mods, resource packs or shader packs for the mod browser.
+#[tauri::command(async)]
+pub async fn search_mods(
+    search: ModSearch,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<SearchPage> {
+    let curseforge = match search.source {
+        ModSource::CurseForge => {
+            let settings_state: State<SettingsState> = app_handle
+                .try_state()
+                .expect("`SettingsState` should already be managed.");
+            let settings_manager = settings_state.0.lock().await;
+            Some(settings_manager.curseforge_client()?)
+        }
+        ModSource::Modrinth => None,
+    };
+    mod_search::search_mods(&search, curseforge.as_ref()).await
+}
+
+/// The description, changelogs and gallery of a Modrinth project, from the cache when Modrinth can't
+/// be reached.
+#[tauri::command(async)]
+pub async fn get_mod_details(
+    project: String,
+    app_handle: AppHandle<Wry>,
+) -> ManifestResult<ModDetails> {
+    let resource_state: State<ResourceState> = app_handle
+        .try_state()
+        .expect("`ResourceState` should already be managed.");
+    let cache = resource_state.0.response_cache();
+
+    mod_details::get_mod_details(&cache, &project).await
+}
+
+#[tauri::command(async)]
+pub async fn get_instance_release_channel(
+    instance_name: String,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<ReleaseChannel> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let instance_manager = instance_state.0.lock().await;
+
+    instance_manager.get_instance_release_channel(&instance_name)
+}
+
+#[tauri::command(async)]
+pub async fn set_instance_release_channel(
+    instance_name: String,
+    release_channel: ReleaseChannel,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<()> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let mut instance_manager = instance_state.0.lock().await;
+
+    instance_manager.set_instance_release_channel(&instance_name, release_channel)
+}
+
+/// Lists the versions of a mod for the instance's minecraft version, on the instance's release
+/// channel unless `channel` is given. Versions for every loader are listed without `loader`.
+#[tauri::command(async)]
+pub async fn list_mod_versions(
+    instance_name: String,
+    project: ModProject,
+    channel: Option<ReleaseChannel>,
+    loader: Option<ModLoader>,
+    game_version: Option<String>,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<Vec<ModVersion>> {
+    let filter = {
+        let instance_state: State<InstanceState> = app_handle
+            .try_state()
+            .expect("`InstanceState` should already be managed.");
+        let instance_manager = instance_state.0.lock().await;
+        let config = instance_manager.get_instance_configuration(&instance_name)?;
+        ModVersionFilter {
+            channel: channel.unwrap_or(config.release_channel),
+            loader,
+            game_version: Some(game_version.unwrap_or_else(|| config.version_id.clone())),
+        }
+    };
+    let curseforge = match project {
+        ModProject::CurseForge { .. } => {
+            let settings_state: State<SettingsState> = app_handle
+                .try_state()
+                .expect("`SettingsState` should already be managed.");
+            let settings_manager = settings_state.0.lock().await;
+            Some(settings_manager.curseforge_client()?)
+        }
+        ModProject::Modrinth { .. } => None,
+    };
+    mod_versions::list_mod_versions(&project, &filter, curseforge.as_ref()).await
+}
+
+#[tauri::command(async)]
+pub async fn get_instance_notes(
+    instance_name: String,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<String> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let instance_manager = instance_state.0.lock().await;
+
+    instance_manager.get_instance_notes(&instance_name)
+}
+
+#[tauri::command(async)]
+pub async fn set_instance_notes(
+    instance_name: String,
+    notes: String,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<()> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let mut instance_manager = instance_state.0.lock().await;
+
+    instance_manager.set_instance_notes(&instance_name, notes)
+}
+
+#[tauri::command(async)]
+pub async fn open_instance_folder(
+    instance_name: String,
+    folder: InstanceFolder,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<()> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let instance_manager = instance_state.0.lock().await;
+
+    let path = instance_manager.get_instance_folder(&instance_name, folder)?;
+    Ok(open_in_file_manager(&path)?)
+}
+
+#[tauri::command(async)]
+pub async fn get_instance_worlds(
+    instance_name: String,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<Vec<WorldEntry>> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let instance_manager = instance_state.0.lock().await;
+
+    let saves_dir = instance_manager.get_instance_folder(&instance_name, InstanceFolder::Saves)?;
+    Ok(list_worlds(
+        &saves_dir,
+        &instance_manager.world_icons_dir(&instance_name),
+    )?)
+}
+
+#[tauri::command(async)]
+pub async fn get_world_details(
+    instance_name: String,
+    folder_name: String,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<WorldDetails> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let instance_manager = instance_state.0.lock().await;
+
+    let saves_dir = instance_manager.get_instance_folder(&instance_name, InstanceFolder::Saves)?;
+    read_world_details(
+        &saves_dir,
+        &instance_manager.world_icons_dir(&instance_name),
+        &folder_name,
+    )
+}
+
+/// Statistics of every world of the instance added up, for the stats dashboard.
+#[tauri::command(async)]
+pub async fn get_instance_statistics(
+    instance_name: String,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<InstanceStatistics> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let instance_manager = instance_state.0.lock().await;
+
+    let saves_dir = instance_manager.get_instance_folder(&instance_name, InstanceFolder::Saves)?;
+    Ok(aggregate_statistics(&saves_dir)?)
+}
+
+/// Advancement progress of every player in a world, keyed by advancement id.
+#[tauri::command(async)]
+pub async fn get_world_advancements(
+    instance_name: String,
+    folder_name: String,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<Vec<PlayerAdvancements>> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let instance_manager = instance_state.0.lock().await;
+
+    let saves_dir = instance_manager.get_instance_folder(&instance_name, InstanceFolder::Saves)?;
+    read_advancements(&resolve_world_dir(&saves_dir, &folder_name)?)
+}
+
+#[tauri::command(async)]
+pub async fn get_instance_servers(
+    instance_name: String,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<Vec<ServerEntry>> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let instance_manager = instance_state.0.lock().await;
+
+    let instance_dir =
+        instance_manager.get_instance_folder(&instance_name, InstanceFolder::Root)?;
+    list_servers(&instance_dir.join("servers.dat"))
+}
+
+#[tauri::command(async)]
+pub async fn add_instance_server(
+    instance_name: String,
+    name: String,
+    ip: String,
+    index: Option<usize>,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<()> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let instance_manager = instance_state.0.lock().await;
+
+    let instance_dir =
+        instance_manager.get_instance_folder(&instance_name, InstanceFolder::Root)?;
+    add_server(&instance_dir.join("servers.dat"), &name, &ip, index)
+}
+
+#[tauri::command(async)]
+pub async fn remove_instance_server(
+    instance_name: String,
+    index: usize,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<()> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let instance_manager = instance_state.0.lock().await;
+
+    let instance_dir =
+        instance_manager.get_instance_folder(&instance_name, InstanceFolder::Root)?;
+    remove_server(&instance_dir.join("servers.dat"), index)
+}
+
+#[tauri::command(async)]
+pub async fn move_instance_server(
+    instance_name: String,
+    from: usize,
+    to: usize,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<()> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let instance_manager = instance_state.0.lock().await;
+
+    let instance_dir =
+        instance_manager.get_instance_folder(&instance_name, InstanceFolder::Root)?;
+    move_server(&instance_dir.join("servers.dat"), from, to)
+}
+
+#[tauri::command(async)]
+pub async fn get_instance_screenshots(
+    instance_name: String,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<Vec<ScreenshotEntry>> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let instance_manager = instance_state.0.lock().await;
+
+    let screenshots_dir =
+        instance_manager.get_instance_folder(&instance_name, InstanceFolder::Screenshots)?;
+    Ok(list_screenshots(&screenshots_dir)?)
+}
+
+/// Returns the path of a thumbnail for the screenshot, generating it if needed.
+#[tauri::command(async)]
+pub async fn get_screenshot_thumbnail(
+    instance_name: String,
+    file_name: String,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<PathBuf> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let instance_manager = instance_state.0.lock().await;
+
+    let screenshots_dir =
+        instance_manager.get_instance_folder(&instance_name, InstanceFolder::Screenshots)?;
+    get_thumbnail(
+        &screenshots_dir,
+        &instance_manager.thumbnails_dir(&instance_name),
+        &file_name,
+    )
+}
+
+#[tauri::command(async)]
+pub async fn delete_instance_screenshot(
+    instance_name: String,
+    file_name: String,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<()> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let instance_manager = instance_state.0.lock().await;
+
+    let screenshots_dir =
+        instance_manager.get_instance_folder(&instance_name, InstanceFolder::Screenshots)?;
+    delete_screenshot(
+        &screenshots_dir,
+        &instance_manager.thumbnails_dir(&instance_name),
+        &file_name,
+    )
+}
+
+/// Lists the files in the instance's `config` folder for the config editor.
+#[tauri::command(async)]
+pub async fn get_instance_config_files(
+    instance_name: String,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<Vec<ConfigFileEntry>> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let instance_manager = instance_state.0.lock().await;
+
+    let config_dir =
+        instance_manager.get_instance_folder(&instance_name, InstanceFolder::Config)?;
+    let backups_dir = instance_manager
+        .get_instance_folder(&instance_name, InstanceFolder::Root)?
+        .join(CONFIG_BACKUPS_DIR);
+    list_config_files(&config_dir, &backups_dir)
+}
+
+#[tauri::command(async)]
+pub async fn read_instance_config_file(
+    instance_name: String,
+    path: String,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<String> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let instance_manager = instance_state.0.lock().await;
+
+    let config_dir =
+        instance_manager.get_instance_folder(&instance_name, InstanceFolder::Config)?;
+    read_config_file(&config_dir, &path)
+}
+
+#[tauri::command(async)]
+pub async fn write_instance_config_file(
+    instance_name: String,
+    path: String,
+    contents: String,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<()> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let instance_manager = instance_state.0.lock().await;
+
+    let _lock = instance_manager.lock_instance(&instance_name, InstanceOperation::EditConfig)?;
+    let config_dir =
+        instance_manager.get_instance_folder(&instance_name, InstanceFolder::Config)?;
+    let backups_dir = instance_manager
+        .get_instance_folder(&instance_name, InstanceFolder::Root)?
+        .join(CONFIG_BACKUPS_DIR);
+    write_config_file(&config_dir, &backups_dir, &path, &contents)
+}
+
+/// Undoes the last edit of a config file made through `write_instance_config_file`.
+#[tauri::command(async)]
+pub async fn restore_instance_config_file(
+    instance_name: String,
+    path: String,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<()> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let instance_manager = instance_state.0.lock().await;
+
+    let _lock = instance_manager.lock_instance(&instance_name, InstanceOperation::EditConfig)?;
+    let config_dir =
+        instance_manager.get_instance_folder(&instance_name, InstanceFolder::Config)?;
+    let backups_dir = instance_manager
+        .get_instance_folder(&instance_name, InstanceFolder::Root)?
+        .join(CONFIG_BACKUPS_DIR);
+    restore_config_file(&config_dir, &backups_dir, &path)
+}
+
+#[tauri::command(async)]
+pub async fn reveal_instance_screenshot(
+    instance_name: String,
+    file_name: String,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<()> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let instance_manager = instance_state.0.lock().await;
+
+    let screenshots_dir =
+        instance_manager.get_instance_folder(&instance_name, InstanceFolder::Screenshots)?;
+    let path = get_screenshot_path(&screenshots_dir, &file_name)?;
+    Ok(reveal_in_file_manager(&path)?)
+}
+
+#[tauri::command(async)]
+pub async fn get_instance_logs(
+    instance_name: String,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<Vec<LogFileEntry>> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let instance_manager = instance_state.0.lock().await;
+
+    let logs_dir = instance_manager.get_instance_folder(&instance_name, InstanceFolder::Logs)?;
+    Ok(list_logs(&logs_dir)?)
+}
+
+/// Returns the parsed lines of a log that match `filter`, gzipped logs are decompressed first.
+#[tauri::command(async)]
+pub async fn read_instance_log(
+    instance_name: String,
+    file_name: String,
+    filter: Option<LogFilter>,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<Vec<LogLine>> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let instance_manager = instance_state.0.lock().await;
+
+    let logs_dir = instance_manager.get_instance_folder(&instance_name, InstanceFolder::Logs)?;
+    read_log(&logs_dir, &file_name, &filter.unwrap_or_default())
+}
+
+#[tauri::command(async)]
+pub async fn start_instance_log_tail(
+    instance_name: String,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<()> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let mut instance_manager = instance_state.0.lock().await;
+
+    instance_manager.start_log_tail(&instance_name, app_handle.clone())
+}
+
+#[tauri::command(async)]
+pub async fn stop_instance_log_tail(instance_name: String, app_handle: AppHandle<Wry>) {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let mut instance_manager = instance_state.0.lock().await;
+
+    instance_manager.stop_log_tail(&instance_name);
+}
+
+/// Starts emitting `instance-content-changed` events for the instance, e.g. while its mods are shown.
+#[tauri::command(async)]
+pub async fn watch_instance_content(
+    instance_name: String,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<()> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let mut instance_manager = instance_state.0.lock().await;
+
+    instance_manager.start_content_watch(&instance_name, app_handle.clone())
+}
+
+#[tauri::command(async)]
+pub async fn unwatch_instance_content(instance_name: String, app_handle: AppHandle<Wry>) {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let mut instance_manager = instance_state.0.lock().await;
+
+    instance_manager.stop_content_watch(&instance_name);
+}
+
+#[tauri::command(async)]
+pub async fn get_instance_crash_reports(
+    instance_name: String,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<Vec<CrashReportEntry>> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let instance_manager = instance_state.0.lock().await;
+
+    let crash_reports_dir =
+        instance_manager.get_instance_folder(&instance_name, InstanceFolder::CrashReports)?;
+    Ok(list_crash_reports(&crash_reports_dir)?)
+}
+
+/// Parses a crash report into its sections along with the mods that probably caused it.
+#[tauri::command(async)]
+pub async fn get_crash_report(
+    instance_name: String,
+    file_name: String,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<CrashReport> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let instance_manager = instance_state.0.lock().await;
+
+    let crash_reports_dir =
+        instance_manager.get_instance_folder(&instance_name, InstanceFolder::CrashReports)?;
+    read_crash_report(&crash_reports_dir, &file_name)
+}
+
+#[tauri::command(async)]
+pub async fn get_global_settings(app_handle: AppHandle<Wry>) -> LaunchSettings {
+    let settings_state: State<SettingsState> = app_handle
+        .try_state()
+        .expect("`SettingsState` should already be managed.");
+    let settings_manager = settings_state.0.lock().await;
+
+    settings_manager.get_launch_settings().clone()
+}
+
+#[tauri::command(async)]
+pub async fn set_global_settings(
+    settings: LaunchSettings,
+    app_handle: AppHandle<Wry>,
+) -> ManifestResult<()> {
+    let settings_state: State<SettingsState> = app_handle
+        .try_state()
+        .expect("`SettingsState` should already be managed.");
+    let mut settings_manager = settings_state.0.lock().await;
+
+    settings_manager.set_launch_settings(settings);
+    settings_manager.serialize_settings()?;
+    Ok(())
+}
+
+#[tauri::command(async)]
+pub async fn get_verification_settings(app_handle: AppHandle<Wry>) -> VerificationSettings {
+    let settings_state: State<SettingsState> = app_handle
+        .try_state()
+        .expect("`SettingsState` should already be managed.");
+    let settings_manager = settings_state.0.lock().await;
+
+    settings_manager.get_verification_settings().clone()
+}
+
+#[tauri::command(async)]
+pub async fn set_verification_settings(
+    verification: VerificationSettings,
+    app_handle: AppHandle<Wry>,
+) -> ManifestResult<()> {
+    let settings_state: State<SettingsState> = app_handle
+        .try_state()
+        .expect("`SettingsState` should already be managed.");
+    let mut settings_manager = settings_state.0.lock().await;
+
+    settings_manager.set_verification_settings(verification);
+    settings_manager.serialize_settings()?;
+    Ok(())
+}
+
+#[tauri::command(async)]
+pub async fn get_backup_settings(app_handle: AppHandle<Wry>) -> BackupSettings {
+    let settings_state: State<SettingsState> = app_handle
+        .try_state()
+        .expect("`SettingsState` should already be managed.");
+    let settings_manager = settings_state.0.lock().await;
+
+    settings_manager.get_backup_settings().clone()
+}
+
+#[tauri::command(async)]
+pub async fn set_backup_settings(
+    backups: BackupSettings,
+    app_handle: AppHandle<Wry>,
+) -> ManifestResult<()> {
+    let settings_state: State<SettingsState> = app_handle
+        .try_state()
+        .expect("`SettingsState` should already be managed.");
+    let mut settings_manager = settings_state.0.lock().await;
+
+    settings_manager.set_backup_settings(backups);
+    settings_manager.serialize_settings()?;
+    Ok(())
+}
+
+/// Reports how much space the shared libraries, assets and mods library save, and which large files
+/// are duplicated between instances.
+#[tauri::command(async)]
+pub async fn get_storage_report(app_handle: AppHandle<Wry>) -> InstanceResult<StorageReport> {
+    let resource_state: State<ResourceState> = app_handle
+        .try_state()
+        .expect("`ResourceState` should already be managed.");
+    let resource_manager = &resource_state.0;
+
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let instance_manager = instance_state.0.lock().await;
+
+    let dirs = StoreDirs {
+        libraries: resource_manager.libraries_dir(),
+        versions: resource_manager.version_dir(),
+        assets: resource_manager.assets_dir(),
+        mods_library: instance_manager.mods_library_dir(),
+    };
+    let mut instances = Vec::new();
+    for instance_name in instance_manager.get_instance_names() {
+        let config = instance_manager.get_instance_configuration(&instance_name)?;
+        instances.push(InstanceUsage {
+            instance_dir: instance_manager.instances_dir().join(&instance_name),
+            arguments: config.arguments.clone(),
+        });
+    }
+    drop(instance_manager);
+
+    let report = tauri::async_runtime::spawn_blocking(move || storage_report(&dirs, &instances))
+        .await
+        .map_err(|error| io::Error::new(io::ErrorKind::Other, error.to_string()))??;
+    hash_cache::save();
+    Ok(report)
+}
+
+#[tauri::command(async)]
+pub async fn get_storage_settings(app_handle: AppHandle<Wry>) -> StorageSettings {
+    let settings_state: State<SettingsState> = app_handle
+        .try_state()
+        .expect("`SettingsState` should already be managed.");
+    let settings_manager = settings_state.0.lock().await;
+
+    settings_manager.get_storage_settings().clone()
+}
+
+/// Turning the shared mods library on moves the mods of every instance into it, turning it off
+/// leaves the links in place, they are regular files to every instance.
+#[tauri::command(async)]
+pub async fn set_storage_settings(
+    storage: StorageSettings,
+    app_handle: AppHandle<Wry>,
+) -> ManifestResult<()> {
+    if storage.shared_mods_library {
+        let instance_state: State<InstanceState> = app_handle
+            .try_state()
+            .expect("`InstanceState` should already be managed.");
+        let (library_dir, instances_dir) = {
+            let instance_manager = instance_state.0.lock().await;
+            (
+                instance_manager.mods_library_dir(),
+                instance_manager.instances_dir(),
+            )
+        };
+        tauri::async_runtime::spawn_blocking(move || adopt_all_mods(&library_dir, &instances_dir))
+            .await
+            .map_err(|error| io::Error::new(io::ErrorKind::Other, error.to_string()))??;
+    }
+
+    let settings_state: State<SettingsState> = app_handle
+        .try_state()
+        .expect("`SettingsState` should already be managed.");
+    let mut settings_manager = settings_state.0.lock().await;
+
+    settings_manager.set_storage_settings(storage);
+    settings_manager.serialize_settings()?;
+    Ok(())
+}
+
+/// Moves the mods of every instance into the shared mods library.
+fn adopt_all_mods(library_dir: &Path, instances_dir: &Path) -> io::Result<()> {
+    let library = ModsLibrary::new(library_dir.into());
+    let mut adopted = 0;
+    for entry in fs::read_dir(instances_dir)? {
+        adopted += library.adopt_dir(&entry?.path().join("mods"))?;
+    }
+    info!("Moved {} mods into the mods library", adopted);
+    Ok(())
+}
+
+/// The shared mods library at `library_dir` when it is turned on in the settings.
+async fn mods_library(app_handle: &AppHandle<Wry>, library_dir: PathBuf) -> Option<ModsLibrary> {
+    let settings_state: State<SettingsState> = app_handle
+        .try_state()
+        .expect("`SettingsState` should already be managed.");
+    let settings_manager = settings_state.0.lock().await;
+    if settings_manager.get_storage_settings().shared_mods_library {
+        Some(ModsLibrary::new(library_dir))
+    } else {
+        None
+    }
+}
+
+#[tauri::command(async)]
+pub async fn get_curseforge_settings(app_handle: AppHandle<Wry>) -> CurseForgeStatus {
+    let settings_state: State<SettingsState> = app_handle
+        .try_state()
+        .expect("`SettingsState` should already be managed.");
+    let settings_manager = settings_state.0.lock().await;
+
+    settings_manager.get_curseforge_settings().status()
+}
+
+/// Saves the api key or proxy after checking that CurseForge accepts it. Empty values clear the setting.
+#[tauri::command(async)]
+pub async fn set_curseforge_settings(
+    api_key: Option<String>,
+    proxy_url: Option<String>,
+    app_handle: AppHandle<Wry>,
+) -> CurseForgeResult<CurseForgeStatus> {
+    let non_empty = |value: Option<String>| {
+        value
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+    let curseforge_settings = CurseForgeSettings {
+        api_key: non_empty(api_key),
+        proxy_url: non_empty(proxy_url),
+        ..Default::default()
+    };
+    if curseforge_settings.api_key.is_some() || curseforge_settings.proxy_url.is_some() {
+        CurseForgeClient::new(&curseforge_settings)?
+            .validate()
+            .await?;
+    }
+
+    let settings_state: State<SettingsState> = app_handle
+        .try_state()
+        .expect("`SettingsState` should already be managed.");
+    let mut settings_manager = settings_state.0.lock().await;
+
+    let status = curseforge_settings.status();
+    settings_manager.set_curseforge_settings(curseforge_settings)?;
+    settings_manager.serialize_settings()?;
+    Ok(status)
+}
+
+#[tauri::command(async)]
+pub async fn validate_curseforge_settings(app_handle: AppHandle<Wry>) -> CurseForgeResult<()> {
+    let client = {
+        let settings_state: State<SettingsState> = app_handle
+            .try_state()
+            .expect("`SettingsState` should already be managed.");
+        let settings_manager = settings_state.0.lock().await;
+        settings_manager.curseforge_client()?
+    };
+    client.validate().await
+}
+
+#[tauri::command(async)]
+pub async fn get_network_settings(app_handle: AppHandle<Wry>) -> NetworkStatus {
+    let settings_state: State<SettingsState> = app_handle
+        .try_state()
+        .expect("`SettingsState` should already be managed.");
+    let settings_manager = settings_state.0.lock().await;
+
+    settings_manager.get_network_settings().status()
+}
+
+/// Applies and saves the proxy and extra root certificates, invalid settings are rejected and the
+/// current ones stay in use. Empty values clear the setting, except that a missing `proxy_password`
+/// keeps the saved one since it is never sent to the frontend.
+#[tauri::command(async)]
+pub async fn set_network_settings(
+    proxy_url: Option<String>,
+    proxy_username: Option<String>,
+    proxy_password: Option<String>,
+    ca_certificates: Vec<PathBuf>,
+    app_handle: AppHandle<Wry>,
+) -> NetworkResult<NetworkStatus> {
+    let non_empty = |value: Option<String>| {
+        value
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+
+    let settings_state: State<SettingsState> = app_handle
+        .try_state()
+        .expect("`SettingsState` should already be managed.");
+    let mut settings_manager = settings_state.0.lock().await;
+
+    let proxy_password = match proxy_password {
+        Some(proxy_password) if proxy_password.is_empty() => None,
+        Some(proxy_password) => Some(proxy_password),
+        None => settings_manager
+            .get_network_settings()
+            .proxy_password
+            .clone(),
+    };
+    let network_settings = NetworkSettings {
+        proxy_url: non_empty(proxy_url),
+        proxy_username: non_empty(proxy_username),
+        proxy_password,
+        ca_certificates,
+        ..Default::default()
+    };
+    network::apply(&network_settings)?;
+
+    let status = network_settings.status();
+    settings_manager.set_network_settings(network_settings)?;
+    settings_manager.serialize_settings()?;
+    Ok(status)
+}
+
+#[tauri::command(async)]
+pub async fn get_mirror_settings(app_handle: AppHandle<Wry>) -> MirrorSettings {
+    let settings_state: State<SettingsState> = app_handle
+        .try_state()
+        .expect("`SettingsState` should already be managed.");
+    let settings_manager = settings_state.0.lock().await;
+
+    settings_manager.get_mirror_settings().clone()
+}
+
+/// The mirror settings for BMCLAPI, so users don't have to look up its urls.
+#[tauri::command(async)]
+pub async fn get_bmclapi_mirror_settings() -> MirrorSettings {
+    MirrorSettings::bmclapi()
+}
+
+/// Cached manifests keep being used until they expire, they are the same on every mirror.
+#[tauri::command(async)]
+pub async fn set_mirror_settings(
+    mirrors: MirrorSettings,
+    app_handle: AppHandle<Wry>,
+) -> ManifestResult<()> {
+    if let Some(url) = mirrors.invalid_url() {
+        return Err(ManifestError::InvalidSettingError(format!(
+            "Invalid mirror url {}",
+            url
+        )));
+    }
+
+    let settings_state: State<SettingsState> = app_handle
+        .try_state()
+        .expect("`SettingsState` should already be managed.");
+    let mut settings_manager = settings_state.0.lock().await;
+
+    mirrors::apply(&mirrors);
+    settings_manager.set_mirror_settings(mirrors);
+    settings_manager.serialize_settings()?;
+    Ok(())
+}
+
+#[tauri::command(async)]
+pub async fn get_locale() -> LocaleStatus {
+    i18n::locale_status()
+}
+
+/// Messages sent from now on are in `locale`, e.g. `de`.
+#[tauri::command(async)]
+pub async fn set_locale(locale: String, app_handle: AppHandle<Wry>) -> ManifestResult<()> {
+    if !i18n::is_available(&locale) {
+        return Err(ManifestError::InvalidSettingError(format!(
+            "No translations for {}",
+            locale
+        )));
+    }
+
+    let settings_state: State<SettingsState> = app_handle
+        .try_state()
+        .expect("`SettingsState` should already be managed.");
+    let mut settings_manager = settings_state.0.lock().await;
+
+    i18n::set_locale(&locale);
+    settings_manager.set_locale(locale);
+    settings_manager.serialize_settings()?;
+    Ok(())
+}
+
+/// Where the user left the launcher, the window itself is restored by the backend.
+#[tauri::command(async)]
+pub async fn get_ui_state(app_handle: AppHandle<Wry>) -> UiState {
+    let settings_state: State<SettingsState> = app_handle
+        .try_state()
+        .expect("`SettingsState` should already be managed.");
+    let settings_manager = settings_state.0.lock().await;
+    settings_manager.get_ui_state().clone()
+}
+
+#[tauri::command(async)]
+pub async fn set_ui_state(
+    selected_instance: Option<String>,
+    last_tab: Option<String>,
+    app_handle: AppHandle<Wry>,
+) -> ManifestResult<()> {
+    let settings_state: State<SettingsState> = app_handle
+        .try_state()
+        .expect("`SettingsState` should already be managed.");
+    let mut settings_manager = settings_state.0.lock().await;
+
+    settings_manager.set_ui_preferences(selected_instance, last_tab);
+    settings_manager.serialize_settings()?;
+    Ok(())
+}
+
+#[tauri::command(async)]
+pub async fn get_launcher_behavior(app_handle: AppHandle<Wry>) -> LauncherBehavior {
+    let settings_state: State<SettingsState> = app_handle
+        .try_state()
+        .expect("`SettingsState` should already be managed.");
+    let settings_manager = settings_state.0.lock().await;
+
+    settings_manager.get_launcher_behavior()
+}
+
+/// Applies from the next launch on, running games keep the behavior they were started with.
+#[tauri::command(async)]
+pub async fn set_launcher_behavior(
+    launcher_behavior: LauncherBehavior,
+    app_handle: AppHandle<Wry>,
+) -> ManifestResult<()> {
+    let settings_state: State<SettingsState> = app_handle
+        .try_state()
+        .expect("`SettingsState` should already be managed.");
+    let mut settings_manager = settings_state.0.lock().await;
+
+    settings_manager.set_launcher_behavior(launcher_behavior);
+    settings_manager.serialize_settings()?;
+    Ok(())
+}
+
+#[tauri::command(async)]
+pub async fn get_instance_settings(
+    instance_name: String,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<LaunchSettings> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let instance_manager = instance_state.0.lock().await;
+
+    Ok(instance_manager
+        .get_instance_settings(&instance_name)?
+        .clone())
+}
+
+#[tauri::command(async)]
+pub async fn set_instance_settings(
+    instance_name: String,
+    settings: LaunchSettings,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<()> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let mut instance_manager = instance_state.0.lock().await;
+
+    instance_manager.set_instance_settings(&instance_name, settings)
+}
+
+/// Returns the settings an instance would be launched with after layering its overrides over the
+/// global settings and defaults.
+#[tauri::command(async)]
+pub async fn get_effective_settings(
+    instance_name: String,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<LaunchSettings> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let instance_manager = instance_state.0.lock().await;
+
+    let settings_state: State<SettingsState> = app_handle
+        .try_state()
+        .expect("`SettingsState` should already be managed.");
+    let settings_manager = settings_state.0.lock().await;
+
+    let modded = instance_manager.is_instance_modded(&instance_name);
+    let instance_settings = instance_manager.get_instance_settings(&instance_name)?;
+    Ok(settings_manager.resolve_launch_settings(instance_settings, modded))
+}
+
+/// Returns the detected system memory with the max memory recommended for an instance, and whether
+/// its configured max memory is more than the system has.
+#[tauri::command(async)]
+pub async fn get_memory_recommendation(
+    instance_name: String,
+    app_handle: AppHandle<Wry>,
+) -> InstanceResult<MemoryRecommendation> {
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let instance_manager = instance_state.0.lock().await;
+
+    let settings_state: State<SettingsState> = app_handle
+        .try_state()
+        .expect("`SettingsState` should already be managed.");
+    let settings_manager = settings_state.0.lock().await;
+
+    let modded = instance_manager.is_instance_modded(&instance_name);
+    let instance_settings = instance_manager.get_instance_settings(&instance_name)?;
+    Ok(settings_manager.memory_recommendation(instance_settings, modded))
 }
@@ -0,0 +1,272 @@
+//! Lets the user keep the launcher's data (instances, libraries, assets, java runtimes...) somewhere else
+//! than the default app directory. The default directory always holds a pointer to the current one.
+
+use std::{
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::state::{
+    instance_manager::copy_entry,
+    resource_manager::{ManifestError, ManifestResult, PathContext},
+    task_manager::TaskHandle,
+};
+
+/// Written to the default app directory when the data lives somewhere else.
+const DATA_DIR_FILE: &str = "data-dir.json";
+/// Instance configuration fields that hold absolute paths into the data directory.
+const PATH_FIELDS: [&str; 3] = ["jvm_path", "arguments", "feature_arguments"];
+/// What a path in a launch argument can follow or be followed by besides path separators, e.g.
+/// `-Dkey=path` and classpath separators.
+const PATH_DELIMITERS: [char; 6] = ['=', ':', ';', ',', '"', ' '];
+
+#[derive(Debug, Deserialize, Serialize)]
+struct DataDirPointer {
+    #[serde(rename = "dataDir")]
+    data_dir: PathBuf,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DataDirInfo {
+    pub current: PathBuf,
+    pub default: PathBuf,
+}
+
+/// The data directory the launcher was started with. Moving it only takes effect after a restart.
+#[derive(Debug)]
+pub struct DataDirState(pub DataDirInfo);
+
+impl DataDirState {
+    pub fn new(default_dir: &Path, current_dir: &Path) -> Self {
+        Self(DataDirInfo {
+            current: current_dir.into(),
+            default: default_dir.into(),
+        })
+    }
+}
+
+/// The directory the launcher keeps its data in, `default_dir` unless the user moved it.
+pub fn resolve_data_dir(default_dir: &Path) -> PathBuf {
+    let pointer = fs::read(default_dir.join(DATA_DIR_FILE))
+        .ok()
+        .and_then(|bytes| serde_json::from_slice::<DataDirPointer>(&bytes).ok());
+    match pointer {
+        Some(pointer) if pointer.data_dir.is_dir() => pointer.data_dir,
+        Some(pointer) => {
+            warn!(
+                "Data directory {} is missing, using the default directory",
+                pointer.data_dir.display()
+            );
+            default_dir.into()
+        }
+        None => default_dir.into(),
+    }
+}
+
+/// Checks that `new_dir` can take over the data from `current_dir`: it has to be an absolute path
+/// outside of the current data and either missing or empty. The default directory counts as empty
+/// when it only holds the pointer to the current one.
+pub fn check_data_dir(
+    default_dir: &Path,
+    current_dir: &Path,
+    new_dir: &Path,
+) -> ManifestResult<()> {
+    let invalid = |reason: &str| {
+        Err(ManifestError::DataDirError(format!(
+            "{} {}",
+            new_dir.display(),
+            reason
+        )))
+    };
+    if !new_dir.is_absolute() {
+        return invalid("is not an absolute path");
+    }
+    if new_dir.starts_with(current_dir) || current_dir.starts_with(new_dir) {
+        return invalid("overlaps the current data directory");
+    }
+    if new_dir.is_file() {
+        return invalid("is a file");
+    }
+    if new_dir.is_dir() {
+        let mut entries = fs::read_dir(new_dir).with_path(new_dir)?;
+        let has_data = entries.any(|entry| match entry {
+            Ok(entry) => new_dir != default_dir || entry.file_name() != DATA_DIR_FILE,
+            Err(_) => true,
+        });
+        if has_data {
+            return invalid("is not empty");
+        }
+    }
+    Ok(())
+}
+
+/// Moves everything in `current_dir` to `new_dir`, rewrites the paths stored in instance configurations
+/// and points `default_dir` at the new directory. The launcher has to restart afterwards, every manager
+/// still holds the old directory.
+pub fn move_data_dir(
+    default_dir: &Path,
+    current_dir: &Path,
+    new_dir: &Path,
+    task: &TaskHandle,
+) -> ManifestResult<()> {
+    check_data_dir(default_dir, current_dir, new_dir)?;
+    let (current_path, new_path) = match (current_dir.to_str(), new_dir.to_str()) {
+        (Some(current_path), Some(new_path)) => (current_path, new_path),
+        _ => {
+            return Err(ManifestError::DataDirError(
+                "Data directories have to be valid utf8".into(),
+            ))
+        }
+    };
+    fs::create_dir_all(new_dir).with_path(new_dir)?;
+
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(current_dir).with_path(current_dir)? {
+        let entry = entry.with_path(current_dir)?;
+        if entry.file_name() != DATA_DIR_FILE {
+            entries.push(entry.file_name());
+        }
+    }
+    let mut moved = Vec::new();
+    for (index, name) in entries.iter().enumerate() {
+        task.set_progress(index, entries.len());
+        let (from, to) = (current_dir.join(name), new_dir.join(name));
+        if let Err(error) = move_entry(&from, &to) {
+            move_back(current_dir, new_dir, &moved);
+            return Err(error);
+        }
+        moved.push(name.clone());
+    }
+    task.set_progress(entries.len(), entries.len());
+
+    // Without the pointer the launcher would look for the moved data in the old directory.
+    if let Err(error) = write_pointer(default_dir, new_dir) {
+        move_back(current_dir, new_dir, &moved);
+        return Err(error);
+    }
+    rewrite_instance_paths(&new_dir.join("instances"), current_path, new_path);
+    info!(
+        "Moved the data directory from {} to {}",
+        current_dir.display(),
+        new_dir.display()
+    );
+    Ok(())
+}
+
+/// Puts back what was already moved, so the launcher still finds its data where it was.
+fn move_back(current_dir: &Path, new_dir: &Path, moved: &[OsString]) {
+    for name in moved {
+        if let Err(error) = move_entry(&new_dir.join(name), &current_dir.join(name)) {
+            warn!("Could not move {:?} back: {}", name, error);
+        }
+    }
+}
+
+/// Renames the entry, copying and removing it when `to` is on another filesystem.
+fn move_entry(from: &Path, to: &Path) -> ManifestResult<()> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    copy_entry(from, to).with_path(to)?;
+    if from.is_dir() {
+        fs::remove_dir_all(from).with_path(from)?;
+    } else {
+        fs::remove_file(from).with_path(from)?;
+    }
+    Ok(())
+}
+
+fn write_pointer(default_dir: &Path, data_dir: &Path) -> ManifestResult<()> {
+    let path = default_dir.join(DATA_DIR_FILE);
+    if data_dir == default_dir {
+        if path.exists() {
+            fs::remove_file(&path).with_path(&path)?;
+        }
+        return Ok(());
+    }
+    let pointer = DataDirPointer {
+        data_dir: data_dir.into(),
+    };
+    fs::write(&path, serde_json::to_vec(&pointer)?).with_path(&path)
+}
+
+/// Instance configurations store the java path and the launch arguments with absolute paths to the
+/// libraries, assets and the instance itself. The data is already moved, so an instance whose
+/// configuration can't be rewritten is only logged and skipped.
+fn rewrite_instance_paths(instances_dir: &Path, from: &str, to: &str) {
+    if !instances_dir.is_dir() {
+        return;
+    }
+    let entries = match fs::read_dir(instances_dir) {
+        Ok(entries) => entries,
+        Err(error) => {
+            warn!("Could not read {}: {}", instances_dir.display(), error);
+            return;
+        }
+    };
+    for entry in entries.flatten() {
+        let config_path = entry.path().join("config.json");
+        if !config_path.is_file() {
+            continue;
+        }
+        if let Err(error) = rewrite_config_paths(&config_path, from, to) {
+            warn!(
+                "Could not rewrite the paths in {}: {}",
+                config_path.display(),
+                error
+            );
+        }
+    }
+}
+
+fn rewrite_config_paths(config_path: &Path, from: &str, to: &str) -> ManifestResult<()> {
+    let mut config: Value = serde_json::from_slice(&fs::read(config_path).with_path(config_path)?)?;
+    if let Some(fields) = config.as_object_mut() {
+        for field in PATH_FIELDS {
+            if let Some(value) = fields.get_mut(field) {
+                replace_in_strings(value, from, to);
+            }
+        }
+    }
+    fs::write(config_path, serde_json::to_vec(&config)?).with_path(config_path)
+}
+
+fn replace_in_strings(value: &mut Value, from: &str, to: &str) {
+    match value {
+        Value::String(string) => *string = replace_path_prefix(string, from, to),
+        Value::Array(values) => values
+            .iter_mut()
+            .for_each(|value| replace_in_strings(value, from, to)),
+        Value::Object(fields) => fields
+            .values_mut()
+            .for_each(|value| replace_in_strings(value, from, to)),
+        _ => {}
+    }
+}
+
+/// Replaces `from` where it starts a path and is followed by a path separator or ends the path, so
+/// `/x/data` is replaced in `/x/data/libraries` but not in `/x/data2`.
+fn replace_path_prefix(string: &str, from: &str, to: &str) -> String {
+    let is_delimiter = |c: char| PATH_DELIMITERS.contains(&c);
+    let mut replaced = String::with_capacity(string.len());
+    let mut rest = string;
+    while let Some(index) = rest.find(from) {
+        let (before, after) = (&rest[..index], &rest[index + from.len()..]);
+        let previous = before.chars().last().or_else(|| replaced.chars().last());
+        let starts_path = previous.map_or(true, is_delimiter);
+        let ends_prefix = after
+            .chars()
+            .next()
+            .map_or(true, |c| c == '/' || c == '\\' || is_delimiter(c));
+        replaced.push_str(before);
+        replaced.push_str(if starts_path && ends_prefix { to } else { from });
+        rest = after;
+    }
+    replaced.push_str(rest);
+    replaced
+}
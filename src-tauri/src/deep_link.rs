@@ -0,0 +1,287 @@
+use std::{
+    env, fs,
+    io::{self, Read, Write},
+    net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    sync::Arc,
+    thread,
+    time::Duration,
+};
+
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use tauri::async_runtime::Mutex;
+use url::Url;
+
+/// Url schemes the launcher handles, e.g. from the "Install" buttons on curseforge and modrinth.
+pub const URL_SCHEMES: [&str; 3] = ["curseforge", "modrinth", "web+modrinth"];
+pub const MRPACK_EXTENSION: &str = "mrpack";
+/// Written to the app dir by the running launcher, holds the port it listens for forwarded links on.
+const PORT_FILE_NAME: &str = "autmc-deep-link.port";
+const FORWARD_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// A modpack the user asked to install from outside the launcher.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "source", rename_all = "camelCase")]
+pub enum ImportRequest {
+    /// `curseforge://install?addonId=<project id>&fileId=<file id>`
+    CurseForge {
+        #[serde(rename = "projectId")]
+        project_id: u32,
+        #[serde(rename = "fileId")]
+        file_id: u32,
+    },
+    /// `modrinth://modpack/<id or slug>[/version/<version id>]`, also accepted with `web+modrinth:`.
+    Modrinth {
+        project: String,
+        version: Option<String>,
+    },
+    /// A `.mrpack` file opened with the launcher.
+    Mrpack { path: PathBuf },
+}
+
+/// Holds the import request the launcher was started with until the frontend is ready to handle it.
+#[derive(Debug, Default)]
+pub struct PendingImportState(pub Arc<Mutex<Option<ImportRequest>>>);
+
+impl PendingImportState {
+    pub fn new(request: Option<ImportRequest>) -> Self {
+        Self(Arc::new(Mutex::new(request)))
+    }
+}
+
+/// Finds the first launch argument that is a deep link or a modpack file, along with the argument.
+pub fn import_request_from_args() -> Option<(String, ImportRequest)> {
+    env::args()
+        .skip(1)
+        .find_map(|arg| parse_import_request(&arg).map(|request| (arg, request)))
+}
+
+pub fn parse_import_request(arg: &str) -> Option<ImportRequest> {
+    let path = Path::new(arg);
+    if path.extension() == Some(MRPACK_EXTENSION.as_ref()) && path.is_file() {
+        return Some(ImportRequest::Mrpack {
+            path: path.canonicalize().ok()?,
+        });
+    }
+
+    let url = Url::parse(arg).ok()?;
+    match url.scheme() {
+        "curseforge" => parse_curseforge_link(&url),
+        "modrinth" | "web+modrinth" => parse_modrinth_link(&url),
+        _ => None,
+    }
+}
+
+fn parse_curseforge_link(url: &Url) -> Option<ImportRequest> {
+    if url.host_str() != Some("install") {
+        return None;
+    }
+    let query_value = |key: &str| {
+        url.query_pairs()
+            .find(|(name, _)| name == key)
+            .and_then(|(_, value)| value.parse().ok())
+    };
+    Some(ImportRequest::CurseForge {
+        project_id: query_value("addonId")?,
+        file_id: query_value("fileId")?,
+    })
+}
+
+fn parse_modrinth_link(url: &Url) -> Option<ImportRequest> {
+    // `modrinth://modpack/x` puts the project type in the host, `web+modrinth:modpack/x` in the path.
+    let path = format!("{}{}", url.host_str().unwrap_or_default(), url.path());
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    let (project, version) = match segments.as_slice() {
+        ["modpack", project] => (*project, None),
+        ["modpack", project, "version", version] => (*project, Some(*version)),
+        _ => return None,
+    };
+    // The ids end up in modrinth api urls, only allow what modrinth allows in ids and slugs.
+    if !is_modrinth_id(project) || !version.map(is_modrinth_id).unwrap_or(true) {
+        return None;
+    }
+    Some(ImportRequest::Modrinth {
+        project: project.into(),
+        version: version.map(Into::into),
+    })
+}
+
+fn is_modrinth_id(id: &str) -> bool {
+    !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        && id != "."
+        && id != ".."
+}
+
+/// The argument as it is forwarded, modpack files by their absolute path since the running launcher
+/// has another working directory.
+fn forwarded_argument(arg: &str, request: &ImportRequest) -> String {
+    match request {
+        ImportRequest::Mrpack { path } => path.to_string_lossy().into_owned(),
+        _ => arg.into(),
+    }
+}
+
+/// Hands the argument to an already running launcher so only one launcher manages the instances,
+/// it is parsed again there. Returns false if no launcher is running, in which case this one should
+/// handle the request.
+pub fn forward_to_running_launcher(app_dir: &Path, arg: &str, request: &ImportRequest) -> bool {
+    let port = match fs::read_to_string(app_dir.join(PORT_FILE_NAME))
+        .ok()
+        .and_then(|port| port.trim().parse::<u16>().ok())
+    {
+        Some(port) => port,
+        None => return false,
+    };
+    let address = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+    let forwarded = TcpStream::connect_timeout(&address, FORWARD_TIMEOUT)
+        .and_then(|mut stream| stream.write_all(forwarded_argument(arg, request).as_bytes()));
+    match forwarded {
+        Ok(_) => {
+            info!("Forwarded {:?} to the running launcher", request);
+            true
+        }
+        // The port file is left over from a launcher that didn't shut down cleanly.
+        Err(error) => {
+            debug!("No launcher is listening on port {}: {}", port, error);
+            false
+        }
+    }
+}
+
+/// Listens on localhost for arguments forwarded by launchers started after this one. Anything
+/// connecting can only ask the frontend to start an import, which the user still has to confirm, and
+/// only with an argument that parses like a launch argument.
+pub fn listen_for_forwarded_requests<F>(app_dir: &Path, on_request: F) -> io::Result<()>
+where
+    F: Fn(ImportRequest) + Send + 'static,
+{
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+    fs::write(
+        app_dir.join(PORT_FILE_NAME),
+        listener.local_addr()?.port().to_string(),
+    )?;
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut contents = String::new();
+            let read = stream.and_then(|stream| {
+                stream.set_read_timeout(Some(FORWARD_TIMEOUT))?;
+                stream.take(u16::MAX as u64).read_to_string(&mut contents)
+            });
+            if let Err(error) = read {
+                warn!("Could not read forwarded import request: {}", error);
+                continue;
+            }
+            match parse_import_request(contents.trim()) {
+                Some(request) => on_request(request),
+                None => warn!("Ignoring invalid import request: {:?}", contents),
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Registers the launcher as the handler for `URL_SCHEMES` and `.mrpack` files for the current user.
+/// macOS only reads url schemes and document types from the bundle's Info.plist, so there is nothing to do
+/// at runtime there.
+pub fn register_handlers() -> io::Result<()> {
+    let executable = env::current_exe()?;
+    register_handlers_for(&executable)
+}
+
+#[cfg(target_os = "windows")]
+fn register_handlers_for(executable: &Path) -> io::Result<()> {
+    let open_command = format!("\"{}\" \"%1\"", executable.display());
+    let classes = r"HKCU\Software\Classes";
+    for scheme in URL_SCHEMES {
+        let key = format!(r"{}\{}", classes, scheme);
+        reg_add(&key, None, &format!("URL:{}", scheme))?;
+        reg_add(&key, Some("URL Protocol"), "")?;
+        reg_add(&format!(r"{}\shell\open\command", key), None, &open_command)?;
+    }
+    let file_type = "Autmc.mrpack";
+    reg_add(
+        &format!(r"{}\.{}", classes, MRPACK_EXTENSION),
+        None,
+        file_type,
+    )?;
+    reg_add(
+        &format!(r"{}\{}", classes, file_type),
+        None,
+        "Modrinth Modpack",
+    )?;
+    reg_add(
+        &format!(r"{}\{}\shell\open\command", classes, file_type),
+        None,
+        &open_command,
+    )
+}
+
+#[cfg(target_os = "windows")]
+fn reg_add(key: &str, value_name: Option<&str>, data: &str) -> io::Result<()> {
+    use std::process::Command;
+
+    let mut command = Command::new("reg");
+    command.args(["add", key, "/f", "/t", "REG_SZ", "/d", data]);
+    match value_name {
+        Some(value_name) => command.args(["/v", value_name]),
+        None => command.arg("/ve"),
+    };
+    let status = command.status()?;
+    if !status.success() {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!("reg add {} failed with {}", key, status),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+fn register_handlers_for(_executable: &Path) -> io::Result<()> {
+    debug!("Url schemes are registered through the app bundle on macOS");
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn register_handlers_for(executable: &Path) -> io::Result<()> {
+    use std::process::Command;
+
+    const DESKTOP_FILE_NAME: &str = "autmc-deep-link.desktop";
+    const MRPACK_MIME_TYPE: &str = "application/x-modrinth-modpack+zip";
+
+    let applications_dir = env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No data home directory"))?
+        .join("applications");
+    fs::create_dir_all(&applications_dir)?;
+
+    let mime_types: Vec<String> = URL_SCHEMES
+        .iter()
+        .map(|scheme| format!("x-scheme-handler/{}", scheme))
+        .chain(std::iter::once(MRPACK_MIME_TYPE.to_string()))
+        .collect();
+    let desktop_entry = format!(
+        "[Desktop Entry]\nType=Application\nName=Autmc\nExec=\"{}\" %u\nTerminal=false\nNoDisplay=true\nMimeType={};\n",
+        executable.display(),
+        mime_types.join(";")
+    );
+    fs::write(applications_dir.join(DESKTOP_FILE_NAME), desktop_entry)?;
+
+    let status = Command::new("xdg-mime")
+        .arg("default")
+        .arg(DESKTOP_FILE_NAME)
+        .args(&mime_types)
+        .status()?;
+    if !status.success() {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!("xdg-mime failed with {}", status),
+        ));
+    }
+    Ok(())
+}
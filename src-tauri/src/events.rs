@@ -0,0 +1,229 @@
+use std::{path::PathBuf, time::Duration};
+
+use log::warn;
+use serde::Serialize;
+use tauri::{AppHandle, Manager, Wry};
+
+use crate::{
+    deep_link::ImportRequest,
+    i18n::tr,
+    instance::{
+        fatal_errors::JvmFatalError,
+        logs::{LogLine, LogTailPayload},
+        watcher::ContentChangedPayload,
+    },
+    state::account_manager::AccountStatus,
+};
+
+/// Every event the backend sends to the frontend. Each variant is emitted under its own event name
+/// with only its payload, so listeners get the typed payload directly.
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum LauncherEvent {
+    DownloadProgress(DownloadProgress),
+    InstanceInstallPhase(InstanceInstallPhase),
+    GameExited(GameExited),
+    AccountRefreshed(AccountRefreshed),
+    AccountStatusChanged(AccountStatusChanged),
+    /// Logging in with a Microsoft account failed, contains the message to show the user.
+    LoginFailed(String),
+    /// A log record of the running game, parsed from its stdout.
+    InstanceLogging(LogLine),
+    InstanceLogTail(LogTailPayload),
+    /// Name of the instance that was deleted.
+    InstanceDeleted(String),
+    /// Files were added to or removed from a watched content folder of an instance.
+    InstanceContentChanged(ContentChangedPayload),
+    /// A deep link or modpack file forwarded by a second launcher process.
+    ImportRequested(ImportRequest),
+    JavaFallback(JavaFallback),
+}
+
+impl LauncherEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            LauncherEvent::DownloadProgress(_) => "download-progress",
+            LauncherEvent::InstanceInstallPhase(_) => "instance-install-phase",
+            LauncherEvent::GameExited(_) => "game-exited",
+            LauncherEvent::AccountRefreshed(_) => "account-refreshed",
+            LauncherEvent::AccountStatusChanged(_) => "account-status",
+            LauncherEvent::LoginFailed(_) => "login-failed",
+            LauncherEvent::InstanceLogging(_) => "instance-logging",
+            LauncherEvent::InstanceLogTail(_) => "instance-log-tail",
+            LauncherEvent::InstanceDeleted(_) => "instance-deleted",
+            LauncherEvent::InstanceContentChanged(_) => "instance-content-changed",
+            LauncherEvent::ImportRequested(_) => "import-requested",
+            LauncherEvent::JavaFallback(_) => "java-fallback",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DownloadKind {
+    Libraries,
+    Java,
+    Assets,
+    /// Entries of the native library jars being extracted into the instance.
+    Natives,
+    /// Files of a modpack update being extracted and written into the instance.
+    ModpackFiles,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DownloadProgress {
+    #[serde(rename = "instanceName")]
+    pub instance_name: String,
+    pub kind: DownloadKind,
+    pub completed: usize,
+    pub total: usize,
+    /// Estimated seconds until the phase finishes, unknown until some progress was made.
+    #[serde(rename = "etaSeconds")]
+    pub eta_seconds: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum InstallPhase {
+    VersionManifest,
+    Libraries,
+    GameJar,
+    Java,
+    LoggingConfiguration,
+    Assets,
+    Natives,
+    Finished,
+}
+
+impl InstallPhase {
+    /// Number of phases before `Finished`, so the frontend can show e.g. "step 3 of 7".
+    pub const STEPS: usize = 7;
+
+    /// 1 based position of the phase in an install.
+    pub fn step(self) -> usize {
+        match self {
+            InstallPhase::VersionManifest => 1,
+            InstallPhase::Libraries => 2,
+            InstallPhase::GameJar => 3,
+            InstallPhase::Java => 4,
+            InstallPhase::LoggingConfiguration => 5,
+            InstallPhase::Assets => 6,
+            InstallPhase::Natives => 7,
+            InstallPhase::Finished => Self::STEPS,
+        }
+    }
+
+    /// The name of the phase shown to users, in the current locale.
+    pub fn label(self) -> String {
+        let key = match self {
+            InstallPhase::VersionManifest => "phase.versionManifest",
+            InstallPhase::Libraries => "phase.libraries",
+            InstallPhase::GameJar => "phase.gameJar",
+            InstallPhase::Java => "phase.java",
+            InstallPhase::LoggingConfiguration => "phase.loggingConfiguration",
+            InstallPhase::Assets => "phase.assets",
+            InstallPhase::Natives => "phase.natives",
+            InstallPhase::Finished => "phase.finished",
+        };
+        tr(key, &[])
+    }
+}
+
+/// Estimates how long the rest of a phase takes, assuming the remaining items go as fast as the
+/// `completed` ones did in `elapsed`.
+pub fn estimate_remaining(elapsed: Duration, completed: usize, total: usize) -> Option<Duration> {
+    if completed == 0 || completed > total {
+        return None;
+    }
+    Some(elapsed.mul_f64((total - completed) as f64 / completed as f64))
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InstanceInstallPhase {
+    #[serde(rename = "instanceName")]
+    pub instance_name: String,
+    pub phase: InstallPhase,
+    pub label: String,
+    pub step: usize,
+    pub steps: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GameExited {
+    #[serde(rename = "instanceName")]
+    pub instance_name: String,
+    /// None when the game was killed by a signal.
+    #[serde(rename = "exitCode")]
+    pub exit_code: Option<i32>,
+    pub success: bool,
+    /// Summary of the `hs_err_pid*.log` the JVM wrote if it crashed itself, rather than the game.
+    #[serde(rename = "fatalError")]
+    pub fatal_error: Option<JvmFatalError>,
+}
+
+/// The selected java couldn't run an instance, it was launched with the runtime of its version instead.
+#[derive(Debug, Clone, Serialize)]
+pub struct JavaFallback {
+    #[serde(rename = "instanceName")]
+    pub instance_name: String,
+    /// Why the selected java wasn't used.
+    pub reason: String,
+    #[serde(rename = "javaPath")]
+    pub java_path: PathBuf,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AccountRefreshed {
+    pub uuid: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AccountStatusChanged {
+    pub uuid: String,
+    pub name: String,
+    pub status: AccountStatus,
+}
+
+/// Sends `LauncherEvent`s to every window.
+#[derive(Clone)]
+pub struct EventBus(AppHandle<Wry>);
+
+impl EventBus {
+    pub fn new(app_handle: AppHandle<Wry>) -> Self {
+        Self(app_handle)
+    }
+
+    pub fn emit(&self, event: LauncherEvent) {
+        if let Err(error) = self.0.emit_all(event.name(), &event) {
+            warn!("Could not emit {} event: {}", event.name(), error);
+        }
+    }
+
+    pub fn install_phase(&self, instance_name: &str, phase: InstallPhase) {
+        self.emit(LauncherEvent::InstanceInstallPhase(InstanceInstallPhase {
+            instance_name: instance_name.into(),
+            phase,
+            label: phase.label(),
+            step: phase.step(),
+            steps: InstallPhase::STEPS,
+        }));
+    }
+
+    pub fn download_progress(
+        &self,
+        instance_name: &str,
+        kind: DownloadKind,
+        completed: usize,
+        total: usize,
+        eta: Option<Duration>,
+    ) {
+        self.emit(LauncherEvent::DownloadProgress(DownloadProgress {
+            instance_name: instance_name.into(),
+            kind,
+            completed,
+            total,
+            eta_seconds: eta.map(|eta| eta.as_secs()),
+        }));
+    }
+}
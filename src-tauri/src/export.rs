@@ -0,0 +1,38 @@
+//! Exporters that write our instances as archives, for backups or for other launchers to import,
+//! and as scripts that launch them without the launcher.
+
+pub mod archive;
+pub mod launch_script;
+pub mod mrpack;
+pub mod multimc;
+
+use std::{
+    fs::{self, File},
+    io,
+    path::Path,
+};
+
+use zip::{write::FileOptions, ZipWriter};
+
+use crate::state::instance_manager::InstanceResult;
+
+/// Recursively adds a file or directory to the archive under `name`.
+pub fn add_entry(
+    zip: &mut ZipWriter<File>,
+    path: &Path,
+    name: &str,
+    options: FileOptions,
+) -> InstanceResult<()> {
+    if path.is_dir() {
+        zip.add_directory(name, options)?;
+        for entry in fs::read_dir(path)? {
+            let entry = entry?;
+            let child_name = format!("{}/{}", name, entry.file_name().to_string_lossy());
+            add_entry(zip, &entry.path(), &child_name, options)?;
+        }
+        return Ok(());
+    }
+    zip.start_file(name, options)?;
+    io::copy(&mut File::open(path)?, zip)?;
+    Ok(())
+}
@@ -0,0 +1,136 @@
+use std::{
+    fs::{self, File},
+    io::{self, Write},
+    path::Path,
+};
+
+use log::info;
+use serde::{Deserialize, Serialize};
+use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+use crate::{
+    consts::LAUNCHER_VERSION,
+    state::instance_manager::{InstanceConfiguration, InstanceResult},
+};
+
+use super::add_entry;
+
+/// Describes the archive, written next to the instance's files.
+const MANIFEST_FILE: &str = "autmc-archive.json";
+const ARCHIVE_FORMAT_VERSION: u32 = 1;
+
+/// The parts of an instance to put in an archive. The instance's configuration is always included.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ArchiveComponents {
+    #[serde(default)]
+    pub mods: bool,
+    /// Mod configuration files in `config/`.
+    #[serde(default)]
+    pub config: bool,
+    #[serde(default)]
+    pub saves: bool,
+    /// Game options and the server list.
+    #[serde(default)]
+    pub options: bool,
+    #[serde(rename = "resourcePacks", default)]
+    pub resource_packs: bool,
+    #[serde(rename = "shaderPacks", default)]
+    pub shader_packs: bool,
+    #[serde(default)]
+    pub screenshots: bool,
+}
+
+impl ArchiveComponents {
+    /// Names of the files and folders in an instance directory that the components select.
+    pub fn entries(&self) -> Vec<&'static str> {
+        let mut entries = Vec::new();
+        if self.mods {
+            entries.push("mods");
+        }
+        if self.config {
+            entries.push("config");
+        }
+        if self.saves {
+            entries.push("saves");
+        }
+        if self.options {
+            entries.extend(["options.txt", "optionsof.txt", "servers.dat"]);
+        }
+        if self.resource_packs {
+            entries.push("resourcepacks");
+        }
+        if self.shader_packs {
+            entries.push("shaderpacks");
+        }
+        if self.screenshots {
+            entries.push("screenshots");
+        }
+        entries
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ArchiveManifest<'a> {
+    #[serde(rename = "formatVersion")]
+    format_version: u32,
+    #[serde(rename = "launcherVersion")]
+    launcher_version: &'a str,
+    #[serde(rename = "instanceName")]
+    instance_name: &'a str,
+    #[serde(rename = "versionId")]
+    version_id: &'a str,
+    components: &'a ArchiveComponents,
+}
+
+/// Writes a zip of the instance for backups or moving it to another machine: the instance's
+/// `config.json`, an `autmc-archive.json` describing the archive and the selected components.
+pub fn export_instance(
+    config: &InstanceConfiguration,
+    instance_dir: &Path,
+    components: &ArchiveComponents,
+    destination: &Path,
+) -> InstanceResult<()> {
+    let result = write_archive(config, instance_dir, components, destination);
+    // Don't leave a partial archive behind.
+    if result.is_err() {
+        let _ = fs::remove_file(destination);
+    }
+    result?;
+    info!(
+        "Exported instance {} archive to {}",
+        config.instance_name,
+        destination.display()
+    );
+    Ok(())
+}
+
+fn write_archive(
+    config: &InstanceConfiguration,
+    instance_dir: &Path,
+    components: &ArchiveComponents,
+    destination: &Path,
+) -> InstanceResult<()> {
+    let mut zip = ZipWriter::new(File::create(destination)?);
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    let manifest = ArchiveManifest {
+        format_version: ARCHIVE_FORMAT_VERSION,
+        launcher_version: LAUNCHER_VERSION,
+        instance_name: &config.instance_name,
+        version_id: &config.version_id,
+        components,
+    };
+    zip.start_file(MANIFEST_FILE, options)?;
+    zip.write_all(&serde_json::to_vec_pretty(&manifest).map_err(io::Error::from)?)?;
+    zip.start_file("config.json", options)?;
+    zip.write_all(&serde_json::to_vec_pretty(config).map_err(io::Error::from)?)?;
+
+    for entry in components.entries() {
+        let path = instance_dir.join(entry);
+        if path.exists() {
+            add_entry(&mut zip, &path, entry, options)?;
+        }
+    }
+    zip.finish()?;
+    Ok(())
+}
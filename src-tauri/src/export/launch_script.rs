@@ -0,0 +1,156 @@
+use std::{fs, path::Path};
+
+use log::info;
+use serde::Deserialize;
+
+use crate::{
+    consts::LAUNCHER_NAME,
+    instance::launch_preview::LaunchCommand,
+    state::{account_manager::Account, instance_manager::InstanceResult},
+};
+
+/// The environment variable a script reads the access token from, tokens expire and shouldn't be
+/// kept in a file.
+const ACCESS_TOKEN_VARIABLE: &str = "MC_ACCESS_TOKEN";
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ScriptKind {
+    /// A `sh` script for linux and macos.
+    Shell,
+    /// A `.bat` file for windows.
+    Batch,
+}
+
+impl Default for ScriptKind {
+    fn default() -> Self {
+        if cfg!(target_os = "windows") {
+            ScriptKind::Batch
+        } else {
+            ScriptKind::Shell
+        }
+    }
+}
+
+/// Writes a script to `destination` that runs `command` from the instance directory. Offline accounts
+/// keep their placeholder token, other accounts need the token in `MC_ACCESS_TOKEN`.
+pub fn export_launch_script(
+    command: &LaunchCommand,
+    account: &Account,
+    instance_name: &str,
+    kind: ScriptKind,
+    destination: &Path,
+) -> InstanceResult<()> {
+    let needs_token = !account.offline;
+    let script = match kind {
+        ScriptKind::Shell => shell_script(command, account, instance_name, needs_token),
+        ScriptKind::Batch => batch_script(command, account, instance_name, needs_token),
+    };
+    fs::write(destination, script)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::prelude::PermissionsExt;
+
+        let mut permissions = fs::metadata(destination)?.permissions();
+        permissions.set_mode(0o755);
+        fs::set_permissions(destination, permissions)?;
+    }
+    info!(
+        "Exported a launch script for {} to {}",
+        instance_name,
+        destination.display()
+    );
+    Ok(())
+}
+
+fn shell_script(
+    command: &LaunchCommand,
+    account: &Account,
+    instance_name: &str,
+    needs_token: bool,
+) -> String {
+    let mut script = String::from("#!/bin/sh\n");
+    script.push_str(&format!(
+        "# Launches {} as {} without {}.\n",
+        instance_name, account.name, LAUNCHER_NAME
+    ));
+    if needs_token {
+        script.push_str(&format!(
+            "# Set {} to a current access token of the account before running it.\n",
+            ACCESS_TOKEN_VARIABLE
+        ));
+        script.push_str(&format!(
+            ": \"${{{0}:?Set {0} to the access token of {1}}}\"\n",
+            ACCESS_TOKEN_VARIABLE, account.name
+        ));
+    }
+    script.push_str(&format!(
+        "cd {} || exit 1\n",
+        shell_quote(&command.working_dir.to_string_lossy())
+    ));
+    script.push_str(&format!(
+        "exec {}",
+        shell_quote(&command.java.to_string_lossy())
+    ));
+    for (index, argument) in command.arguments.iter().enumerate() {
+        let argument = if needs_token && command.is_access_token(index) {
+            format!("\"${}\"", ACCESS_TOKEN_VARIABLE)
+        } else {
+            shell_quote(argument)
+        };
+        script.push_str(" \\\n  ");
+        script.push_str(&argument);
+    }
+    script.push('\n');
+    script
+}
+
+fn batch_script(
+    command: &LaunchCommand,
+    account: &Account,
+    instance_name: &str,
+    needs_token: bool,
+) -> String {
+    let mut script = String::from("@echo off\r\n");
+    script.push_str(&format!(
+        "rem Launches {} as {} without {}.\r\n",
+        instance_name, account.name, LAUNCHER_NAME
+    ));
+    if needs_token {
+        script.push_str(&format!(
+            "rem Set {} to a current access token of the account before running it.\r\n",
+            ACCESS_TOKEN_VARIABLE
+        ));
+        script.push_str(&format!(
+            "if \"%{0}%\"==\"\" (\r\n  echo Set {0} to the access token of {1}\r\n  exit /b 1\r\n)\r\n",
+            ACCESS_TOKEN_VARIABLE, account.name
+        ));
+    }
+    script.push_str(&format!(
+        "cd /d {}\r\n",
+        batch_quote(&command.working_dir.to_string_lossy())
+    ));
+    script.push_str(&batch_quote(&command.java.to_string_lossy()));
+    for (index, argument) in command.arguments.iter().enumerate() {
+        let argument = if needs_token && command.is_access_token(index) {
+            format!("\"%{}%\"", ACCESS_TOKEN_VARIABLE)
+        } else {
+            batch_quote(argument)
+        };
+        script.push_str(" ^\r\n  ");
+        script.push_str(&argument);
+    }
+    script.push_str("\r\n");
+    script
+}
+
+/// Single quotes keep everything literal in `sh`, a single quote itself has to end the quoting.
+fn shell_quote(argument: &str) -> String {
+    format!("'{}'", argument.replace('\'', "'\\''"))
+}
+
+/// Double quotes keep spaces and special characters literal in `cmd`, percent signs still have to
+/// be doubled so they aren't expanded as variables.
+fn batch_quote(argument: &str) -> String {
+    format!("\"{}\"", argument.replace('%', "%%").replace('"', "\"\""))
+}
@@ -0,0 +1,187 @@
+//! Exports instances as Modrinth packs (`.mrpack`). Files Modrinth hosts are only referenced in the
+//! pack's index, everything else, like configs, scripts or mods from other sites, is put into the
+//! pack's `overrides` folder.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::{self, File},
+    io::{self, Write},
+    path::Path,
+};
+
+use log::{info, warn};
+use serde::Serialize;
+use serde_json::{json, Value};
+use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+use crate::{
+    instance::modpack::ModpackState,
+    state::instance_manager::{InstanceConfiguration, InstanceResult},
+    web_services::{downloader::run_blocking, hash_cache, modrinth::versions_from_hashes},
+};
+
+use super::{add_entry, archive::ArchiveComponents};
+
+const MRPACK_INDEX: &str = "modrinth.index.json";
+const OVERRIDES_DIR: &str = "overrides";
+/// Folders with files Modrinth may host, files anywhere else are always overrides.
+const DOWNLOADABLE_DIRS: [&str; 3] = ["mods", "resourcepacks", "shaderpacks"];
+
+/// Where the exported files ended up, paths are relative to the instance directory.
+#[derive(Debug, Default, Serialize)]
+pub struct MrpackExportReport {
+    /// Files the pack downloads from Modrinth.
+    pub downloads: Vec<String>,
+    /// Files copied into the pack's `overrides` folder.
+    pub overrides: Vec<String>,
+}
+
+/// Writes the instance as a `.mrpack` with the files the components select. Without a connection to
+/// Modrinth every file becomes an override.
+pub async fn export_instance(
+    config: &InstanceConfiguration,
+    instance_dir: &Path,
+    components: &ArchiveComponents,
+    name: &str,
+    pack_version: &str,
+    destination: &Path,
+) -> InstanceResult<MrpackExportReport> {
+    let (files, hashes) = {
+        let instance_dir = instance_dir.to_path_buf();
+        let entries = components.entries();
+        run_blocking(move || collect_files(&instance_dir, &entries)).await??
+    };
+
+    let sha1s: Vec<String> = hashes.values().cloned().collect();
+    let versions = match versions_from_hashes(&sha1s).await {
+        Ok(versions) => versions,
+        Err(error) => {
+            warn!(
+                "Could not look up the exported files on Modrinth: {}",
+                error
+            );
+            Default::default()
+        }
+    };
+
+    let mut index_files = Vec::new();
+    let mut downloadable = BTreeSet::new();
+    for (path, sha1) in &hashes {
+        let file = versions
+            .get(sha1)
+            .and_then(|version| version.files.iter().find(|file| file.hashes.sha1 == *sha1));
+        // The index needs both hashes, files Modrinth didn't give a sha512 for are overrides.
+        let (url, sha512) =
+            match file.and_then(|file| Some((&file.url, file.hashes.sha512.as_ref()?))) {
+                Some(file) => file,
+                None => continue,
+            };
+        index_files.push(json!({
+            "path": path,
+            "hashes": { "sha1": sha1, "sha512": sha512 },
+            "env": { "client": "required", "server": "required" },
+            "downloads": [url],
+            "fileSize": fs::metadata(instance_dir.join(path))?.len(),
+        }));
+        downloadable.insert(path.clone());
+    }
+
+    let mut dependencies = BTreeMap::new();
+    // Mod loaders are only known for instances that were installed from a pack.
+    if let Ok(modpack) = ModpackState::load(instance_dir) {
+        dependencies.extend(modpack.dependencies);
+    }
+    dependencies.insert("minecraft".to_string(), config.version_id.clone());
+    let index = json!({
+        "formatVersion": 1,
+        "game": "minecraft",
+        "versionId": pack_version,
+        "name": name,
+        "files": index_files,
+        "dependencies": dependencies,
+    });
+
+    let (downloads, overrides): (Vec<String>, Vec<String>) = files
+        .into_iter()
+        .partition(|path| downloadable.contains(path));
+    let result = {
+        let instance_dir = instance_dir.to_path_buf();
+        let destination = destination.to_path_buf();
+        let overrides = overrides.clone();
+        run_blocking(move || write_pack(&instance_dir, &index, &overrides, &destination)).await?
+    };
+    // Don't leave a partial pack behind.
+    if result.is_err() {
+        let _ = fs::remove_file(destination);
+    }
+    result?;
+    info!(
+        "Exported instance {} as a Modrinth pack to {} with {} downloads and {} overrides",
+        config.instance_name,
+        destination.display(),
+        downloads.len(),
+        overrides.len()
+    );
+    Ok(MrpackExportReport {
+        downloads,
+        overrides,
+    })
+}
+
+/// The files under the selected entries and the sha1s of the ones Modrinth may host.
+fn collect_files(
+    instance_dir: &Path,
+    entries: &[&str],
+) -> io::Result<(Vec<String>, BTreeMap<String, String>)> {
+    let mut files = Vec::new();
+    for entry in entries {
+        let path = instance_dir.join(entry);
+        if path.exists() {
+            collect_entry(&path, entry.to_string(), &mut files)?;
+        }
+    }
+
+    let mut hashes = BTreeMap::new();
+    for file in &files {
+        let top_level = file.split('/').next().unwrap_or_default();
+        if DOWNLOADABLE_DIRS.contains(&top_level) {
+            hashes.insert(
+                file.clone(),
+                hash_cache::file_sha1(&instance_dir.join(file))?,
+            );
+        }
+    }
+    Ok((files, hashes))
+}
+
+fn collect_entry(path: &Path, name: String, files: &mut Vec<String>) -> io::Result<()> {
+    if !path.is_dir() {
+        files.push(name);
+        return Ok(());
+    }
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let child_name = format!("{}/{}", name, entry.file_name().to_string_lossy());
+        collect_entry(&entry.path(), child_name, files)?;
+    }
+    Ok(())
+}
+
+fn write_pack(
+    instance_dir: &Path,
+    index: &Value,
+    overrides: &[String],
+    destination: &Path,
+) -> InstanceResult<()> {
+    let mut zip = ZipWriter::new(File::create(destination)?);
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    zip.start_file(MRPACK_INDEX, options)?;
+    zip.write_all(&serde_json::to_vec_pretty(index).map_err(io::Error::from)?)?;
+    for file in overrides {
+        let name = format!("{}/{}", OVERRIDES_DIR, file);
+        add_entry(&mut zip, &instance_dir.join(file), &name, options)?;
+    }
+    zip.finish()?;
+    Ok(())
+}
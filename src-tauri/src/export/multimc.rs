@@ -0,0 +1,164 @@
+use std::{
+    fs::{self, File},
+    io::Write,
+    path::Path,
+};
+
+use log::info;
+use serde_json::json;
+use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+use crate::{
+    instance::config_files::CONFIG_BACKUPS_DIR,
+    state::{
+        instance_manager::{InstanceConfiguration, InstanceResult},
+        settings_manager::LaunchSettings,
+    },
+};
+
+use super::add_entry;
+
+/// Top level instance entries that only make sense to this launcher or to previous runs.
+const EXCLUDED_ENTRIES: [&str; 6] = [
+    "config.json",
+    "config.json.bak",
+    "natives",
+    "logs",
+    "crash-reports",
+    CONFIG_BACKUPS_DIR,
+];
+/// MultiMC keeps the game directory in a `.minecraft` folder next to its instance files.
+const GAME_DIR: &str = ".minecraft";
+
+/// Writes a zip that MultiMC and Prism can import: an `instance.cfg` and `mmc-pack.json` next to a
+/// `.minecraft` folder holding the instance's files. Only the instance's own settings are exported,
+/// settings inherited from the global settings are left to the other launcher's defaults.
+pub fn export_instance(
+    config: &InstanceConfiguration,
+    instance_dir: &Path,
+    destination: &Path,
+) -> InstanceResult<()> {
+    let result = write_archive(config, instance_dir, destination);
+    // Don't leave a partial archive behind.
+    if result.is_err() {
+        let _ = fs::remove_file(destination);
+    }
+    result?;
+    info!(
+        "Exported instance {} to {}",
+        config.instance_name,
+        destination.display()
+    );
+    Ok(())
+}
+
+fn write_archive(
+    config: &InstanceConfiguration,
+    instance_dir: &Path,
+    destination: &Path,
+) -> InstanceResult<()> {
+    let mut zip = ZipWriter::new(File::create(destination)?);
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    zip.start_file("instance.cfg", options)?;
+    zip.write_all(instance_cfg(config).as_bytes())?;
+    zip.start_file("mmc-pack.json", options)?;
+    zip.write_all(mmc_pack(&config.version_id).as_bytes())?;
+
+    for entry in fs::read_dir(instance_dir)? {
+        let entry = entry?;
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+        if EXCLUDED_ENTRIES.contains(&file_name.as_str()) {
+            continue;
+        }
+        add_entry(
+            &mut zip,
+            &entry.path(),
+            &format!("{}/{}", GAME_DIR, file_name),
+            options,
+        )?;
+    }
+    zip.finish()?;
+    Ok(())
+}
+
+fn mmc_pack(version_id: &str) -> String {
+    // MultiMC resolves the LWJGL component and everything else the version needs by itself.
+    let pack = json!({
+        "formatVersion": 1,
+        "components": [
+            {
+                "uid": "net.minecraft",
+                "version": version_id,
+                "important": true,
+            }
+        ],
+    });
+    pack.to_string()
+}
+
+fn instance_cfg(config: &InstanceConfiguration) -> String {
+    let mut lines = vec![
+        "InstanceType=OneSix".to_string(),
+        format!("name={}", escape_ini(&config.instance_name)),
+        format!("notes={}", escape_ini(&config.notes)),
+        "iconKey=default".to_string(),
+    ];
+    if let Some(last_played) = config.last_played {
+        // MultiMC stores milliseconds.
+        lines.push(format!("lastLaunchTime={}", last_played * 1000));
+    }
+    lines.extend(settings_lines(&config.settings));
+    let mut cfg = lines.join("\n");
+    cfg.push('\n');
+    cfg
+}
+
+/// The MultiMC overrides for the settings an instance sets itself.
+fn settings_lines(settings: &LaunchSettings) -> Vec<String> {
+    let mut lines = Vec::new();
+    if settings.max_memory.is_some() || settings.min_memory.is_some() {
+        lines.push("OverrideMemory=true".to_string());
+        if let Some(max_memory) = settings.max_memory {
+            lines.push(format!("MaxMemAlloc={}", max_memory));
+        }
+        if let Some(min_memory) = settings.min_memory {
+            lines.push(format!("MinMemAlloc={}", min_memory));
+        }
+    }
+
+    let mut java_arguments: Vec<&str> = settings
+        .gc_preset
+        .map(|preset| preset.arguments(None))
+        .unwrap_or_default()
+        .to_vec();
+    if let Some(arguments) = &settings.java_arguments {
+        java_arguments.extend(arguments.iter().map(String::as_str));
+    }
+    if !java_arguments.is_empty() {
+        lines.push("OverrideJavaArgs=true".to_string());
+        lines.push(format!("JvmArgs={}", escape_ini(&java_arguments.join(" "))));
+    }
+
+    if let Some(resolution) = &settings.resolution {
+        lines.push("OverrideWindow=true".to_string());
+        lines.push(format!("MinecraftWinWidth={}", resolution.width));
+        lines.push(format!("MinecraftWinHeight={}", resolution.height));
+    }
+    lines
+}
+
+/// Escapes a value the way MultiMC's ini parser expects, values are single lines.
+fn escape_ini(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,141 @@
+//! Checks run by the onboarding wizard the first time the launcher starts. They prepare the data
+//! directory and report anything that would make installing an instance fail later on.
+
+use std::{fs, io, path::Path, time::Duration};
+
+use log::{info, warn};
+use serde::Serialize;
+
+use crate::{
+    consts::VANILLA_MANIFEST_URL,
+    state::resource_manager::ResourceManager,
+    system,
+    web_services::{mirrors, network},
+};
+
+/// Written to the data directory once the user finished the onboarding wizard.
+const FIRST_RUN_MARKER_FILE: &str = ".setup-complete";
+/// Roughly what a java runtime, the assets and a single modded instance take up, in megabytes.
+const MIN_FREE_DISK_SPACE: u64 = 2048;
+const NETWORK_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SetupStep {
+    Directories,
+    DiskSpace,
+    Network,
+    VersionManifest,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SetupCheck {
+    pub step: SetupStep,
+    pub passed: bool,
+    /// Why the check failed, or a note for the user when it passed with a caveat.
+    pub message: Option<String>,
+}
+
+impl SetupCheck {
+    fn passed(step: SetupStep) -> Self {
+        Self {
+            step,
+            passed: true,
+            message: None,
+        }
+    }
+
+    fn failed(step: SetupStep, message: String) -> Self {
+        warn!("First run check {:?} failed: {}", step, message);
+        Self {
+            step,
+            passed: false,
+            message: Some(message),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SetupReport {
+    #[serde(rename = "dataDir")]
+    pub data_dir: String,
+    /// Free space on the drive of the data directory in megabytes, None if it could not be detected.
+    #[serde(rename = "availableDiskSpace")]
+    pub available_disk_space: Option<u64>,
+    pub checks: Vec<SetupCheck>,
+}
+
+/// Whether the onboarding wizard still has to run for the data directory.
+pub fn is_first_run(app_dir: &Path) -> bool {
+    !app_dir.join(FIRST_RUN_MARKER_FILE).exists()
+}
+
+/// Remembers that the onboarding wizard finished, so it isn't shown again.
+pub fn complete_first_run(app_dir: &Path) -> io::Result<()> {
+    info!("First run setup completed");
+    fs::write(app_dir.join(FIRST_RUN_MARKER_FILE), b"")
+}
+
+/// Creates the directory layout, checks the disk space and network and downloads the version
+/// manifest. Every check runs even if an earlier one failed, so the wizard can list all problems.
+pub async fn run_setup_checks(resource_manager: &ResourceManager) -> SetupReport {
+    let data_dir = resource_manager.app_dir().to_path_buf();
+    let mut checks = Vec::new();
+
+    checks.push(match resource_manager.create_directories() {
+        Ok(_) => SetupCheck::passed(SetupStep::Directories),
+        Err(error) => SetupCheck::failed(
+            SetupStep::Directories,
+            format!("Could not create the data directory: {}", error),
+        ),
+    });
+
+    let available_disk_space = system::available_disk_space(&data_dir);
+    checks.push(match available_disk_space {
+        Some(available) if available < MIN_FREE_DISK_SPACE => SetupCheck::failed(
+            SetupStep::DiskSpace,
+            format!(
+                "Only {}MB are free, at least {}MB are recommended",
+                available, MIN_FREE_DISK_SPACE
+            ),
+        ),
+        Some(_) => SetupCheck::passed(SetupStep::DiskSpace),
+        None => SetupCheck {
+            step: SetupStep::DiskSpace,
+            passed: true,
+            message: Some("The free disk space could not be detected".into()),
+        },
+    });
+
+    let reachable = network::client()
+        .head(mirrors::mirrored(VANILLA_MANIFEST_URL))
+        .timeout(NETWORK_TIMEOUT)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status());
+    checks.push(match reachable {
+        Ok(_) => SetupCheck::passed(SetupStep::Network),
+        Err(error) => SetupCheck::failed(
+            SetupStep::Network,
+            format!("Could not reach the Minecraft servers: {}", error),
+        ),
+    });
+
+    checks.push(match resource_manager.download_manifests().await {
+        Ok(_) if resource_manager.is_offline() => SetupCheck::failed(
+            SetupStep::VersionManifest,
+            "Only an outdated version list is available".into(),
+        ),
+        Ok(_) => SetupCheck::passed(SetupStep::VersionManifest),
+        Err(error) => SetupCheck::failed(
+            SetupStep::VersionManifest,
+            format!("Could not download the version list: {}", error),
+        ),
+    });
+
+    SetupReport {
+        data_dir: data_dir.display().to_string(),
+        available_disk_space,
+        checks,
+    }
+}
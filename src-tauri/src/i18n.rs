@@ -0,0 +1,99 @@
+//! Translations of the messages the backend shows to users: errors, install phases and notifications.
+//! Messages are looked up by key in the catalogs in `src-tauri/locales`, which are compiled into the
+//! launcher. Keys missing from a catalog fall back to English, so a partial translation still works.
+
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    sync::{PoisonError, RwLock},
+};
+
+use log::{info, warn};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+
+pub const DEFAULT_LOCALE: &str = "en";
+
+/// <Locale, catalog json>
+const BUNDLED_LOCALES: [(&str, &str); 2] = [
+    ("en", include_str!("../locales/en.json")),
+    ("de", include_str!("../locales/de.json")),
+];
+
+// <Locale, <Message key, message>>
+static CATALOGS: Lazy<HashMap<&'static str, HashMap<String, String>>> = Lazy::new(|| {
+    BUNDLED_LOCALES
+        .iter()
+        .map(|(locale, json)| {
+            let catalog =
+                serde_json::from_str(json).expect("The bundled locales should be valid json");
+            (*locale, catalog)
+        })
+        .collect()
+});
+
+static LOCALE: Lazy<RwLock<String>> = Lazy::new(|| RwLock::new(DEFAULT_LOCALE.into()));
+
+#[derive(Debug, Serialize)]
+pub struct LocaleStatus {
+    pub locale: String,
+    pub available: Vec<&'static str>,
+}
+
+/// The locale in use and the ones that can be selected.
+pub fn locale_status() -> LocaleStatus {
+    LocaleStatus {
+        locale: current_locale(),
+        available: available_locales(),
+    }
+}
+
+/// The locales messages can be translated into.
+pub fn available_locales() -> Vec<&'static str> {
+    BUNDLED_LOCALES.iter().map(|(locale, _)| *locale).collect()
+}
+
+pub fn is_available(locale: &str) -> bool {
+    BUNDLED_LOCALES
+        .iter()
+        .any(|(bundled, _)| *bundled == locale)
+}
+
+/// Translates messages into `locale` from now on, unknown locales use English.
+pub fn set_locale(locale: &str) {
+    let locale = if is_available(locale) {
+        locale
+    } else {
+        warn!("No translations for {}, using {}", locale, DEFAULT_LOCALE);
+        DEFAULT_LOCALE
+    };
+    info!("Using locale {}", locale);
+    *LOCALE.write().unwrap_or_else(PoisonError::into_inner) = locale.into();
+}
+
+pub fn current_locale() -> String {
+    LOCALE
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+}
+
+/// The message for `key` in the current locale, with every `{name}` placeholder replaced by the value
+/// of `name` in `args`. Unknown keys are returned as they are.
+pub fn tr(key: &str, args: &[(&str, &dyn Display)]) -> String {
+    let locale = LOCALE.read().unwrap_or_else(PoisonError::into_inner);
+    let message = [locale.as_str(), DEFAULT_LOCALE]
+        .iter()
+        .find_map(|locale| CATALOGS.get(*locale)?.get(key));
+    let mut message = match message {
+        Some(message) => message.clone(),
+        None => {
+            warn!("No message for {}", key);
+            return key.into();
+        }
+    };
+    for (name, value) in args {
+        message = message.replace(&format!("{{{}}}", name), &value.to_string());
+    }
+    message
+}
@@ -0,0 +1,213 @@
+//! Importers that turn other launchers' profiles and instances into our own instances.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State, Wry};
+
+use crate::{
+    state::{
+        instance_manager::copy_entry,
+        resource_manager::{
+            ManifestError, ManifestResult, PathContext, ResourceManager, ResourceState,
+        },
+        task_manager::TaskHandle,
+    },
+    web_services::resources::create_instance,
+};
+
+pub mod atlauncher;
+pub mod official_launcher;
+pub mod technic;
+pub mod version_bundle;
+
+/// The parts of a game directory the user chose to bring along.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct ImportOptions {
+    #[serde(default)]
+    pub saves: bool,
+    #[serde(rename = "resourcePacks", default)]
+    pub resource_packs: bool,
+    #[serde(default)]
+    pub options: bool,
+}
+
+impl ImportOptions {
+    /// Names of the files and folders in a game directory that the options select.
+    pub fn entries(&self) -> Vec<&'static str> {
+        let mut entries = Vec::new();
+        if self.saves {
+            entries.push("saves");
+        }
+        if self.resource_packs {
+            entries.push("resourcepacks");
+        }
+        if self.options {
+            entries.push("options.txt");
+        }
+        entries
+    }
+}
+
+/// Launchers whose instances are plain game directories that can be copied over as a whole.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExternalLauncher {
+    AtLauncher,
+    Technic,
+}
+
+impl ExternalLauncher {
+    /// The launcher's default data directory, if it exists.
+    pub fn detect_dir(&self) -> Option<PathBuf> {
+        match self {
+            ExternalLauncher::AtLauncher => atlauncher::detect_atlauncher_dir(),
+            ExternalLauncher::Technic => technic::detect_technic_dir(),
+        }
+    }
+
+    pub fn list_instances(
+        &self,
+        launcher_dir: &Path,
+        resource_manager: &ResourceManager,
+    ) -> ManifestResult<Vec<ExternalInstance>> {
+        match self {
+            ExternalLauncher::AtLauncher => {
+                atlauncher::list_instances(launcher_dir, resource_manager)
+            }
+            ExternalLauncher::Technic => technic::list_packs(launcher_dir, resource_manager),
+        }
+    }
+
+    pub fn read_instance(
+        &self,
+        instance_dir: &Path,
+        resource_manager: &ResourceManager,
+    ) -> ManifestResult<ExternalInstance> {
+        match self {
+            ExternalLauncher::AtLauncher => {
+                atlauncher::read_instance(instance_dir, resource_manager)
+            }
+            ExternalLauncher::Technic => technic::read_pack(instance_dir, resource_manager),
+        }
+    }
+
+    /// Top level entries of an instance that belong to the launcher rather than the game.
+    fn excluded_entries(&self) -> &'static [&'static str] {
+        match self {
+            ExternalLauncher::AtLauncher => &atlauncher::EXCLUDED_ENTRIES,
+            ExternalLauncher::Technic => &technic::EXCLUDED_ENTRIES,
+        }
+    }
+}
+
+/// An instance of another launcher that can be imported.
+#[derive(Debug, Serialize)]
+pub struct ExternalInstance {
+    pub name: String,
+    /// The instance's game directory.
+    pub path: PathBuf,
+    /// The vanilla version an import will be created with. None if it could not be determined.
+    #[serde(rename = "vanillaVersionId")]
+    pub vanilla_version_id: Option<String>,
+    /// The mod loader the instance uses, e.g. `Forge 43.1.1`. Imports don't install it.
+    #[serde(rename = "modLoader")]
+    pub mod_loader: Option<String>,
+}
+
+/// Copies the named files and folders from a game directory into an instance directory,
+/// skipping the ones the game directory doesn't have.
+pub fn copy_game_files(
+    game_dir: &Path,
+    instance_dir: &Path,
+    entries: &[&str],
+) -> ManifestResult<()> {
+    for entry in entries {
+        let source = game_dir.join(entry);
+        if !source.exists() {
+            debug!("Nothing to import at {}", source.display());
+            continue;
+        }
+        copy_entry(&source, &instance_dir.join(entry)).with_path(&source)?;
+    }
+    Ok(())
+}
+
+/// Installs the vanilla version of an imported profile or instance into a new instance and returns
+/// the new instance's directory.
+pub async fn create_imported_instance(
+    vanilla_version_id: Option<String>,
+    source_name: &str,
+    instance_name: &str,
+    app_handle: &AppHandle<Wry>,
+    task: &TaskHandle,
+) -> ManifestResult<PathBuf> {
+    let vanilla_version_id = vanilla_version_id.ok_or_else(|| {
+        ManifestError::ImportError(format!(
+            "{} does not use a known minecraft version",
+            source_name
+        ))
+    })?;
+    let instance_dir = {
+        let resource_state: State<ResourceState> = app_handle
+            .try_state()
+            .expect("`ResourceState` should already be managed.");
+        let resource_manager = &resource_state.0;
+
+        resource_manager.instances_dir().join(instance_name)
+    };
+    create_instance(vanilla_version_id, instance_name.into(), app_handle, task).await?;
+    Ok(instance_dir)
+}
+
+/// Creates an instance from another launcher's instance, copying its whole game directory.
+pub async fn import_external_instance(
+    launcher: ExternalLauncher,
+    instance_dir: &Path,
+    instance_name: String,
+    app_handle: &AppHandle<Wry>,
+    task: &TaskHandle,
+) -> ManifestResult<()> {
+    let external = {
+        let resource_state: State<ResourceState> = app_handle
+            .try_state()
+            .expect("`ResourceState` should already be managed.");
+        let resource_manager = &resource_state.0;
+
+        launcher.read_instance(instance_dir, resource_manager)?
+    };
+
+    let new_instance_dir = create_imported_instance(
+        external.vanilla_version_id.clone(),
+        &external.name,
+        &instance_name,
+        app_handle,
+        task,
+    )
+    .await?;
+    let excluded = launcher.excluded_entries();
+    let entries: Vec<String> = fs::read_dir(&external.path)
+        .with_path(&external.path)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|file_name| !excluded.contains(&file_name.as_str()))
+        .collect();
+    let entries: Vec<&str> = entries.iter().map(String::as_str).collect();
+    copy_game_files(&external.path, &new_instance_dir, &entries)?;
+    info!(
+        "Imported {:?} instance {} into {}",
+        launcher, external.name, instance_name
+    );
+    Ok(())
+}
+
+/// Returns the version if the vanilla manifest knows it.
+fn known_vanilla_version(resource_manager: &ResourceManager, version_id: &str) -> Option<String> {
+    resource_manager
+        .get_vanilla_manifest_from_version(version_id)
+        .map(|version| version.id)
+}
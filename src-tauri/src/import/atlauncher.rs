@@ -0,0 +1,94 @@
+use std::{
+    fs::{self, File},
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+
+use log::warn;
+use serde::Deserialize;
+
+use crate::{
+    import::{known_vanilla_version, ExternalInstance},
+    state::resource_manager::{ManifestResult, PathContext, ResourceManager},
+};
+
+const INSTANCE_FILE: &str = "instance.json";
+/// ATLauncher's own files, and natives which are extracted again for the new instance.
+pub const EXCLUDED_ENTRIES: [&str; 5] = [INSTANCE_FILE, "logs", "crash-reports", "natives", "bin"];
+
+/// The subset of an ATLauncher `instance.json` needed to import it.
+#[derive(Debug, Deserialize)]
+struct AtLauncherInstance {
+    /// The minecraft version.
+    id: String,
+    launcher: LauncherDetails,
+}
+
+#[derive(Debug, Deserialize)]
+struct LauncherDetails {
+    name: String,
+    #[serde(rename = "loaderVersion")]
+    loader_version: Option<LoaderVersion>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LoaderVersion {
+    /// `Forge`, `Fabric` or `Quilt`.
+    #[serde(rename = "type")]
+    loader_type: String,
+    version: String,
+}
+
+/// ATLauncher is portable, these are the places its installers and packages put it.
+pub fn detect_atlauncher_dir() -> Option<PathBuf> {
+    let candidates = [
+        tauri::api::path::data_dir().map(|dir| dir.join("ATLauncher")),
+        tauri::api::path::home_dir().map(|dir| dir.join("ATLauncher")),
+    ];
+    candidates
+        .into_iter()
+        .flatten()
+        .find(|dir| dir.join("instances").is_dir())
+}
+
+pub fn list_instances(
+    atlauncher_dir: &Path,
+    resource_manager: &ResourceManager,
+) -> ManifestResult<Vec<ExternalInstance>> {
+    let instances_dir = atlauncher_dir.join("instances");
+    let mut instances = Vec::new();
+    for entry in fs::read_dir(&instances_dir).with_path(&instances_dir)? {
+        let path = entry.with_path(&instances_dir)?.path();
+        if !path.join(INSTANCE_FILE).is_file() {
+            continue;
+        }
+        match read_instance(&path, resource_manager) {
+            Ok(instance) => instances.push(instance),
+            Err(error) => warn!(
+                "Skipping ATLauncher instance at {}: {}",
+                path.display(),
+                error
+            ),
+        }
+    }
+    instances.sort_by_key(|instance| instance.name.to_lowercase());
+    Ok(instances)
+}
+
+pub fn read_instance(
+    instance_dir: &Path,
+    resource_manager: &ResourceManager,
+) -> ManifestResult<ExternalInstance> {
+    let path = instance_dir.join(INSTANCE_FILE);
+    let reader = BufReader::new(File::open(&path).with_path(&path)?);
+    let instance = serde_json::from_reader::<BufReader<File>, AtLauncherInstance>(reader)?;
+    Ok(ExternalInstance {
+        name: instance.launcher.name,
+        path: instance_dir.into(),
+        vanilla_version_id: known_vanilla_version(resource_manager, &instance.id),
+        mod_loader: instance
+            .launcher
+            .loader_version
+            .map(|loader| format!("{} {}", loader.loader_type, loader.version)),
+    })
+}
@@ -0,0 +1,218 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State, Wry};
+
+use crate::{
+    import::{copy_game_files, create_imported_instance, ImportOptions},
+    state::{
+        resource_manager::{
+            ManifestError, ManifestResult, PathContext, ResourceManager, ResourceState,
+        },
+        task_manager::TaskHandle,
+    },
+};
+
+const LAUNCHER_PROFILES_FILE: &str = "launcher_profiles.json";
+
+/// The subset of `launcher_profiles.json` needed to import profiles.
+#[derive(Debug, Deserialize)]
+struct LauncherProfiles {
+    #[serde(default)]
+    profiles: HashMap<String, RawProfile>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawProfile {
+    #[serde(default)]
+    name: String,
+    /// `custom`, `latest-release` or `latest-snapshot`.
+    #[serde(rename = "type", default)]
+    profile_type: String,
+    #[serde(rename = "lastVersionId")]
+    last_version_id: Option<String>,
+    #[serde(rename = "gameDir")]
+    game_dir: Option<PathBuf>,
+    #[serde(rename = "lastUsed")]
+    last_used: Option<String>,
+}
+
+/// The version json of a modded version installed by the official launcher. Only used to find the
+/// vanilla version it is based on.
+#[derive(Debug, Deserialize)]
+struct InheritingVersion {
+    #[serde(rename = "inheritsFrom")]
+    inherits_from: Option<String>,
+}
+
+/// A profile of the official launcher.
+#[derive(Debug, Serialize)]
+pub struct OfficialProfile {
+    pub id: String,
+    pub name: String,
+    /// The version the profile launches, which may be a modded version like `fabric-loader-0.14.9-1.19.2`.
+    #[serde(rename = "versionId")]
+    pub version_id: String,
+    /// The vanilla version an import will be created with. None if it could not be determined.
+    #[serde(rename = "vanillaVersionId")]
+    pub vanilla_version_id: Option<String>,
+    #[serde(rename = "gameDir")]
+    pub game_dir: PathBuf,
+    /// ISO 8601 timestamp of the last time the profile was played.
+    #[serde(rename = "lastUsed")]
+    pub last_used: Option<String>,
+}
+
+/// The official launcher's default `.minecraft` directory, if it exists.
+pub fn detect_minecraft_dir() -> Option<PathBuf> {
+    #[cfg(target_os = "windows")]
+    let minecraft_dir = tauri::api::path::data_dir()?.join(".minecraft");
+    #[cfg(target_os = "macos")]
+    let minecraft_dir = tauri::api::path::data_dir()?.join("minecraft");
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let minecraft_dir = tauri::api::path::home_dir()?.join(".minecraft");
+
+    if minecraft_dir.join(LAUNCHER_PROFILES_FILE).is_file() {
+        Some(minecraft_dir)
+    } else {
+        None
+    }
+}
+
+/// Reads the profiles of the official launcher, most recently used first.
+pub fn list_profiles(
+    minecraft_dir: &Path,
+    resource_manager: &ResourceManager,
+) -> ManifestResult<Vec<OfficialProfile>> {
+    let path = minecraft_dir.join(LAUNCHER_PROFILES_FILE);
+    let reader = BufReader::new(File::open(&path).with_path(&path)?);
+    let launcher_profiles = serde_json::from_reader::<BufReader<File>, LauncherProfiles>(reader)?;
+
+    let mut profiles: Vec<OfficialProfile> = launcher_profiles
+        .profiles
+        .into_iter()
+        .map(|(id, profile)| resolve_profile(minecraft_dir, resource_manager, id, profile))
+        .collect();
+    profiles.sort_by(|a, b| b.last_used.cmp(&a.last_used));
+    Ok(profiles)
+}
+
+fn resolve_profile(
+    minecraft_dir: &Path,
+    resource_manager: &ResourceManager,
+    id: String,
+    profile: RawProfile,
+) -> OfficialProfile {
+    let (default_name, latest_type) = match profile.profile_type.as_str() {
+        "latest-release" => ("Latest Release", Some("release")),
+        "latest-snapshot" => ("Latest Snapshot", Some("snapshot")),
+        _ => ("Unnamed Profile", None),
+    };
+    // The latest profiles don't keep a fixed version, they always launch the newest one.
+    let version_id = match (latest_type, profile.last_version_id) {
+        (Some(version_type), _) => resource_manager
+            .get_latest_vanilla_version(version_type)
+            .unwrap_or_default(),
+        (None, Some(version_id)) => version_id,
+        (None, None) => String::new(),
+    };
+    let vanilla_version_id = find_vanilla_version(minecraft_dir, resource_manager, &version_id);
+    let name = if profile.name.trim().is_empty() {
+        default_name.into()
+    } else {
+        profile.name
+    };
+    OfficialProfile {
+        id,
+        name,
+        vanilla_version_id,
+        version_id,
+        game_dir: profile
+            .game_dir
+            .unwrap_or_else(|| minecraft_dir.to_path_buf()),
+        last_used: profile.last_used,
+    }
+}
+
+/// Follows `inheritsFrom` in the launcher's version jsons until a version from the vanilla manifest is found.
+fn find_vanilla_version(
+    minecraft_dir: &Path,
+    resource_manager: &ResourceManager,
+    version_id: &str,
+) -> Option<String> {
+    let mut version_id = version_id.to_string();
+    // Guard against version jsons that inherit from each other.
+    for _ in 0..8 {
+        if resource_manager
+            .get_vanilla_manifest_from_version(&version_id)
+            .is_some()
+        {
+            return Some(version_id);
+        }
+        let path = minecraft_dir
+            .join("versions")
+            .join(&version_id)
+            .join(format!("{}.json", version_id));
+        let file = File::open(&path).ok()?;
+        let version =
+            serde_json::from_reader::<BufReader<File>, InheritingVersion>(BufReader::new(file));
+        match version {
+            Ok(version) => version_id = version.inherits_from?,
+            Err(error) => {
+                warn!(
+                    "Could not read version json at {}: {}",
+                    path.display(),
+                    error
+                );
+                return None;
+            }
+        }
+    }
+    None
+}
+
+/// Creates an instance from an official launcher profile, installing its vanilla version and copying
+/// the parts of its game directory selected in `options`. Mod loaders are not carried over.
+pub async fn import_profile(
+    minecraft_dir: &Path,
+    profile_id: &str,
+    instance_name: String,
+    options: ImportOptions,
+    app_handle: &AppHandle<Wry>,
+    task: &TaskHandle,
+) -> ManifestResult<()> {
+    let profile = {
+        let resource_state: State<ResourceState> = app_handle
+            .try_state()
+            .expect("`ResourceState` should already be managed.");
+        let resource_manager = &resource_state.0;
+
+        list_profiles(minecraft_dir, resource_manager)?
+            .into_iter()
+            .find(|profile| profile.id == profile_id)
+            .ok_or_else(|| {
+                ManifestError::ImportError(format!("Unknown launcher profile: {}", profile_id))
+            })?
+    };
+
+    let instance_dir = create_imported_instance(
+        profile.vanilla_version_id.clone(),
+        &profile.name,
+        &instance_name,
+        app_handle,
+        task,
+    )
+    .await?;
+    copy_game_files(&profile.game_dir, &instance_dir, &options.entries())?;
+    info!(
+        "Imported official launcher profile {} into {}",
+        profile.name, instance_name
+    );
+    Ok(())
+}
@@ -0,0 +1,94 @@
+use std::{
+    fs::{self, File},
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+
+use log::warn;
+use serde::Deserialize;
+
+use crate::{
+    import::{known_vanilla_version, ExternalInstance},
+    state::resource_manager::{ManifestResult, PathContext, ResourceManager},
+};
+
+/// Holds the pack's version json and modpack jar, the game files are next to it.
+const BIN_DIR: &str = "bin";
+/// Technic's own files, which don't belong in the game directory of the new instance.
+pub const EXCLUDED_ENTRIES: [&str; 3] = [BIN_DIR, "logs", "crash-reports"];
+
+/// The subset of a pack's `bin/version.json` needed to find its minecraft version.
+#[derive(Debug, Deserialize)]
+struct PackVersion {
+    id: String,
+    #[serde(rename = "inheritsFrom")]
+    inherits_from: Option<String>,
+    /// Set by older forge packs instead of `inheritsFrom`.
+    jar: Option<String>,
+}
+
+pub fn detect_technic_dir() -> Option<PathBuf> {
+    #[cfg(target_os = "windows")]
+    let technic_dir = tauri::api::path::data_dir()?.join(".technic");
+    #[cfg(target_os = "macos")]
+    let technic_dir = tauri::api::path::data_dir()?.join("technic");
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let technic_dir = tauri::api::path::home_dir()?.join(".technic");
+
+    if technic_dir.join("modpacks").is_dir() {
+        Some(technic_dir)
+    } else {
+        None
+    }
+}
+
+pub fn list_packs(
+    technic_dir: &Path,
+    resource_manager: &ResourceManager,
+) -> ManifestResult<Vec<ExternalInstance>> {
+    let modpacks_dir = technic_dir.join("modpacks");
+    let mut packs = Vec::new();
+    for entry in fs::read_dir(&modpacks_dir).with_path(&modpacks_dir)? {
+        let path = entry.with_path(&modpacks_dir)?.path();
+        // Packs that were never fully installed have no version json.
+        if !path.join(BIN_DIR).join("version.json").is_file() {
+            continue;
+        }
+        match read_pack(&path, resource_manager) {
+            Ok(pack) => packs.push(pack),
+            Err(error) => warn!("Skipping Technic pack at {}: {}", path.display(), error),
+        }
+    }
+    packs.sort_by_key(|pack| pack.name.to_lowercase());
+    Ok(packs)
+}
+
+pub fn read_pack(
+    pack_dir: &Path,
+    resource_manager: &ResourceManager,
+) -> ManifestResult<ExternalInstance> {
+    let path = pack_dir.join(BIN_DIR).join("version.json");
+    let reader = BufReader::new(File::open(&path).with_path(&path)?);
+    let version = serde_json::from_reader::<BufReader<File>, PackVersion>(reader)?;
+    let minecraft_version = version
+        .inherits_from
+        .or(version.jar)
+        .unwrap_or_else(|| version.id.clone());
+    let vanilla_version_id = known_vanilla_version(resource_manager, &minecraft_version);
+    // Packs without a loader use the vanilla version id itself.
+    let mod_loader = if version.id != minecraft_version {
+        Some(version.id)
+    } else {
+        None
+    };
+    Ok(ExternalInstance {
+        // Technic names the pack folders after the pack's slug.
+        name: pack_dir
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        path: pack_dir.into(),
+        vanilla_version_id,
+        mod_loader,
+    })
+}
@@ -0,0 +1,306 @@
+//! Imports vanilla versions from a game directory laid out like `.minecraft`, or a zip of one, so
+//! instances can be created on machines without internet access. The version json, client jar and
+//! asset index are required, libraries and asset objects are copied when the bundle has them. Every
+//! file is checked against the hashes in the version json before it is used.
+
+use std::{
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+};
+
+use bytes::Bytes;
+use log::{debug, info, warn};
+use serde::Serialize;
+use zip::ZipArchive;
+
+use crate::{
+    state::resource_manager::{ManifestError, ManifestResult, PathContext},
+    web_services::{
+        downloader::{hash_bytes, is_file_valid, validate_file_hash, Downloadable, Verification},
+        manifest::vanilla::{AssetObject, VanillaManifestVersion, VanillaVersion},
+    },
+};
+
+/// Where imported files are copied to, the shared directories of the resource manager.
+#[derive(Debug)]
+pub struct VersionDirs {
+    pub versions: PathBuf,
+    pub libraries: PathBuf,
+    pub assets: PathBuf,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SkippedVersion {
+    pub id: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct VersionBundleReport {
+    pub imported: Vec<String>,
+    pub skipped: Vec<SkippedVersion>,
+    /// Libraries the bundle doesn't have, they are downloaded when an instance is created.
+    #[serde(rename = "missingLibraries")]
+    pub missing_libraries: usize,
+    /// Asset objects the bundle doesn't have, they are downloaded when an instance is created.
+    #[serde(rename = "missingAssets")]
+    pub missing_assets: usize,
+}
+
+/// The directory with the `versions` folder, which zips often wrap in a folder like `.minecraft`.
+fn find_bundle_root(dir: &Path) -> Option<PathBuf> {
+    if dir.join("versions").is_dir() {
+        return Some(dir.into());
+    }
+    let entries: Vec<PathBuf> = fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect();
+    match entries.as_slice() {
+        [single] if single.join("versions").is_dir() => Some(single.clone()),
+        _ => None,
+    }
+}
+
+fn extract_bundle(zip_path: &Path, destination: &Path) -> ManifestResult<()> {
+    let mut archive = ZipArchive::new(File::open(zip_path).with_path(zip_path)?)?;
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index)?;
+        let path = match file.enclosed_name() {
+            Some(path) => destination.join(path),
+            None => {
+                warn!(
+                    "Skipping unsafe path {} in {}",
+                    file.name(),
+                    zip_path.display()
+                );
+                continue;
+            }
+        };
+        if file.is_dir() {
+            fs::create_dir_all(&path).with_path(&path)?;
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).with_path(parent)?;
+        }
+        io::copy(&mut file, &mut File::create(&path).with_path(&path)?).with_path(&path)?;
+    }
+    Ok(())
+}
+
+fn copy_file(from: &Path, to: &Path) -> ManifestResult<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent).with_path(parent)?;
+    }
+    fs::copy(from, to).with_path(from)?;
+    Ok(())
+}
+
+/// Copies the libraries of a version the bundle has and the launcher doesn't, returns how many are
+/// missing from both.
+fn import_libraries(
+    root: &Path,
+    version: &VanillaVersion,
+    dirs: &VersionDirs,
+) -> ManifestResult<usize> {
+    let bundle_libraries = root.join("libraries");
+    let mut missing = 0;
+    for artifact in version
+        .libraries
+        .iter()
+        .flat_map(|library| library.artifacts())
+    {
+        let target = artifact.path(&dirs.libraries);
+        if is_file_valid(
+            &target,
+            artifact.hash(),
+            artifact.size(),
+            Verification::Fast,
+        ) {
+            continue;
+        }
+        let source = artifact.path(&bundle_libraries);
+        if validate_file_hash(&source, artifact.hash()) {
+            copy_file(&source, &target)?;
+        } else {
+            missing += 1;
+        }
+    }
+    Ok(missing)
+}
+
+/// Copies the asset index and the objects the bundle has, returns how many objects are missing from
+/// both. Objects are only checked by size, there can be thousands of them and the install checks them
+/// again with the configured verification.
+fn import_assets(
+    root: &Path,
+    version: &VanillaVersion,
+    dirs: &VersionDirs,
+) -> ManifestResult<usize> {
+    let asset_index = &version.asset_index;
+    let index_name = format!("{}.json", asset_index.id);
+    let source_index = root.join("assets").join("indexes").join(&index_name);
+    if !validate_file_hash(&source_index, asset_index.metadata.hash()) {
+        return Err(ManifestError::ImportError(format!(
+            "The asset index {} is missing or doesn't match the version",
+            asset_index.id
+        )));
+    }
+    let index_bytes = fs::read(&source_index).with_path(&source_index)?;
+    let asset_object: AssetObject = serde_json::from_slice(&index_bytes).map_err(|error| {
+        ManifestError::ImportError(format!("Invalid asset index {}: {}", asset_index.id, error))
+    })?;
+    copy_file(
+        &source_index,
+        &dirs.assets.join("indexes").join(&index_name),
+    )?;
+
+    let bundle_objects = root.join("assets").join("objects");
+    let objects = dirs.assets.join("objects");
+    let mut missing = 0;
+    for asset in &asset_object.objects {
+        let target = asset.path(&objects);
+        if is_file_valid(&target, asset.hash(), asset.size(), Verification::Fast) {
+            continue;
+        }
+        let source = asset.path(&bundle_objects);
+        if is_file_valid(&source, asset.hash(), asset.size(), Verification::Fast) {
+            copy_file(&source, &target)?;
+        } else {
+            missing += 1;
+        }
+    }
+
+    let client_logger = &version.logging.client;
+    let log_config = Path::new("log_configs").join(client_logger.file_id());
+    let source_log_config = root.join("assets").join(&log_config);
+    if validate_file_hash(&source_log_config, client_logger.file_hash()) {
+        copy_file(&source_log_config, &dirs.assets.join(&log_config))?;
+    }
+    Ok(missing)
+}
+
+/// Imports a single version of the bundle. Versions the bundle can't provide fail with an
+/// `ImportError`, other errors mean the launcher's directories couldn't be written.
+fn import_version(
+    root: &Path,
+    version_id: &str,
+    dirs: &VersionDirs,
+    report: &mut VersionBundleReport,
+) -> ManifestResult<VanillaManifestVersion> {
+    let version_dir = root.join("versions").join(version_id);
+    let json_path = version_dir.join(format!("{}.json", version_id));
+    let json_bytes = Bytes::from(fs::read(&json_path).map_err(|_| {
+        ManifestError::ImportError(format!("{} has no version json", version_dir.display()))
+    })?);
+    // Modded versions only hold the changes to the version they inherit from.
+    let version: VanillaVersion = serde_json::from_slice(&json_bytes).map_err(|_| {
+        ManifestError::ImportError(
+            "Not a vanilla version, modded versions can't be imported".into(),
+        )
+    })?;
+
+    let client = &version.downloads.client;
+    let jar_path = [format!("{}.jar", version_id), "client.jar".into()]
+        .iter()
+        .map(|file_name| version_dir.join(file_name))
+        .find(|path| validate_file_hash(path, client.hash()))
+        .ok_or_else(|| {
+            ManifestError::ImportError(
+                "The client jar is missing or doesn't match the version".into(),
+            )
+        })?;
+
+    let missing_assets = import_assets(root, &version, dirs)?;
+    let missing_libraries = import_libraries(root, &version, dirs)?;
+
+    let target_dir = dirs.versions.join(version_id);
+    copy_file(&jar_path, &target_dir.join("client.jar"))?;
+    let target_json = target_dir.join(format!("{}.json", version_id));
+    fs::write(&target_json, &json_bytes).with_path(&target_json)?;
+
+    report.missing_assets += missing_assets;
+    report.missing_libraries += missing_libraries;
+    Ok(VanillaManifestVersion::imported(
+        &version,
+        hash_bytes(&json_bytes),
+    ))
+}
+
+fn import_bundle_root(
+    root: &Path,
+    dirs: &VersionDirs,
+) -> ManifestResult<(VersionBundleReport, Vec<VanillaManifestVersion>)> {
+    let mut report = VersionBundleReport::default();
+    let mut versions = Vec::new();
+    let versions_dir = root.join("versions");
+    for entry in fs::read_dir(&versions_dir).with_path(&versions_dir)? {
+        let entry = entry.with_path(&versions_dir)?;
+        if !entry.path().is_dir() {
+            continue;
+        }
+        let version_id = entry.file_name().to_string_lossy().into_owned();
+        match import_version(root, &version_id, dirs, &mut report) {
+            Ok(version) => {
+                debug!("Imported version {}", version_id);
+                report.imported.push(version_id);
+                versions.push(version);
+            }
+            Err(ManifestError::ImportError(reason)) => {
+                warn!("Skipping version {}: {}", version_id, reason);
+                report.skipped.push(SkippedVersion {
+                    id: version_id,
+                    reason,
+                });
+            }
+            Err(error) => return Err(error),
+        }
+    }
+    Ok((report, versions))
+}
+
+/// Imports every vanilla version in `source`, a game directory or a zip of one. Zips are extracted
+/// into `staging_dir` first, which is removed afterwards. Returns the manifest entries to register
+/// the versions with.
+pub fn import_version_bundle(
+    source: &Path,
+    dirs: &VersionDirs,
+    staging_dir: &Path,
+) -> ManifestResult<(VersionBundleReport, Vec<VanillaManifestVersion>)> {
+    let is_zip = source.is_file();
+    let bundle_dir = if is_zip {
+        if staging_dir.exists() {
+            fs::remove_dir_all(staging_dir).with_path(staging_dir)?;
+        }
+        extract_bundle(source, staging_dir)?;
+        staging_dir
+    } else {
+        source
+    };
+
+    let result = match find_bundle_root(bundle_dir) {
+        Some(root) => import_bundle_root(&root, dirs),
+        None => Err(ManifestError::ImportError(format!(
+            "{} has no versions folder",
+            source.display()
+        ))),
+    };
+    if is_zip {
+        if let Err(error) = fs::remove_dir_all(staging_dir) {
+            warn!("Could not remove {}: {}", staging_dir.display(), error);
+        }
+    }
+    if let Ok((report, _)) = &result {
+        info!(
+            "Imported {} versions from {}, skipped {}",
+            report.imported.len(),
+            source.display(),
+            report.skipped.len()
+        );
+    }
+    result
+}
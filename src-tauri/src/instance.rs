@@ -0,0 +1,40 @@
+use std::path::{Path, PathBuf};
+
+pub mod advancements;
+pub mod backups;
+pub mod config_files;
+pub mod content;
+pub mod crash_reports;
+pub mod fatal_errors;
+pub mod gpu;
+pub mod launch_metrics;
+pub mod launch_preview;
+pub mod lockfile;
+pub mod log4j;
+pub mod logs;
+pub mod mod_metadata;
+pub mod mod_search;
+pub mod mod_versions;
+pub mod modpack;
+pub mod mods_library;
+pub mod process_group;
+pub mod process_priority;
+pub mod screenshots;
+pub mod servers;
+pub mod statistics;
+pub mod sync;
+pub mod trash;
+pub mod watcher;
+pub mod worlds;
+
+/// Resolves a file name from the frontend inside of `dir`. Returns None for anything that isn't an
+/// existing file directly inside of `dir`, so paths like `../config.json` can't escape it.
+pub fn resolve_file_in(dir: &Path, file_name: &str) -> Option<PathBuf> {
+    let is_plain_name = Path::new(file_name).file_name() == Some(file_name.as_ref());
+    let path = dir.join(file_name);
+    if is_plain_name && path.is_file() {
+        Some(path)
+    } else {
+        None
+    }
+}
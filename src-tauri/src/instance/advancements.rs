@@ -0,0 +1,127 @@
+//! Progress towards advancements, which the game keeps for every player in
+//! `saves/${world}/advancements/${uuid}.json` since 1.12:
+//!
+//! ```json
+//! {
+//!   "minecraft:story/mine_stone": {
+//!     "criteria": { "get_stone": "2023-01-01 12:00:00 +0100" },
+//!     "done": true
+//!   },
+//!   "DataVersion": 3337
+//! }
+//! ```
+
+use std::{collections::BTreeMap, fs, path::Path};
+
+use chrono::DateTime;
+use log::warn;
+use serde::{de::IgnoredAny, Deserialize, Serialize};
+
+use crate::state::instance_manager::InstanceResult;
+
+/// Unlocked recipes are kept with the advancements, they aren't shown as progress.
+const RECIPES_PREFIX: &str = "minecraft:recipes/";
+const CRITERION_TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S %z";
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum AdvancementsEntry {
+    Advancement {
+        #[serde(default)]
+        criteria: BTreeMap<String, String>,
+        #[serde(default)]
+        done: bool,
+    },
+    /// `DataVersion`, which isn't an advancement.
+    Other(IgnoredAny),
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AdvancementProgress {
+    pub done: bool,
+    /// The criteria met so far and the timestamp in seconds of when they were, which is None if
+    /// it couldn't be read. Criteria that weren't met aren't known without the game's data.
+    pub criteria: BTreeMap<String, Option<i64>>,
+    /// Timestamp in seconds of the last criterion, for advancements that are done.
+    #[serde(rename = "completedAt")]
+    pub completed_at: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PlayerAdvancements {
+    /// The uuid of the player, the name of the file.
+    pub uuid: String,
+    /// Keyed by advancement id, e.g. `minecraft:story/mine_stone`.
+    pub advancements: BTreeMap<String, AdvancementProgress>,
+    /// How many of the advancements are done.
+    pub completed: usize,
+}
+
+/// Reads the advancement progress of every player that joined the world in `world_dir`, sorted by
+/// uuid. Worlds from before 1.12 or without any players have none.
+pub fn read_advancements(world_dir: &Path) -> InstanceResult<Vec<PlayerAdvancements>> {
+    let advancements_dir = world_dir.join("advancements");
+    let mut players = Vec::new();
+    if !advancements_dir.is_dir() {
+        return Ok(players);
+    }
+    for entry in fs::read_dir(&advancements_dir)? {
+        let path = entry?.path();
+        if path.extension() != Some("json".as_ref()) {
+            continue;
+        }
+        let entries: BTreeMap<String, AdvancementsEntry> =
+            match serde_json::from_slice(&fs::read(&path)?) {
+                Ok(entries) => entries,
+                Err(error) => {
+                    warn!("Could not read advancements {}: {}", path.display(), error);
+                    continue;
+                }
+            };
+        let advancements: BTreeMap<String, AdvancementProgress> = entries
+            .into_iter()
+            .filter(|(id, _)| !id.starts_with(RECIPES_PREFIX))
+            .filter_map(|(id, entry)| match entry {
+                AdvancementsEntry::Advancement { criteria, done } => {
+                    Some((id, advancement_progress(criteria, done)))
+                }
+                AdvancementsEntry::Other(_) => None,
+            })
+            .collect();
+        players.push(PlayerAdvancements {
+            uuid: path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            completed: advancements
+                .values()
+                .filter(|advancement| advancement.done)
+                .count(),
+            advancements,
+        });
+    }
+    players.sort_by(|a, b| a.uuid.cmp(&b.uuid));
+    Ok(players)
+}
+
+fn advancement_progress(criteria: BTreeMap<String, String>, done: bool) -> AdvancementProgress {
+    let criteria: BTreeMap<String, Option<i64>> = criteria
+        .into_iter()
+        .map(|(name, time)| {
+            let time = DateTime::parse_from_str(&time, CRITERION_TIME_FORMAT)
+                .ok()
+                .map(|time| time.timestamp());
+            (name, time)
+        })
+        .collect();
+    let completed_at = if done {
+        criteria.values().flatten().max().copied()
+    } else {
+        None
+    };
+    AdvancementProgress {
+        done,
+        criteria,
+        completed_at,
+    }
+}
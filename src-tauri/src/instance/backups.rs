@@ -0,0 +1,215 @@
+//! Restore points of whole instances, zipped into `${app_dir}/backups/${instance_name}`.
+
+use std::{
+    cmp::Reverse,
+    fs::{self, File},
+    io,
+    path::Path,
+};
+
+use chrono::{Local, NaiveDateTime, TimeZone};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use zip::{write::FileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::{
+    export::add_entry,
+    instance::resolve_file_in,
+    state::instance_manager::{InstanceError, InstanceResult},
+};
+
+/// Left out of backups since they are extracted from the shared libraries again on launch, or only
+/// describe the instance's state at the time it was backed up.
+const EXCLUDED_ENTRIES: [&str; 3] = ["natives", ".sync-state.json", "config.json.tmp"];
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d_%H-%M-%S";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BackupReason {
+    Manual,
+    Scheduled,
+    BeforeVersionChange,
+    BeforeModpackUpdate,
+}
+
+impl BackupReason {
+    fn name(self) -> &'static str {
+        match self {
+            BackupReason::Manual => "manual",
+            BackupReason::Scheduled => "scheduled",
+            BackupReason::BeforeVersionChange => "beforeVersionChange",
+            BackupReason::BeforeModpackUpdate => "beforeModpackUpdate",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "manual" => Some(BackupReason::Manual),
+            "scheduled" => Some(BackupReason::Scheduled),
+            "beforeVersionChange" => Some(BackupReason::BeforeVersionChange),
+            "beforeModpackUpdate" => Some(BackupReason::BeforeModpackUpdate),
+            _ => None,
+        }
+    }
+
+    /// Backups the user didn't ask for are removed once there are too many of them.
+    fn is_automatic(self) -> bool {
+        self != BackupReason::Manual
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupEntry {
+    #[serde(rename = "fileName")]
+    pub file_name: String,
+    /// Timestamp in seconds of when the backup was made.
+    pub timestamp: i64,
+    pub reason: BackupReason,
+    /// Size of the archive in bytes.
+    pub size: u64,
+}
+
+impl BackupEntry {
+    /// Reads an entry from a backup named `${timestamp}_${reason}.zip`.
+    fn read(path: &Path) -> Option<Self> {
+        let file_name = path.file_name()?.to_string_lossy().into_owned();
+        let stem = file_name.strip_suffix(".zip")?;
+        let (timestamp, reason) = stem.rsplit_once('_')?;
+        let timestamp = NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT).ok()?;
+        let timestamp = Local.from_local_datetime(&timestamp).single()?.timestamp();
+        Some(Self {
+            reason: BackupReason::from_name(reason)?,
+            timestamp,
+            size: fs::metadata(path).ok()?.len(),
+            file_name,
+        })
+    }
+}
+
+/// Lists the backups of an instance, newest first.
+pub fn list_backups(backups_dir: &Path) -> io::Result<Vec<BackupEntry>> {
+    if !backups_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut backups = Vec::new();
+    for entry in fs::read_dir(backups_dir)? {
+        if let Some(backup) = BackupEntry::read(&entry?.path()) {
+            backups.push(backup);
+        }
+    }
+    backups.sort_by_key(|backup| Reverse(backup.timestamp));
+    Ok(backups)
+}
+
+/// Zips `instance_dir` into a new backup.
+pub fn create_backup(
+    instance_dir: &Path,
+    backups_dir: &Path,
+    reason: BackupReason,
+) -> InstanceResult<BackupEntry> {
+    fs::create_dir_all(backups_dir)?;
+    let file_name = format!(
+        "{}_{}.zip",
+        Local::now().format(TIMESTAMP_FORMAT),
+        reason.name()
+    );
+    let backup_path = backups_dir.join(&file_name);
+    let tmp_path = backup_path.with_extension("zip.tmp");
+    let mut zip = ZipWriter::new(File::create(&tmp_path)?);
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+    for entry in fs::read_dir(instance_dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if EXCLUDED_ENTRIES.contains(&name.as_str()) {
+            continue;
+        }
+        add_entry(&mut zip, &entry.path(), &name, options)?;
+    }
+    zip.finish()?;
+    // Only complete archives get a name that is listed.
+    fs::rename(&tmp_path, &backup_path)?;
+    info!(
+        "Backed up {} to {}",
+        instance_dir.display(),
+        backup_path.display()
+    );
+    BackupEntry::read(&backup_path).ok_or_else(|| InstanceError::UnknownBackup(file_name.clone()))
+}
+
+/// Removes the oldest automatic backups until at most `max_backups` of them are left.
+pub fn prune_backups(backups_dir: &Path, max_backups: usize) -> io::Result<()> {
+    let automatic = list_backups(backups_dir)?
+        .into_iter()
+        .filter(|backup| backup.reason.is_automatic());
+    for backup in automatic.skip(max_backups) {
+        info!("Removing old backup {}", backup.file_name);
+        fs::remove_file(backups_dir.join(&backup.file_name))?;
+    }
+    Ok(())
+}
+
+pub fn delete_backup(backups_dir: &Path, file_name: &str) -> InstanceResult<()> {
+    let path = resolve_file_in(backups_dir, file_name)
+        .ok_or_else(|| InstanceError::UnknownBackup(file_name.into()))?;
+    Ok(fs::remove_file(path)?)
+}
+
+/// Replaces `instance_dir` with the contents of a backup. The backup is extracted next to the
+/// backups first, so a failed extraction leaves the instance as it was.
+pub fn restore_backup(
+    instance_dir: &Path,
+    backups_dir: &Path,
+    file_name: &str,
+) -> InstanceResult<()> {
+    let backup_path = resolve_file_in(backups_dir, file_name)
+        .ok_or_else(|| InstanceError::UnknownBackup(file_name.into()))?;
+    let staging_dir = backups_dir.join(".restoring");
+    let previous_dir = backups_dir.join(".previous");
+    for dir in [&staging_dir, &previous_dir] {
+        if dir.exists() {
+            fs::remove_dir_all(dir)?;
+        }
+    }
+
+    let mut archive = ZipArchive::new(File::open(&backup_path)?)?;
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index)?;
+        let path = match file.enclosed_name() {
+            Some(path) => staging_dir.join(path),
+            None => {
+                warn!("Skipping unsafe path {} in {}", file.name(), file_name);
+                continue;
+            }
+        };
+        if file.is_dir() {
+            fs::create_dir_all(&path)?;
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        io::copy(&mut file, &mut File::create(&path)?)?;
+    }
+
+    fs::rename(instance_dir, &previous_dir)?;
+    if let Err(error) = fs::rename(&staging_dir, instance_dir) {
+        fs::rename(&previous_dir, instance_dir)?;
+        return Err(error.into());
+    }
+    fs::remove_dir_all(&previous_dir)?;
+    info!("Restored {} from {}", instance_dir.display(), file_name);
+    Ok(())
+}
+
+/// Whether the newest backup is older than `interval_hours`, or there is none.
+pub fn is_backup_due(backups_dir: &Path, interval_hours: u32) -> bool {
+    let newest = list_backups(backups_dir)
+        .ok()
+        .and_then(|backups| backups.first().map(|backup| backup.timestamp));
+    match newest {
+        Some(timestamp) => {
+            Local::now().timestamp() - timestamp >= i64::from(interval_hours) * 60 * 60
+        }
+        None => true,
+    }
+}
@@ -0,0 +1,175 @@
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use log::info;
+use serde::Serialize;
+
+use crate::state::instance_manager::{InstanceError, InstanceResult};
+
+/// Previous versions of edited config files, relative to the instance directory.
+pub const CONFIG_BACKUPS_DIR: &str = "config-backups";
+/// Files larger than this aren't opened in the editor.
+const MAX_CONFIG_FILE_SIZE: u64 = 1024 * 1024;
+/// Extensions of the text formats mods write their configs in.
+const EDITABLE_EXTENSIONS: [&str; 11] = [
+    "toml",
+    "json",
+    "json5",
+    "properties",
+    "cfg",
+    "conf",
+    "txt",
+    "yml",
+    "yaml",
+    "ini",
+    "snbt",
+];
+
+#[derive(Debug, Serialize)]
+pub struct ConfigFileEntry {
+    /// Path relative to the `config` folder, using `/` as the separator.
+    pub path: String,
+    /// Size of the file in bytes.
+    pub size: u64,
+    /// Timestamp in seconds of the last modification.
+    pub modified: Option<i64>,
+    /// Whether the file is a text config that `read_config_file` and `write_config_file` accept.
+    pub editable: bool,
+    #[serde(rename = "hasBackup")]
+    pub has_backup: bool,
+}
+
+/// Lists every file in the `config` folder and its subfolders, sorted by path.
+pub fn list_config_files(
+    config_dir: &Path,
+    backups_dir: &Path,
+) -> InstanceResult<Vec<ConfigFileEntry>> {
+    let mut entries = Vec::new();
+    collect_config_files(config_dir, config_dir, backups_dir, &mut entries)?;
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(entries)
+}
+
+fn collect_config_files(
+    config_dir: &Path,
+    dir: &Path,
+    backups_dir: &Path,
+    entries: &mut Vec<ConfigFileEntry>,
+) -> InstanceResult<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let metadata = entry.metadata()?;
+        if metadata.is_dir() {
+            collect_config_files(config_dir, &path, backups_dir, entries)?;
+            continue;
+        }
+        let relative_path = match path.strip_prefix(config_dir) {
+            Ok(relative_path) => relative_path,
+            Err(_) => continue,
+        };
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map(|duration| duration.as_secs() as i64);
+        entries.push(ConfigFileEntry {
+            path: relative_path
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/"),
+            size: metadata.len(),
+            modified,
+            editable: is_editable(&path) && metadata.len() <= MAX_CONFIG_FILE_SIZE,
+            has_backup: backups_dir.join(relative_path).is_file(),
+        });
+    }
+    Ok(())
+}
+
+fn is_editable(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| EDITABLE_EXTENSIONS.contains(&extension.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+/// Resolves a path from the frontend relative to `dir`, rejecting absolute paths and `..` so it can't
+/// escape the folder.
+fn resolve_relative_path(dir: &Path, relative_path: &str) -> InstanceResult<PathBuf> {
+    let relative = Path::new(relative_path);
+    let is_plain = relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)));
+    if relative_path.is_empty() || !is_plain {
+        return Err(InstanceError::UnknownConfigFile(relative_path.into()));
+    }
+    Ok(dir.join(relative))
+}
+
+/// Resolves an existing editable config file.
+fn resolve_config_file(config_dir: &Path, relative_path: &str) -> InstanceResult<PathBuf> {
+    let path = resolve_relative_path(config_dir, relative_path)?;
+    if !path.is_file() {
+        return Err(InstanceError::UnknownConfigFile(relative_path.into()));
+    }
+    if !is_editable(&path) {
+        return Err(InstanceError::UneditableConfigFile(relative_path.into()));
+    }
+    Ok(path)
+}
+
+pub fn read_config_file(config_dir: &Path, relative_path: &str) -> InstanceResult<String> {
+    let path = resolve_config_file(config_dir, relative_path)?;
+    if fs::metadata(&path)?.len() > MAX_CONFIG_FILE_SIZE {
+        return Err(InstanceError::UneditableConfigFile(relative_path.into()));
+    }
+    Ok(fs::read_to_string(&path)?)
+}
+
+/// Replaces the contents of an existing config file. The previous contents are kept in `backups_dir`
+/// so the last edit can be undone with `restore_config_file`.
+pub fn write_config_file(
+    config_dir: &Path,
+    backups_dir: &Path,
+    relative_path: &str,
+    contents: &str,
+) -> InstanceResult<()> {
+    let path = resolve_config_file(config_dir, relative_path)?;
+    if contents.len() as u64 > MAX_CONFIG_FILE_SIZE {
+        return Err(InstanceError::UneditableConfigFile(relative_path.into()));
+    }
+    let backup_path = resolve_relative_path(backups_dir, relative_path)?;
+    if let Some(parent) = backup_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::copy(&path, &backup_path)?;
+
+    // Write to a temporary file first so a failed write doesn't leave a truncated config behind.
+    let mut tmp_path = path.clone().into_os_string();
+    tmp_path.push(".tmp");
+    fs::write(&tmp_path, contents)?;
+    fs::rename(&tmp_path, &path)?;
+    info!("Saved config file {}", path.display());
+    Ok(())
+}
+
+/// Puts the contents from before the last `write_config_file` back.
+pub fn restore_config_file(
+    config_dir: &Path,
+    backups_dir: &Path,
+    relative_path: &str,
+) -> InstanceResult<()> {
+    let path = resolve_config_file(config_dir, relative_path)?;
+    let backup_path = resolve_relative_path(backups_dir, relative_path)?;
+    if !backup_path.is_file() {
+        return Err(InstanceError::UnknownConfigFile(relative_path.into()));
+    }
+    fs::rename(&backup_path, &path)?;
+    info!("Restored config file {}", path.display());
+    Ok(())
+}
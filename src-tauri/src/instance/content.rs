@@ -0,0 +1,424 @@
+//! Installs mods, resource packs and shader packs from local files, e.g. ones dropped onto the
+//! launcher. What a file is gets decided by its contents rather than its name.
+
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use log::{debug, info};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use zip::ZipArchive;
+
+use crate::{
+    state::instance_manager::{InstanceError, InstanceResult},
+    web_services::{
+        downloader::{download_bytes_from_url, validate_hash},
+        modrinth::{latest_version, ModrinthVersion},
+    },
+};
+
+use super::mods_library::{write_mod, ModsLibrary};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ContentKind {
+    Mod,
+    ResourcePack,
+    ShaderPack,
+}
+
+impl ContentKind {
+    /// Folder inside of the instance the content is installed to.
+    pub fn folder(self) -> &'static str {
+        match self {
+            ContentKind::Mod => "mods",
+            ContentKind::ResourcePack => "resourcepacks",
+            ContentKind::ShaderPack => "shaderpacks",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ModLoader {
+    Fabric,
+    Quilt,
+    Forge,
+}
+
+impl ModLoader {
+    /// The api most Fabric and Quilt mods depend on, as (Modrinth project, loader name on Modrinth).
+    pub fn api_project(self) -> Option<(&'static str, &'static str)> {
+        match self {
+            ModLoader::Fabric => Some(("fabric-api", "fabric")),
+            ModLoader::Quilt => Some(("qsl", "quilt")),
+            ModLoader::Forge => None,
+        }
+    }
+
+    /// The loader's name on Modrinth.
+    pub fn modrinth_name(self) -> &'static str {
+        match self {
+            ModLoader::Fabric => "fabric",
+            ModLoader::Quilt => "quilt",
+            ModLoader::Forge => "forge",
+        }
+    }
+
+    /// The loader's `modLoaderType` on CurseForge.
+    pub fn curseforge_type(self) -> u32 {
+        match self {
+            ModLoader::Fabric => 4,
+            ModLoader::Quilt => 5,
+            ModLoader::Forge => 1,
+        }
+    }
+}
+
+/// Mod ids of Fabric API, its pre 1.16 id and the Quilt one, which provides the Fabric API ids too.
+const API_MOD_IDS: [&str; 3] = ["fabric-api", "fabric", "quilted_fabric_api"];
+
+/// What a mod or pack says about itself, every field is optional since files in the wild are sloppy.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ContentMetadata {
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub version: Option<String>,
+    pub description: Option<String>,
+    pub loader: Option<ModLoader>,
+    /// Ids of the mods this one depends on.
+    #[serde(skip)]
+    pub depends: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InstalledContent {
+    #[serde(rename = "fileName")]
+    pub file_name: String,
+    pub kind: ContentKind,
+    pub metadata: ContentMetadata,
+    /// A file with the same name was overwritten.
+    pub replaced: bool,
+}
+
+#[derive(Deserialize)]
+struct FabricModJson {
+    id: Option<String>,
+    name: Option<String>,
+    version: Option<String>,
+    description: Option<String>,
+    #[serde(default)]
+    depends: HashMap<String, Value>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LocalInstallReport {
+    pub installed: Vec<InstalledContent>,
+    /// Offered when the installed mods need an api mod the instance doesn't have.
+    #[serde(rename = "apiSuggestion")]
+    pub api_suggestion: Option<ApiSuggestion>,
+}
+
+/// A local file that was recognized, but not installed yet.
+#[derive(Debug)]
+pub struct LocalContent {
+    pub path: PathBuf,
+    pub file_name: String,
+    pub kind: ContentKind,
+    pub metadata: ContentMetadata,
+}
+
+fn read_entry(archive: &mut ZipArchive<File>, name: &str) -> Option<String> {
+    let mut file = archive.by_name(name).ok()?;
+    let mut contents = String::new();
+    file.read_to_string(&mut contents).ok()?;
+    Some(contents)
+}
+
+fn json_str(value: &Value, pointer: &str) -> Option<String> {
+    value.pointer(pointer)?.as_str().map(String::from)
+}
+
+fn fabric_metadata(contents: &str) -> Option<ContentMetadata> {
+    let mod_json: FabricModJson = serde_json::from_str(contents).ok()?;
+    Some(ContentMetadata {
+        id: mod_json.id,
+        name: mod_json.name,
+        version: mod_json.version,
+        description: mod_json.description,
+        loader: Some(ModLoader::Fabric),
+        depends: mod_json.depends.into_keys().collect(),
+    })
+}
+
+fn quilt_metadata(contents: &str) -> Option<ContentMetadata> {
+    let value: Value = serde_json::from_str(contents).ok()?;
+    Some(ContentMetadata {
+        id: json_str(&value, "/quilt_loader/id"),
+        name: json_str(&value, "/quilt_loader/metadata/name"),
+        version: json_str(&value, "/quilt_loader/version"),
+        description: json_str(&value, "/quilt_loader/metadata/description"),
+        loader: Some(ModLoader::Quilt),
+        // Dependencies are either an id or an object with one.
+        depends: value
+            .pointer("/quilt_loader/depends")
+            .and_then(Value::as_array)
+            .map(|depends| {
+                depends
+                    .iter()
+                    .filter_map(|depend| match depend {
+                        Value::String(id) => Some(id.clone()),
+                        depend => json_str(depend, "/id"),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default(),
+    })
+}
+
+/// Reads the first mod of a `mods.toml`. Only the plain `key = "value"` lines are needed, which
+/// saves pulling in a toml parser.
+fn forge_metadata(contents: &str) -> ContentMetadata {
+    let mods_section = contents
+        .split("[[mods]]")
+        .nth(1)
+        .map(|section| section.split("[[").next().unwrap_or(section))
+        .unwrap_or("");
+    let field = |key: &str| {
+        let pattern = format!(r#"(?m)^\s*{}\s*=\s*"([^"]*)""#, key);
+        Regex::new(&pattern)
+            .ok()?
+            .captures(mods_section)
+            .map(|captures| captures[1].to_string())
+    };
+    ContentMetadata {
+        id: field("modId"),
+        name: field("displayName"),
+        // `${file.jarVersion}` is filled in from the manifest at build time of the mod.
+        version: field("version").filter(|version| !version.starts_with("${")),
+        description: None,
+        loader: Some(ModLoader::Forge),
+        depends: Vec::new(),
+    }
+}
+
+/// `mcmod.info` of mods from before 1.13, either a list of mods or an object holding one.
+fn legacy_forge_metadata(contents: &str) -> Option<ContentMetadata> {
+    let value: Value = serde_json::from_str(contents).ok()?;
+    let first = match value.pointer("/modList/0") {
+        Some(first) => first,
+        None => value.get(0)?,
+    };
+    Some(ContentMetadata {
+        id: json_str(first, "/modid"),
+        name: json_str(first, "/name"),
+        version: json_str(first, "/version"),
+        description: json_str(first, "/description"),
+        loader: Some(ModLoader::Forge),
+        depends: Vec::new(),
+    })
+}
+
+/// The description of a `pack.mcmeta` is a string or a text component.
+fn pack_metadata(contents: &str) -> Option<ContentMetadata> {
+    let value: Value = serde_json::from_str(contents).ok()?;
+    let description = match value.pointer("/pack/description")? {
+        Value::String(description) => Some(description.clone()),
+        Value::Object(component) => component
+            .get("text")
+            .and_then(Value::as_str)
+            .map(String::from),
+        _ => None,
+    };
+    Some(ContentMetadata {
+        description,
+        ..Default::default()
+    })
+}
+
+/// Recognizes a local jar or zip file. Jars are always mods, zips are resource packs when they
+/// have a `pack.mcmeta` and shader packs when they have a `shaders` folder.
+pub fn inspect_local_file(path: &Path) -> InstanceResult<LocalContent> {
+    let file_name = path
+        .file_name()
+        .map(|file_name| file_name.to_string_lossy().into_owned())
+        .ok_or_else(|| InstanceError::UnsupportedContent(path.display().to_string()))?;
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase());
+    let is_jar = match extension.as_deref() {
+        Some("jar") => true,
+        Some("zip") => false,
+        _ => return Err(InstanceError::UnsupportedContent(file_name)),
+    };
+    let mut archive = ZipArchive::new(File::open(path)?)?;
+
+    let (kind, metadata) = if let Some(contents) = read_entry(&mut archive, "fabric.mod.json") {
+        (ContentKind::Mod, fabric_metadata(&contents))
+    } else if let Some(contents) = read_entry(&mut archive, "quilt.mod.json") {
+        (ContentKind::Mod, quilt_metadata(&contents))
+    } else if let Some(contents) = read_entry(&mut archive, "META-INF/mods.toml") {
+        (ContentKind::Mod, Some(forge_metadata(&contents)))
+    } else if let Some(contents) = read_entry(&mut archive, "mcmod.info") {
+        (ContentKind::Mod, legacy_forge_metadata(&contents))
+    } else if is_jar {
+        // Library mods and very old mods carry no metadata at all.
+        (ContentKind::Mod, None)
+    } else if let Some(contents) = read_entry(&mut archive, "pack.mcmeta") {
+        (ContentKind::ResourcePack, pack_metadata(&contents))
+    } else if archive
+        .file_names()
+        .any(|name| name.starts_with("shaders/"))
+    {
+        (ContentKind::ShaderPack, None)
+    } else {
+        return Err(InstanceError::UnsupportedContent(file_name));
+    };
+    debug!("Recognized {} as {:?}", file_name, kind);
+    Ok(LocalContent {
+        path: path.into(),
+        file_name,
+        kind,
+        metadata: metadata.unwrap_or_default(),
+    })
+}
+
+/// Copies recognized files into their folders of the instance, overwriting files of the same name.
+pub fn install_local_content(
+    instance_dir: &Path,
+    contents: Vec<LocalContent>,
+    library: Option<&ModsLibrary>,
+) -> InstanceResult<Vec<InstalledContent>> {
+    let mut installed = Vec::new();
+    for content in contents {
+        let folder = instance_dir.join(content.kind.folder());
+        fs::create_dir_all(&folder)?;
+        let destination = folder.join(&content.file_name);
+        let replaced = destination.exists();
+        fs::copy(&content.path, &destination)?;
+        if let (ContentKind::Mod, Some(library)) = (content.kind, library) {
+            library.adopt(&destination)?;
+        }
+        info!("Installed {} into {}", content.file_name, folder.display());
+        installed.push(InstalledContent {
+            file_name: content.file_name,
+            kind: content.kind,
+            metadata: content.metadata,
+            replaced,
+        });
+    }
+    Ok(installed)
+}
+
+/// Returns the loader whose api one of the `installed` mods depends on, when no mod in `mods_dir`
+/// provides it.
+pub fn missing_api(mods_dir: &Path, installed: &[InstalledContent]) -> Option<ModLoader> {
+    let loader = installed
+        .iter()
+        .filter(|content| {
+            content
+                .metadata
+                .depends
+                .iter()
+                .any(|depend| API_MOD_IDS.contains(&depend.as_str()))
+        })
+        .find_map(|content| content.metadata.loader)?;
+    let provided = fs::read_dir(mods_dir)
+        .ok()?
+        .filter_map(|entry| inspect_local_file(&entry.ok()?.path()).ok())
+        .any(|content| {
+            content
+                .metadata
+                .id
+                .map_or(false, |id| API_MOD_IDS.contains(&id.as_str()))
+        });
+    if provided {
+        None
+    } else {
+        Some(loader)
+    }
+}
+
+/// An api mod that the installed mods need, offered to the user before it is installed.
+#[derive(Debug, Clone, Serialize)]
+pub struct ApiSuggestion {
+    pub loader: ModLoader,
+    /// The Modrinth project.
+    pub project: String,
+    #[serde(rename = "versionNumber")]
+    pub version_number: String,
+    #[serde(rename = "fileName")]
+    pub file_name: String,
+}
+
+async fn latest_api_version(
+    loader: ModLoader,
+    game_version: &str,
+) -> InstanceResult<(&'static str, ModrinthVersion)> {
+    let (project, loader_name) = loader
+        .api_project()
+        .ok_or_else(|| InstanceError::UnsupportedContent(format!("{:?} api", loader)))?;
+    let version = latest_version(project, loader_name, game_version)
+        .await?
+        .ok_or_else(|| InstanceError::NoCompatibleVersion {
+            project: project.into(),
+            game_version: game_version.into(),
+        })?;
+    Ok((project, version))
+}
+
+/// Finds the newest version of the loader's api for the minecraft version.
+pub async fn suggest_api(loader: ModLoader, game_version: &str) -> InstanceResult<ApiSuggestion> {
+    let (project, version) = latest_api_version(loader, game_version).await?;
+    let file = version
+        .primary_file()
+        .ok_or_else(|| InstanceError::InvalidDownload(project.into()))?;
+    Ok(ApiSuggestion {
+        loader,
+        project: project.into(),
+        version_number: version.version_number.clone(),
+        file_name: file.filename.clone(),
+    })
+}
+
+/// Downloads the newest version of the loader's api for the minecraft version into `mods_dir`.
+pub async fn install_api(
+    mods_dir: &Path,
+    loader: ModLoader,
+    game_version: &str,
+    library: Option<&ModsLibrary>,
+) -> InstanceResult<InstalledContent> {
+    let (project, version) = latest_api_version(loader, game_version).await?;
+    let file = version
+        .primary_file()
+        .ok_or_else(|| InstanceError::InvalidDownload(project.into()))?;
+    // The file name comes from the api, it must not point outside of the mods folder.
+    if Path::new(&file.filename).file_name() != Some(file.filename.as_ref()) {
+        return Err(InstanceError::InvalidDownload(file.filename.clone()));
+    }
+    let bytes = download_bytes_from_url(&file.url).await?;
+    if !validate_hash(&bytes, &file.hashes.sha1) {
+        return Err(InstanceError::InvalidDownload(file.filename.clone()));
+    }
+    let path = mods_dir.join(&file.filename);
+    let replaced = path.exists();
+    write_mod(library, &bytes, &path)?;
+    info!(
+        "Installed {} {} into {}",
+        project,
+        version.version_number,
+        mods_dir.display()
+    );
+    let content = inspect_local_file(&path)?;
+    Ok(InstalledContent {
+        file_name: content.file_name,
+        kind: content.kind,
+        metadata: content.metadata,
+        replaced,
+    })
+}
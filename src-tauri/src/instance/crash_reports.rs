@@ -0,0 +1,294 @@
+use std::{
+    cmp::Reverse,
+    fs, io,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use indexmap::IndexMap;
+use regex::Regex;
+use serde::Serialize;
+
+use crate::{
+    instance::resolve_file_in,
+    redact::redact,
+    state::instance_manager::{InstanceError, InstanceResult},
+};
+
+/// Separates the summary at the top of a report from its sections.
+const WALKTHROUGH_PREFIX: &str = "A detailed walkthrough of the error";
+const SYSTEM_DETAILS_SECTION: &str = "System Details";
+
+/// Forge lists suspected mods as `Create (create), Version: 0.5.0`.
+const SUSPECTED_MOD_PATTERN: &str =
+    r"^(?P<name>.+?) \((?P<id>[^()\s]+)\)(?:, Version: (?P<version>.+))?$";
+/// Mod ids that show up in stacktraces, from forge's transformed classes
+/// (`TRANSFORMER/create@0.5.0/...`), mixin configs (`pl:mixin:APP:create.mixins.json`) and the
+/// names of fabric's injected handlers (`handler$zfe000$sodium$onRender`).
+const STACKTRACE_MOD_PATTERN: &str = concat!(
+    r"TRANSFORMER/(?P<transformer>[a-z0-9_\-]+)@",
+    r"|pl:mixin:APP:(?P<mixin>[a-z0-9_\-]+)\.mixins\.json",
+    r"|\$[a-z]{3}\d{3}\$(?P<handler>[a-z0-9_]+)\$"
+);
+/// Mod ids from stacktraces that are part of the game or its loaders and never the culprit.
+const IGNORED_MOD_IDS: [&str; 7] = [
+    "minecraft",
+    "forge",
+    "fml",
+    "mixin",
+    "javafmllanguage",
+    "fabricloader",
+    "quilt_loader",
+];
+
+#[derive(Debug, Serialize)]
+pub struct CrashReportEntry {
+    #[serde(rename = "fileName")]
+    pub file_name: String,
+    /// Size of the file in bytes.
+    pub size: u64,
+    /// Timestamp in seconds of when the report was written.
+    pub modified: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CrashReport {
+    pub time: Option<String>,
+    pub description: Option<String>,
+    /// The stacktrace of the error that crashed the game.
+    pub stacktrace: String,
+    /// Every section except for the system details, e.g. `Head` or `Affected level`.
+    pub sections: Vec<CrashReportSection>,
+    #[serde(rename = "systemDetails")]
+    pub system_details: IndexMap<String, String>,
+    /// Mods the loader suspected, followed by any other mods found in the stacktraces.
+    #[serde(rename = "affectedMods")]
+    pub affected_mods: Vec<AffectedMod>,
+    /// Id of the mod most likely to have caused the crash.
+    #[serde(rename = "probableCulprit")]
+    pub probable_culprit: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct CrashReportSection {
+    pub title: String,
+    pub details: IndexMap<String, String>,
+    pub stacktrace: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AffectedMod {
+    pub id: String,
+    pub name: Option<String>,
+    pub version: Option<String>,
+}
+
+/// Lists the crash reports in a `crash-reports` folder, newest first.
+pub fn list_crash_reports(crash_reports_dir: &Path) -> io::Result<Vec<CrashReportEntry>> {
+    let mut crash_reports = Vec::new();
+    for entry in fs::read_dir(crash_reports_dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if path.extension() != Some("txt".as_ref()) {
+            continue;
+        }
+        let metadata = entry.metadata()?;
+        crash_reports.push(CrashReportEntry {
+            file_name: entry.file_name().to_string_lossy().into_owned(),
+            size: metadata.len(),
+            modified: metadata
+                .modified()
+                .ok()
+                .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+                .map(|duration| duration.as_secs() as i64),
+        });
+    }
+    crash_reports.sort_by_key(|crash_report| Reverse(crash_report.modified));
+    Ok(crash_reports)
+}
+
+pub fn get_crash_report_path(crash_reports_dir: &Path, file_name: &str) -> InstanceResult<PathBuf> {
+    resolve_file_in(crash_reports_dir, file_name)
+        .ok_or_else(|| InstanceError::UnknownCrashReport(file_name.into()))
+}
+
+pub fn read_crash_report(crash_reports_dir: &Path, file_name: &str) -> InstanceResult<CrashReport> {
+    let path = get_crash_report_path(crash_reports_dir, file_name)?;
+    let contents = fs::read(path)?;
+    let contents = redact(&String::from_utf8_lossy(&contents));
+    Ok(parse_crash_report(&contents))
+}
+
+pub fn parse_crash_report(contents: &str) -> CrashReport {
+    let mut lines = contents.lines();
+    let mut time = None;
+    let mut description = None;
+    let mut stacktrace = Vec::new();
+    for line in lines.by_ref() {
+        if line.starts_with(WALKTHROUGH_PREFIX) {
+            break;
+        }
+        if let Some(value) = line.strip_prefix("Time: ") {
+            time = Some(value.to_string());
+        } else if let Some(value) = line.strip_prefix("Description: ") {
+            description = Some(value.to_string());
+        } else if description.is_some() && !line.trim().is_empty() {
+            stacktrace.push(line);
+        }
+    }
+
+    let mut sections = parse_sections(lines);
+    let system_details = sections
+        .iter()
+        .position(|section| section.title == SYSTEM_DETAILS_SECTION)
+        .map(|index| sections.remove(index).details)
+        .unwrap_or_default();
+    let stacktrace = stacktrace.join("\n");
+    let affected_mods = find_affected_mods(&stacktrace, &sections);
+    CrashReport {
+        time,
+        description,
+        stacktrace,
+        probable_culprit: affected_mods
+            .first()
+            .map(|affected_mod| affected_mod.id.clone()),
+        sections,
+        system_details,
+        affected_mods,
+    }
+}
+
+/// Parses sections like the following, where lines indented past a detail continue its value.
+/// ```text
+/// -- Head --
+/// Thread: Render thread
+/// Stacktrace:
+///     at net.minecraft.client.Minecraft.run(Minecraft.java:100)
+///
+/// -- System Details --
+/// Details:
+///     Minecraft Version: 1.19.2
+///     Mod List:
+///         ...
+/// ```
+fn parse_sections<'a>(lines: impl Iterator<Item = &'a str>) -> Vec<CrashReportSection> {
+    let mut sections: Vec<CrashReportSection> = Vec::new();
+    let mut in_details = false;
+    let mut in_stacktrace = false;
+    let mut last_key: Option<String> = None;
+    for line in lines {
+        if let Some(title) = line
+            .strip_prefix("-- ")
+            .and_then(|line| line.strip_suffix(" --"))
+        {
+            sections.push(CrashReportSection {
+                title: title.to_string(),
+                ..Default::default()
+            });
+            in_details = false;
+            in_stacktrace = false;
+            last_key = None;
+            continue;
+        }
+        let section = match sections.last_mut() {
+            Some(section) => section,
+            None => continue,
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        if line.starts_with('\t') {
+            if in_stacktrace {
+                let stacktrace = section.stacktrace.get_or_insert_with(String::new);
+                if !stacktrace.is_empty() {
+                    stacktrace.push('\n');
+                }
+                stacktrace.push_str(line.trim());
+            } else if in_details && !line.starts_with("\t\t") {
+                last_key = insert_detail(section, line.trim());
+            } else if let Some(value) = last_key
+                .as_ref()
+                .and_then(|key| section.details.get_mut(key))
+            {
+                if !value.is_empty() {
+                    value.push('\n');
+                }
+                value.push_str(line.trim());
+            }
+            continue;
+        }
+
+        in_details = false;
+        in_stacktrace = false;
+        match line.trim_end() {
+            "Details:" => in_details = true,
+            "Stacktrace:" => in_stacktrace = true,
+            line => last_key = insert_detail(section, line),
+        }
+    }
+    sections
+}
+
+/// Inserts a `Key: value` line, returning the key so following lines can continue its value.
+fn insert_detail(section: &mut CrashReportSection, line: &str) -> Option<String> {
+    let (key, value) = match line.split_once(": ") {
+        Some((key, value)) => (key, value.trim()),
+        None => (line.strip_suffix(':')?, ""),
+    };
+    section.details.insert(key.to_string(), value.to_string());
+    Some(key.to_string())
+}
+
+fn find_affected_mods(stacktrace: &str, sections: &[CrashReportSection]) -> Vec<AffectedMod> {
+    let suspected_pattern =
+        Regex::new(SUSPECTED_MOD_PATTERN).expect("The suspected mod pattern should be valid");
+    let stacktrace_pattern =
+        Regex::new(STACKTRACE_MOD_PATTERN).expect("The stacktrace mod pattern should be valid");
+
+    let mut affected_mods: Vec<AffectedMod> = Vec::new();
+    let suspected = sections
+        .iter()
+        .flat_map(|section| section.details.iter())
+        .filter(|(key, _)| key.starts_with("Suspected Mod"))
+        .flat_map(|(_, value)| value.lines());
+    for line in suspected {
+        if let Some(captures) = suspected_pattern.captures(line.trim()) {
+            affected_mods.push(AffectedMod {
+                id: captures["id"].to_string(),
+                name: Some(captures["name"].to_string()),
+                version: captures
+                    .name("version")
+                    .map(|version| version.as_str().to_string()),
+            });
+        }
+    }
+
+    let stacktraces = std::iter::once(stacktrace).chain(
+        sections
+            .iter()
+            .filter_map(|section| section.stacktrace.as_deref()),
+    );
+    for stacktrace in stacktraces {
+        for captures in stacktrace_pattern.captures_iter(stacktrace) {
+            let id = ["transformer", "mixin", "handler"]
+                .iter()
+                .find_map(|group| captures.name(group))
+                .map(|id| id.as_str())
+                .unwrap_or_default();
+            if IGNORED_MOD_IDS.contains(&id)
+                || affected_mods
+                    .iter()
+                    .any(|affected_mod| affected_mod.id == id)
+            {
+                continue;
+            }
+            affected_mods.push(AffectedMod {
+                id: id.to_string(),
+                name: None,
+                version: None,
+            });
+        }
+    }
+    affected_mods
+}
@@ -0,0 +1,112 @@
+//! Fatal errors of the JVM itself, e.g. crashes in graphics drivers or running out of native memory.
+//! The game can't write a crash report for those, the JVM writes an `hs_err_pid<pid>.log` into its
+//! working directory instead. Only the summary at the top of the file is parsed:
+//! ```text
+//! #
+//! # A fatal error has been detected by the Java Runtime Environment:
+//! #
+//! #  EXCEPTION_ACCESS_VIOLATION (0xc0000005) at pc=0x00007ffb1a2b3c4d, pid=1234, tid=5678
+//! #
+//! # JRE version: OpenJDK Runtime Environment (17.0.8+7) (build 17.0.8+7)
+//! # Java VM: OpenJDK 64-Bit Server VM (17.0.8+7, mixed mode, tiered, compressed oops, g1 gc, windows-amd64)
+//! # Problematic frame:
+//! # C  [atio6axx.dll+0x1a2b3c]
+//! ```
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use log::{info, warn};
+use serde::Serialize;
+
+const FATAL_ERROR_LOG_PREFIX: &str = "hs_err_pid";
+const FATAL_ERROR_HEADER: &str = "A fatal error has been detected by the Java Runtime Environment";
+const PROBLEMATIC_FRAME: &str = "Problematic frame:";
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct JvmFatalError {
+    #[serde(rename = "fileName")]
+    pub file_name: String,
+    /// What went wrong, e.g. `EXCEPTION_ACCESS_VIOLATION (0xc0000005) at pc=...` or
+    /// `There is insufficient memory for the Java Runtime Environment to continue.`
+    pub reason: Option<String>,
+    /// The native or java frame that crashed, e.g. `C  [atio6axx.dll+0x1a2b3c]`. A library there is
+    /// usually the cause, like a graphics driver.
+    #[serde(rename = "problematicFrame")]
+    pub problematic_frame: Option<String>,
+    #[serde(rename = "jreVersion")]
+    pub jre_version: Option<String>,
+    #[serde(rename = "javaVm")]
+    pub java_vm: Option<String>,
+}
+
+/// Parses the summary of an `hs_err_pid*.log`, the comment block the file starts with.
+pub fn parse_fatal_error(file_name: &str, contents: &str) -> JvmFatalError {
+    let mut fatal_error = JvmFatalError {
+        file_name: file_name.into(),
+        ..Default::default()
+    };
+    let summary = contents
+        .lines()
+        .take_while(|line| line.starts_with('#'))
+        .map(|line| line.trim_start_matches('#').trim())
+        .filter(|line| !line.is_empty() && !line.starts_with(FATAL_ERROR_HEADER));
+    let mut in_problematic_frame = false;
+    for line in summary {
+        if in_problematic_frame {
+            fatal_error.problematic_frame = Some(line.into());
+            in_problematic_frame = false;
+        } else if line == PROBLEMATIC_FRAME {
+            in_problematic_frame = true;
+        } else if let Some(version) = line.strip_prefix("JRE version: ") {
+            fatal_error.jre_version = Some(version.into());
+        } else if let Some(vm) = line.strip_prefix("Java VM: ") {
+            fatal_error.java_vm = Some(vm.into());
+        } else if fatal_error.reason.is_none() {
+            fatal_error.reason = Some(line.into());
+        }
+    }
+    fatal_error
+}
+
+/// Finds the newest `hs_err_pid*.log` in `working_dir` that was written at or after `since`, the time
+/// the game was started. Older files belong to earlier crashes.
+pub fn find_fatal_error(working_dir: &Path, since: SystemTime) -> Option<JvmFatalError> {
+    let entries = match fs::read_dir(working_dir) {
+        Ok(entries) => entries,
+        Err(error) => {
+            warn!("Could not look for JVM fatal error logs: {}", error);
+            return None;
+        }
+    };
+    let (path, _): (PathBuf, SystemTime) = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            let file_name = entry.file_name();
+            let file_name = file_name.to_string_lossy();
+            file_name.starts_with(FATAL_ERROR_LOG_PREFIX) && file_name.ends_with(".log")
+        })
+        .filter_map(|entry| {
+            let modified = entry.metadata().ok()?.modified().ok()?;
+            Some((entry.path(), modified))
+        })
+        .filter(|(_, modified)| *modified >= since)
+        .max_by_key(|(_, modified)| *modified)?;
+
+    let contents = match fs::read(&path) {
+        Ok(contents) => contents,
+        Err(error) => {
+            warn!("Could not read {}: {}", path.display(), error);
+            return None;
+        }
+    };
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    info!("The JVM crashed, see {}", path.display());
+    Some(parse_fatal_error(
+        &file_name,
+        &String::from_utf8_lossy(&contents),
+    ))
+}
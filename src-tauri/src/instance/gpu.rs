@@ -0,0 +1,70 @@
+use std::{path::Path, process::Command};
+
+/// Prepares `command` to run the game on the discrete gpu of a hybrid graphics system. Linux selects
+/// the gpu through environment variables, NVIDIA's proprietary driver uses render offload while mesa
+/// drivers use `DRI_PRIME`.
+#[cfg(target_os = "linux")]
+pub fn apply_gpu_preference(command: &mut Command, _jvm_path: &Path, discrete: bool) {
+    if !discrete {
+        return;
+    }
+    if Path::new("/proc/driver/nvidia").exists() {
+        command
+            .env("__NV_PRIME_RENDER_OFFLOAD", "1")
+            .env("__GLX_VENDOR_LIBRARY_NAME", "nvidia")
+            .env("__VK_LAYER_NV_optimus", "NVIDIA_only");
+    } else {
+        command.env("DRI_PRIME", "1");
+    }
+}
+
+/// Windows keeps a per executable preference in the registry, which is written for the instance's
+/// java before it is spawned.
+#[cfg(target_os = "windows")]
+pub fn apply_gpu_preference(_command: &mut Command, jvm_path: &Path, discrete: bool) {
+    if let Err(error) = windows::set_gpu_preference(jvm_path, discrete) {
+        log::warn!(
+            "Could not set the gpu preference for {}: {}",
+            jvm_path.display(),
+            error
+        );
+    }
+}
+
+/// Nothing to set up elsewhere, macOS picks the gpu on its own.
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+pub fn apply_gpu_preference(_command: &mut Command, _jvm_path: &Path, _discrete: bool) {}
+
+#[cfg(target_os = "windows")]
+mod windows {
+    use std::{io, path::Path, process::Command};
+
+    /// Registry key the Windows graphics settings store their per application preferences in.
+    const GPU_PREFERENCES_KEY: &str = r"HKCU\Software\Microsoft\DirectX\UserGpuPreferences";
+
+    /// `GpuPreference=2` is "high performance", removing the value goes back to letting Windows decide.
+    /// Java runtimes can be shared by instances, so the preference is set again on every launch.
+    pub fn set_gpu_preference(jvm_path: &Path, discrete: bool) -> io::Result<()> {
+        let jvm_path = jvm_path.to_string_lossy();
+        let mut command = Command::new("reg");
+        if discrete {
+            command.args(&[
+                "add",
+                GPU_PREFERENCES_KEY,
+                "/v",
+                &jvm_path,
+                "/t",
+                "REG_SZ",
+                "/d",
+                "GpuPreference=2;",
+                "/f",
+            ]);
+            command.output()?;
+        } else {
+            // Fails when there is no preference for the runtime, which is fine.
+            command.args(&["delete", GPU_PREFERENCES_KEY, "/v", &jvm_path, "/f"]);
+            command.output()?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,222 @@
+//! How long the launches of an instance took, kept in `.launches.json` in the instance directory. Each
+//! launch is split into phases so slow launches can be traced back to a step, the game window opening
+//! is detected from the game's log output.
+
+use std::{
+    fs, io,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+pub const LAUNCH_METRICS_FILE: &str = ".launches.json";
+/// Older launches are dropped, they don't say much about the instance as it is now.
+const MAX_RECORDED_LAUNCHES: usize = 50;
+
+/// Lines the game logs once its window is created, `Backend library` since 1.13 and `LWJGL Version`
+/// before that.
+const WINDOW_OPEN_MARKERS: [&str; 2] = ["Backend library: LWJGL version", "LWJGL Version: "];
+
+/// The steps of a launch, in the order they happen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LaunchPhase {
+    /// Checking the natives, the files they are extracted from and the java to launch with.
+    Verification,
+    /// Building the jvm and game arguments.
+    Arguments,
+    /// Pulling the synced folders and running the pre launch hook.
+    Hooks,
+    /// Starting the java process.
+    Spawn,
+    /// From the java process starting to the game window opening.
+    Window,
+}
+
+/// Durations in milliseconds of the phases of a single launch.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct LaunchTiming {
+    /// Timestamp in seconds of when the launch started.
+    #[serde(rename = "launchedAt")]
+    pub launched_at: i64,
+    #[serde(rename = "verificationMs")]
+    pub verification_ms: u64,
+    #[serde(rename = "argumentsMs")]
+    pub arguments_ms: u64,
+    #[serde(rename = "hooksMs")]
+    pub hooks_ms: u64,
+    #[serde(rename = "spawnMs")]
+    pub spawn_ms: u64,
+    /// None if the game exited before its window opened, or logged in a way that wasn't recognized.
+    #[serde(rename = "windowMs")]
+    pub window_ms: Option<u64>,
+}
+
+impl LaunchTiming {
+    /// From clicking play to the game window, None if the window wasn't seen.
+    pub fn total_ms(&self) -> Option<u64> {
+        self.window_ms.map(|window_ms| {
+            self.verification_ms + self.arguments_ms + self.hooks_ms + self.spawn_ms + window_ms
+        })
+    }
+}
+
+/// Times the phases of a launch in progress, each phase lasts from the end of the previous one.
+#[derive(Debug)]
+pub struct LaunchTimer {
+    phase_start: Instant,
+    timing: LaunchTiming,
+}
+
+impl LaunchTimer {
+    pub fn start() -> Self {
+        Self {
+            phase_start: Instant::now(),
+            timing: LaunchTiming {
+                launched_at: chrono::Local::now().timestamp(),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Ends `phase`, the next phase starts now.
+    pub fn end_phase(&mut self, phase: LaunchPhase) {
+        let now = Instant::now();
+        let elapsed = duration_ms(now.duration_since(self.phase_start));
+        self.phase_start = now;
+        let timing = &mut self.timing;
+        match phase {
+            LaunchPhase::Verification => timing.verification_ms = elapsed,
+            LaunchPhase::Arguments => timing.arguments_ms = elapsed,
+            LaunchPhase::Hooks => timing.hooks_ms = elapsed,
+            LaunchPhase::Spawn => timing.spawn_ms = elapsed,
+            LaunchPhase::Window => timing.window_ms = Some(elapsed),
+        }
+    }
+
+    /// Adds the launch to the metrics of the instance. Failures are only logged, launching doesn't
+    /// depend on them.
+    pub fn record(self, instance_dir: &Path) {
+        debug!(
+            "Launch of {} took {:?}",
+            instance_dir.display(),
+            self.timing
+        );
+        let result = LaunchMetrics::load(instance_dir).and_then(|mut metrics| {
+            metrics.launches.push(self.timing);
+            let excess = metrics.launches.len().saturating_sub(MAX_RECORDED_LAUNCHES);
+            metrics.launches.drain(..excess);
+            metrics.save(instance_dir)
+        });
+        if let Err(error) = result {
+            warn!(
+                "Could not record the launch time of {}: {}",
+                instance_dir.display(),
+                error
+            );
+        }
+    }
+}
+
+fn duration_ms(duration: Duration) -> u64 {
+    duration.as_millis() as u64
+}
+
+/// Whether `line` of the game's output means its window has opened.
+pub fn is_window_open_line(line: &str) -> bool {
+    WINDOW_OPEN_MARKERS
+        .iter()
+        .any(|marker| line.contains(marker))
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct LaunchMetrics {
+    /// Oldest first.
+    #[serde(default)]
+    pub launches: Vec<LaunchTiming>,
+}
+
+impl LaunchMetrics {
+    /// Loads the metrics of an instance, instances that were never launched start out empty.
+    pub fn load(instance_dir: &Path) -> io::Result<Self> {
+        match fs::read(instance_dir.join(LAUNCH_METRICS_FILE)) {
+            Ok(bytes) => Ok(serde_json::from_slice(&bytes)?),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(error) => Err(error),
+        }
+    }
+
+    pub fn save(&self, instance_dir: &Path) -> io::Result<()> {
+        fs::write(
+            instance_dir.join(LAUNCH_METRICS_FILE),
+            serde_json::to_vec_pretty(self)?,
+        )
+    }
+
+    pub fn stats(&self) -> LaunchStats {
+        let phase = |duration: fn(&LaunchTiming) -> Option<u64>| {
+            PhaseStats::of(self.launches.iter().filter_map(duration).collect())
+        };
+        LaunchStats {
+            launches: self.launches.len(),
+            window_not_detected: self
+                .launches
+                .iter()
+                .filter(|launch| launch.window_ms.is_none())
+                .count(),
+            verification: phase(|launch| Some(launch.verification_ms)),
+            arguments: phase(|launch| Some(launch.arguments_ms)),
+            hooks: phase(|launch| Some(launch.hooks_ms)),
+            spawn: phase(|launch| Some(launch.spawn_ms)),
+            window: phase(|launch| launch.window_ms),
+            total: phase(LaunchTiming::total_ms),
+            history: self.launches.clone(),
+        }
+    }
+}
+
+/// Milliseconds a phase took over the recorded launches.
+#[derive(Debug, Clone, Serialize)]
+pub struct PhaseStats {
+    #[serde(rename = "averageMs")]
+    pub average_ms: u64,
+    #[serde(rename = "medianMs")]
+    pub median_ms: u64,
+    #[serde(rename = "fastestMs")]
+    pub fastest_ms: u64,
+    #[serde(rename = "slowestMs")]
+    pub slowest_ms: u64,
+}
+
+impl PhaseStats {
+    /// None without any durations.
+    fn of(mut durations: Vec<u64>) -> Option<Self> {
+        if durations.is_empty() {
+            return None;
+        }
+        durations.sort_unstable();
+        Some(Self {
+            average_ms: durations.iter().sum::<u64>() / durations.len() as u64,
+            median_ms: durations[durations.len() / 2],
+            fastest_ms: durations[0],
+            slowest_ms: durations[durations.len() - 1],
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct LaunchStats {
+    pub launches: usize,
+    /// Launches whose window opening wasn't seen, they are left out of the window and total stats.
+    #[serde(rename = "windowNotDetected")]
+    pub window_not_detected: usize,
+    pub verification: Option<PhaseStats>,
+    pub arguments: Option<PhaseStats>,
+    pub hooks: Option<PhaseStats>,
+    pub spawn: Option<PhaseStats>,
+    pub window: Option<PhaseStats>,
+    pub total: Option<PhaseStats>,
+    /// Every recorded launch, oldest first.
+    pub history: Vec<LaunchTiming>,
+}
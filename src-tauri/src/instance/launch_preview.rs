@@ -0,0 +1,82 @@
+//! The command a launch would run, for users and maintainers to inspect without starting the game.
+//! Tokens are redacted so a preview can be shared when asking for help.
+
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+use crate::redact::redact;
+
+/// What the access token argument shows instead of the token.
+const MASKED_TOKEN: &str = "[access token]";
+
+/// The game argument followed by the account's access token.
+const ACCESS_TOKEN_ARGUMENT: &str = "--accessToken";
+
+/// Everything needed to start the game of an instance.
+#[derive(Debug, Clone)]
+pub struct LaunchCommand {
+    pub java: PathBuf,
+    pub working_dir: PathBuf,
+    pub arguments: Vec<String>,
+}
+
+impl LaunchCommand {
+    /// Whether the argument at `index` is the account's access token.
+    pub fn is_access_token(&self, index: usize) -> bool {
+        index > 0 && self.arguments[index - 1] == ACCESS_TOKEN_ARGUMENT
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LaunchPreview {
+    pub java: PathBuf,
+    #[serde(rename = "workingDir")]
+    pub working_dir: PathBuf,
+    pub arguments: Vec<String>,
+    /// The java path and arguments joined into a single line, quoted where needed.
+    #[serde(rename = "commandLine")]
+    pub command_line: String,
+}
+
+impl LaunchPreview {
+    pub fn new(command: &LaunchCommand) -> Self {
+        // Anything besides the access token that looks like a token is redacted too.
+        let arguments: Vec<String> = command
+            .arguments
+            .iter()
+            .enumerate()
+            .map(|(index, argument)| {
+                if command.is_access_token(index) {
+                    MASKED_TOKEN.into()
+                } else {
+                    redact(argument)
+                }
+            })
+            .collect();
+        let command_line = std::iter::once(command.java.to_string_lossy().into_owned())
+            .chain(arguments.iter().cloned())
+            .map(|argument| quote(&argument))
+            .collect::<Vec<String>>()
+            .join(" ");
+        Self {
+            java: command.java.clone(),
+            working_dir: command.working_dir.clone(),
+            arguments,
+            command_line,
+        }
+    }
+}
+
+/// Wraps an argument in double quotes if it is empty or contains whitespace or quotes.
+fn quote(argument: &str) -> String {
+    let needs_quotes = argument.is_empty()
+        || argument
+            .chars()
+            .any(|c| c.is_whitespace() || c == '"' || c == '\'');
+    if needs_quotes {
+        format!("\"{}\"", argument.replace('"', "\\\""))
+    } else {
+        argument.into()
+    }
+}
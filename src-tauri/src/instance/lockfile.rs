@@ -0,0 +1,224 @@
+//! Lockfiles record the exact files an instance runs with: the game jar, the libraries and the mod
+//! jars, each with its hash and where to download it again. An instance whose files were changed or
+//! corrupted can be put back to the state of its lockfile.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::Path,
+};
+
+use chrono::Local;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    state::instance_manager::{InstanceError, InstanceResult},
+    web_services::{
+        downloader::{download_bytes_from_url, validate_hash},
+        hash_cache,
+        modrinth::versions_from_hashes,
+    },
+};
+
+use super::{
+    modpack::is_safe_path,
+    mods_library::{write_mod, ModsLibrary},
+};
+
+/// Kept in the instance directory.
+pub const LOCKFILE: &str = "instance.lock.json";
+const LOCKFILE_SCHEMA_VERSION: u32 = 1;
+const MODS_DIR: &str = "mods";
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LockedFile {
+    /// Path relative to the folder the file is kept in, with `/` as separator.
+    pub path: String,
+    pub sha1: String,
+    /// Where the file can be downloaded again, None for files that aren't hosted anywhere known.
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Lockfile {
+    #[serde(rename = "schemaVersion")]
+    pub schema_version: u32,
+    /// Timestamp in seconds of when the lockfile was generated.
+    #[serde(rename = "generatedAt")]
+    pub generated_at: i64,
+    #[serde(rename = "versionId")]
+    pub version_id: String,
+    /// Mod loaders and their versions, taken from the modpack the instance was installed from.
+    #[serde(default)]
+    pub loaders: BTreeMap<String, String>,
+    /// Relative to the versions folder.
+    #[serde(rename = "gameJar")]
+    pub game_jar: LockedFile,
+    /// Relative to the libraries folder.
+    pub libraries: Vec<LockedFile>,
+    /// Relative to the instance directory.
+    pub mods: Vec<LockedFile>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct LockfileRestoreReport {
+    /// Files that were missing or changed and were downloaded again.
+    pub restored: Vec<String>,
+    /// Files that were missing or changed but can't be downloaded.
+    pub unavailable: Vec<String>,
+    /// Mod jars that aren't in the lockfile and were moved to the trash.
+    pub removed: Vec<String>,
+}
+
+impl Lockfile {
+    pub fn new(
+        version_id: String,
+        loaders: BTreeMap<String, String>,
+        game_jar: LockedFile,
+        libraries: Vec<LockedFile>,
+        mods: Vec<LockedFile>,
+    ) -> Self {
+        Self {
+            schema_version: LOCKFILE_SCHEMA_VERSION,
+            generated_at: Local::now().timestamp(),
+            version_id,
+            loaders,
+            game_jar,
+            libraries,
+            mods,
+        }
+    }
+
+    pub fn load(instance_dir: &Path) -> InstanceResult<Self> {
+        let bytes = fs::read(instance_dir.join(LOCKFILE)).map_err(|_| {
+            let instance_name = instance_dir.file_name().unwrap_or_default();
+            InstanceError::NoLockfile(instance_name.to_string_lossy().into_owned())
+        })?;
+        let lockfile: Self = serde_json::from_slice(&bytes)
+            .map_err(|error| InstanceError::InvalidLockfile(error.to_string()))?;
+        if lockfile.schema_version > LOCKFILE_SCHEMA_VERSION {
+            return Err(InstanceError::InvalidLockfile(format!(
+                "Unsupported schema version {}",
+                lockfile.schema_version
+            )));
+        }
+        Ok(lockfile)
+    }
+
+    pub fn save(&self, instance_dir: &Path) -> InstanceResult<()> {
+        let json = serde_json::to_vec_pretty(self)
+            .map_err(|error| InstanceError::InvalidLockfile(error.to_string()))?;
+        Ok(fs::write(instance_dir.join(LOCKFILE), json)?)
+    }
+
+    /// Mod jars in the instance that the lockfile doesn't know about.
+    pub fn unlocked_mods(&self, instance_dir: &Path) -> InstanceResult<Vec<String>> {
+        let locked: BTreeSet<&str> = self.mods.iter().map(|file| file.path.as_str()).collect();
+        Ok(list_mod_jars(instance_dir)?
+            .into_iter()
+            .filter(|file_name| !locked.contains(format!("{}/{}", MODS_DIR, file_name).as_str()))
+            .collect())
+    }
+}
+
+fn list_mod_jars(instance_dir: &Path) -> InstanceResult<Vec<String>> {
+    let mods_dir = instance_dir.join(MODS_DIR);
+    if !mods_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut file_names = Vec::new();
+    for entry in fs::read_dir(mods_dir)? {
+        let path = entry?.path();
+        if path.is_file()
+            && path
+                .extension()
+                .map_or(false, |extension| extension == "jar")
+        {
+            if let Some(file_name) = path.file_name() {
+                file_names.push(file_name.to_string_lossy().into_owned());
+            }
+        }
+    }
+    file_names.sort();
+    Ok(file_names)
+}
+
+fn read_file_hash(path: &Path) -> Option<String> {
+    hash_cache::file_sha1(path).ok()
+}
+
+/// Hashes the mod jars of an instance. Their download urls are looked up on Modrinth, jars from
+/// anywhere else are locked without one.
+pub async fn lock_mods(instance_dir: &Path) -> InstanceResult<Vec<LockedFile>> {
+    let mut mods = Vec::new();
+    for file_name in list_mod_jars(instance_dir)? {
+        let sha1 = hash_cache::file_sha1(&instance_dir.join(MODS_DIR).join(&file_name))?;
+        mods.push(LockedFile {
+            path: format!("{}/{}", MODS_DIR, file_name),
+            sha1,
+            url: None,
+        });
+    }
+
+    let hashes: Vec<String> = mods.iter().map(|file| file.sha1.clone()).collect();
+    match versions_from_hashes(&hashes).await {
+        Ok(versions) => {
+            for file in mods.iter_mut() {
+                file.url = versions.get(&file.sha1).and_then(|version| {
+                    version
+                        .files
+                        .iter()
+                        .find(|candidate| candidate.hashes.sha1 == file.sha1)
+                        .map(|candidate| candidate.url.clone())
+                });
+            }
+        }
+        // The lockfile is still useful to detect changes without the urls.
+        Err(error) => warn!("Could not look up mod downloads on Modrinth: {}", error),
+    }
+    Ok(mods)
+}
+
+/// Downloads the files in `root` whose contents don't match the lockfile again, through `library`
+/// for mods when the shared mods library is turned on.
+pub async fn restore_files(
+    root: &Path,
+    files: &[LockedFile],
+    library: Option<&ModsLibrary>,
+    report: &mut LockfileRestoreReport,
+) -> InstanceResult<()> {
+    for file in files {
+        if !is_safe_path(&file.path) {
+            return Err(InstanceError::InvalidLockfile(format!(
+                "Unsafe path {}",
+                file.path
+            )));
+        }
+        let path = root.join(&file.path);
+        if read_file_hash(&path).as_ref() == Some(&file.sha1) {
+            continue;
+        }
+        let url = match &file.url {
+            Some(url) => url,
+            None => {
+                warn!("{} changed and has no download", file.path);
+                report.unavailable.push(file.path.clone());
+                continue;
+            }
+        };
+        debug!("Restoring {} from {}", file.path, url);
+        let bytes = download_bytes_from_url(url).await?;
+        if !validate_hash(&bytes, &file.sha1) {
+            return Err(InstanceError::InvalidDownload(file.path.clone()));
+        }
+        write_mod(library, &bytes, &path)?;
+        report.restored.push(file.path.clone());
+    }
+    info!(
+        "Restored {} files in {}",
+        report.restored.len(),
+        root.display()
+    );
+    Ok(())
+}
@@ -0,0 +1,133 @@
+//! The game logs to stdout with the logging configuration of its version manifest, which writes
+//! every log call as a log4j xml event:
+//!
+//! ```xml
+//! <log4j:Event logger="net.minecraft.client.Minecraft" timestamp="1672531200000" level="INFO" thread="Render thread">
+//!   <log4j:Message><![CDATA[Setting user: Player]]></log4j:Message>
+//! </log4j:Event>
+//! ```
+
+use chrono::{Local, TimeZone};
+use log::debug;
+use regex::Regex;
+use xmltree::Element;
+
+use crate::{
+    instance::logs::{parse_log_line, LogLevel, LogLine, LOG_LINE_PATTERN},
+    redact::redact,
+};
+
+const EVENT_START: &str = "<log4j:Event";
+const EVENT_END: &str = "</log4j:Event>";
+
+/// Events use the `log4j` prefix without declaring it, they are parsed inside an element that does.
+const LOG4J_NAMESPACE: &str = "http://jakarta.apache.org/log4j/";
+
+/// Turns the game's output into log records as it is read line by line.
+pub struct Log4jStream {
+    pattern: Regex,
+    /// The lines of an event that isn't complete yet.
+    event: Option<String>,
+}
+
+impl Log4jStream {
+    pub fn new() -> Self {
+        Self {
+            pattern: Regex::new(LOG_LINE_PATTERN).expect("The log line pattern should be valid"),
+            event: None,
+        }
+    }
+
+    /// Reads the next line of output, returns a record once an event is complete. Lines outside of
+    /// events are records of their own, they are either in the log pattern of instances installed
+    /// before the xml events were parsed or written to stdout directly, e.g. by the jvm.
+    pub fn push_line(&mut self, line: &str) -> Option<LogLine> {
+        if let Some(event) = &mut self.event {
+            event.push('\n');
+            event.push_str(line);
+            if !line.contains(EVENT_END) {
+                return None;
+            }
+            return self.event.take().map(|event| parse_event(&event));
+        }
+        if line.trim_start().starts_with(EVENT_START) {
+            if line.contains(EVENT_END) {
+                return Some(parse_event(line));
+            }
+            self.event = Some(line.into());
+            return None;
+        }
+        let line = redact(line);
+        Some(parse_log_line(&self.pattern, &line).unwrap_or_else(|| unknown_record(line)))
+    }
+
+    /// The event the game didn't finish writing before it exited, if any.
+    pub fn finish(&mut self) -> Option<LogLine> {
+        self.event
+            .take()
+            .map(|event| unknown_record(redact(&event)))
+    }
+}
+
+impl Default for Log4jStream {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Parses a complete event, events that aren't valid xml are kept as they are.
+fn parse_event(event: &str) -> LogLine {
+    let wrapped = format!(
+        "<Events xmlns:log4j=\"{}\">{}</Events>",
+        LOG4J_NAMESPACE, event
+    );
+    let element = match Element::parse(wrapped.as_bytes()) {
+        Ok(root) => root.get_child("Event").cloned(),
+        Err(error) => {
+            debug!("Could not parse log4j event: {}", error);
+            None
+        }
+    };
+    let element = match element {
+        Some(element) => element,
+        None => return unknown_record(redact(event)),
+    };
+
+    let mut message = element
+        .get_child("Message")
+        .and_then(|message| message.get_text())
+        .unwrap_or_default()
+        .into_owned();
+    // Stacktraces are appended to the message like in `latest.log`.
+    if let Some(throwable) = element
+        .get_child("Throwable")
+        .and_then(|throwable| throwable.get_text())
+    {
+        message.push('\n');
+        message.push_str(throwable.trim_end());
+    }
+    let time = element
+        .attributes
+        .get("timestamp")
+        .and_then(|timestamp| timestamp.parse::<i64>().ok())
+        .and_then(|millis| Local.timestamp_millis_opt(millis).single())
+        .map(|time| time.format("%H:%M:%S").to_string());
+    LogLine {
+        time,
+        thread: element.attributes.get("thread").cloned(),
+        level: element
+            .attributes
+            .get("level")
+            .map_or(LogLevel::Unknown, |level| LogLevel::parse(level)),
+        message: redact(&message),
+    }
+}
+
+fn unknown_record(message: String) -> LogLine {
+    LogLine {
+        time: None,
+        thread: None,
+        level: LogLevel::Unknown,
+        message,
+    }
+}
@@ -0,0 +1,249 @@
+use std::{
+    cmp::Reverse,
+    fs::{self, File},
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom},
+    path::Path,
+    time::UNIX_EPOCH,
+};
+
+use flate2::read::GzDecoder;
+use log::warn;
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    instance::resolve_file_in,
+    redact::redact,
+    state::instance_manager::{InstanceError, InstanceResult},
+};
+
+const LATEST_LOG: &str = "latest.log";
+
+/// Matches the vanilla log pattern `[12:34:56] [Render thread/INFO]: message`. Forge adds the logger
+/// name after the level, e.g. `[12:34:56] [main/INFO] [net.minecraftforge.Forge/]: message`.
+pub const LOG_LINE_PATTERN: &str = r"^\[(?P<time>[^\]]+)\] \[(?P<thread>.+?)/(?P<level>[A-Z]+)\](?: \[[^\]]*\])?: (?P<message>.*)$";
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Fatal,
+    /// Output that doesn't follow the log pattern, e.g. from before log4j is set up.
+    Unknown,
+}
+
+impl LogLevel {
+    pub fn parse(level: &str) -> Self {
+        match level {
+            "TRACE" => LogLevel::Trace,
+            "DEBUG" => LogLevel::Debug,
+            "INFO" => LogLevel::Info,
+            "WARN" => LogLevel::Warn,
+            "ERROR" => LogLevel::Error,
+            "FATAL" => LogLevel::Fatal,
+            _ => LogLevel::Unknown,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LogLine {
+    pub time: Option<String>,
+    pub thread: Option<String>,
+    pub level: LogLevel,
+    /// Lines that don't start a new entry (e.g. stacktraces) are appended to the previous message.
+    pub message: String,
+}
+
+/// Which log lines to return, every line matches an empty filter.
+#[derive(Debug, Default, Deserialize)]
+pub struct LogFilter {
+    /// Only lines with one of these levels.
+    pub levels: Option<Vec<LogLevel>>,
+    /// Only lines whose thread or message contains this text, ignoring case.
+    pub text: Option<String>,
+}
+
+impl LogFilter {
+    fn matches(&self, line: &LogLine, text: Option<&str>) -> bool {
+        if let Some(levels) = &self.levels {
+            if !levels.contains(&line.level) {
+                return false;
+            }
+        }
+        match text {
+            Some(text) => {
+                line.message.to_lowercase().contains(text)
+                    || matches!(&line.thread, Some(thread) if thread.to_lowercase().contains(text))
+            }
+            None => true,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct LogFileEntry {
+    #[serde(rename = "fileName")]
+    pub file_name: String,
+    /// Size of the file in bytes, compressed for `.log.gz` files.
+    pub size: u64,
+    /// Timestamp in seconds of the last write to the log.
+    pub modified: Option<i64>,
+}
+
+/// Lists `latest.log`, `debug.log` and the gzipped logs of previous runs, newest first.
+pub fn list_logs(logs_dir: &Path) -> io::Result<Vec<LogFileEntry>> {
+    let mut logs = Vec::new();
+    for entry in fs::read_dir(logs_dir)? {
+        let entry = entry?;
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+        if !file_name.ends_with(".log") && !file_name.ends_with(".log.gz") {
+            continue;
+        }
+        let metadata = entry.metadata()?;
+        logs.push(LogFileEntry {
+            file_name,
+            size: metadata.len(),
+            modified: metadata
+                .modified()
+                .ok()
+                .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+                .map(|duration| duration.as_secs() as i64),
+        });
+    }
+    logs.sort_by_key(|log| Reverse(log.modified));
+    Ok(logs)
+}
+
+/// Reads and parses a log file, decompressing it first if needed.
+pub fn read_log(
+    logs_dir: &Path,
+    file_name: &str,
+    filter: &LogFilter,
+) -> InstanceResult<Vec<LogLine>> {
+    let path = resolve_file_in(logs_dir, file_name)
+        .ok_or_else(|| InstanceError::UnknownLogFile(file_name.into()))?;
+    let file = File::open(&path)?;
+    let reader: Box<dyn Read> = if file_name.ends_with(".gz") {
+        Box::new(GzDecoder::new(file))
+    } else {
+        Box::new(file)
+    };
+    let lines = parse_log(BufReader::new(reader))?;
+
+    let text = filter.text.as_ref().map(|text| text.to_lowercase());
+    Ok(lines
+        .into_iter()
+        .filter(|line| filter.matches(line, text.as_deref()))
+        .collect())
+}
+
+pub fn parse_log<R: BufRead>(reader: R) -> io::Result<Vec<LogLine>> {
+    let pattern = Regex::new(LOG_LINE_PATTERN).expect("The log line pattern should be valid");
+    let mut lines: Vec<LogLine> = Vec::new();
+    for line in reader.lines() {
+        let line = redact(&line?);
+        if let Some(line) = parse_log_line(&pattern, &line) {
+            lines.push(line);
+            continue;
+        }
+        match lines.last_mut() {
+            Some(previous) => {
+                previous.message.push('\n');
+                previous.message.push_str(&line);
+            }
+            None => lines.push(LogLine {
+                time: None,
+                thread: None,
+                level: LogLevel::Unknown,
+                message: line,
+            }),
+        }
+    }
+    Ok(lines)
+}
+
+/// Parses a line matching `LOG_LINE_PATTERN`, other lines continue the previous entry.
+pub fn parse_log_line(pattern: &Regex, line: &str) -> Option<LogLine> {
+    let captures = pattern.captures(line)?;
+    Some(LogLine {
+        time: Some(captures["time"].into()),
+        thread: Some(captures["thread"].into()),
+        level: LogLevel::parse(&captures["level"]),
+        message: captures["message"].into(),
+    })
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LogTailPayload {
+    #[serde(rename = "instanceName")]
+    pub instance_name: String,
+    pub lines: Vec<String>,
+}
+
+/// Follows `latest.log` as the game writes to it, for games whose stdout isn't captured by the
+/// launcher. Only lines written after the tail started are reported, watching stops once dropped.
+pub struct LogTail {
+    _watcher: RecommendedWatcher,
+}
+
+impl LogTail {
+    pub fn start<F>(logs_dir: &Path, on_lines: F) -> notify::Result<Self>
+    where
+        F: Fn(Vec<String>) + Send + 'static,
+    {
+        let log_path = logs_dir.join(LATEST_LOG);
+        let mut offset = fs::metadata(&log_path).map_or(0, |metadata| metadata.len());
+        // The game moves `latest.log` into an archive when it starts, so the directory is watched
+        // instead of the file itself.
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<Event>| match event {
+                Ok(event) if event.paths.iter().any(|path| path == &log_path) => {
+                    if event.kind.is_create() {
+                        offset = 0;
+                    }
+                    match read_new_lines(&log_path, &mut offset) {
+                        Ok(lines) if !lines.is_empty() => on_lines(lines),
+                        Ok(_) => {}
+                        Err(error) => warn!("Could not read {}: {}", log_path.display(), error),
+                    }
+                }
+                Ok(_) => {}
+                Err(error) => warn!("Error watching {}: {}", log_path.display(), error),
+            })?;
+        watcher.watch(logs_dir, RecursiveMode::NonRecursive)?;
+        Ok(Self { _watcher: watcher })
+    }
+}
+
+/// Reads the complete lines written past `offset`, moving it to the end of the last one. A file
+/// that got shorter was replaced by a new log and is read from the start.
+fn read_new_lines(log_path: &Path, offset: &mut u64) -> io::Result<Vec<String>> {
+    let mut file = match File::open(log_path) {
+        Ok(file) => file,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(error),
+    };
+    if file.metadata()?.len() < *offset {
+        *offset = 0;
+    }
+    file.seek(SeekFrom::Start(*offset))?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+
+    // A partially written line is picked up with the next change.
+    let complete = match bytes.iter().rposition(|&byte| byte == b'\n') {
+        Some(index) => &bytes[..=index],
+        None => return Ok(Vec::new()),
+    };
+    *offset += complete.len() as u64;
+    Ok(String::from_utf8_lossy(complete)
+        .lines()
+        .map(redact)
+        .collect())
+}
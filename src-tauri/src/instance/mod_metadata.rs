@@ -0,0 +1,67 @@
+//! Launcher metadata about the mod jars of an instance, kept in `.mods.json` in the instance
+//! directory. Pinned mods are kept at their current version by modpack updates.
+
+use std::{collections::BTreeMap, fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+pub const MOD_METADATA_FILE: &str = ".mods.json";
+const MODS_DIR: &str = "mods";
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PinnedMod {
+    /// Modrinth project the jar belongs to, None if it isn't on Modrinth. Newer versions of the
+    /// project are skipped too, even though they have another file name.
+    #[serde(rename = "projectId")]
+    pub project_id: Option<String>,
+    /// Timestamp in seconds of when the mod was pinned.
+    #[serde(rename = "pinnedAt")]
+    pub pinned_at: i64,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ModMetadata {
+    // <File name in the mods folder, pin>
+    #[serde(default)]
+    pub pinned: BTreeMap<String, PinnedMod>,
+}
+
+impl ModMetadata {
+    /// Loads the metadata of an instance, instances without any start out empty.
+    pub fn load(instance_dir: &Path) -> io::Result<Self> {
+        match fs::read(instance_dir.join(MOD_METADATA_FILE)) {
+            Ok(bytes) => Ok(serde_json::from_slice(&bytes)?),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(error) => Err(error),
+        }
+    }
+
+    pub fn save(&self, instance_dir: &Path) -> io::Result<()> {
+        fs::write(
+            instance_dir.join(MOD_METADATA_FILE),
+            serde_json::to_vec_pretty(self)?,
+        )
+    }
+
+    pub fn is_pinned(&self, file_name: &str) -> bool {
+        self.pinned.contains_key(file_name)
+    }
+
+    /// Whether a file of a modpack, at `path` relative to the instance directory, must be left alone.
+    /// That is the case for pinned jars and for other versions of a pinned Modrinth project.
+    pub fn pins_pack_file(&self, path: &str, project_id: Option<&str>) -> bool {
+        let path = Path::new(path);
+        if path.parent() != Some(Path::new(MODS_DIR)) {
+            return false;
+        }
+        let is_pinned_jar = path.file_name().map_or(false, |file_name| {
+            self.is_pinned(&file_name.to_string_lossy())
+        });
+        is_pinned_jar
+            || project_id.map_or(false, |project_id| {
+                self.pinned
+                    .values()
+                    .any(|pin| pin.project_id.as_deref() == Some(project_id))
+            })
+    }
+}
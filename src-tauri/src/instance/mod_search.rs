@@ -0,0 +1,251 @@
+//! Searches mods, resource packs and shader packs on Modrinth or CurseForge for the mod browser. The
+//! frontend describes what it wants with a `ModSearch`, the api specific queries are built here.
+
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    state::instance_manager::{InstanceError, InstanceResult},
+    web_services::{
+        curseforge::{CurseForgeClient, CurseForgeError, CurseForgeMod},
+        modrinth::{self, ModrinthSearchHit},
+    },
+};
+
+use super::{
+    content::{ContentKind, ModLoader},
+    mod_versions::ModProject,
+};
+
+/// CurseForge doesn't return more than 50 results at once.
+const MAX_PAGE_SIZE: u32 = 50;
+
+fn default_page_size() -> u32 {
+    20
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ModSource {
+    Modrinth,
+    CurseForge,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SearchSort {
+    Relevance,
+    Downloads,
+    Updated,
+}
+
+impl Default for SearchSort {
+    fn default() -> Self {
+        SearchSort::Relevance
+    }
+}
+
+/// A search from the mod browser, filters that are None or empty aren't applied.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ModSearch {
+    pub source: ModSource,
+    #[serde(default)]
+    pub query: String,
+    /// Mods when not given.
+    #[serde(default)]
+    pub kind: Option<ContentKind>,
+    #[serde(default)]
+    pub loader: Option<ModLoader>,
+    #[serde(rename = "gameVersion", default)]
+    pub game_version: Option<String>,
+    /// Category slugs on Modrinth, category ids on CurseForge. Results are in all of them.
+    #[serde(default)]
+    pub categories: Vec<String>,
+    #[serde(default)]
+    pub sort: SearchSort,
+    /// Starts at 0.
+    #[serde(default)]
+    pub page: u32,
+    #[serde(rename = "pageSize", default = "default_page_size")]
+    pub page_size: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchHit {
+    /// Passed to `list_mod_versions` to list the versions of the result.
+    pub project: ModProject,
+    pub title: String,
+    pub description: String,
+    pub author: String,
+    pub downloads: u64,
+    #[serde(rename = "iconUrl")]
+    pub icon_url: Option<String>,
+    pub categories: Vec<String>,
+    /// RFC 3339 timestamp.
+    pub updated: String,
+}
+
+impl From<ModrinthSearchHit> for SearchHit {
+    fn from(hit: ModrinthSearchHit) -> Self {
+        SearchHit {
+            project: ModProject::Modrinth { id: hit.project_id },
+            title: hit.title,
+            description: hit.description,
+            author: hit.author,
+            downloads: hit.downloads,
+            icon_url: hit.icon_url,
+            categories: hit.categories,
+            updated: hit.date_modified,
+        }
+    }
+}
+
+impl From<CurseForgeMod> for SearchHit {
+    fn from(project: CurseForgeMod) -> Self {
+        SearchHit {
+            project: ModProject::CurseForge { id: project.id },
+            title: project.name,
+            description: project.summary,
+            author: project
+                .authors
+                .into_iter()
+                .next()
+                .map(|author| author.name)
+                .unwrap_or_default(),
+            downloads: project.download_count as u64,
+            icon_url: project.logo.map(|logo| logo.url),
+            categories: project
+                .categories
+                .into_iter()
+                .map(|category| category.slug)
+                .collect(),
+            updated: project.date_modified,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchPage {
+    pub hits: Vec<SearchHit>,
+    pub page: u32,
+    #[serde(rename = "pageSize")]
+    pub page_size: u32,
+    #[serde(rename = "totalHits")]
+    pub total_hits: u64,
+    #[serde(rename = "totalPages")]
+    pub total_pages: u64,
+}
+
+/// Runs the search on its platform. Searching CurseForge without a client fails with
+/// `MissingApiKey`.
+pub async fn search_mods(
+    search: &ModSearch,
+    curseforge: Option<&CurseForgeClient>,
+) -> InstanceResult<SearchPage> {
+    let page_size = search.page_size.clamp(1, MAX_PAGE_SIZE);
+    let offset = search
+        .page
+        .checked_mul(page_size)
+        .ok_or(InstanceError::InvalidSearchPage(search.page))?;
+    let (hits, total_hits): (Vec<SearchHit>, u64) = match search.source {
+        ModSource::Modrinth => {
+            let index = match search.sort {
+                SearchSort::Relevance => "relevance",
+                SearchSort::Downloads => "downloads",
+                SearchSort::Updated => "updated",
+            };
+            let response = modrinth::search(
+                &search.query,
+                &modrinth_facets(search),
+                index,
+                offset,
+                page_size,
+            )
+            .await?;
+            let hits = response.hits.into_iter().map(SearchHit::from).collect();
+            (hits, response.total_hits)
+        }
+        ModSource::CurseForge => {
+            let client = curseforge.ok_or(CurseForgeError::MissingApiKey)?;
+            let page = client
+                .search_mods(&curseforge_query(search, offset, page_size))
+                .await?;
+            let hits = page.data.into_iter().map(SearchHit::from).collect();
+            (hits, page.pagination.total_count)
+        }
+    };
+    debug!(
+        "Search for {:?} on {:?} found {} results",
+        search.query, search.source, total_hits
+    );
+    Ok(SearchPage {
+        hits,
+        page: search.page,
+        page_size,
+        total_hits,
+        total_pages: (total_hits + u64::from(page_size) - 1) / u64::from(page_size),
+    })
+}
+
+/// Every inner list is one filter, Modrinth ANDs the lists and ORs the entries of a list.
+fn modrinth_facets(search: &ModSearch) -> Vec<Vec<String>> {
+    let project_type = match search.kind.unwrap_or(ContentKind::Mod) {
+        ContentKind::Mod => "mod",
+        ContentKind::ResourcePack => "resourcepack",
+        ContentKind::ShaderPack => "shader",
+    };
+    let mut facets = vec![vec![format!("project_type:{}", project_type)]];
+    if let Some(loader) = search.loader {
+        facets.push(vec![format!("categories:{}", loader.modrinth_name())]);
+    }
+    if let Some(game_version) = &search.game_version {
+        facets.push(vec![format!("versions:{}", game_version)]);
+    }
+    for category in &search.categories {
+        facets.push(vec![format!("categories:{}", category)]);
+    }
+    facets
+}
+
+fn curseforge_query(
+    search: &ModSearch,
+    offset: u32,
+    page_size: u32,
+) -> Vec<(&'static str, String)> {
+    // The class ids CurseForge uses for the kinds of minecraft projects.
+    let class_id = match search.kind.unwrap_or(ContentKind::Mod) {
+        ContentKind::Mod => 6,
+        ContentKind::ResourcePack => 12,
+        ContentKind::ShaderPack => 6552,
+    };
+    // 1 is featured, 2 popularity, 3 last updated and 6 total downloads.
+    let sort_field = match search.sort {
+        SearchSort::Relevance => 2,
+        SearchSort::Downloads => 6,
+        SearchSort::Updated => 3,
+    };
+    let mut query = vec![
+        ("classId", class_id.to_string()),
+        ("searchFilter", search.query.clone()),
+        ("sortField", sort_field.to_string()),
+        ("sortOrder", "desc".to_string()),
+        ("index", offset.to_string()),
+        ("pageSize", page_size.to_string()),
+    ];
+    if let Some(loader) = search.loader {
+        query.push(("modLoaderType", loader.curseforge_type().to_string()));
+    }
+    if let Some(game_version) = &search.game_version {
+        query.push(("gameVersion", game_version.clone()));
+    }
+    let category_ids: Vec<&str> = search
+        .categories
+        .iter()
+        .map(String::as_str)
+        .filter(|id| id.parse::<u32>().is_ok())
+        .collect();
+    if !category_ids.is_empty() {
+        query.push(("categoryIds", format!("[{}]", category_ids.join(","))));
+    }
+    query
+}
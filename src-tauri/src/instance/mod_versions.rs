@@ -0,0 +1,181 @@
+//! Lists the versions of a mod on Modrinth or CurseForge, filtered by release channel, mod loader
+//! and minecraft version.
+
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    state::instance_manager::InstanceResult,
+    web_services::{
+        curseforge::{CurseForgeClient, CurseForgeError, CurseForgeFile},
+        modrinth::{project_versions, ModrinthVersion},
+    },
+};
+
+use super::content::ModLoader;
+
+/// How stable a mod version is, ordered from the most to the least stable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ReleaseChannel {
+    Release,
+    Beta,
+    Alpha,
+}
+
+impl Default for ReleaseChannel {
+    fn default() -> Self {
+        ReleaseChannel::Release
+    }
+}
+
+impl ReleaseChannel {
+    /// Whether versions on `channel` are offered to someone who picked this channel, each channel
+    /// includes the more stable ones.
+    pub fn includes(self, channel: ReleaseChannel) -> bool {
+        channel <= self
+    }
+
+    fn from_modrinth(version_type: &str) -> Self {
+        match version_type {
+            "beta" => ReleaseChannel::Beta,
+            "alpha" => ReleaseChannel::Alpha,
+            _ => ReleaseChannel::Release,
+        }
+    }
+
+    fn from_curseforge(release_type: u8) -> Self {
+        match release_type {
+            2 => ReleaseChannel::Beta,
+            3 => ReleaseChannel::Alpha,
+            _ => ReleaseChannel::Release,
+        }
+    }
+}
+
+/// A mod on one of the platforms.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "source", rename_all = "camelCase")]
+pub enum ModProject {
+    /// The project id or slug.
+    Modrinth {
+        id: String,
+    },
+    CurseForge {
+        id: u32,
+    },
+}
+
+/// Which versions to list, versions for any loader or minecraft version are listed when those are
+/// None.
+#[derive(Debug, Clone, Default)]
+pub struct ModVersionFilter {
+    pub channel: ReleaseChannel,
+    pub loader: Option<ModLoader>,
+    pub game_version: Option<String>,
+}
+
+/// A version of a mod, the same for both platforms.
+#[derive(Debug, Clone, Serialize)]
+pub struct ModVersion {
+    /// The Modrinth version id or the CurseForge file id.
+    pub id: String,
+    pub name: String,
+    pub channel: ReleaseChannel,
+    #[serde(rename = "gameVersions")]
+    pub game_versions: Vec<String>,
+    /// Loader names in lowercase, like `fabric`.
+    pub loaders: Vec<String>,
+    #[serde(rename = "fileName")]
+    pub file_name: String,
+    /// None for CurseForge files whose authors don't allow downloads outside of CurseForge.
+    pub url: Option<String>,
+    /// RFC 3339 timestamp.
+    pub published: String,
+}
+
+impl ModVersion {
+    fn from_modrinth(version: ModrinthVersion) -> Option<Self> {
+        let file = version.primary_file()?.clone();
+        Some(ModVersion {
+            name: if version.name.is_empty() {
+                version.version_number.clone()
+            } else {
+                version.name
+            },
+            id: version.id,
+            channel: ReleaseChannel::from_modrinth(&version.version_type),
+            game_versions: version.game_versions,
+            loaders: version.loaders,
+            file_name: file.filename,
+            url: Some(file.url),
+            published: version.date_published,
+        })
+    }
+
+    fn from_curseforge(file: CurseForgeFile) -> Self {
+        // Game versions are minecraft versions, loaders and environments like `Client` in one list.
+        let (game_versions, tags): (Vec<String>, Vec<String>) = file
+            .game_versions
+            .into_iter()
+            .partition(|tag| tag.starts_with(|c: char| c.is_ascii_digit()));
+        let loaders = tags
+            .iter()
+            .map(|tag| tag.to_lowercase())
+            .filter(|tag| ["forge", "fabric", "quilt", "neoforge"].contains(&tag.as_str()))
+            .collect();
+        ModVersion {
+            id: file.id.to_string(),
+            name: file.display_name,
+            channel: ReleaseChannel::from_curseforge(file.release_type),
+            game_versions,
+            loaders,
+            file_name: file.file_name,
+            url: file.download_url,
+            published: file.file_date,
+        }
+    }
+}
+
+/// Lists the versions of the project that pass the filter, newest first. Listing a CurseForge
+/// project without a client fails with `MissingApiKey`.
+pub async fn list_mod_versions(
+    project: &ModProject,
+    filter: &ModVersionFilter,
+    curseforge: Option<&CurseForgeClient>,
+) -> InstanceResult<Vec<ModVersion>> {
+    let game_version = filter.game_version.as_deref();
+    let mut versions: Vec<ModVersion> = match project {
+        ModProject::Modrinth { id } => {
+            let loader = filter.loader.map(ModLoader::modrinth_name);
+            project_versions(id, loader, game_version)
+                .await?
+                .into_iter()
+                .filter_map(ModVersion::from_modrinth)
+                .collect()
+        }
+        ModProject::CurseForge { id } => {
+            let client = curseforge.ok_or(CurseForgeError::MissingApiKey)?;
+            let loader_type = filter.loader.map(ModLoader::curseforge_type);
+            let mut versions: Vec<ModVersion> = client
+                .mod_files(*id, game_version, loader_type)
+                .await?
+                .into_iter()
+                .map(ModVersion::from_curseforge)
+                .collect();
+            // CurseForge doesn't promise an order, the timestamps sort like dates.
+            versions.sort_by(|a, b| b.published.cmp(&a.published));
+            versions
+        }
+    };
+    let found = versions.len();
+    versions.retain(|version| filter.channel.includes(version.channel));
+    debug!(
+        "Listing {} of {} versions of {:?} on the {:?} channel",
+        versions.len(),
+        found,
+        project,
+        filter.channel
+    );
+    Ok(versions)
+}
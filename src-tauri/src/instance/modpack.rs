@@ -0,0 +1,416 @@
+//! Updates instances that were installed from a Modrinth pack (`.mrpack`) to another version of the
+//! pack. The files the pack installed are recorded in `.modpack.json` with their hashes, so files
+//! the user added or changed since then are told apart from the pack's and left alone.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fs::{self, File},
+    io::Read,
+    path::{Component, Path, PathBuf},
+};
+
+use bytes::Bytes;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use zip::ZipArchive;
+
+use crate::{
+    state::instance_manager::{InstanceError, InstanceResult},
+    web_services::{
+        downloader::{
+            download_bytes_from_url, hash_bytes, run_blocking_with_progress, validate_hash,
+        },
+        hash_cache,
+        modrinth::project_from_cdn_url,
+    },
+};
+
+use super::{
+    mod_metadata::ModMetadata,
+    mods_library::{write_mod, ModsLibrary},
+};
+
+/// Files installed by the pack, kept in the instance directory.
+pub const MODPACK_STATE_FILE: &str = ".modpack.json";
+const MRPACK_INDEX: &str = "modrinth.index.json";
+/// Override folders of a pack, later ones take precedence.
+const OVERRIDE_DIRS: [&str; 2] = ["overrides/", "client-overrides/"];
+
+#[derive(Debug, Deserialize)]
+struct MrpackIndex {
+    name: String,
+    #[serde(rename = "versionId")]
+    version_id: String,
+    files: Vec<MrpackFile>,
+    #[serde(default)]
+    dependencies: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MrpackFile {
+    path: String,
+    hashes: HashMap<String, String>,
+    downloads: Vec<String>,
+    #[serde(default)]
+    env: Option<MrpackEnv>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MrpackEnv {
+    client: String,
+}
+
+/// Where a file of the pack comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PackSource {
+    Download(Vec<String>),
+    /// Name of the entry in the `.mrpack` archive.
+    Override(String),
+}
+
+#[derive(Debug, Clone)]
+struct PackFile {
+    sha1: String,
+    source: PackSource,
+}
+
+/// A version of a pack read from its `.mrpack`.
+#[derive(Debug)]
+pub struct Modpack {
+    path: PathBuf,
+    name: String,
+    version_id: String,
+    dependencies: BTreeMap<String, String>,
+    // <Path relative to the instance directory, file>
+    files: BTreeMap<String, PackFile>,
+}
+
+/// The installed version of the pack and the hashes of the files it installed.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct ModpackState {
+    pub name: String,
+    #[serde(rename = "versionId")]
+    pub version_id: String,
+    pub dependencies: BTreeMap<String, String>,
+    // <Path relative to the instance directory, sha1>
+    pub files: BTreeMap<String, String>,
+}
+
+impl ModpackState {
+    pub fn load(instance_dir: &Path) -> InstanceResult<Self> {
+        let bytes = fs::read(instance_dir.join(MODPACK_STATE_FILE)).map_err(|_| {
+            let instance_name = instance_dir.file_name().unwrap_or_default();
+            InstanceError::NotAModpack(instance_name.to_string_lossy().into_owned())
+        })?;
+        serde_json::from_slice(&bytes)
+            .map_err(|error| InstanceError::InvalidModpack(error.to_string()))
+    }
+
+    fn save(&self, instance_dir: &Path) -> InstanceResult<()> {
+        let json = serde_json::to_vec_pretty(self)
+            .map_err(|error| InstanceError::InvalidModpack(error.to_string()))?;
+        Ok(fs::write(instance_dir.join(MODPACK_STATE_FILE), json)?)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PackChangeKind {
+    Added,
+    Removed,
+    Updated,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PackChange {
+    pub path: String,
+    pub kind: PackChangeKind,
+    /// Whether the file is one of the pack's overrides, usually configs, rather than a download.
+    #[serde(rename = "isOverride")]
+    pub is_override: bool,
+    /// The user added, changed or deleted the file since the pack installed it, or pinned it, so it
+    /// is kept as is.
+    pub preserved: bool,
+    /// The file is a pinned mod or another version of one.
+    pub pinned: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ModpackUpdatePreview {
+    pub name: String,
+    #[serde(rename = "fromVersion")]
+    pub from_version: String,
+    #[serde(rename = "toVersion")]
+    pub to_version: String,
+    pub changes: Vec<PackChange>,
+    /// Minecraft or mod loader versions that differ, as (dependency, installed, new). Updates that
+    /// change them can't be applied in place.
+    #[serde(rename = "dependencyChanges")]
+    pub dependency_changes: Vec<(String, Option<String>, Option<String>)>,
+}
+
+/// Pack paths end up joined to the instance directory, only plain relative paths are allowed.
+pub fn is_safe_path(path: &str) -> bool {
+    !path.is_empty()
+        && Path::new(path)
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+}
+
+impl PackFile {
+    /// The Modrinth project the file is downloaded from, if any.
+    fn project_id(&self) -> Option<&str> {
+        match &self.source {
+            PackSource::Download(urls) => urls.iter().find_map(|url| project_from_cdn_url(url)),
+            PackSource::Override(_) => None,
+        }
+    }
+}
+
+fn read_file_hash(path: &Path) -> Option<String> {
+    hash_cache::file_sha1(path).ok()
+}
+
+impl Modpack {
+    /// Reads the index and overrides of a `.mrpack`.
+    pub fn read(path: &Path) -> InstanceResult<Self> {
+        let mut archive = ZipArchive::new(File::open(path)?)?;
+        let index: MrpackIndex = {
+            let mut entry = archive
+                .by_name(MRPACK_INDEX)
+                .map_err(|_| InstanceError::InvalidModpack(format!("Missing {}", MRPACK_INDEX)))?;
+            let mut contents = String::new();
+            entry.read_to_string(&mut contents)?;
+            serde_json::from_str(&contents)
+                .map_err(|error| InstanceError::InvalidModpack(error.to_string()))?
+        };
+
+        let mut files = BTreeMap::new();
+        for file in index.files {
+            if file.env.map_or(false, |env| env.client == "unsupported") {
+                continue;
+            }
+            let sha1 = match file.hashes.get("sha1") {
+                Some(sha1) => sha1.clone(),
+                None => {
+                    return Err(InstanceError::InvalidModpack(format!(
+                        "{} has no sha1",
+                        file.path
+                    )))
+                }
+            };
+            if !is_safe_path(&file.path) {
+                return Err(InstanceError::InvalidModpack(format!(
+                    "Unsafe path {}",
+                    file.path
+                )));
+            }
+            files.insert(
+                file.path,
+                PackFile {
+                    sha1,
+                    source: PackSource::Download(file.downloads),
+                },
+            );
+        }
+
+        for override_dir in OVERRIDE_DIRS {
+            for index in 0..archive.len() {
+                let mut entry = archive.by_index(index)?;
+                let relative = match entry.name().strip_prefix(override_dir) {
+                    Some(relative) if !entry.is_dir() && is_safe_path(relative) => {
+                        relative.to_string()
+                    }
+                    _ => continue,
+                };
+                let mut bytes = Vec::new();
+                entry.read_to_end(&mut bytes)?;
+                files.insert(
+                    relative,
+                    PackFile {
+                        sha1: hash_bytes(&Bytes::from(bytes)),
+                        source: PackSource::Override(entry.name().to_string()),
+                    },
+                );
+            }
+        }
+
+        Ok(Self {
+            path: path.into(),
+            name: index.name,
+            version_id: index.version_id,
+            dependencies: index.dependencies,
+            files,
+        })
+    }
+
+    /// Compares the installed version of the pack with this one. A file is preserved when its
+    /// contents on disk differ from what the installed version put there, or when it is pinned in
+    /// `mod_metadata`.
+    pub fn diff(
+        &self,
+        instance_dir: &Path,
+        installed: &ModpackState,
+        mod_metadata: &ModMetadata,
+    ) -> ModpackUpdatePreview {
+        let paths: BTreeSet<&String> = installed.files.keys().chain(self.files.keys()).collect();
+        let mut changes = Vec::new();
+        for path in paths {
+            let old_hash = installed.files.get(path);
+            let new_file = self.files.get(path);
+            let kind = match (old_hash, new_file) {
+                (None, Some(_)) => PackChangeKind::Added,
+                (Some(_), None) => PackChangeKind::Removed,
+                (Some(old_hash), Some(new_file)) if *old_hash != new_file.sha1 => {
+                    PackChangeKind::Updated
+                }
+                _ => continue,
+            };
+            let pinned = mod_metadata
+                .pins_pack_file(path, new_file.and_then(|new_file| new_file.project_id()));
+            let local_hash = read_file_hash(&instance_dir.join(path));
+            let changed_by_user = match (kind, &local_hash) {
+                // A file already at the path was put there by the user, unless it is the same file.
+                (PackChangeKind::Added, Some(local_hash)) => {
+                    new_file.map_or(true, |new_file| *local_hash != new_file.sha1)
+                }
+                (PackChangeKind::Added, None) => false,
+                // Files the user deleted are gone already.
+                (PackChangeKind::Removed, None) => continue,
+                _ => local_hash.as_ref() != old_hash,
+            };
+            changes.push(PackChange {
+                path: path.clone(),
+                kind,
+                is_override: matches!(
+                    new_file.map(|new_file| &new_file.source),
+                    Some(PackSource::Override(_))
+                ),
+                preserved: changed_by_user || pinned,
+                pinned,
+            });
+        }
+
+        let dependencies: BTreeSet<&String> = installed
+            .dependencies
+            .keys()
+            .chain(self.dependencies.keys())
+            .collect();
+        let dependency_changes = dependencies
+            .into_iter()
+            .filter_map(|dependency| {
+                let old = installed.dependencies.get(dependency);
+                let new = self.dependencies.get(dependency);
+                (old != new).then(|| (dependency.clone(), old.cloned(), new.cloned()))
+            })
+            .collect();
+
+        ModpackUpdatePreview {
+            name: self.name.clone(),
+            from_version: installed.version_id.clone(),
+            to_version: self.version_id.clone(),
+            changes,
+            dependency_changes,
+        }
+    }
+
+    async fn download(&self, path: &str, urls: &[String], sha1: &str) -> InstanceResult<Bytes> {
+        for url in urls {
+            match download_bytes_from_url(url).await {
+                Ok(bytes) if validate_hash(&bytes, sha1) => return Ok(bytes),
+                Ok(_) => warn!("{} from {} has an invalid hash", path, url),
+                Err(error) => warn!("Could not download {} from {}: {}", path, url, error),
+            }
+        }
+        Err(InstanceError::ModpackDownloadFailed(path.into()))
+    }
+
+    /// Applies the changes of `diff` that aren't preserved and records this version as installed.
+    /// Everything is downloaded before the first file in the instance is touched. Mod jars go through
+    /// `library` when the shared mods library is turned on. `on_progress` is called with the number of
+    /// files written or removed so far and the total.
+    pub async fn apply(
+        &self,
+        instance_dir: &Path,
+        preview: &ModpackUpdatePreview,
+        library: Option<&ModsLibrary>,
+        on_progress: impl Fn(usize, usize),
+    ) -> InstanceResult<()> {
+        if !preview.dependency_changes.is_empty() {
+            return Err(InstanceError::ModpackDependenciesChanged);
+        }
+        let mut writes = Vec::new();
+        let mut overrides = Vec::new();
+        for change in preview.changes.iter().filter(|change| !change.preserved) {
+            let file = match self.files.get(&change.path) {
+                Some(file) => file,
+                None => continue,
+            };
+            match &file.source {
+                PackSource::Download(urls) => {
+                    let bytes = self.download(&change.path, urls, &file.sha1).await?;
+                    writes.push((change.path.clone(), bytes));
+                }
+                PackSource::Override(name) => overrides.push((change.path.clone(), name.clone())),
+            }
+        }
+        let removals: Vec<String> = preview
+            .changes
+            .iter()
+            .filter(|change| change.kind == PackChangeKind::Removed && !change.preserved)
+            .map(|change| change.path.clone())
+            .collect();
+
+        // Large packs extract thousands of overrides, that happens off the async runtime.
+        let total = writes.len() + overrides.len() + removals.len();
+        let pack_path = self.path.clone();
+        let instance_dir_path = instance_dir.to_path_buf();
+        let library = library.cloned();
+        let extraction = move |file_done: &dyn Fn()| -> InstanceResult<()> {
+            let mut archive = ZipArchive::new(File::open(&pack_path)?)?;
+            for (path, name) in overrides {
+                let mut bytes = Vec::new();
+                archive.by_name(&name)?.read_to_end(&mut bytes)?;
+                writes.push((path, Bytes::from(bytes)));
+            }
+
+            for path in removals {
+                debug!("Removing {} from the pack", path);
+                fs::remove_file(instance_dir_path.join(&path))?;
+                file_done();
+            }
+            for (path, bytes) in writes {
+                debug!("Writing {} from the pack", path);
+                let is_mod = path.starts_with("mods/") && path.ends_with(".jar");
+                write_mod(
+                    library.as_ref().filter(|_| is_mod),
+                    &bytes,
+                    &instance_dir_path.join(&path),
+                )?;
+                file_done();
+            }
+            Ok(())
+        };
+        run_blocking_with_progress(total, extraction, on_progress).await??;
+
+        ModpackState {
+            name: self.name.clone(),
+            version_id: self.version_id.clone(),
+            dependencies: self.dependencies.clone(),
+            files: self
+                .files
+                .iter()
+                .map(|(path, file)| (path.clone(), file.sha1.clone()))
+                .collect(),
+        }
+        .save(instance_dir)?;
+        info!(
+            "Updated {} in {} from {} to {}",
+            self.name,
+            instance_dir.display(),
+            preview.from_version,
+            preview.to_version
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,137 @@
+//! Optional shared storage for mod jars at `${app_dir}/mods-library`. Every jar is stored once under its
+//! sha1 and hard linked into the mods folders of instances, so a mod used by many instances only takes
+//! up the space of one copy.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use bytes::Bytes;
+use log::{debug, info, warn};
+
+use crate::web_services::downloader::hash_bytes;
+
+#[derive(Debug, Clone)]
+pub struct ModsLibrary {
+    dir: PathBuf,
+}
+
+impl ModsLibrary {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// Where the jar with `sha1` is stored, split by the first two characters of the hash like the
+    /// asset objects.
+    fn stored_path(&self, sha1: &str) -> PathBuf {
+        self.dir.join(&sha1[..2]).join(format!("{}.jar", sha1))
+    }
+
+    /// Stores `bytes` unless a jar with the same hash is stored already, returns its path.
+    fn store(&self, bytes: &Bytes) -> io::Result<PathBuf> {
+        let stored_path = self.stored_path(&hash_bytes(bytes));
+        if !stored_path.is_file() {
+            if let Some(parent) = stored_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            // Written next to it first, a partial file must never be linked into an instance.
+            let temp_path = stored_path.with_extension("jar.tmp");
+            fs::write(&temp_path, bytes)?;
+            fs::rename(&temp_path, &stored_path)?;
+            debug!("Stored {} in the mods library", stored_path.display());
+        }
+        Ok(stored_path)
+    }
+
+    /// Replaces the jar at `path` by a link to its stored copy, storing it first if needed.
+    pub fn adopt(&self, path: &Path) -> io::Result<()> {
+        let stored_path = self.store(&Bytes::from(fs::read(path)?))?;
+        link_or_copy(&stored_path, path)
+    }
+
+    /// Adopts every jar in a mods folder, returns how many there were.
+    pub fn adopt_dir(&self, mods_dir: &Path) -> io::Result<usize> {
+        if !mods_dir.is_dir() {
+            return Ok(0);
+        }
+        let mut adopted = 0;
+        for entry in fs::read_dir(mods_dir)? {
+            let path = entry?.path();
+            if path.is_file()
+                && path
+                    .extension()
+                    .map_or(false, |extension| extension == "jar")
+            {
+                self.adopt(&path)?;
+                adopted += 1;
+            }
+        }
+        Ok(adopted)
+    }
+
+    /// Deletes the stored jars that aren't linked into any instance anymore, returns the freed bytes.
+    /// The link count isn't available on other systems, jars are kept there.
+    #[cfg(unix)]
+    pub fn prune(&self) -> io::Result<u64> {
+        use std::os::unix::fs::MetadataExt;
+
+        if !self.dir.is_dir() {
+            return Ok(0);
+        }
+        let mut freed = 0;
+        for prefix in fs::read_dir(&self.dir)? {
+            let prefix = prefix?.path();
+            if !prefix.is_dir() {
+                continue;
+            }
+            for entry in fs::read_dir(&prefix)? {
+                let entry = entry?;
+                let metadata = entry.metadata()?;
+                if metadata.nlink() <= 1 {
+                    fs::remove_file(entry.path())?;
+                    freed += metadata.len();
+                }
+            }
+        }
+        info!("Pruned {} bytes from the mods library", freed);
+        Ok(freed)
+    }
+
+    #[cfg(not(unix))]
+    pub fn prune(&self) -> io::Result<u64> {
+        Ok(0)
+    }
+}
+
+/// Links `destination` to `stored_path`, replacing what is there. Hard links only work within one
+/// filesystem, instances on another drive get a copy instead.
+fn link_or_copy(stored_path: &Path, destination: &Path) -> io::Result<()> {
+    // Linked to a temporary name first so the jar isn't gone if linking fails.
+    let temp_path = destination.with_extension("jar.linking");
+    if let Err(error) = fs::hard_link(stored_path, &temp_path) {
+        warn!(
+            "Could not link {} into {}, copying it: {}",
+            stored_path.display(),
+            destination.display(),
+            error
+        );
+        fs::copy(stored_path, &temp_path)?;
+    }
+    fs::rename(&temp_path, destination)
+}
+
+/// Writes a mod jar to `destination`, through `library` when the shared mods library is turned on.
+pub fn write_mod(
+    library: Option<&ModsLibrary>,
+    bytes: &Bytes,
+    destination: &Path,
+) -> io::Result<()> {
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    match library {
+        Some(library) => link_or_copy(&library.store(bytes)?, destination),
+        None => fs::write(destination, bytes),
+    }
+}
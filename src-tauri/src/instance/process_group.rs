@@ -0,0 +1,175 @@
+//! Runs the game in its own process group on unix and in a job object on windows, so the jvm and
+//! everything it starts can be stopped together and don't outlive the launcher.
+
+use std::{
+    io,
+    process::{Child, Command},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use log::debug;
+
+/// Makes the process spawned by `command` the leader of a new process group. On linux it is also
+/// terminated when the launcher dies, even if it is killed.
+#[cfg(unix)]
+pub fn configure_process_group(command: &mut Command) {
+    use std::os::unix::process::CommandExt;
+
+    // Only async signal safe functions may be called between fork and exec.
+    unsafe {
+        command.pre_exec(|| {
+            if libc::setpgid(0, 0) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            // The signal is sent when the spawning thread exits, games are spawned from the async
+            // runtime whose threads live as long as the launcher.
+            #[cfg(target_os = "linux")]
+            libc::prctl(libc::PR_SET_PDEATHSIG, libc::SIGTERM);
+            Ok(())
+        });
+    }
+}
+
+/// Starts the process spawned by `command` in a new console process group, it is added to a job
+/// object by `ProcessGroup::new` once it runs.
+#[cfg(windows)]
+pub fn configure_process_group(command: &mut Command) {
+    use std::os::windows::process::CommandExt;
+    use windows_sys::Win32::System::Threading::CREATE_NEW_PROCESS_GROUP;
+
+    command.creation_flags(CREATE_NEW_PROCESS_GROUP);
+}
+
+/// The game process and every process it started.
+#[derive(Debug)]
+pub struct ProcessGroup {
+    pid: u32,
+    /// Set once the user stopped the game, so its exit isn't reported as a crash.
+    killed: AtomicBool,
+    #[cfg(windows)]
+    job: Option<windows::JobObject>,
+}
+
+impl ProcessGroup {
+    /// Call right after spawning a command set up by `configure_process_group`.
+    pub fn new(child: &Child) -> Self {
+        Self {
+            pid: child.id(),
+            killed: AtomicBool::new(false),
+            #[cfg(windows)]
+            job: match windows::JobObject::assign(child) {
+                Ok(job) => Some(job),
+                Err(error) => {
+                    log::warn!("Could not add the game to a job object: {}", error);
+                    None
+                }
+            },
+        }
+    }
+
+    pub fn was_killed(&self) -> bool {
+        self.killed.load(Ordering::SeqCst)
+    }
+
+    /// Stops every process of the group immediately, for games that don't respond anymore.
+    pub fn kill(&self) -> io::Result<()> {
+        self.killed.store(true, Ordering::SeqCst);
+        debug!("Killing the process group of {}", self.pid);
+        self.signal(true)
+    }
+
+    /// Asks every process of the group to exit, the jvm runs its shutdown hooks first.
+    pub fn terminate(&self) -> io::Result<()> {
+        debug!("Terminating the process group of {}", self.pid);
+        self.signal(false)
+    }
+
+    #[cfg(unix)]
+    fn signal(&self, kill: bool) -> io::Result<()> {
+        let signal = if kill { libc::SIGKILL } else { libc::SIGTERM };
+        // The game is the leader of its group, so the group id is its pid.
+        if unsafe { libc::killpg(self.pid as libc::pid_t, signal) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// Windows can't ask a process without a window to exit, both stop the processes immediately.
+    #[cfg(windows)]
+    fn signal(&self, _kill: bool) -> io::Result<()> {
+        match &self.job {
+            Some(job) => job.terminate(),
+            None => {
+                let status = Command::new("taskkill")
+                    .args(&["/T", "/F", "/PID", &self.pid.to_string()])
+                    .status()?;
+                if !status.success() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::Other,
+                        format!("taskkill exited with {}", status),
+                    ));
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use std::{io, mem, os::windows::io::AsRawHandle, process::Child, ptr};
+
+    use windows_sys::Win32::{
+        Foundation::{CloseHandle, HANDLE},
+        System::JobObjects::{
+            AssignProcessToJobObject, CreateJobObjectW, JobObjectExtendedLimitInformation,
+            SetInformationJobObject, TerminateJobObject, JOBOBJECT_EXTENDED_LIMIT_INFORMATION,
+            JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE,
+        },
+    };
+
+    /// A job whose processes are terminated once its last handle is closed, which windows does
+    /// when the launcher exits for any reason.
+    #[derive(Debug)]
+    pub struct JobObject(HANDLE);
+
+    impl JobObject {
+        pub fn assign(child: &Child) -> io::Result<Self> {
+            let job = unsafe { CreateJobObjectW(ptr::null(), ptr::null()) };
+            if job == 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let job = JobObject(job);
+            let mut limits: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = unsafe { mem::zeroed() };
+            limits.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+            let limits_set = unsafe {
+                SetInformationJobObject(
+                    job.0,
+                    JobObjectExtendedLimitInformation,
+                    &limits as *const _ as *const _,
+                    mem::size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as u32,
+                )
+            };
+            if limits_set == 0 {
+                return Err(io::Error::last_os_error());
+            }
+            if unsafe { AssignProcessToJobObject(job.0, child.as_raw_handle() as HANDLE) } == 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(job)
+        }
+
+        pub fn terminate(&self) -> io::Result<()> {
+            if unsafe { TerminateJobObject(self.0, 1) } == 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        }
+    }
+
+    impl Drop for JobObject {
+        fn drop(&mut self) {
+            unsafe { CloseHandle(self.0) };
+        }
+    }
+}
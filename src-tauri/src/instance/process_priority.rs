@@ -0,0 +1,145 @@
+use std::{process::Command, thread};
+
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+/// How the os schedules the game process compared to everything else running.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ProcessPriority {
+    Low,
+    BelowNormal,
+    Normal,
+    AboveNormal,
+    /// Raising the priority above normal needs root on linux and macos.
+    High,
+}
+
+impl Default for ProcessPriority {
+    fn default() -> Self {
+        ProcessPriority::Normal
+    }
+}
+
+impl ProcessPriority {
+    /// The `nice` value on linux and macos, lower values are scheduled first.
+    #[cfg(unix)]
+    fn niceness(self) -> i32 {
+        match self {
+            ProcessPriority::Low => 15,
+            ProcessPriority::BelowNormal => 5,
+            ProcessPriority::Normal => 0,
+            ProcessPriority::AboveNormal => -5,
+            ProcessPriority::High => -10,
+        }
+    }
+
+    /// The name of the windows priority class.
+    #[cfg(windows)]
+    fn priority_class(self) -> &'static str {
+        match self {
+            ProcessPriority::Low => "Idle",
+            ProcessPriority::BelowNormal => "BelowNormal",
+            ProcessPriority::Normal => "Normal",
+            ProcessPriority::AboveNormal => "AboveNormal",
+            ProcessPriority::High => "High",
+        }
+    }
+}
+
+/// Sets the priority of the process spawned by `command` before it runs, every thread the jvm
+/// starts inherits it. Failing to set it, e.g. raising it without root, leaves the game at the
+/// default priority.
+#[cfg(unix)]
+pub fn configure_process_priority(command: &mut Command, priority: ProcessPriority) {
+    use std::os::unix::process::CommandExt;
+
+    if priority == ProcessPriority::Normal {
+        return;
+    }
+    let niceness = priority.niceness();
+    // Only async signal safe functions may be called between fork and exec.
+    unsafe {
+        command.pre_exec(move || {
+            libc::setpriority(libc::PRIO_PROCESS, 0, niceness);
+            Ok(())
+        });
+    }
+}
+
+/// The priority class is set once the game runs, see `apply_process_priority`.
+#[cfg(windows)]
+pub fn configure_process_priority(_command: &mut Command, _priority: ProcessPriority) {}
+
+/// Sets the cpu cores of the spawned game, and on windows its priority, an empty `cores` allows
+/// every core. The game keeps running with the defaults when either can't be set, so failures are
+/// only logged.
+pub fn apply_process_priority(pid: u32, priority: ProcessPriority, cores: &[usize]) {
+    if priority == ProcessPriority::Normal && cores.is_empty() {
+        return;
+    }
+    let cores = cores.to_vec();
+    // Starting powershell takes a moment, the launch shouldn't wait for it.
+    thread::spawn(move || {
+        #[cfg(windows)]
+        let commands = windows_commands(pid, priority, &cores);
+        #[cfg(unix)]
+        let commands = unix_commands(pid, &cores);
+        for mut command in commands {
+            debug!("Setting the process priority: {:?}", command);
+            match command.output() {
+                Ok(output) if !output.status.success() => warn!(
+                    "Could not set the process priority of {}: {}",
+                    pid,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ),
+                Ok(_) => {}
+                Err(error) => warn!("Could not set the process priority of {}: {}", pid, error),
+            }
+        }
+    });
+}
+
+#[cfg(unix)]
+fn unix_commands(pid: u32, cores: &[usize]) -> Vec<Command> {
+    let mut commands = Vec::new();
+    if !cores.is_empty() {
+        if cfg!(target_os = "linux") {
+            let cores: Vec<String> = cores.iter().map(|core| core.to_string()).collect();
+            // `-a` includes the threads the jvm already started.
+            let mut taskset = Command::new("taskset");
+            taskset.args(["-a", "-p", "-c", &cores.join(","), &pid.to_string()]);
+            commands.push(taskset);
+        } else {
+            warn!("Cpu affinity can't be set on this os");
+        }
+    }
+    commands
+}
+
+#[cfg(windows)]
+fn windows_commands(pid: u32, priority: ProcessPriority, cores: &[usize]) -> Vec<Command> {
+    use std::os::windows::process::CommandExt;
+    use windows_sys::Win32::System::Threading::CREATE_NO_WINDOW;
+
+    let mut script = format!("$process = Get-Process -Id {};", pid);
+    if priority != ProcessPriority::Normal {
+        script.push_str(&format!(
+            " $process.PriorityClass = '{}';",
+            priority.priority_class()
+        ));
+    }
+    // Processes are limited to the cores of their processor group, at most 64.
+    let mask = cores
+        .iter()
+        .filter(|core| **core < 64)
+        .fold(0u64, |mask, core| mask | (1 << core));
+    if mask != 0 {
+        script.push_str(&format!(" $process.ProcessorAffinity = {};", mask));
+    }
+    let mut powershell = Command::new("powershell");
+    powershell
+        .args(["-NoProfile", "-NonInteractive", "-Command", &script])
+        .creation_flags(CREATE_NO_WINDOW);
+    vec![powershell]
+}
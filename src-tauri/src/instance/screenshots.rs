@@ -0,0 +1,116 @@
+use std::{
+    cmp::Reverse,
+    fs, io,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use image::imageops::FilterType;
+use log::{debug, warn};
+use serde::Serialize;
+
+use crate::{
+    instance::resolve_file_in,
+    state::instance_manager::{InstanceError, InstanceResult},
+};
+
+/// Thumbnails fit inside of a square with this size in pixels.
+const THUMBNAIL_SIZE: u32 = 256;
+
+#[derive(Debug, Serialize)]
+pub struct ScreenshotEntry {
+    #[serde(rename = "fileName")]
+    pub file_name: String,
+    pub path: PathBuf,
+    /// Timestamp in seconds of when the screenshot was taken.
+    pub timestamp: Option<i64>,
+    pub width: u32,
+    pub height: u32,
+    /// Size of the file in bytes.
+    pub size: u64,
+}
+
+/// Lists the screenshots in a `screenshots` folder, newest first.
+pub fn list_screenshots(screenshots_dir: &Path) -> io::Result<Vec<ScreenshotEntry>> {
+    let mut screenshots = Vec::new();
+    for entry in fs::read_dir(screenshots_dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if path.extension() != Some("png".as_ref()) {
+            continue;
+        }
+        let metadata = entry.metadata()?;
+        let (width, height) = match image::image_dimensions(&path) {
+            Ok(dimensions) => dimensions,
+            Err(error) => {
+                warn!("Could not read screenshot {}: {}", path.display(), error);
+                continue;
+            }
+        };
+        let timestamp = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map(|duration| duration.as_secs() as i64);
+        screenshots.push(ScreenshotEntry {
+            file_name: entry.file_name().to_string_lossy().into_owned(),
+            path,
+            timestamp,
+            width,
+            height,
+            size: metadata.len(),
+        });
+    }
+    screenshots.sort_by_key(|screenshot| Reverse(screenshot.timestamp));
+    Ok(screenshots)
+}
+
+/// Resolves a screenshot by file name, rejecting anything that isn't directly inside `screenshots_dir`.
+pub fn get_screenshot_path(screenshots_dir: &Path, file_name: &str) -> InstanceResult<PathBuf> {
+    resolve_file_in(screenshots_dir, file_name)
+        .ok_or_else(|| InstanceError::UnknownScreenshot(file_name.into()))
+}
+
+/// Returns the path of a cached thumbnail for a screenshot, generating it when it is missing or
+/// older than the screenshot.
+pub fn get_thumbnail(
+    screenshots_dir: &Path,
+    thumbnails_dir: &Path,
+    file_name: &str,
+) -> InstanceResult<PathBuf> {
+    let screenshot_path = get_screenshot_path(screenshots_dir, file_name)?;
+    let thumbnail_path = thumbnails_dir.join(file_name);
+    if is_thumbnail_current(&screenshot_path, &thumbnail_path) {
+        return Ok(thumbnail_path);
+    }
+    debug!("Generating thumbnail for {}", screenshot_path.display());
+    fs::create_dir_all(thumbnails_dir)?;
+    let thumbnail =
+        image::open(&screenshot_path)?.resize(THUMBNAIL_SIZE, THUMBNAIL_SIZE, FilterType::Triangle);
+    thumbnail.save(&thumbnail_path)?;
+    Ok(thumbnail_path)
+}
+
+/// Whether the thumbnail at `thumbnail_path` was generated after the image it shows last changed.
+pub fn is_thumbnail_current(screenshot_path: &Path, thumbnail_path: &Path) -> bool {
+    let modified = |path: &Path| fs::metadata(path).and_then(|metadata| metadata.modified());
+    match (modified(screenshot_path), modified(thumbnail_path)) {
+        (Ok(screenshot), Ok(thumbnail)) => thumbnail >= screenshot,
+        _ => false,
+    }
+}
+
+/// Deletes a screenshot along with its cached thumbnail.
+pub fn delete_screenshot(
+    screenshots_dir: &Path,
+    thumbnails_dir: &Path,
+    file_name: &str,
+) -> InstanceResult<()> {
+    let screenshot_path = get_screenshot_path(screenshots_dir, file_name)?;
+    fs::remove_file(screenshot_path)?;
+    let thumbnail_path = thumbnails_dir.join(file_name);
+    if thumbnail_path.exists() {
+        fs::remove_file(thumbnail_path)?;
+    }
+    Ok(())
+}
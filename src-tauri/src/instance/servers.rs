@@ -0,0 +1,140 @@
+use std::path::Path;
+
+use indexmap::IndexMap;
+use serde::Serialize;
+
+use crate::{
+    nbt::{read_nbt_file, write_nbt_file, Tag},
+    state::instance_manager::{InstanceError, InstanceResult},
+};
+
+/// A multiplayer server from an instance's `servers.dat`, in the order the game lists them.
+#[derive(Debug, Serialize)]
+pub struct ServerEntry {
+    pub name: String,
+    pub ip: String,
+    /// Base64 encoded png of the server icon, the game saves it after pinging the server.
+    pub icon: Option<String>,
+    /// Whether the server's resource pack is accepted, unset means the game prompts.
+    #[serde(rename = "acceptTextures")]
+    pub accept_textures: Option<bool>,
+}
+
+impl ServerEntry {
+    fn from_tag(tag: &Tag) -> Self {
+        let get_str = |key: &str| tag.get(key).and_then(Tag::as_str).map(String::from);
+        Self {
+            name: get_str("name").unwrap_or_default(),
+            ip: get_str("ip").unwrap_or_default(),
+            icon: get_str("icon"),
+            accept_textures: tag
+                .get("acceptTextures")
+                .and_then(Tag::as_i64)
+                .map(|value| value != 0),
+        }
+    }
+}
+
+/// The raw server compounds are kept so fields this launcher doesn't know about survive a rewrite.
+struct ServerList {
+    root_name: String,
+    root: Tag,
+}
+
+impl ServerList {
+    /// Loads `servers.dat`, a missing file is an empty list.
+    fn load(path: &Path) -> InstanceResult<Self> {
+        if !path.exists() {
+            let mut root = IndexMap::new();
+            root.insert("servers".into(), Tag::List(Vec::new()));
+            return Ok(Self {
+                root_name: String::new(),
+                root: Tag::Compound(root),
+            });
+        }
+        let (root_name, root) = read_nbt_file(path)?;
+        Ok(Self { root_name, root })
+    }
+
+    fn servers(&self) -> &[Tag] {
+        self.root
+            .get("servers")
+            .and_then(Tag::as_list)
+            .unwrap_or_default()
+    }
+
+    fn servers_mut(&mut self) -> &mut Vec<Tag> {
+        let root = self
+            .root
+            .as_compound_mut()
+            .expect("Nbt roots are always compounds");
+        let servers = root
+            .entry("servers".into())
+            .or_insert_with(|| Tag::List(Vec::new()));
+        if !matches!(servers, Tag::List(_)) {
+            *servers = Tag::List(Vec::new());
+        }
+        match servers {
+            Tag::List(servers) => servers,
+            _ => unreachable!(),
+        }
+    }
+
+    fn check_index(&self, index: usize) -> InstanceResult<()> {
+        if index < self.servers().len() {
+            Ok(())
+        } else {
+            Err(InstanceError::UnknownServer(index))
+        }
+    }
+
+    /// `servers.dat` is the only nbt file the game doesn't compress.
+    fn save(&self, path: &Path) -> InstanceResult<()> {
+        Ok(write_nbt_file(path, &self.root_name, &self.root, false)?)
+    }
+}
+
+pub fn list_servers(servers_path: &Path) -> InstanceResult<Vec<ServerEntry>> {
+    let server_list = ServerList::load(servers_path)?;
+    Ok(server_list
+        .servers()
+        .iter()
+        .map(ServerEntry::from_tag)
+        .collect())
+}
+
+/// Adds a server at `index`, or at the end of the list when no index is given.
+pub fn add_server(
+    servers_path: &Path,
+    name: &str,
+    ip: &str,
+    index: Option<usize>,
+) -> InstanceResult<()> {
+    let mut server_list = ServerList::load(servers_path)?;
+    let mut server = IndexMap::new();
+    server.insert("name".into(), Tag::String(name.into()));
+    server.insert("ip".into(), Tag::String(ip.into()));
+
+    let servers = server_list.servers_mut();
+    let index = index.unwrap_or(servers.len()).min(servers.len());
+    servers.insert(index, Tag::Compound(server));
+    server_list.save(servers_path)
+}
+
+pub fn remove_server(servers_path: &Path, index: usize) -> InstanceResult<()> {
+    let mut server_list = ServerList::load(servers_path)?;
+    server_list.check_index(index)?;
+    server_list.servers_mut().remove(index);
+    server_list.save(servers_path)
+}
+
+/// Moves the server at `from` so it ends up at `to`.
+pub fn move_server(servers_path: &Path, from: usize, to: usize) -> InstanceResult<()> {
+    let mut server_list = ServerList::load(servers_path)?;
+    server_list.check_index(from)?;
+    server_list.check_index(to)?;
+    let servers = server_list.servers_mut();
+    let server = servers.remove(from);
+    servers.insert(to, server);
+    server_list.save(servers_path)
+}
@@ -0,0 +1,153 @@
+//! Totals of the statistics the game keeps for every player in `saves/${world}/stats/${uuid}.json`.
+//! 1.13 grouped them by category, e.g. `stats."minecraft:custom"."minecraft:deaths"`, older versions
+//! use flat names like `stat.deaths`.
+
+use std::{fs, io, path::Path};
+
+use log::warn;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// The play time statistic in ticks, named `play_one_minute` before 1.17.
+const PLAY_TIME_STATS: [&str; 2] = ["minecraft:play_time", "minecraft:play_one_minute"];
+const DEATHS_STAT: &str = "minecraft:deaths";
+/// Blocks mined, keyed by block id.
+const MINED_CATEGORY: &str = "minecraft:mined";
+const CUSTOM_CATEGORY: &str = "minecraft:custom";
+
+const LEGACY_PLAY_TIME_STAT: &str = "stat.playOneMinute";
+const LEGACY_DEATHS_STAT: &str = "stat.deaths";
+/// Followed by the block id, e.g. `stat.mineBlock.minecraft.stone`.
+const LEGACY_MINED_PREFIX: &str = "stat.mineBlock.";
+
+const TICKS_PER_SECOND: u64 = 20;
+
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct Statistics {
+    #[serde(rename = "blocksMined")]
+    pub blocks_mined: u64,
+    pub deaths: u64,
+    /// In seconds.
+    #[serde(rename = "playTime")]
+    pub play_time: u64,
+}
+
+impl Statistics {
+    fn add(&mut self, other: &Statistics) {
+        self.blocks_mined += other.blocks_mined;
+        self.deaths += other.deaths;
+        self.play_time += other.play_time;
+    }
+
+    /// The statistics of a single player.
+    fn parse(stats: &Value) -> Self {
+        let empty = Map::new();
+        let category = |name: &str| {
+            stats
+                .get("stats")
+                .and_then(|stats| stats.get(name))
+                .and_then(|category| category.as_object())
+                .unwrap_or(&empty)
+        };
+        let custom = category(CUSTOM_CATEGORY);
+        let stat = |name: &str, legacy_name: &str| {
+            custom
+                .get(name)
+                .or_else(|| stats.get(legacy_name))
+                .and_then(|value| value.as_u64())
+                .unwrap_or_default()
+        };
+
+        let mut blocks_mined: u64 = category(MINED_CATEGORY)
+            .values()
+            .filter_map(|count| count.as_u64())
+            .sum();
+        if let Some(stats) = stats.as_object() {
+            blocks_mined += stats
+                .iter()
+                .filter(|(name, _)| name.starts_with(LEGACY_MINED_PREFIX))
+                .filter_map(|(_, count)| count.as_u64())
+                .sum::<u64>();
+        }
+        let play_time_ticks = PLAY_TIME_STATS
+            .iter()
+            .map(|name| stat(name, LEGACY_PLAY_TIME_STAT))
+            .find(|ticks| *ticks > 0)
+            .unwrap_or_default();
+        Self {
+            blocks_mined,
+            deaths: stat(DEATHS_STAT, LEGACY_DEATHS_STAT),
+            play_time: play_time_ticks / TICKS_PER_SECOND,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct WorldStatistics {
+    #[serde(rename = "folderName")]
+    pub folder_name: String,
+    #[serde(flatten)]
+    pub statistics: Statistics,
+}
+
+/// The statistics of every world of an instance and their totals.
+#[derive(Debug, Default, Serialize)]
+pub struct InstanceStatistics {
+    /// Only worlds with statistics, sorted by folder name.
+    pub worlds: Vec<WorldStatistics>,
+    pub totals: Statistics,
+}
+
+/// Adds up the statistics of every player in every world of a `saves` folder.
+pub fn aggregate_statistics(saves_dir: &Path) -> io::Result<InstanceStatistics> {
+    let mut aggregated = InstanceStatistics::default();
+    if !saves_dir.exists() {
+        return Ok(aggregated);
+    }
+    for entry in fs::read_dir(saves_dir)? {
+        let world_dir = entry?.path();
+        if !world_dir.join("level.dat").is_file() {
+            continue;
+        }
+        if let Some(statistics) = read_world_statistics(&world_dir) {
+            aggregated.totals.add(&statistics);
+            aggregated.worlds.push(WorldStatistics {
+                folder_name: world_dir
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+                statistics,
+            });
+        }
+    }
+    aggregated
+        .worlds
+        .sort_by(|a, b| a.folder_name.cmp(&b.folder_name));
+    Ok(aggregated)
+}
+
+/// Adds up the statistics of every player that joined a world, None if the world has none.
+pub fn read_world_statistics(world_dir: &Path) -> Option<Statistics> {
+    let entries = fs::read_dir(world_dir.join("stats")).ok()?;
+    let mut statistics: Option<Statistics> = None;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension() != Some("json".as_ref()) {
+            continue;
+        }
+        let stats: Value = match fs::read(&path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        {
+            Some(stats) => stats,
+            None => {
+                warn!("Could not read statistics {}", path.display());
+                continue;
+            }
+        };
+        statistics
+            .get_or_insert_with(Statistics::default)
+            .add(&Statistics::parse(&stats));
+    }
+    statistics
+}
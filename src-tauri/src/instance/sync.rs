@@ -0,0 +1,236 @@
+//! Mirrors parts of an instance to a folder outside of the launcher, e.g. one kept in sync by
+//! Syncthing or Dropbox, so worlds and settings follow the player between computers. The folder is
+//! pulled before the game starts and pushed to after it exits.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs, io,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+
+/// What was synced last, kept in the instance directory to tell which side changed since then.
+const SYNC_STATE_FILE: &str = ".sync-state.json";
+
+/// Parts of an instance that can be synced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SyncEntry {
+    Saves,
+    Config,
+    Options,
+    ResourcePacks,
+    ShaderPacks,
+    Screenshots,
+}
+
+impl SyncEntry {
+    /// Path of the entry relative to the instance directory and the sync folder.
+    pub fn path(self) -> &'static str {
+        match self {
+            SyncEntry::Saves => "saves",
+            SyncEntry::Config => "config",
+            SyncEntry::Options => "options.txt",
+            SyncEntry::ResourcePacks => "resourcepacks",
+            SyncEntry::ShaderPacks => "shaderpacks",
+            SyncEntry::Screenshots => "screenshots",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SyncSettings {
+    /// The folder the entries are mirrored to.
+    pub directory: PathBuf,
+    pub entries: Vec<SyncEntry>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SyncDirection {
+    /// Sync folder to instance, before launching.
+    Pull,
+    /// Instance to sync folder, after the game exited.
+    Push,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct SyncReport {
+    /// Files copied to the other side.
+    pub copied: Vec<String>,
+    /// Files changed on both sides since the last sync, neither copy was touched.
+    pub conflicts: Vec<String>,
+}
+
+/// Size and modification time of a file, compared to the ones recorded when it was last synced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+struct FileStamp {
+    size: u64,
+    modified: u64,
+}
+
+impl FileStamp {
+    fn read(path: &Path) -> Option<Self> {
+        let metadata = fs::metadata(path).ok()?;
+        let modified = metadata
+            .modified()
+            .ok()?
+            .duration_since(UNIX_EPOCH)
+            .ok()?
+            .as_secs();
+        Some(Self {
+            size: metadata.len(),
+            modified,
+        })
+    }
+}
+
+/// Both sides of a file right after it was synced.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+struct SyncedFile {
+    local: FileStamp,
+    remote: FileStamp,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct SyncState {
+    // <Path relative to the instance directory, stamps>
+    files: BTreeMap<String, SyncedFile>,
+}
+
+impl SyncState {
+    fn load(instance_dir: &Path) -> Self {
+        fs::read(instance_dir.join(SYNC_STATE_FILE))
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self, instance_dir: &Path) -> io::Result<()> {
+        let json = serde_json::to_vec_pretty(self)?;
+        fs::write(instance_dir.join(SYNC_STATE_FILE), json)
+    }
+}
+
+/// Lists the files under `path` relative to `root`, `path` may be a single file.
+fn collect_files(root: &Path, path: &Path, files: &mut BTreeSet<String>) -> io::Result<()> {
+    if path.is_dir() {
+        for entry in fs::read_dir(path)? {
+            collect_files(root, &entry?.path(), files)?;
+        }
+    } else if path.is_file() {
+        if let Ok(relative) = path.strip_prefix(root) {
+            // Forward slashes so the state file reads the same on every platform.
+            let relative = relative
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            files.insert(relative);
+        }
+    }
+    Ok(())
+}
+
+fn copy_file(from: &Path, to: &Path) -> io::Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::copy(from, to)?;
+    Ok(())
+}
+
+/// Copies the files that changed on the source side of `direction` since the last sync. A file that
+/// changed on both sides is reported as a conflict and left alone on both, unless `overwrite` is set
+/// to settle conflicts in favor of the source. Deletions aren't synced, a file deleted on one side
+/// after it was synced stays on the other and is only copied back once it changes there.
+pub fn sync_instance(
+    instance_dir: &Path,
+    settings: &SyncSettings,
+    direction: SyncDirection,
+    overwrite: bool,
+) -> io::Result<SyncReport> {
+    let mut state = SyncState::load(instance_dir);
+    let mut report = SyncReport::default();
+    // A missing folder is likely on a drive that isn't mounted, syncing would copy everything again.
+    if !settings.directory.is_dir() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!(
+                "The sync folder {} does not exist",
+                settings.directory.display()
+            ),
+        ));
+    }
+
+    let mut files = BTreeSet::new();
+    for entry in &settings.entries {
+        collect_files(instance_dir, &instance_dir.join(entry.path()), &mut files)?;
+        collect_files(
+            &settings.directory,
+            &settings.directory.join(entry.path()),
+            &mut files,
+        )?;
+    }
+
+    for file in files {
+        let local_path = instance_dir.join(&file);
+        let remote_path = settings.directory.join(&file);
+        let local = FileStamp::read(&local_path);
+        let remote = FileStamp::read(&remote_path);
+        let synced = state.files.get(&file).copied();
+        let local_changed = local != synced.map(|synced| synced.local);
+        let remote_changed = remote != synced.map(|synced| synced.remote);
+
+        let (source, source_path, destination_path, source_changed, destination_changed) =
+            match direction {
+                SyncDirection::Pull => (
+                    remote,
+                    &remote_path,
+                    &local_path,
+                    remote_changed,
+                    local_changed,
+                ),
+                SyncDirection::Push => (
+                    local,
+                    &local_path,
+                    &remote_path,
+                    local_changed,
+                    remote_changed,
+                ),
+            };
+        if source.is_none() || !source_changed {
+            continue;
+        }
+        // Files that were never synced are only a conflict if both copies differ.
+        let identical_copies = synced.is_none() && local == remote;
+        if destination_changed && destination_path.exists() && !identical_copies && !overwrite {
+            warn!("Sync conflict in {}: {}", instance_dir.display(), file);
+            report.conflicts.push(file);
+            continue;
+        }
+        if !identical_copies {
+            debug!("Syncing {} ({:?})", file, direction);
+            copy_file(source_path, destination_path)?;
+            report.copied.push(file.clone());
+        }
+        if let (Some(local), Some(remote)) =
+            (FileStamp::read(&local_path), FileStamp::read(&remote_path))
+        {
+            state.files.insert(file, SyncedFile { local, remote });
+        }
+    }
+
+    state.save(instance_dir)?;
+    info!(
+        "Synced {} ({:?}): {} copied, {} conflicts",
+        instance_dir.display(),
+        direction,
+        report.copied.len(),
+        report.conflicts.len()
+    );
+    Ok(report)
+}
@@ -0,0 +1,165 @@
+//! Launcher managed trash at `${app_dir}/.trash`. Deleted instances, mods and worlds are moved here
+//! and can be restored until they expire. Every entry is a folder holding the deleted file or folder
+//! and an `entry.json` describing where it came from.
+
+use std::{
+    cmp::Reverse,
+    fs, io,
+    path::{Component, Path, PathBuf},
+};
+
+use chrono::Local;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+/// Entries older than this are deleted for good.
+pub const TRASH_RETENTION_DAYS: i64 = 7;
+const ENTRY_FILE: &str = "entry.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TrashKind {
+    Instance,
+    Mod,
+    World,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TrashEntry {
+    /// Name of the entry's folder in the trash.
+    pub id: String,
+    pub kind: TrashKind,
+    #[serde(rename = "instanceName")]
+    pub instance_name: String,
+    /// Where the deleted file was, relative to the instance directory. Empty for whole instances.
+    #[serde(rename = "relativePath")]
+    pub relative_path: PathBuf,
+    /// Timestamp in seconds of when the file was deleted.
+    #[serde(rename = "deletedAt")]
+    pub deleted_at: i64,
+    /// Timestamp in seconds of when the entry is deleted for good.
+    #[serde(rename = "expiresAt")]
+    pub expires_at: i64,
+}
+
+impl TrashEntry {
+    fn load(entry_dir: &Path) -> Option<Self> {
+        let bytes = fs::read(entry_dir.join(ENTRY_FILE)).ok()?;
+        serde_json::from_slice(&bytes).ok()
+    }
+
+    /// Name of the deleted file or folder inside of the entry's folder.
+    fn item_name(&self) -> String {
+        match self.relative_path.file_name() {
+            Some(file_name) => file_name.to_string_lossy().into_owned(),
+            None => self.instance_name.clone(),
+        }
+    }
+
+    /// Where the entry is put back to.
+    pub fn original_path(&self, instances_dir: &Path) -> PathBuf {
+        instances_dir
+            .join(&self.instance_name)
+            .join(&self.relative_path)
+    }
+}
+
+/// Moves `path` into the trash. `relative_path` is where it is inside of the instance, or empty when
+/// `path` is the instance itself.
+pub fn move_to_trash(
+    trash_dir: &Path,
+    path: &Path,
+    kind: TrashKind,
+    instance_name: &str,
+    relative_path: &Path,
+) -> io::Result<TrashEntry> {
+    let now = Local::now();
+    let mut id = now.format("%Y-%m-%dT%H-%M-%S%.3f").to_string();
+    // Deleting several mods at once can happen within the same millisecond.
+    let mut suffix = 1;
+    while trash_dir.join(&id).exists() {
+        id = format!("{}-{}", now.format("%Y-%m-%dT%H-%M-%S%.3f"), suffix);
+        suffix += 1;
+    }
+
+    let entry = TrashEntry {
+        id,
+        kind,
+        instance_name: instance_name.into(),
+        relative_path: relative_path.into(),
+        deleted_at: now.timestamp(),
+        expires_at: now.timestamp() + TRASH_RETENTION_DAYS * 24 * 60 * 60,
+    };
+    let entry_dir = trash_dir.join(&entry.id);
+    fs::create_dir_all(&entry_dir)?;
+    fs::write(
+        entry_dir.join(ENTRY_FILE),
+        serde_json::to_vec_pretty(&entry)?,
+    )?;
+    if let Err(error) = fs::rename(path, entry_dir.join(entry.item_name())) {
+        fs::remove_dir_all(&entry_dir)?;
+        return Err(error);
+    }
+    info!("Moved {} to the trash as {}", path.display(), entry.id);
+    Ok(entry)
+}
+
+/// Lists the entries in the trash, newest first.
+pub fn list_trash(trash_dir: &Path) -> io::Result<Vec<TrashEntry>> {
+    if !trash_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(trash_dir)? {
+        if let Some(entry) = TrashEntry::load(&entry?.path()) {
+            entries.push(entry);
+        }
+    }
+    entries.sort_by_key(|entry| Reverse(entry.deleted_at));
+    Ok(entries)
+}
+
+/// Returns the entry with `id`, None for ids that aren't a plain folder name in the trash.
+pub fn get_trash_entry(trash_dir: &Path, id: &str) -> Option<TrashEntry> {
+    let mut components = Path::new(id).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(_)), None) => TrashEntry::load(&trash_dir.join(id)),
+        _ => None,
+    }
+}
+
+/// Moves an entry back to `destination`, which must not exist.
+pub fn restore_from_trash(
+    trash_dir: &Path,
+    entry: &TrashEntry,
+    destination: &Path,
+) -> io::Result<()> {
+    if destination.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", destination.display()),
+        ));
+    }
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let entry_dir = trash_dir.join(&entry.id);
+    fs::rename(entry_dir.join(entry.item_name()), destination)?;
+    fs::remove_dir_all(&entry_dir)?;
+    info!("Restored {} from the trash", destination.display());
+    Ok(())
+}
+
+/// Deletes the entries that expired, or every entry with `all`.
+pub fn purge_trash(trash_dir: &Path, all: bool) -> io::Result<()> {
+    let now = Local::now().timestamp();
+    for entry in list_trash(trash_dir)? {
+        if all || entry.expires_at <= now {
+            info!("Deleting {} from the trash", entry.id);
+            if let Err(error) = fs::remove_dir_all(trash_dir.join(&entry.id)) {
+                warn!("Could not delete {} from the trash: {}", entry.id, error);
+            }
+        }
+    }
+    Ok(())
+}
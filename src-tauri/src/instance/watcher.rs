@@ -0,0 +1,112 @@
+//! Watches the content folders of an instance so lists in the frontend follow files that are dropped
+//! in or removed outside of the launcher.
+
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, RecvTimeoutError},
+    thread,
+    time::Duration,
+};
+
+use log::{debug, warn};
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+
+/// Changes are reported once a folder was quiet for this long, copying a file causes several
+/// events in a row.
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Folders of an instance whose contents are listed in the frontend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ContentFolder {
+    Mods,
+    ResourcePacks,
+    Screenshots,
+}
+
+impl ContentFolder {
+    const ALL: [ContentFolder; 3] = [
+        ContentFolder::Mods,
+        ContentFolder::ResourcePacks,
+        ContentFolder::Screenshots,
+    ];
+
+    pub fn path(self) -> &'static str {
+        match self {
+            ContentFolder::Mods => "mods",
+            ContentFolder::ResourcePacks => "resourcepacks",
+            ContentFolder::Screenshots => "screenshots",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ContentChangedPayload {
+    #[serde(rename = "instanceName")]
+    pub instance_name: String,
+    pub folder: ContentFolder,
+}
+
+/// Reports which content folders of an instance changed until it is dropped.
+pub struct ContentWatcher {
+    _watcher: RecommendedWatcher,
+}
+
+impl ContentWatcher {
+    pub fn start<F>(instance_dir: &Path, on_change: F) -> notify::Result<Self>
+    where
+        F: Fn(ContentFolder) + Send + 'static,
+    {
+        let folders: Vec<(ContentFolder, PathBuf)> = ContentFolder::ALL
+            .iter()
+            .map(|&folder| (folder, instance_dir.join(folder.path())))
+            .collect();
+        for (_, path) in &folders {
+            fs::create_dir_all(path)?;
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        let watched = folders.clone();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<Event>| match event {
+                Ok(event) if !event.kind.is_access() => {
+                    for (folder, path) in &watched {
+                        if event.paths.iter().any(|changed| changed.starts_with(path)) {
+                            // The receiver only hangs up once the watcher is dropped.
+                            let _ = sender.send(*folder);
+                        }
+                    }
+                }
+                Ok(_) => {}
+                Err(error) => warn!("Error watching instance content: {}", error),
+            })?;
+        for (_, path) in &folders {
+            watcher.watch(path, RecursiveMode::NonRecursive)?;
+        }
+
+        // Ends once the watcher and with it the sender is dropped.
+        thread::spawn(move || {
+            while let Ok(folder) = receiver.recv() {
+                let mut changed = BTreeSet::new();
+                changed.insert(folder);
+                loop {
+                    match receiver.recv_timeout(DEBOUNCE) {
+                        Ok(folder) => {
+                            changed.insert(folder);
+                        }
+                        Err(RecvTimeoutError::Timeout) => break,
+                        Err(RecvTimeoutError::Disconnected) => return,
+                    }
+                }
+                for folder in changed {
+                    debug!("Instance content changed in {:?}", folder);
+                    on_change(folder);
+                }
+            }
+        });
+        Ok(Self { _watcher: watcher })
+    }
+}
@@ -0,0 +1,331 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    fs,
+    hash::{Hash, Hasher},
+    io,
+    path::{Path, PathBuf},
+};
+
+use image::{imageops::FilterType, ImageResult, Rgba, RgbaImage};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    instance::{screenshots::is_thumbnail_current, statistics::read_world_statistics},
+    nbt::{read_nbt_file, NbtResult, Tag},
+    state::instance_manager::{InstanceError, InstanceResult},
+};
+
+/// World icons are 64x64, thumbnails are scaled up to this size without smoothing to stay sharp.
+const THUMBNAIL_SIZE: u32 = 128;
+/// Placeholders are a grid of this many blocks in each direction.
+const PLACEHOLDER_BLOCKS: u32 = 8;
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum GameMode {
+    Survival,
+    Creative,
+    Adventure,
+    Spectator,
+    Hardcore,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Difficulty {
+    Peaceful,
+    Easy,
+    Normal,
+    Hard,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct WorldBorder {
+    #[serde(rename = "centerX")]
+    pub center_x: f64,
+    #[serde(rename = "centerZ")]
+    pub center_z: f64,
+    /// Width of the border in blocks.
+    pub size: f64,
+}
+
+/// Everything the Worlds view shows about a single world, read without launching the game.
+#[derive(Debug, Serialize)]
+pub struct WorldDetails {
+    #[serde(flatten)]
+    pub world: WorldEntry,
+    pub difficulty: Option<Difficulty>,
+    #[serde(rename = "difficultyLocked")]
+    pub difficulty_locked: bool,
+    /// Whether cheats are allowed.
+    #[serde(rename = "allowCommands")]
+    pub allow_commands: bool,
+    /// Missing before 1.13.
+    #[serde(rename = "enabledDatapacks")]
+    pub enabled_datapacks: Vec<String>,
+    #[serde(rename = "disabledDatapacks")]
+    pub disabled_datapacks: Vec<String>,
+    #[serde(rename = "worldBorder")]
+    pub world_border: Option<WorldBorder>,
+    /// Seconds played in the world by every player together, None without any statistics.
+    #[serde(rename = "playTime")]
+    pub play_time: Option<u64>,
+}
+
+/// A singleplayer world inside of an instance's `saves` folder.
+#[derive(Debug, Serialize)]
+pub struct WorldEntry {
+    /// Name of the world's folder, this is what identifies a world.
+    #[serde(rename = "folderName")]
+    pub folder_name: String,
+    /// The display name, which can differ from the folder name.
+    #[serde(rename = "levelName")]
+    pub level_name: String,
+    pub seed: Option<i64>,
+    #[serde(rename = "gameMode")]
+    pub game_mode: Option<GameMode>,
+    /// Name of the minecraft version the world was last opened in, missing before 1.9.
+    pub version: Option<String>,
+    /// Timestamp in milliseconds of the last time the world was opened.
+    #[serde(rename = "lastPlayed")]
+    pub last_played: Option<i64>,
+    /// Size of the world folder in bytes.
+    pub size: u64,
+    #[serde(rename = "iconPath")]
+    pub icon_path: Option<PathBuf>,
+    /// The cached thumbnail of the icon, or of a placeholder for worlds without one.
+    #[serde(rename = "thumbnailPath")]
+    pub thumbnail_path: Option<PathBuf>,
+}
+
+/// Lists the worlds in a `saves` folder. Folders without a readable `level.dat` are skipped.
+/// Thumbnails of their icons are cached in `thumbnails_dir`.
+pub fn list_worlds(saves_dir: &Path, thumbnails_dir: &Path) -> io::Result<Vec<WorldEntry>> {
+    let mut worlds = Vec::new();
+    if !saves_dir.exists() {
+        return Ok(worlds);
+    }
+    for entry in fs::read_dir(saves_dir)? {
+        let world_dir = entry?.path();
+        if !world_dir.join("level.dat").is_file() {
+            continue;
+        }
+        match read_world(&world_dir, thumbnails_dir) {
+            Ok(world) => worlds.push(world),
+            Err(error) => warn!("Could not read world {}: {:?}", world_dir.display(), error),
+        }
+    }
+    Ok(worlds)
+}
+
+/// Resolves a world by its folder name, rejecting anything that isn't a world directly inside
+/// `saves_dir`.
+pub fn resolve_world_dir(saves_dir: &Path, folder_name: &str) -> InstanceResult<PathBuf> {
+    let is_plain_name = Path::new(folder_name).file_name() == Some(folder_name.as_ref());
+    let world_dir = saves_dir.join(folder_name);
+    if !is_plain_name || !world_dir.join("level.dat").is_file() {
+        return Err(InstanceError::UnknownWorld(folder_name.into()));
+    }
+    Ok(world_dir)
+}
+
+/// Reads the details of the world in `saves_dir/folder_name`.
+pub fn read_world_details(
+    saves_dir: &Path,
+    thumbnails_dir: &Path,
+    folder_name: &str,
+) -> InstanceResult<WorldDetails> {
+    let world_dir = resolve_world_dir(saves_dir, folder_name)?;
+    let (_, root) = read_nbt_file(&world_dir.join("level.dat"))?;
+    let data = root.get("Data");
+    let get = |key: &str| data.and_then(|data| data.get(key));
+    let is_set = |key: &str| get(key).and_then(|tag| tag.as_i64()) == Some(1);
+    let datapacks = |key: &str| -> Vec<String> {
+        get("DataPacks")
+            .and_then(|datapacks| datapacks.get(key))
+            .and_then(|datapacks| datapacks.as_list())
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|datapack| datapack.as_str().map(String::from))
+            .collect()
+    };
+
+    let difficulty = match get("Difficulty").and_then(|tag| tag.as_i64()) {
+        Some(0) => Some(Difficulty::Peaceful),
+        Some(1) => Some(Difficulty::Easy),
+        Some(2) => Some(Difficulty::Normal),
+        Some(3) => Some(Difficulty::Hard),
+        _ => None,
+    };
+    let border = |key: &str| get(key).and_then(|tag| tag.as_f64());
+    let world_border = match (
+        border("BorderCenterX"),
+        border("BorderCenterZ"),
+        border("BorderSize"),
+    ) {
+        (Some(center_x), Some(center_z), Some(size)) => Some(WorldBorder {
+            center_x,
+            center_z,
+            size,
+        }),
+        _ => None,
+    };
+
+    Ok(WorldDetails {
+        world: world_entry(&world_dir, thumbnails_dir, &root)?,
+        difficulty,
+        difficulty_locked: is_set("DifficultyLocked"),
+        allow_commands: is_set("allowCommands"),
+        enabled_datapacks: datapacks("Enabled"),
+        disabled_datapacks: datapacks("Disabled"),
+        world_border,
+        play_time: read_world_statistics(&world_dir).map(|statistics| statistics.play_time),
+    })
+}
+
+fn read_world(world_dir: &Path, thumbnails_dir: &Path) -> NbtResult<WorldEntry> {
+    let (_, root) = read_nbt_file(&world_dir.join("level.dat"))?;
+    world_entry(world_dir, thumbnails_dir, &root)
+}
+
+fn world_entry(world_dir: &Path, thumbnails_dir: &Path, root: &Tag) -> NbtResult<WorldEntry> {
+    let folder_name = world_dir
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let data = root.get("Data");
+    let get = |key: &str| data.and_then(|data| data.get(key));
+
+    // 1.16+ moved the seed into the world generation settings.
+    let seed = get("WorldGenSettings")
+        .and_then(|settings| settings.get("seed"))
+        .or_else(|| get("RandomSeed"))
+        .and_then(|seed| seed.as_i64());
+    let hardcore = get("hardcore").and_then(|tag| tag.as_i64()) == Some(1);
+    let game_mode = match get("GameType").and_then(|tag| tag.as_i64()) {
+        _ if hardcore => Some(GameMode::Hardcore),
+        Some(0) => Some(GameMode::Survival),
+        Some(1) => Some(GameMode::Creative),
+        Some(2) => Some(GameMode::Adventure),
+        Some(3) => Some(GameMode::Spectator),
+        _ => None,
+    };
+    let icon_path = world_dir.join("icon.png");
+    let icon_path = if icon_path.is_file() {
+        Some(icon_path)
+    } else {
+        None
+    };
+    let thumbnail_path =
+        match get_world_thumbnail(icon_path.as_deref(), thumbnails_dir, &folder_name, seed) {
+            Ok(thumbnail_path) => Some(thumbnail_path),
+            Err(error) => {
+                warn!(
+                    "Could not create a thumbnail for {}: {}",
+                    folder_name, error
+                );
+                None
+            }
+        };
+
+    Ok(WorldEntry {
+        level_name: get("LevelName")
+            .and_then(|name| name.as_str())
+            .map_or_else(|| folder_name.clone(), String::from),
+        folder_name,
+        seed,
+        game_mode,
+        version: get("Version")
+            .and_then(|version| version.get("Name"))
+            .and_then(|name| name.as_str())
+            .map(String::from),
+        last_played: get("LastPlayed").and_then(|tag| tag.as_i64()),
+        size: directory_size(world_dir)?,
+        icon_path,
+        thumbnail_path,
+    })
+}
+
+/// Returns the path of a cached thumbnail for a world, generating it when it is missing or older
+/// than the world's icon. Worlds without an icon get a placeholder that is the same for every world
+/// with the same seed, until the game saves an icon.
+fn get_world_thumbnail(
+    icon_path: Option<&Path>,
+    thumbnails_dir: &Path,
+    folder_name: &str,
+    seed: Option<i64>,
+) -> ImageResult<PathBuf> {
+    let thumbnail_path = thumbnails_dir.join(format!("{}.png", folder_name));
+    let thumbnail = match icon_path {
+        Some(icon_path) if is_thumbnail_current(icon_path, &thumbnail_path) => {
+            return Ok(thumbnail_path)
+        }
+        None if thumbnail_path.is_file() => return Ok(thumbnail_path),
+        Some(icon_path) => {
+            debug!("Generating thumbnail for {}", icon_path.display());
+            image::open(icon_path)?
+                .resize(THUMBNAIL_SIZE, THUMBNAIL_SIZE, FilterType::Nearest)
+                .into_rgba8()
+        }
+        None => {
+            debug!("Generating placeholder thumbnail for {}", folder_name);
+            let seed = seed.unwrap_or_else(|| {
+                let mut hasher = DefaultHasher::new();
+                folder_name.hash(&mut hasher);
+                hasher.finish() as i64
+            });
+            placeholder_thumbnail(seed as u64)
+        }
+    };
+    fs::create_dir_all(thumbnails_dir)?;
+    thumbnail.save(&thumbnail_path)?;
+    Ok(thumbnail_path)
+}
+
+/// A block of sky above a layer of grass and dirt, with the shade of every block picked by `seed`.
+fn placeholder_thumbnail(seed: u64) -> RgbaImage {
+    const SKY: [u8; 3] = [126, 171, 255];
+    const GRASS: [u8; 3] = [95, 159, 53];
+    const DIRT: [u8; 3] = [134, 96, 67];
+
+    // Xorshift, a zero state would only ever produce zeros.
+    let mut state = seed | 1;
+    let shades: Vec<u8> = (0..PLACEHOLDER_BLOCKS * PLACEHOLDER_BLOCKS)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % 32) as u8
+        })
+        .collect();
+    let block_size = THUMBNAIL_SIZE / PLACEHOLDER_BLOCKS;
+    RgbaImage::from_fn(THUMBNAIL_SIZE, THUMBNAIL_SIZE, |x, y| {
+        let (column, row) = (x / block_size, y / block_size);
+        let (color, shade) = match row {
+            0..=3 => (SKY, 0),
+            4 => (GRASS, shades[(row * PLACEHOLDER_BLOCKS + column) as usize]),
+            _ => (DIRT, shades[(row * PLACEHOLDER_BLOCKS + column) as usize]),
+        };
+        Rgba([
+            color[0].saturating_sub(shade),
+            color[1].saturating_sub(shade),
+            color[2].saturating_sub(shade),
+            255,
+        ])
+    })
+}
+
+fn directory_size(path: &Path) -> io::Result<u64> {
+    let mut size = 0;
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        size += if metadata.is_dir() {
+            directory_size(&entry.path())?
+        } else {
+            metadata.len()
+        };
+    }
+    Ok(size)
+}
@@ -0,0 +1,39 @@
+use std::{path::Path, process::Command};
+
+use log::debug;
+
+/// Runs `java -version` and returns the major version of the runtime.
+pub fn probe_java_major_version(java_path: &Path) -> Result<u32, String> {
+    let output = Command::new(java_path)
+        .arg("-version")
+        .output()
+        .map_err(|error| format!("Could not run {}: {}", java_path.display(), error))?;
+    // `-version` prints to stderr, some distributions use stdout instead.
+    let text = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stderr),
+        String::from_utf8_lossy(&output.stdout)
+    );
+    debug!("Java version output: {}", text);
+    parse_java_major_version(&text).ok_or_else(|| {
+        format!(
+            "Could not read the java version of {}: {}",
+            java_path.display(),
+            text.lines().next().unwrap_or_default()
+        )
+    })
+}
+
+/// Parses the major version out of `java -version` output, e.g. `java version "1.8.0_351"` is 8 and
+/// `openjdk version "17.0.8" 2023-07-18` is 17.
+pub fn parse_java_major_version(output: &str) -> Option<u32> {
+    let version = output.split('"').nth(1)?;
+    let mut components = version.split(|c: char| !c.is_ascii_digit());
+    let major = components.next()?.parse::<u32>().ok()?;
+    // Java 8 and older use the `1.<major>` format.
+    if major == 1 {
+        components.next()?.parse().ok()
+    } else {
+        Some(major)
+    }
+}
@@ -0,0 +1,114 @@
+//! What the launcher does with its window while a game runs. The backend keeps running in every
+//! case, so the game is still supervised and its playtime recorded when the window is gone.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use tauri::{
+    AppHandle, CustomMenuItem, Manager, SystemTray, SystemTrayEvent, SystemTrayMenu, WindowBuilder,
+    WindowUrl, Wry,
+};
+
+use crate::{state::instance_manager::InstanceState, window_state::restore_window};
+
+const MAIN_WINDOW: &str = "main";
+const SHOW_MENU_ITEM: &str = "show";
+const QUIT_MENU_ITEM: &str = "quit";
+
+/// Games supervised by the launcher, it keeps running without any window until they exited.
+static RUNNING_GAMES: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LauncherBehavior {
+    KeepOpen,
+    /// Hides the window until the game exits or the tray icon is clicked.
+    MinimizeToTray,
+    /// Closes the window and opens a new one when the game exits.
+    Exit,
+}
+
+impl Default for LauncherBehavior {
+    fn default() -> Self {
+        LauncherBehavior::KeepOpen
+    }
+}
+
+/// The tray icon that brings a hidden launcher back.
+pub fn system_tray() -> SystemTray {
+    let menu = SystemTrayMenu::new()
+        .add_item(CustomMenuItem::new(SHOW_MENU_ITEM, "Show launcher"))
+        .add_item(CustomMenuItem::new(QUIT_MENU_ITEM, "Quit"));
+    SystemTray::new().with_menu(menu)
+}
+
+pub fn on_system_tray_event(app_handle: &AppHandle<Wry>, event: SystemTrayEvent) {
+    match event {
+        SystemTrayEvent::LeftClick { .. } => show_main_window(app_handle),
+        SystemTrayEvent::MenuItemClick { id, .. } if id == SHOW_MENU_ITEM => {
+            show_main_window(app_handle)
+        }
+        SystemTrayEvent::MenuItemClick { id, .. } if id == QUIT_MENU_ITEM => quit(app_handle),
+        _ => {}
+    }
+}
+
+/// Whether the launcher has to keep running after its last window closed.
+pub fn is_game_running() -> bool {
+    RUNNING_GAMES.load(Ordering::SeqCst) > 0
+}
+
+/// Call once the game process is spawned.
+pub fn on_game_started(app_handle: &AppHandle<Wry>, behavior: LauncherBehavior) {
+    RUNNING_GAMES.fetch_add(1, Ordering::SeqCst);
+    let window = match app_handle.get_window(MAIN_WINDOW) {
+        Some(window) => window,
+        None => return,
+    };
+    let result = match behavior {
+        LauncherBehavior::KeepOpen => Ok(()),
+        LauncherBehavior::MinimizeToTray => window.hide(),
+        LauncherBehavior::Exit => {
+            info!("Closing the launcher window while the game runs");
+            window.close()
+        }
+    };
+    if let Err(error) = result {
+        warn!("Could not {:?} the launcher window: {}", behavior, error);
+    }
+}
+
+/// Call once the game exited, brings the launcher back if it was hidden or closed. A launcher the
+/// user closed while the game ran exits with the last game instead.
+pub fn on_game_exited(app_handle: &AppHandle<Wry>, behavior: LauncherBehavior) {
+    let running_games = RUNNING_GAMES.fetch_sub(1, Ordering::SeqCst) - 1;
+    if behavior != LauncherBehavior::KeepOpen {
+        show_main_window(app_handle);
+    } else if running_games == 0 && app_handle.get_window(MAIN_WINDOW).is_none() {
+        info!("The last game exited, closing the launcher");
+        app_handle.exit(0);
+    }
+}
+
+/// Exits the launcher, running games are asked to exit with it instead of being left behind.
+fn quit(app_handle: &AppHandle<Wry>) {
+    let instance_state = app_handle.state::<InstanceState>();
+    tauri::async_runtime::block_on(instance_state.0.lock()).terminate_running_instances();
+    app_handle.exit(0);
+}
+
+/// Shows the main window, opening a new one if it was closed.
+fn show_main_window(app_handle: &AppHandle<Wry>) {
+    let result = match app_handle.get_window(MAIN_WINDOW) {
+        Some(window) => window.show().and_then(|_| window.set_focus()),
+        None => WindowBuilder::new(app_handle, MAIN_WINDOW, WindowUrl::App("index.html".into()))
+            .title("Mc Launcher")
+            .inner_size(800.0, 600.0)
+            .build()
+            .map(|window| restore_window(&window)),
+    };
+    if let Err(error) = result {
+        warn!("Could not show the launcher window: {}", error);
+    }
+}
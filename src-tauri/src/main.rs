@@ -5,42 +5,132 @@
 
 mod commands;
 mod consts;
+mod data_dir;
+mod deep_link;
+mod events;
+mod export;
+mod first_run;
+mod i18n;
+mod import;
+mod instance;
+mod java;
+mod launcher_behavior;
+mod nbt;
+mod notifications;
+mod redact;
 mod state;
+mod storage;
+mod system;
 #[cfg(test)]
 mod tests;
 mod web_services;
+mod window_state;
 
-use commands::show_microsoft_login_page;
+use commands::{refresh_all_accounts, run_scheduled_backups, show_microsoft_login_page};
 use log::{error, info, warn, debug};
 use regex::Regex;
 use serde::ser::StdError;
-use state::{account_manager::AccountState, redirect};
+use state::{
+    account_manager::{AccountState, AccountStatus},
+    redirect,
+};
 use std::{
     fs::{self},
     path::{Path, PathBuf}, io::{BufReader, BufRead},
+    time::Duration,
 };
 use tauri::{
     http::{Request, Response, ResponseBuilder},
-    App, AppHandle, Manager, Wry,
+    App, AppHandle, Manager, RunEvent, Wry,
 };
-use web_services::authentication::{authenticate, validate_account, AuthMode};
+use web_services::authentication::{authenticate, AuthMode};
 
 use crate::{
     commands::{
-        get_account_skin, get_instance_path, launch_instance, load_instances, obtain_manifests,
-        obtain_version,
+        add_instance_server, add_offline_account, add_yggdrasil_account, apply_modpack_update,
+        apply_skin_url, assign_instance_group, cancel_task, change_account_name, change_version,
+        check_name_availability, check_new_data_dir, clear_finished_tasks, clone_instance,
+        complete_first_run, create_instance_backup, create_instance_group, delete_instance,
+        delete_instance_backup, delete_instance_group, delete_instance_mod,
+        delete_instance_screenshot, delete_instance_world, detect_external_launcher,
+        detect_official_launcher, detect_skin_model, empty_trash, export_instance_archive,
+        export_instance_mrpack, export_instance_multimc, export_launch_script,
+        generate_instance_lockfile, get_account_avatar, get_account_skin, get_accounts,
+        get_backup_settings, get_bmclapi_mirror_settings, get_crash_report,
+        get_curseforge_settings, get_data_dir, get_effective_settings, get_external_instances,
+        get_global_settings, get_instance_backups, get_instance_config_files,
+        get_instance_crash_reports, get_instance_groups, get_instance_logs, get_instance_notes,
+        get_instance_path, get_instance_release_channel, get_instance_screenshots,
+        get_instance_servers, get_instance_settings, get_instance_statistics, get_instance_sync,
+        get_instance_worlds, get_instances_sorted, get_launch_stats, get_launcher_behavior,
+        get_locale, get_memory_recommendation, get_mirror_settings, get_mod_details,
+        get_mod_metadata, get_name_change_eligibility, get_network_settings,
+        get_official_launcher_profiles, get_screenshot_thumbnail, get_skin_preview,
+        get_storage_report, get_storage_settings, get_tasks, get_trash, get_ui_state,
+        get_verification_settings, get_world_advancements, get_world_details,
+        import_external_instance, import_official_launcher_profile, import_version_bundle,
+        install_loader_api, install_local_files, is_first_run, is_offline, kill_instance,
+        launch_instance, list_mod_versions, load_instances, move_data_dir, move_instance_group,
+        move_instance_in_group, move_instance_server, obtain_manifests, obtain_version,
+        open_instance_folder, preview_launch, preview_modpack_update, read_instance_config_file,
+        read_instance_log, remove_instance_server, rename_instance, restore_instance_backup,
+        restore_instance_config_file, restore_instance_from_lockfile, restore_trash_entry,
+        reveal_instance_screenshot, run_first_run_checks, search_mods, set_backup_settings,
+        set_curseforge_settings, set_global_settings, set_instance_notes,
+        set_instance_release_channel, set_instance_settings, set_instance_sync,
+        set_launcher_behavior, set_locale, set_mirror_settings, set_mod_pinned,
+        set_network_settings, set_storage_settings, set_ui_state, set_verification_settings,
+        start_instance_log_tail, stop_instance_log_tail, sync_instance_now, take_pending_import,
+        unwatch_instance_content, upload_skin, validate_curseforge_settings, verify_instance_files,
+        watch_instance_content, write_instance_config_file,
+    },
+    data_dir::{resolve_data_dir, DataDirState},
+    deep_link::{
+        forward_to_running_launcher, import_request_from_args, listen_for_forwarded_requests,
+        register_handlers, ImportRequest, PendingImportState,
     },
-    state::{instance_manager::InstanceState, resource_manager::ResourceState},
+    events::{AccountRefreshed, EventBus, LauncherEvent},
+    instance::{mods_library::ModsLibrary, trash::purge_trash},
+    launcher_behavior::{is_game_running, on_system_tray_event, system_tray},
+    notifications::WindowFocusState,
+    state::{
+        instance_manager::InstanceState, resource_manager::ResourceState,
+        settings_manager::SettingsState, task_manager::TaskState,
+    },
+    window_state::{restore_window, save_window},
 };
 
 const MAX_LOGS: usize = 20;
+/// How often instances are checked for a due scheduled backup.
+const BACKUP_CHECK_INTERVAL: Duration = Duration::from_secs(15 * 60);
 fn main() {
+    let context = tauri::generate_context!();
+    let import_request = import_request_from_args();
+    if let (Some((arg, request)), Some(app_dir)) = (
+        &import_request,
+        tauri::api::path::app_config_dir(context.config()),
+    ) {
+        if forward_to_running_launcher(&app_dir, arg, request) {
+            return;
+        }
+    }
+    let import_request = import_request.map(|(_, request)| request);
+
     tauri::Builder::default()
-        .setup(setup)
+        .setup(move |app| setup(app, import_request))
         .register_uri_scheme_protocol("autmc", autmc_uri_scheme)
+        .system_tray(system_tray())
+        .on_system_tray_event(on_system_tray_event)
         .on_window_event(|event| match event.event() {
             tauri::WindowEvent::CloseRequested { .. } => {
                 info!("Closing");
+                save_window(event.window());
+            }
+            tauri::WindowEvent::Focused(focused) => {
+                event
+                    .window()
+                    .state::<WindowFocusState>()
+                    .set_focused(*focused);
             }
             _ => {}
         })
@@ -51,30 +141,216 @@ fn main() {
             get_instance_path,
             load_instances,
             get_account_skin,
-            launch_instance
+            launch_instance,
+            add_offline_account,
+            is_offline,
+            rename_instance,
+            delete_instance,
+            clone_instance,
+            get_instance_groups,
+            create_instance_group,
+            delete_instance_group,
+            assign_instance_group,
+            move_instance_group,
+            move_instance_in_group,
+            get_instances_sorted,
+            change_version,
+            get_instance_notes,
+            set_instance_notes,
+            open_instance_folder,
+            get_global_settings,
+            set_global_settings,
+            get_instance_settings,
+            set_instance_settings,
+            get_effective_settings,
+            get_instance_worlds,
+            get_instance_servers,
+            add_instance_server,
+            remove_instance_server,
+            move_instance_server,
+            get_instance_screenshots,
+            get_screenshot_thumbnail,
+            delete_instance_screenshot,
+            reveal_instance_screenshot,
+            get_instance_logs,
+            read_instance_log,
+            get_instance_crash_reports,
+            get_crash_report,
+            start_instance_log_tail,
+            stop_instance_log_tail,
+            get_tasks,
+            cancel_task,
+            clear_finished_tasks,
+            take_pending_import,
+            detect_official_launcher,
+            get_official_launcher_profiles,
+            import_official_launcher_profile,
+            detect_external_launcher,
+            get_external_instances,
+            import_external_instance,
+            export_instance_multimc,
+            add_yggdrasil_account,
+            get_curseforge_settings,
+            set_curseforge_settings,
+            validate_curseforge_settings,
+            get_verification_settings,
+            set_verification_settings,
+            export_instance_archive,
+            get_instance_config_files,
+            read_instance_config_file,
+            write_instance_config_file,
+            restore_instance_config_file,
+            get_data_dir,
+            check_new_data_dir,
+            move_data_dir,
+            get_memory_recommendation,
+            is_first_run,
+            run_first_run_checks,
+            complete_first_run,
+            get_account_avatar,
+            get_skin_preview,
+            get_name_change_eligibility,
+            check_name_availability,
+            change_account_name,
+            get_instance_sync,
+            set_instance_sync,
+            sync_instance_now,
+            create_instance_backup,
+            get_instance_backups,
+            delete_instance_backup,
+            restore_instance_backup,
+            get_backup_settings,
+            set_backup_settings,
+            delete_instance_mod,
+            delete_instance_world,
+            get_trash,
+            restore_trash_entry,
+            empty_trash,
+            watch_instance_content,
+            unwatch_instance_content,
+            install_local_files,
+            preview_modpack_update,
+            apply_modpack_update,
+            install_loader_api,
+            get_mod_metadata,
+            set_mod_pinned,
+            generate_instance_lockfile,
+            restore_instance_from_lockfile,
+            get_storage_settings,
+            set_storage_settings,
+            get_storage_report,
+            get_network_settings,
+            set_network_settings,
+            get_mirror_settings,
+            get_bmclapi_mirror_settings,
+            set_mirror_settings,
+            import_version_bundle,
+            get_launch_stats,
+            get_locale,
+            set_locale,
+            get_ui_state,
+            set_ui_state,
+            verify_instance_files,
+            export_instance_mrpack,
+            get_instance_release_channel,
+            set_instance_release_channel,
+            list_mod_versions,
+            get_mod_details,
+            search_mods,
+            get_accounts,
+            detect_skin_model,
+            upload_skin,
+            apply_skin_url,
+            preview_launch,
+            export_launch_script,
+            get_launcher_behavior,
+            set_launcher_behavior,
+            kill_instance,
+            get_world_details,
+            get_instance_statistics,
+            get_world_advancements
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(context)
+        .expect("error while running tauri application")
+        .run(|_, event| {
+            // The game is still supervised after the window closed, see `launcher_behavior`.
+            if let RunEvent::ExitRequested { api, .. } = event {
+                if is_game_running() {
+                    api.prevent_exit();
+                }
+            }
+        });
 }
 
 /// First thing called on application setup.
-fn setup(app: &mut App<Wry>) -> Result<(), Box<(dyn StdError + 'static)>> {
+fn setup(
+    app: &mut App<Wry>,
+    import_request: Option<ImportRequest>,
+) -> Result<(), Box<(dyn StdError + 'static)>> {
     let path_resolver = app.path_resolver();
 
-    let app_dir = path_resolver.app_config_dir().unwrap();
-    fs::create_dir_all(&app_dir)?;
+    let default_app_dir = path_resolver.app_config_dir().unwrap();
+    fs::create_dir_all(&default_app_dir)?;
+    let app_dir = resolve_data_dir(&default_app_dir);
 
     let log_dir = path_resolver.app_log_dir().unwrap();
     fs::create_dir_all(&log_dir)?;
     init_logger(&log_dir)?;
     info!("Starting Autmc");
+    info!("Using data directory {}", app_dir.display());
+    web_services::hash_cache::load(app_dir.join("cache").join("file_hashes.json"));
 
     // Attach the account manager to the app using 'AccountState'
     app.manage(AccountState::new(&app_dir));
     app.manage(ResourceState::new(&app_dir));
     app.manage(InstanceState::new(&app_dir));
+    app.manage(SettingsState::new(&app_dir));
+    app.manage(TaskState::new());
+    app.manage(DataDirState::new(&default_app_dir, &app_dir));
+    app.manage(WindowFocusState::new());
+    app.manage(PendingImportState::new(import_request));
     let app_handle = app.handle();
 
+    let events = EventBus::new(app_handle.clone());
+    let main_window = app.get_window("main");
+    if let Some(window) = &main_window {
+        restore_window(window);
+    }
+    let listening = listen_for_forwarded_requests(&default_app_dir, move |request| {
+        info!("Received forwarded import request {:?}", request);
+        if let Some(window) = &main_window {
+            if let Err(error) = window.set_focus() {
+                warn!("Could not focus the main window: {}", error);
+            }
+        }
+        events.emit(LauncherEvent::ImportRequested(request));
+    });
+    if let Err(error) = listening {
+        warn!("Could not listen for forwarded import requests: {}", error);
+    }
+    // Only release builds claim the url schemes, a dev build would take them over from the installed launcher.
+    if !cfg!(debug_assertions) {
+        std::thread::spawn(|| {
+            if let Err(error) = register_handlers() {
+                warn!("Could not register the deep link handlers: {}", error);
+            }
+        });
+    }
+
+    if let Err(error) = purge_trash(&app_dir.join(".trash"), false) {
+        warn!("Could not delete expired entries from the trash: {}", error);
+    }
+    // Jars of deleted instances and removed mods are only freed here.
+    if let Err(error) = ModsLibrary::new(app_dir.join("mods-library")).prune() {
+        warn!("Could not prune the mods library: {}", error);
+    }
+
+    let backup_handle = app_handle.clone();
+    std::thread::spawn(move || loop {
+        std::thread::sleep(BACKUP_CHECK_INTERVAL);
+        tauri::async_runtime::block_on(run_scheduled_backups(&backup_handle));
+    });
+
     // Spawn an async thread and use the app_handle to refresh active account.
     // TODO: Maybe emit event to display a toast telling the user what happened.
     tauri::async_runtime::spawn(async move {
@@ -82,7 +358,7 @@ fn setup(app: &mut App<Wry>) -> Result<(), Box<(dyn StdError + 'static)>> {
         let resource_state: tauri::State<ResourceState> = app_handle
             .try_state()
             .expect("`ResourceState` should already be managed.");
-        let mut resource_manager = resource_state.0.lock().await;
+        let resource_manager = &resource_state.0;
         match resource_manager.download_manifests().await {
             Ok(_) => {}
             Err(error) => error!("Manifest Error: {:#?}", error),
@@ -91,35 +367,35 @@ fn setup(app: &mut App<Wry>) -> Result<(), Box<(dyn StdError + 'static)>> {
         let account_state: tauri::State<AccountState> = app_handle
             .try_state()
             .expect("`AccountState` should already be managed.");
-        let mut account_manager = account_state.0.lock().await;
-        match account_manager.deserialize_accounts() {
-            Ok(_) => {}
-            Err(_) => {
-                // If no accounts are saved, bail on this thread since user will need to enter credentials.
-                info!("No account.json exists!");
+        let deserialized = account_state.0.lock().await.deserialize_accounts();
+        if deserialized.is_err() {
+            // If no accounts are saved, bail on this thread since user will need to enter credentials.
+            info!("No accounts are saved!");
 
-                if let Err(error) = redirect(&app_handle, "login") {
-                    error!("{}", error.to_string());
-                }
-                return;
+            if let Err(error) = redirect(&app_handle, "login") {
+                error!("{}", error.to_string());
             }
+            return;
         }
-        let deserialized_account = account_manager.get_active_account();
-        // If there is some active account, retrieve it and attempt to refresh access tokens.
-        match deserialized_account {
-            Some(active_account) => {
-                let validation_result = validate_account(active_account).await;
-                // FIXME: Dont just unwrap, give user any auth errors.
-                let account = validation_result.unwrap();
-                // Save account to account manager.
-                account_manager.add_and_activate_account(account);
-
-                match account_manager.serialize_accounts() {
-                    Ok(_) => {}
-                    Err(err) => warn!("Could not properly serialize account information: {}", err),
+
+        // Refresh every account up front so expired ones are marked instead of failing at launch time.
+        match refresh_all_accounts(&app_handle).await {
+            Some(AccountStatus::Ok) => {
+                let account_manager = account_state.0.lock().await;
+                if let Some(account) = account_manager.get_active_account() {
+                    EventBus::new(app_handle.clone()).emit(LauncherEvent::AccountRefreshed(
+                        AccountRefreshed {
+                            uuid: account.uuid.clone(),
+                            name: account.name.clone(),
+                        },
+                    ));
                 }
             }
-            None => {
+            // The network is unreachable, keep the stored access token so existing instances can still be launched.
+            Some(AccountStatus::NetworkError) => {
+                warn!("Could not refresh the active account, continuing offline");
+            }
+            _ => {
                 if let Err(error) = redirect(&app_handle, "login") {
                     error!("{}", error.to_string());
                 }
@@ -145,12 +421,15 @@ fn autmc_uri_scheme(
     let handle = app_handle.clone();
     // Spawn a thread to handle authentication.
     tauri::async_runtime::spawn(async move {
-        // TODO: Emit an authentication error
         let auth_mode = AuthMode::Full(url);
-        let res = authenticate(auth_mode).await;
-        println!("Auth Result: {:#?}", &res);
-        // FIXME: Dont just unwrap, give user any auth errors.
-        let account = res.unwrap();
+        let account = match authenticate(auth_mode).await {
+            Ok(account) => account,
+            Err(err) => {
+                error!("Could not log in: {:?}", err);
+                EventBus::new(handle.clone()).emit(LauncherEvent::LoginFailed(err.to_string()));
+                return;
+            }
+        };
 
         let account_state: tauri::State<AccountState> = handle
             .try_state()
@@ -189,7 +468,7 @@ fn init_logger(log_dir: &PathBuf) -> Result<(), fern::InitError> {
                 record.line().unwrap_or(0),
                 chrono::Local::now().format("%Y-%m-%dT%H:%M:%S"),
                 record.level(),
-                message
+                redact::redact(&message.to_string())
             ))
         })
         .level(log::LevelFilter::Debug)
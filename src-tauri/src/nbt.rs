@@ -0,0 +1,386 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use indexmap::IndexMap;
+use serde::Serialize;
+
+use crate::i18n::tr;
+
+pub type NbtResult<T> = Result<T, NbtError>;
+
+#[derive(Debug)]
+pub enum NbtError {
+    IoError(io::Error),
+    InvalidTagType(u8),
+    /// The root of a file has to be a compound tag.
+    InvalidRoot(u8),
+    NegativeLength(i32),
+    /// Strings longer than 65535 bytes can't be written.
+    StringTooLong(usize),
+    /// Lists and compounds nested deeper than `MAX_DEPTH`.
+    TooDeep(usize),
+}
+
+impl Serialize for NbtError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.message())
+    }
+}
+
+impl NbtError {
+    /// The message shown to users, in the current locale.
+    pub fn message(&self) -> String {
+        match self {
+            NbtError::IoError(error) => error.to_string(),
+            NbtError::InvalidTagType(tag_type) => {
+                tr("error.nbt.invalidTagType", &[("tagType", tag_type)])
+            }
+            NbtError::InvalidRoot(tag_type) => {
+                tr("error.nbt.invalidRoot", &[("tagType", tag_type)])
+            }
+            NbtError::NegativeLength(length) => {
+                tr("error.nbt.negativeLength", &[("length", length)])
+            }
+            NbtError::StringTooLong(length) => tr("error.nbt.stringTooLong", &[("length", length)]),
+            NbtError::TooDeep(depth) => tr("error.nbt.tooDeep", &[("depth", depth)]),
+        }
+    }
+}
+
+impl From<io::Error> for NbtError {
+    fn from(error: io::Error) -> Self {
+        NbtError::IoError(error)
+    }
+}
+
+const TAG_END: u8 = 0;
+const TAG_BYTE: u8 = 1;
+const TAG_SHORT: u8 = 2;
+const TAG_INT: u8 = 3;
+const TAG_LONG: u8 = 4;
+const TAG_FLOAT: u8 = 5;
+const TAG_DOUBLE: u8 = 6;
+const TAG_BYTE_ARRAY: u8 = 7;
+const TAG_STRING: u8 = 8;
+const TAG_LIST: u8 = 9;
+const TAG_COMPOUND: u8 = 10;
+const TAG_INT_ARRAY: u8 = 11;
+const TAG_LONG_ARRAY: u8 = 12;
+
+/// Lengths are read from the file, so they only reserve up to this many elements upfront and grow
+/// as elements are actually read. A corrupt length then fails at the end of the input instead of
+/// allocating gigabytes.
+const MAX_PREALLOCATED: usize = 4096;
+/// Minecraft's own limit on nested lists and compounds, deeper nesting in a corrupt file would
+/// otherwise overflow the stack.
+const MAX_DEPTH: usize = 512;
+
+/// A single nbt value. Compounds keep the order their entries were read in.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Tag {
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    ByteArray(Vec<i8>),
+    String(String),
+    List(Vec<Tag>),
+    Compound(IndexMap<String, Tag>),
+    IntArray(Vec<i32>),
+    LongArray(Vec<i64>),
+}
+
+impl Tag {
+    fn tag_type(&self) -> u8 {
+        match self {
+            Tag::Byte(_) => TAG_BYTE,
+            Tag::Short(_) => TAG_SHORT,
+            Tag::Int(_) => TAG_INT,
+            Tag::Long(_) => TAG_LONG,
+            Tag::Float(_) => TAG_FLOAT,
+            Tag::Double(_) => TAG_DOUBLE,
+            Tag::ByteArray(_) => TAG_BYTE_ARRAY,
+            Tag::String(_) => TAG_STRING,
+            Tag::List(_) => TAG_LIST,
+            Tag::Compound(_) => TAG_COMPOUND,
+            Tag::IntArray(_) => TAG_INT_ARRAY,
+            Tag::LongArray(_) => TAG_LONG_ARRAY,
+        }
+    }
+
+    /// Looks up an entry if this is a compound.
+    pub fn get(&self, key: &str) -> Option<&Tag> {
+        match self {
+            Tag::Compound(map) => map.get(key),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Tag::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[Tag]> {
+        match self {
+            Tag::List(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn as_compound_mut(&mut self) -> Option<&mut IndexMap<String, Tag>> {
+        match self {
+            Tag::Compound(map) => Some(map),
+            _ => None,
+        }
+    }
+
+    /// Any integer tag, widened to an i64.
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Tag::Byte(value) => Some(*value as i64),
+            Tag::Short(value) => Some(*value as i64),
+            Tag::Int(value) => Some(*value as i64),
+            Tag::Long(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Any floating point tag, widened to an f64.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Tag::Float(value) => Some(*value as f64),
+            Tag::Double(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+/// Reads an nbt file, minecraft gzips most of them (e.g. `level.dat`) but not all (e.g. `servers.dat`).
+/// Returns the name of the root compound and the compound itself.
+pub fn read_nbt_file(path: &Path) -> NbtResult<(String, Tag)> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut magic = [0u8; 2];
+    reader.read_exact(&mut magic)?;
+    let header = io::Cursor::new(magic);
+    if magic == [0x1f, 0x8b] {
+        read_nbt(&mut GzDecoder::new(header.chain(reader)))
+    } else {
+        read_nbt(&mut header.chain(reader))
+    }
+}
+
+/// Reads an uncompressed named root compound.
+pub fn read_nbt<R: Read>(reader: &mut R) -> NbtResult<(String, Tag)> {
+    let tag_type = read_u8(reader)?;
+    if tag_type != TAG_COMPOUND {
+        return Err(NbtError::InvalidRoot(tag_type));
+    }
+    let name = read_string(reader)?;
+    let root = read_payload(reader, tag_type, 0)?;
+    Ok((name, root))
+}
+
+/// Reads the payload of a tag nested `depth` lists and compounds deep.
+fn read_payload<R: Read>(reader: &mut R, tag_type: u8, depth: usize) -> NbtResult<Tag> {
+    if depth > MAX_DEPTH {
+        return Err(NbtError::TooDeep(MAX_DEPTH));
+    }
+    Ok(match tag_type {
+        TAG_BYTE => Tag::Byte(read_u8(reader)? as i8),
+        TAG_SHORT => Tag::Short(i16::from_be_bytes(read_array(reader)?)),
+        TAG_INT => Tag::Int(read_i32(reader)?),
+        TAG_LONG => Tag::Long(i64::from_be_bytes(read_array(reader)?)),
+        TAG_FLOAT => Tag::Float(f32::from_be_bytes(read_array(reader)?)),
+        TAG_DOUBLE => Tag::Double(f64::from_be_bytes(read_array(reader)?)),
+        TAG_BYTE_ARRAY => {
+            let length = read_length(reader)?;
+            let bytes = read_bytes(reader, length)?;
+            Tag::ByteArray(bytes.into_iter().map(|byte| byte as i8).collect())
+        }
+        TAG_STRING => Tag::String(read_string(reader)?),
+        TAG_LIST => {
+            let element_type = read_u8(reader)?;
+            let length = read_length(reader)?;
+            let mut values = Vec::with_capacity(length.min(MAX_PREALLOCATED));
+            for _ in 0..length {
+                values.push(read_payload(reader, element_type, depth + 1)?);
+            }
+            Tag::List(values)
+        }
+        TAG_COMPOUND => {
+            let mut map = IndexMap::new();
+            loop {
+                let entry_type = read_u8(reader)?;
+                if entry_type == TAG_END {
+                    break;
+                }
+                let name = read_string(reader)?;
+                map.insert(name, read_payload(reader, entry_type, depth + 1)?);
+            }
+            Tag::Compound(map)
+        }
+        TAG_INT_ARRAY => {
+            let length = read_length(reader)?;
+            let mut values = Vec::with_capacity(length.min(MAX_PREALLOCATED));
+            for _ in 0..length {
+                values.push(read_i32(reader)?);
+            }
+            Tag::IntArray(values)
+        }
+        TAG_LONG_ARRAY => {
+            let length = read_length(reader)?;
+            let mut values = Vec::with_capacity(length.min(MAX_PREALLOCATED));
+            for _ in 0..length {
+                values.push(i64::from_be_bytes(read_array(reader)?));
+            }
+            Tag::LongArray(values)
+        }
+        _ => return Err(NbtError::InvalidTagType(tag_type)),
+    })
+}
+
+fn read_array<R: Read, const N: usize>(reader: &mut R) -> io::Result<[u8; N]> {
+    let mut bytes = [0u8; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_u8<R: Read>(reader: &mut R) -> io::Result<u8> {
+    Ok(read_array::<R, 1>(reader)?[0])
+}
+
+fn read_i32<R: Read>(reader: &mut R) -> io::Result<i32> {
+    Ok(i32::from_be_bytes(read_array(reader)?))
+}
+
+/// Reads `length` bytes, failing if the input ends before.
+fn read_bytes<R: Read>(reader: &mut R, length: usize) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(length.min(MAX_PREALLOCATED));
+    reader
+        .by_ref()
+        .take(length as u64)
+        .read_to_end(&mut bytes)?;
+    if bytes.len() != length {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            format!(
+                "expected {} bytes, the input ended after {}",
+                length,
+                bytes.len()
+            ),
+        ));
+    }
+    Ok(bytes)
+}
+
+fn read_length<R: Read>(reader: &mut R) -> NbtResult<usize> {
+    let length = read_i32(reader)?;
+    usize::try_from(length).map_err(|_| NbtError::NegativeLength(length))
+}
+
+// NOTE: Nbt strings are java's modified utf8, which only differs from utf8 for null and
+//       supplementary characters. Those are rare enough in minecraft data to read lossily.
+fn read_string<R: Read>(reader: &mut R) -> io::Result<String> {
+    let length = u16::from_be_bytes(read_array(reader)?) as usize;
+    let bytes = read_bytes(reader, length)?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Writes a named root compound to `path`, replacing the file only once everything was written.
+pub fn write_nbt_file(path: &Path, name: &str, root: &Tag, compressed: bool) -> NbtResult<()> {
+    let temp_path = path.with_extension("dat_tmp");
+    {
+        let file = BufWriter::new(File::create(&temp_path)?);
+        if compressed {
+            let mut encoder = GzEncoder::new(file, Compression::default());
+            write_nbt(&mut encoder, name, root)?;
+            encoder.finish()?.flush()?;
+        } else {
+            let mut file = file;
+            write_nbt(&mut file, name, root)?;
+            file.flush()?;
+        }
+    }
+    fs::rename(&temp_path, path)?;
+    Ok(())
+}
+
+/// Writes an uncompressed named root compound.
+pub fn write_nbt<W: Write>(writer: &mut W, name: &str, root: &Tag) -> NbtResult<()> {
+    if root.tag_type() != TAG_COMPOUND {
+        return Err(NbtError::InvalidRoot(root.tag_type()));
+    }
+    writer.write_all(&[TAG_COMPOUND])?;
+    write_string(writer, name)?;
+    write_payload(writer, root)
+}
+
+fn write_payload<W: Write>(writer: &mut W, tag: &Tag) -> NbtResult<()> {
+    match tag {
+        Tag::Byte(value) => writer.write_all(&value.to_be_bytes())?,
+        Tag::Short(value) => writer.write_all(&value.to_be_bytes())?,
+        Tag::Int(value) => writer.write_all(&value.to_be_bytes())?,
+        Tag::Long(value) => writer.write_all(&value.to_be_bytes())?,
+        Tag::Float(value) => writer.write_all(&value.to_be_bytes())?,
+        Tag::Double(value) => writer.write_all(&value.to_be_bytes())?,
+        Tag::ByteArray(values) => {
+            write_length(writer, values.len())?;
+            let bytes: Vec<u8> = values.iter().map(|&byte| byte as u8).collect();
+            writer.write_all(&bytes)?;
+        }
+        Tag::String(value) => write_string(writer, value)?,
+        Tag::List(values) => {
+            // Empty lists have no element type.
+            let element_type = values.first().map_or(TAG_END, Tag::tag_type);
+            writer.write_all(&[element_type])?;
+            write_length(writer, values.len())?;
+            for value in values {
+                write_payload(writer, value)?;
+            }
+        }
+        Tag::Compound(map) => {
+            for (name, value) in map {
+                writer.write_all(&[value.tag_type()])?;
+                write_string(writer, name)?;
+                write_payload(writer, value)?;
+            }
+            writer.write_all(&[TAG_END])?;
+        }
+        Tag::IntArray(values) => {
+            write_length(writer, values.len())?;
+            for value in values {
+                writer.write_all(&value.to_be_bytes())?;
+            }
+        }
+        Tag::LongArray(values) => {
+            write_length(writer, values.len())?;
+            for value in values {
+                writer.write_all(&value.to_be_bytes())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn write_length<W: Write>(writer: &mut W, length: usize) -> io::Result<()> {
+    writer.write_all(&(length as i32).to_be_bytes())
+}
+
+fn write_string<W: Write>(writer: &mut W, value: &str) -> NbtResult<()> {
+    let length = u16::try_from(value.len()).map_err(|_| NbtError::StringTooLong(value.len()))?;
+    writer.write_all(&length.to_be_bytes())?;
+    writer.write_all(value.as_bytes())?;
+    Ok(())
+}
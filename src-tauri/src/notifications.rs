@@ -0,0 +1,71 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use log::warn;
+use tauri::{api::notification::Notification, AppHandle, Manager, Wry};
+
+use crate::{
+    i18n::tr,
+    state::task_manager::{TaskInfo, TaskKind, TaskStatus},
+};
+
+/// Whether the launcher window has focus. Notifications are only shown while it doesn't, since the
+/// window shows the same information itself.
+pub struct WindowFocusState(pub AtomicBool);
+
+impl WindowFocusState {
+    pub fn new() -> Self {
+        // The window is focused when it first opens.
+        Self(AtomicBool::new(true))
+    }
+
+    pub fn set_focused(&self, focused: bool) {
+        self.0.store(focused, Ordering::Relaxed);
+    }
+}
+
+/// Shows a native desktop notification if the launcher window is unfocused.
+pub fn notify_unfocused(app_handle: &AppHandle<Wry>, title: &str, body: &str) {
+    let focus_state = app_handle.state::<WindowFocusState>();
+    if focus_state.0.load(Ordering::Relaxed) {
+        return;
+    }
+    let identifier = &app_handle.config().tauri.bundle.identifier;
+    if let Err(error) = Notification::new(identifier).title(title).body(body).show() {
+        warn!("Could not show notification: {}", error);
+    }
+}
+
+/// Notifies about installs and imports that ended, cancelled tasks were stopped by the user and
+/// don't need one.
+pub fn notify_task_finished(app_handle: &AppHandle<Wry>, task: &TaskInfo) {
+    let title_key = match (&task.kind, &task.status) {
+        (TaskKind::Install, TaskStatus::Completed) => "notification.installed",
+        (TaskKind::Install, TaskStatus::Failed(_)) => "notification.installFailed",
+        (TaskKind::Import, TaskStatus::Completed) => "notification.imported",
+        (TaskKind::Import, TaskStatus::Failed(_)) => "notification.importFailed",
+        _ => return,
+    };
+    let body = match &task.status {
+        TaskStatus::Failed(message) => tr(
+            "notification.taskFailed",
+            &[("target", &task.target), ("message", message)],
+        ),
+        _ => tr("notification.readyToPlay", &[("target", &task.target)]),
+    };
+    notify_unfocused(app_handle, &tr(title_key, &[]), &body);
+}
+
+pub fn notify_game_crashed(
+    app_handle: &AppHandle<Wry>,
+    instance_name: &str,
+    exit_code: Option<i32>,
+) {
+    let body = match exit_code {
+        Some(code) => tr(
+            "notification.exitedWithCode",
+            &[("instance", &instance_name), ("code", &code)],
+        ),
+        None => tr("notification.terminated", &[("instance", &instance_name)]),
+    };
+    notify_unfocused(app_handle, &tr("notification.gameCrashed", &[]), &body);
+}
@@ -0,0 +1,55 @@
+//! Keeps account tokens out of logs and anything users might share. The tokens of stored accounts are
+//! registered as secrets, text that is logged or shown is passed through `redact`, which also catches
+//! tokens that were never registered by their shape.
+
+use std::{
+    collections::HashSet,
+    sync::{PoisonError, RwLock},
+};
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+const REDACTED: &str = "[redacted]";
+/// Shorter values are placeholders like the `0` offline accounts launch with.
+const MIN_SECRET_LEN: usize = 8;
+
+static SECRETS: Lazy<RwLock<HashSet<String>>> = Lazy::new(Default::default);
+
+/// Json web tokens, like the Minecraft access token.
+static JWT_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"eyJ[A-Za-z0-9_-]+\.[A-Za-z0-9_-]+\.[A-Za-z0-9_-]*")
+        .expect("The jwt pattern should be valid")
+});
+
+/// The access token argument of a launch command, and the session id older game versions log.
+static ARGUMENT_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(--accessToken"?\s+"?|Session ID is token:)[^\s":]+"#)
+        .expect("The argument pattern should be valid")
+});
+
+/// Redacts `secret` from everything passed through `redact` from now on.
+pub fn register_secret(secret: &str) {
+    if secret.len() < MIN_SECRET_LEN {
+        return;
+    }
+    let mut secrets = SECRETS.write().unwrap_or_else(PoisonError::into_inner);
+    secrets.insert(secret.to_string());
+}
+
+/// Replaces registered secrets and anything shaped like a token in `text`.
+pub fn redact(text: &str) -> String {
+    let mut redacted = text.to_string();
+    {
+        let secrets = SECRETS.read().unwrap_or_else(PoisonError::into_inner);
+        for secret in secrets.iter() {
+            if redacted.contains(secret.as_str()) {
+                redacted = redacted.replace(secret.as_str(), REDACTED);
+            }
+        }
+    }
+    let redacted = JWT_PATTERN.replace_all(&redacted, REDACTED);
+    ARGUMENT_PATTERN
+        .replace_all(&redacted, format!("${{1}}{}", REDACTED).as_str())
+        .into_owned()
+}
@@ -2,8 +2,13 @@ use log::error;
 use tauri::{Manager, Wry};
 
 pub mod account_manager;
+pub mod account_storage;
 pub mod resource_manager;
+pub mod secret_storage;
+pub mod instance_lock;
 pub mod instance_manager;
+pub mod settings_manager;
+pub mod task_manager;
 
 /// Attempts to redirect the main window to the specified endpoint
 /// Specify endpoint without a leading `/`.  
@@ -1,26 +1,118 @@
 use std::{
     collections::HashMap,
-    fs::File,
-    io::{BufReader, Error, Write},
+    fs,
+    io::{Error, ErrorKind},
     path::{Path, PathBuf},
     sync::Arc,
 };
 
+use crypto::{digest::Digest, md5::Md5};
 use log::info;
 use serde::{Deserialize, Serialize};
 use tauri::async_runtime::Mutex;
 
+use crate::redact::register_secret;
+
+use super::account_storage::{self, ACCOUNTS_FILE, PLAINTEXT_ACCOUNTS_FILE};
+
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct Account {
     pub uuid: String,
     pub name: String,
     // FIXME: Cache downloaded skins instead of saving url to download everytime.
     pub skin_url: String,
+    /// Model of the skin, `CLASSIC` or `SLIM` (3 pixel wide arms).
+    #[serde(default)]
+    pub skin_variant: String,
+    #[serde(default)]
+    pub cape_url: Option<String>,
     pub microsoft_access_token: String,
     pub microsoft_access_token_expiry: i64,
     pub microsoft_refresh_token: String,
     pub minecraft_access_token: String,
     pub minecraft_access_token_expiry: i64,
+    /// Xbox user id, passed to the game as `${auth_xuid}`. Empty when it couldn't be retrieved.
+    #[serde(default)]
+    pub xuid: String,
+    /// Offline accounts are never refreshed and launch with a placeholder access token.
+    #[serde(default)]
+    pub offline: bool,
+    /// Api root of the Yggdrasil server the account belongs to, None for Microsoft and offline accounts.
+    #[serde(default)]
+    pub auth_server: Option<String>,
+    /// Client token sent along with the access token to a Yggdrasil server.
+    #[serde(default)]
+    pub client_token: String,
+    /// The Microsoft account doesn't own Minecraft, it can only launch the demo.
+    #[serde(default)]
+    pub demo: bool,
+}
+
+impl Account {
+    /// Creates an offline account for `name`, using the same name based uuid the vanilla server uses.
+    pub fn offline(name: &str) -> Self {
+        Self {
+            uuid: name_based_uuid(&format!("OfflinePlayer:{}", name)),
+            name: name.into(),
+            minecraft_access_token: "0".into(),
+            minecraft_access_token_expiry: i64::MAX,
+            offline: true,
+            ..Default::default()
+        }
+    }
+
+    /// Makes sure the account's tokens never end up in a log.
+    fn register_secrets(&self) {
+        register_secret(&self.microsoft_access_token);
+        register_secret(&self.microsoft_refresh_token);
+        register_secret(&self.minecraft_access_token);
+        register_secret(&self.client_token);
+    }
+}
+
+/// A version 3 (name based) uuid without dashes, the same for every call with `name`.
+pub fn name_based_uuid(name: &str) -> String {
+    let mut hasher = Md5::new();
+    hasher.input_str(name);
+    let mut bytes = [0u8; 16];
+    hasher.result(&mut bytes);
+    // Mark the uuid as a version 3 (name based) uuid.
+    bytes[6] = (bytes[6] & 0x0f) | 0x30;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Whether a stored account can still be used, found out by refreshing it when the launcher starts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AccountStatus {
+    /// Not refreshed yet.
+    Unknown,
+    Ok,
+    /// The tokens were rejected, the user has to log in again.
+    NeedsLogin,
+    /// The account's server couldn't be reached, the stored tokens are kept.
+    NetworkError,
+}
+
+impl Default for AccountStatus {
+    fn default() -> Self {
+        AccountStatus::Unknown
+    }
+}
+
+/// What the frontend is told about a stored account, without its tokens.
+#[derive(Debug, Clone, Serialize)]
+pub struct AccountEntry {
+    pub uuid: String,
+    pub name: String,
+    pub active: bool,
+    pub offline: bool,
+    /// Only the demo can be launched with the account.
+    pub demo: bool,
+    #[serde(rename = "authServer")]
+    pub auth_server: Option<String>,
+    pub status: AccountStatus,
 }
 
 #[derive(Debug)]
@@ -38,10 +130,11 @@ pub struct AccountManager {
     path: PathBuf,
     active: Option<String>,
     accounts: HashMap<String, Account>,
+    /// <Uuid, status>, accounts that weren't refreshed yet are missing.
+    #[serde(skip)]
+    statuses: HashMap<String, AccountStatus>,
 }
 
-// FIXME: Storing tokens in plaintext is bad... store them in the platform keystore using keyring-rs
-//        Only need to store ms_access_token, ms_refresh_token, and mc_access_token. Everything else can be in a different format.
 impl AccountManager {
     /// Call on app setup.
     pub fn new(app_dir: &Path) -> Self {
@@ -49,28 +142,48 @@ impl AccountManager {
             path: app_dir.into(),
             active: Default::default(),
             accounts: Default::default(),
+            statuses: Default::default(),
         }
     }
 
-    /// Deserialize account information into `app_dir/accounts.json`
+    /// Deserialize account information from the encrypted `app_dir/accounts.dat`. Plaintext
+    /// `accounts.json` files of older versions are read and encrypted right away.
     pub fn deserialize_accounts(&mut self) -> Result<(), Error> {
-        let path = &self.path.join("accounts.json");
-        let file = File::open(&path)?;
-        let reader = BufReader::new(file);
-        let deserialized_account_manager =
-            serde_json::from_reader::<BufReader<File>, AccountManager>(reader)?;
+        let path = self.path.join(ACCOUNTS_FILE);
+        let plaintext_path = self.path.join(PLAINTEXT_ACCOUNTS_FILE);
+        let (json, migrate) = if path.exists() {
+            let data = fs::read(&path)?;
+            (account_storage::decrypt(&self.path, &data)?, false)
+        } else if plaintext_path.exists() {
+            (fs::read(&plaintext_path)?, true)
+        } else {
+            return Err(Error::new(ErrorKind::NotFound, "No accounts are saved"));
+        };
+        let deserialized_account_manager = serde_json::from_slice::<AccountManager>(&json)?;
         self.active = deserialized_account_manager.active;
         self.accounts = deserialized_account_manager.accounts;
+        for account in self.accounts.values() {
+            account.register_secrets();
+        }
+        if migrate {
+            self.serialize_accounts()?;
+            info!("Encrypted the accounts of an older launcher version");
+        }
         Ok(())
     }
 
-    /// Serialize account information into `app_dir/accounts.json`
+    /// Serialize account information into the encrypted `app_dir/accounts.dat`
     pub fn serialize_accounts(&self) -> Result<(), Error> {
-        let json = serde_json::to_string(&self)?;
-        let path = &self.path.join("accounts.json");
-        let mut file = File::create(path)?;
+        let json = serde_json::to_vec(&self)?;
+        let data = account_storage::encrypt(&self.path, &json)?;
+        fs::write(self.path.join(ACCOUNTS_FILE), data)?;
+        // The tokens must not stay readable in the old file.
+        let plaintext_path = self.path.join(PLAINTEXT_ACCOUNTS_FILE);
+        if plaintext_path.exists() {
+            fs::remove_file(plaintext_path)?;
+        }
         info!("Serialized account manager.");
-        file.write_all(json.as_bytes())
+        Ok(())
     }
 
     /// Get a stored account by uuid.
@@ -97,8 +210,43 @@ impl AccountManager {
         );
     }
 
-    /// Adds an account, overwriting any existing accounts with the same uuid.
+    /// Adds an account, overwriting any existing accounts with the same uuid. Accounts are added right
+    /// after logging in or refreshing, so their status is `Ok`.
     pub fn add_account(&mut self, account: Account) {
+        account.register_secrets();
+        self.statuses
+            .insert(account.uuid.clone(), AccountStatus::Ok);
         self.accounts.insert(account.uuid.clone(), account);
     }
+
+    pub fn accounts(&self) -> impl Iterator<Item = &Account> {
+        self.accounts.values()
+    }
+
+    pub fn get_account_status(&self, uuid: &str) -> AccountStatus {
+        self.statuses.get(uuid).copied().unwrap_or_default()
+    }
+
+    pub fn set_account_status(&mut self, uuid: &str, status: AccountStatus) {
+        self.statuses.insert(uuid.into(), status);
+    }
+
+    /// Every stored account, sorted by name.
+    pub fn get_account_entries(&self) -> Vec<AccountEntry> {
+        let mut entries: Vec<AccountEntry> = self
+            .accounts
+            .values()
+            .map(|account| AccountEntry {
+                uuid: account.uuid.clone(),
+                name: account.name.clone(),
+                active: self.active.as_deref() == Some(account.uuid.as_str()),
+                offline: account.offline,
+                demo: account.demo,
+                auth_server: account.auth_server.clone(),
+                status: self.get_account_status(&account.uuid),
+            })
+            .collect();
+        entries.sort_by_cached_key(|entry| entry.name.to_lowercase());
+        entries
+    }
 }
@@ -0,0 +1,199 @@
+//! Encrypts the accounts file at rest, and the secrets of the settings, see `secret_storage`. The key
+//! is kept in the platform keyring, where there is no keyring it is kept in a file next to the
+//! accounts that only the user can read.
+
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::Path,
+    sync::{Mutex, PoisonError},
+};
+
+use crypto::{
+    aead::{AeadDecryptor, AeadEncryptor},
+    aes::KeySize,
+    aes_gcm::AesGcm,
+};
+use keyring::Entry;
+use log::{info, warn};
+use once_cell::sync::Lazy;
+
+use crate::{consts::LAUNCHER_NAME, state::secret_storage::SECRETS_FILE};
+
+pub const ACCOUNTS_FILE: &str = "accounts.dat";
+/// Written by launcher versions that stored the accounts in plaintext.
+pub const PLAINTEXT_ACCOUNTS_FILE: &str = "accounts.json";
+const KEY_FILE: &str = "accounts.key";
+const KEYRING_USER: &str = "accounts-key";
+/// Starts the encrypted file, also authenticated along with the accounts.
+const HEADER: &[u8] = b"AUTMCACC\x01";
+const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+/// Files encrypted with the key, a key is only created while none of them exists.
+const ENCRYPTED_FILES: [&str; 2] = [ACCOUNTS_FILE, SECRETS_FILE];
+
+/// Keeps the accounts and the secrets from both creating a key when they are first written at once.
+static KEY_LOCK: Lazy<Mutex<()>> = Lazy::new(Mutex::default);
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn missing_key() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        "The key of the encrypted launcher files is gone from the keyring",
+    )
+}
+
+fn random_bytes(buffer: &mut [u8]) -> io::Result<()> {
+    getrandom::getrandom(buffer)
+        .map_err(|error| io::Error::new(io::ErrorKind::Other, error.to_string()))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn key_from_hex(hex: &str) -> io::Result<[u8; KEY_LEN]> {
+    let hex = hex.trim();
+    if hex.len() != KEY_LEN * 2 {
+        return Err(invalid_data("The accounts key has the wrong length"));
+    }
+    let mut key = [0u8; KEY_LEN];
+    for (i, byte) in key.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)
+            .map_err(|_| invalid_data("The accounts key is not valid hex"))?;
+    }
+    Ok(key)
+}
+
+/// Writes a file only the user may read.
+pub fn write_private_file(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)?.write_all(contents)
+}
+
+/// Whether the keyring failed because there is none to keep the key in, like a Linux desktop
+/// without a Secret Service, rather than because the one there refused, like a locked keyring.
+fn keyring_missing(error: &keyring::Error) -> bool {
+    match error {
+        keyring::Error::NoEntry => true,
+        // The Secret Service could not be reached over D-Bus at all, a locked one is `NoStorageAccess`.
+        #[cfg(target_os = "linux")]
+        keyring::Error::PlatformFailure(error) => error.to_string().starts_with("zbus"),
+        _ => false,
+    }
+}
+
+fn keyring_error(error: keyring::Error) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::PermissionDenied,
+        format!(
+            "Could not access the accounts key in the keyring: {}",
+            error
+        ),
+    )
+}
+
+/// Returns the key of the encrypted files, `None` if there is none yet. A key file from an earlier run
+/// without a keyring is kept using, so the accounts stay readable.
+fn load_key(app_dir: &Path) -> io::Result<Option<[u8; KEY_LEN]>> {
+    let key_path = app_dir.join(KEY_FILE);
+    if key_path.is_file() {
+        return key_from_hex(&fs::read_to_string(&key_path)?).map(Some);
+    }
+
+    match Entry::new(LAUNCHER_NAME, KEYRING_USER).get_password() {
+        Ok(hex) => key_from_hex(&hex).map(Some),
+        Err(error) if keyring_missing(&error) => Ok(None),
+        Err(error) => Err(keyring_error(error)),
+    }
+}
+
+/// Creates the key of the encrypted files, stored in the keyring or in a key file if there is no keyring.
+fn create_key(app_dir: &Path) -> io::Result<[u8; KEY_LEN]> {
+    let mut key = [0u8; KEY_LEN];
+    random_bytes(&mut key)?;
+    let hex = to_hex(&key);
+    match Entry::new(LAUNCHER_NAME, KEYRING_USER).set_password(&hex) {
+        Ok(()) => {
+            info!("Stored the accounts key in the keyring");
+            return Ok(key);
+        }
+        Err(error) if keyring_missing(&error) => warn!("The keyring is not available: {}", error),
+        Err(error) => return Err(keyring_error(error)),
+    }
+
+    let key_path = app_dir.join(KEY_FILE);
+    write_private_file(&key_path, hex.as_bytes())?;
+    info!("Stored the accounts key in {}", key_path.display());
+    Ok(key)
+}
+
+/// Encrypts the serialized accounts or secrets, the result is the header, the nonce, the tag and the
+/// ciphertext. A key is only created before the first encrypted file is written, a new one would
+/// lock out what is in the existing files.
+pub fn encrypt(app_dir: &Path, plaintext: &[u8]) -> io::Result<Vec<u8>> {
+    let key = {
+        let _guard = KEY_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+        match load_key(app_dir)? {
+            Some(key) => key,
+            None if !ENCRYPTED_FILES
+                .iter()
+                .any(|file| app_dir.join(file).exists()) =>
+            {
+                create_key(app_dir)?
+            }
+            None => return Err(missing_key()),
+        }
+    };
+    let mut nonce = [0u8; NONCE_LEN];
+    random_bytes(&mut nonce)?;
+
+    let mut ciphertext = vec![0u8; plaintext.len()];
+    let mut tag = [0u8; TAG_LEN];
+    AesGcm::new(KeySize::KeySize256, &key, &nonce, HEADER).encrypt(
+        plaintext,
+        &mut ciphertext,
+        &mut tag,
+    );
+
+    let mut data = Vec::with_capacity(HEADER.len() + NONCE_LEN + TAG_LEN + ciphertext.len());
+    data.extend_from_slice(HEADER);
+    data.extend_from_slice(&nonce);
+    data.extend_from_slice(&tag);
+    data.extend_from_slice(&ciphertext);
+    Ok(data)
+}
+
+/// Decrypts what `encrypt` returned, fails with `InvalidData` if it was changed or the key is another one.
+pub fn decrypt(app_dir: &Path, data: &[u8]) -> io::Result<Vec<u8>> {
+    let body = data
+        .strip_prefix(HEADER)
+        .filter(|body| body.len() >= NONCE_LEN + TAG_LEN)
+        .ok_or_else(|| invalid_data("The file was not encrypted by the launcher"))?;
+    let (nonce, body) = body.split_at(NONCE_LEN);
+    let (tag, ciphertext) = body.split_at(TAG_LEN);
+
+    let key = load_key(app_dir)?.ok_or_else(missing_key)?;
+    let mut plaintext = vec![0u8; ciphertext.len()];
+    let authentic = AesGcm::new(KeySize::KeySize256, &key, nonce, HEADER).decrypt(
+        ciphertext,
+        &mut plaintext,
+        tag,
+    );
+    if !authentic {
+        return Err(invalid_data(
+            "The file could not be decrypted, it was changed or its key is gone",
+        ));
+    }
+    Ok(plaintext)
+}
@@ -0,0 +1,75 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use log::debug;
+use serde::Serialize;
+
+use super::instance_manager::{InstanceError, InstanceResult};
+
+/// Operations that modify an instance, only one of them can work on an instance at a time.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum InstanceOperation {
+    Launch,
+    ChangeVersion,
+    Rename,
+    Delete,
+    EditConfig,
+    Sync,
+    Backup,
+    Restore,
+    InstallContent,
+    ModpackUpdate,
+    Repair,
+    Export,
+}
+
+/// Instances that are being modified, shared with the `InstanceLock`s so a lock is released when it
+/// is dropped. Long running operations such as a version change don't hold the instance manager's
+/// lock the whole time, this keeps other operations off the instance in the meantime.
+#[derive(Debug, Clone, Default)]
+pub struct InstanceLocks(Arc<Mutex<HashMap<String, InstanceOperation>>>);
+
+impl InstanceLocks {
+    /// Marks the instance as busy with `operation`, fails with `InstanceBusy` if another operation
+    /// holds it.
+    pub fn acquire(
+        &self,
+        instance_name: &str,
+        operation: InstanceOperation,
+    ) -> InstanceResult<InstanceLock> {
+        let mut locks = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(running) = locks.get(instance_name) {
+            return Err(InstanceError::InstanceBusy {
+                instance_name: instance_name.into(),
+                operation: *running,
+            });
+        }
+        debug!("Locked {} for {:?}", instance_name, operation);
+        locks.insert(instance_name.into(), operation);
+        Ok(InstanceLock {
+            locks: self.clone(),
+            instance_name: instance_name.into(),
+        })
+    }
+}
+
+/// Keeps an instance locked until it is dropped.
+#[derive(Debug)]
+pub struct InstanceLock {
+    locks: InstanceLocks,
+    instance_name: String,
+}
+
+impl Drop for InstanceLock {
+    fn drop(&mut self) {
+        self.locks
+            .0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&self.instance_name);
+        debug!("Unlocked {}", self.instance_name);
+    }
+}
@@ -1,25 +1,419 @@
-use log::{debug, error, warn};
+use log::{debug, error, info, warn};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::{
     collections::HashMap,
     fs::{self, File},
     io::{self, BufRead, BufReader, Write},
     path::{Path, PathBuf},
-    process::{Child, Command, Stdio},
+    process::{Child, Command, ExitStatus, Stdio},
     sync::{Arc, Mutex},
     thread,
+    time::SystemTime,
 };
 use tauri::{async_runtime::Mutex as AsyncMutex, AppHandle, Manager, Wry};
+use url::Url;
+use zip::result::ZipError;
 
-use crate::web_services::resources::substitute_account_specific_arguments;
+use crate::{
+    events::{EventBus, GameExited, LauncherEvent},
+    i18n::tr,
+    instance::{
+        fatal_errors::find_fatal_error,
+        gpu::apply_gpu_preference,
+        launch_metrics::{is_window_open_line, LaunchPhase, LaunchTimer},
+        launch_preview::LaunchCommand,
+        log4j::Log4jStream,
+        logs::{LogTail, LogTailPayload},
+        mod_versions::ReleaseChannel,
+        process_group::{configure_process_group, ProcessGroup},
+        process_priority::{apply_process_priority, configure_process_priority},
+        sync::{sync_instance, SyncDirection, SyncSettings},
+        trash::{get_trash_entry, move_to_trash, restore_from_trash, TrashEntry, TrashKind},
+        watcher::{ContentChangedPayload, ContentWatcher},
+    },
+    java::probe_java_major_version,
+    launcher_behavior::{on_game_exited, on_game_started, LauncherBehavior},
+    nbt::NbtError,
+    notifications::notify_game_crashed,
+    web_services::{
+        curseforge::CurseForgeError,
+        manifest::vanilla::Argument,
+        resources::{
+            construct_feature_arguments, substitute_account_specific_arguments, LaunchFeatures,
+        },
+        yggdrasil::AuthlibInjector,
+    },
+};
+
+use super::{
+    account_manager::Account,
+    instance_lock::{InstanceLock, InstanceLocks, InstanceOperation},
+    resource_manager::{migrate_instance_configuration, ManifestResult, PathContext},
+    settings_manager::LaunchSettings,
+};
+
+pub type InstanceResult<T> = Result<T, InstanceError>;
+
+#[derive(Debug)]
+pub enum InstanceError {
+    FilesystemError(io::Error),
+    UnknownInstance(String),
+    InstanceExists(String),
+    InstanceRunning(String),
+    InstanceNotRunning(String),
+    InvalidInstanceName(String),
+    UnknownGroup(String),
+    GroupExists(String),
+    /// The selected java executable doesn't exist.
+    JavaNotFound(PathBuf),
+    JavaProbeFailed(String),
+    JavaVersionMismatch {
+        required: u32,
+        found: u32,
+    },
+    /// The selected java is invalid and the runtime of the version couldn't be downloaded instead.
+    JavaUnavailable(String),
+    NbtError(NbtError),
+    UnknownServer(usize),
+    UnknownScreenshot(String),
+    UnknownWorld(String),
+    UnknownLogFile(String),
+    UnknownCrashReport(String),
+    UnknownConfigFile(String),
+    /// Not a text config file, or too large to edit.
+    UneditableConfigFile(String),
+    ImageError(image::ImageError),
+    FileWatcherError(notify::Error),
+    ArchiveError(ZipError),
+    /// The instance is set to a Yggdrasil server the active account doesn't belong to.
+    AuthServerMismatch {
+        required: String,
+        account: String,
+    },
+    AuthlibInjectorUnavailable(String),
+    NoActiveAccount,
+    NativesUnavailable(String),
+    /// The game jar or natives failed the check before a launch and could not be repaired.
+    GameFilesUnavailable(String),
+    /// Another operation is modifying the instance, see `InstanceLocks`.
+    InstanceBusy {
+        instance_name: String,
+        operation: InstanceOperation,
+    },
+    /// Files that changed both in the instance and in its sync folder.
+    SyncConflicts(Vec<String>),
+    UnknownBackup(String),
+    UnknownFile(String),
+    UnknownTrashEntry(String),
+    /// Not a mod, resource pack or shader pack.
+    UnsupportedContent(String),
+    /// The instance wasn't installed from a modpack.
+    NotAModpack(String),
+    InvalidModpack(String),
+    ModpackDownloadFailed(String),
+    /// The new pack version needs another minecraft or mod loader version.
+    ModpackDependenciesChanged,
+    HttpError(reqwest::Error),
+    CurseForgeError(CurseForgeError),
+    /// The project has no version for the instance's minecraft version.
+    NoCompatibleVersion {
+        project: String,
+        game_version: String,
+    },
+    /// A downloaded file didn't match what the api described.
+    InvalidDownload(String),
+    NoLockfile(String),
+    InvalidLockfile(String),
+    /// The lockfile was generated for another minecraft version than the instance has now.
+    LockfileVersionMismatch {
+        locked: String,
+        installed: String,
+    },
+    /// A search page whose offset doesn't fit the search apis.
+    InvalidSearchPage(u32),
+}
 
-use super::account_manager::Account;
+impl Serialize for InstanceError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.message())
+    }
+}
 
-#[derive(Debug, Deserialize, Serialize)]
+impl InstanceError {
+    /// The message shown to users, in the current locale.
+    pub fn message(&self) -> String {
+        match self {
+            InstanceError::FilesystemError(error) => error.to_string(),
+            InstanceError::UnknownInstance(instance_name) => {
+                tr("error.unknownInstance", &[("instance", instance_name)])
+            }
+            InstanceError::InstanceExists(instance_name) => {
+                tr("error.instanceExists", &[("instance", instance_name)])
+            }
+            InstanceError::InstanceRunning(instance_name) => {
+                tr("error.instanceRunning", &[("instance", instance_name)])
+            }
+            InstanceError::InstanceNotRunning(instance_name) => {
+                tr("error.instanceNotRunning", &[("instance", instance_name)])
+            }
+            InstanceError::InvalidInstanceName(instance_name) => {
+                tr("error.invalidInstanceName", &[("instance", instance_name)])
+            }
+            InstanceError::UnknownGroup(group_name) => {
+                tr("error.unknownGroup", &[("group", group_name)])
+            }
+            InstanceError::GroupExists(group_name) => {
+                tr("error.groupExists", &[("group", group_name)])
+            }
+            InstanceError::JavaNotFound(path) => {
+                tr("error.javaNotFound", &[("path", &path.display())])
+            }
+            InstanceError::JavaProbeFailed(message) => message.clone(),
+            InstanceError::JavaVersionMismatch { required, found } => tr(
+                "error.javaVersionMismatch",
+                &[("required", required), ("found", found)],
+            ),
+            InstanceError::JavaUnavailable(message) => {
+                tr("error.javaUnavailable", &[("message", message)])
+            }
+            InstanceError::NbtError(error) => error.message(),
+            InstanceError::UnknownServer(index) => tr("error.unknownServer", &[("index", index)]),
+            InstanceError::UnknownScreenshot(file_name) => {
+                tr("error.unknownScreenshot", &[("file", file_name)])
+            }
+            InstanceError::UnknownWorld(folder_name) => {
+                tr("error.unknownWorld", &[("world", folder_name)])
+            }
+            InstanceError::UnknownLogFile(file_name) => {
+                tr("error.unknownLogFile", &[("file", file_name)])
+            }
+            InstanceError::UnknownCrashReport(file_name) => {
+                tr("error.unknownCrashReport", &[("file", file_name)])
+            }
+            InstanceError::UnknownConfigFile(path) => {
+                tr("error.unknownConfigFile", &[("path", path)])
+            }
+            InstanceError::UneditableConfigFile(path) => {
+                tr("error.uneditableConfigFile", &[("path", path)])
+            }
+            InstanceError::ImageError(error) => error.to_string(),
+            InstanceError::FileWatcherError(error) => error.to_string(),
+            InstanceError::ArchiveError(error) => error.to_string(),
+            InstanceError::AuthServerMismatch { required, account } => tr(
+                "error.authServerMismatch",
+                &[("required", required), ("account", account)],
+            ),
+            InstanceError::AuthlibInjectorUnavailable(message) => {
+                tr("error.authlibInjectorUnavailable", &[("message", message)])
+            }
+            InstanceError::NoActiveAccount => tr("error.noActiveAccount", &[]),
+            InstanceError::NativesUnavailable(message) => {
+                tr("error.nativesUnavailable", &[("message", message)])
+            }
+            InstanceError::GameFilesUnavailable(message) => {
+                tr("error.gameFilesUnavailable", &[("message", message)])
+            }
+            InstanceError::InstanceBusy {
+                instance_name,
+                operation,
+            } => tr(
+                "error.instanceBusy",
+                &[
+                    ("instance", instance_name),
+                    ("operation", &format!("{:?}", operation)),
+                ],
+            ),
+            InstanceError::SyncConflicts(files) => {
+                tr("error.syncConflicts", &[("files", &files.join(", "))])
+            }
+            InstanceError::UnknownBackup(file_name) => {
+                tr("error.unknownBackup", &[("file", file_name)])
+            }
+            InstanceError::UnknownFile(file_name) => {
+                tr("error.unknownFile", &[("file", file_name)])
+            }
+            InstanceError::UnknownTrashEntry(id) => tr("error.unknownTrashEntry", &[("id", id)]),
+            InstanceError::UnsupportedContent(file_name) => {
+                tr("error.unsupportedContent", &[("file", file_name)])
+            }
+            InstanceError::NotAModpack(instance_name) => {
+                tr("error.notAModpack", &[("instance", instance_name)])
+            }
+            InstanceError::InvalidModpack(message) => {
+                tr("error.invalidModpack", &[("message", message)])
+            }
+            InstanceError::ModpackDownloadFailed(path) => {
+                tr("error.modpackDownloadFailed", &[("path", path)])
+            }
+            InstanceError::ModpackDependenciesChanged => {
+                tr("error.modpackDependenciesChanged", &[])
+            }
+            InstanceError::HttpError(error) => error.to_string(),
+            InstanceError::CurseForgeError(error) => error.to_string(),
+            InstanceError::NoCompatibleVersion {
+                project,
+                game_version,
+            } => tr(
+                "error.noCompatibleVersion",
+                &[("project", project), ("version", game_version)],
+            ),
+            InstanceError::InvalidDownload(file_name) => {
+                tr("error.invalidDownload", &[("file", file_name)])
+            }
+            InstanceError::NoLockfile(instance_name) => {
+                tr("error.noLockfile", &[("instance", instance_name)])
+            }
+            InstanceError::InvalidLockfile(message) => {
+                tr("error.invalidLockfile", &[("message", message)])
+            }
+            InstanceError::LockfileVersionMismatch { locked, installed } => tr(
+                "error.lockfileVersionMismatch",
+                &[("locked", locked), ("installed", installed)],
+            ),
+            InstanceError::InvalidSearchPage(page) => {
+                tr("error.invalidSearchPage", &[("page", page)])
+            }
+        }
+    }
+
+    /// Why the selected java can't run the instance, None for errors that aren't about the java.
+    pub fn java_problem(&self) -> Option<String> {
+        match self {
+            InstanceError::JavaNotFound(path) => {
+                Some(tr("java.notFound", &[("path", &path.display())]))
+            }
+            InstanceError::JavaProbeFailed(message) => Some(message.clone()),
+            InstanceError::JavaVersionMismatch { required, found } => Some(tr(
+                "java.tooOld",
+                &[("required", required), ("found", found)],
+            )),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for InstanceError {
+    fn from(error: io::Error) -> Self {
+        InstanceError::FilesystemError(error)
+    }
+}
+
+impl From<reqwest::Error> for InstanceError {
+    fn from(error: reqwest::Error) -> Self {
+        InstanceError::HttpError(error)
+    }
+}
+
+impl From<CurseForgeError> for InstanceError {
+    fn from(error: CurseForgeError) -> Self {
+        InstanceError::CurseForgeError(error)
+    }
+}
+
+impl From<NbtError> for InstanceError {
+    fn from(error: NbtError) -> Self {
+        InstanceError::NbtError(error)
+    }
+}
+
+impl From<image::ImageError> for InstanceError {
+    fn from(error: image::ImageError) -> Self {
+        InstanceError::ImageError(error)
+    }
+}
+
+impl From<notify::Error> for InstanceError {
+    fn from(error: notify::Error) -> Self {
+        InstanceError::FileWatcherError(error)
+    }
+}
+
+impl From<ZipError> for InstanceError {
+    fn from(error: ZipError) -> Self {
+        InstanceError::ArchiveError(error)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct InstanceConfiguration {
+    /// See `INSTANCE_CONFIG_SCHEMA_VERSION`, configs without one are version 0.
+    #[serde(default)]
+    pub schema_version: u32,
     pub instance_name: String,
     pub jvm_path: PathBuf,
     pub arguments: Vec<String>,
+    /// Conditional game arguments that depend on launch options, see `construct_feature_arguments`.
+    #[serde(default)]
+    pub feature_arguments: Vec<Argument>,
+    /// The minecraft version id the instance was created with.
+    #[serde(default)]
+    pub version_id: String,
+    /// Timestamp in seconds of the last time the instance was launched.
+    #[serde(default)]
+    pub last_played: Option<i64>,
+    /// Total seconds the game of this instance ran when started by the launcher.
+    #[serde(default)]
+    pub play_time: u64,
+    /// Freeform description of the instance written by the user.
+    #[serde(default)]
+    pub notes: String,
+    /// Launch settings that override the global settings for this instance.
+    #[serde(default)]
+    pub settings: LaunchSettings,
+    /// Folder the instance is mirrored to around launches, None when syncing is off.
+    #[serde(default)]
+    pub sync: Option<SyncSettings>,
+    /// The least stable mod versions offered for this instance.
+    #[serde(default)]
+    pub release_channel: ReleaseChannel,
+}
+
+/// The order instances are listed in by `get_instance_entries`.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum InstanceSort {
+    /// Most recently played first, instances that were never played last.
+    LastPlayed,
+    Name,
+    /// Newest minecraft version first.
+    Version,
+}
+
+/// Folders inside of an instance that can be opened in the file manager.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum InstanceFolder {
+    Root,
+    Mods,
+    CrashReports,
+    Saves,
+    Screenshots,
+    Logs,
+    Config,
+}
+
+#[derive(Debug, Serialize)]
+pub struct InstanceEntry {
+    #[serde(rename = "instanceName")]
+    pub instance_name: String,
+    #[serde(rename = "versionId")]
+    pub version_id: String,
+    #[serde(rename = "lastPlayed")]
+    pub last_played: Option<i64>,
+    /// In seconds.
+    #[serde(rename = "playTime")]
+    pub play_time: u64,
+}
+
+/// A user defined category of instances. The order of `instances` is the display order.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct InstanceGroup {
+    pub name: String,
+    pub instances: Vec<String>,
 }
 
 pub struct InstanceState(pub Arc<AsyncMutex<InstanceManager>>);
@@ -33,8 +427,20 @@ impl InstanceState {
 pub struct InstanceManager {
     app_dir: PathBuf,
     instance_map: HashMap<String, InstanceConfiguration>,
+    groups: Vec<InstanceGroup>,
     // <Instance name, child process>
     children: HashMap<String, Arc<Mutex<Child>>>,
+    // <Instance name, process group of the child process>
+    process_groups: HashMap<String, Arc<ProcessGroup>>,
+    // <Instance name, post exit hook>
+    exit_hooks: HashMap<String, String>,
+    // <Instance name, timer of a launch whose window hasn't opened yet>
+    launch_timers: HashMap<String, LaunchTimer>,
+    // <Instance name, tail of latest.log>
+    log_tails: HashMap<String, LogTail>,
+    // <Instance name, watcher of the content folders>
+    content_watchers: HashMap<String, ContentWatcher>,
+    locks: InstanceLocks,
 }
 
 impl InstanceManager {
@@ -42,7 +448,14 @@ impl InstanceManager {
         Self {
             app_dir: app_dir.into(),
             instance_map: HashMap::new(),
+            groups: Vec::new(),
             children: HashMap::new(),
+            process_groups: HashMap::new(),
+            exit_hooks: HashMap::new(),
+            launch_timers: HashMap::new(),
+            log_tails: HashMap::new(),
+            content_watchers: HashMap::new(),
+            locks: InstanceLocks::default(),
         }
     }
 
@@ -50,19 +463,60 @@ impl InstanceManager {
         self.app_dir.join("instances")
     }
 
+    /// Returns the screenshot thumbnail cache of an instance at ${app_dir}/cache/thumbnails/${instance_name}
+    pub fn thumbnails_dir(&self, instance_name: &str) -> PathBuf {
+        self.app_dir
+            .join("cache")
+            .join("thumbnails")
+            .join(instance_name)
+    }
+
+    /// Returns the world icon thumbnail cache of an instance at ${app_dir}/cache/world_icons/${instance_name}
+    pub fn world_icons_dir(&self, instance_name: &str) -> PathBuf {
+        self.app_dir
+            .join("cache")
+            .join("world_icons")
+            .join(instance_name)
+    }
+
+    /// Returns the backups of an instance at ${app_dir}/backups/${instance_name}
+    pub fn backups_dir(&self, instance_name: &str) -> PathBuf {
+        self.app_dir.join("backups").join(instance_name)
+    }
+
+    /// Returns the launcher managed trash directory at ${app_dir}/.trash
+    pub fn trash_dir(&self) -> PathBuf {
+        self.app_dir.join(".trash")
+    }
+
+    /// Returns the shared mods library at ${app_dir}/mods-library
+    pub fn mods_library_dir(&self) -> PathBuf {
+        self.app_dir.join("mods-library")
+    }
+
     /// Add the config.json to an instance folder. Used to relaunch the instance again.
     pub fn add_instance(&self, config: InstanceConfiguration) -> Result<(), io::Error> {
-        let path = self
-            .instances_dir()
-            .join(&config.instance_name)
-            .join("config.json");
-        let mut file = File::create(path)?;
-        let json = serde_json::to_string(&config)?;
+        self.write_instance_configuration(&config)
+    }
+
+    /// Writes the config.json for an instance. The json is written to a temporary file first and then
+    /// moved over the old config so a failed write never leaves a truncated config behind.
+    fn write_instance_configuration(
+        &self,
+        config: &InstanceConfiguration,
+    ) -> Result<(), io::Error> {
+        let instance_dir = self.instances_dir().join(&config.instance_name);
+        let tmp_path = instance_dir.join("config.json.tmp");
+        let mut file = File::create(&tmp_path)?;
+        let json = serde_json::to_string(config)?;
         file.write_all(json.as_bytes())?;
-        Ok(())
+        file.sync_all()?;
+        fs::rename(tmp_path, instance_dir.join("config.json"))
     }
 
     pub fn deserialize_instances(&mut self) {
+        // Start from a clean slate so instances removed from disk are not listed anymore.
+        self.instance_map.clear();
         let paths = fs::read_dir(self.instances_dir());
         if let Err(e) = paths {
             error!("Error loading instances from disk: {}", e);
@@ -76,11 +530,10 @@ impl InstanceManager {
                 continue;
             }
             let reader = BufReader::new(file.unwrap());
-            let instance =
-                serde_json::from_reader::<BufReader<File>, InstanceConfiguration>(reader);
+            let instance = self.load_instance_configuration(reader, &instance_path);
             if let Err(e) = instance {
                 warn!(
-                    "Error loading `config.json` for instance at {}: {}",
+                    "Error loading `config.json` for instance at {}: {:#?}",
                     instance_path.display(),
                     e
                 );
@@ -91,6 +544,45 @@ impl InstanceManager {
         }
     }
 
+    /// Loads the config of a single instance again after its directory was replaced, e.g. by restoring
+    /// a backup. A config written under an older name of the instance is moved to the current one.
+    pub fn reload_instance(&mut self, instance_name: &str) -> ManifestResult<()> {
+        let instance_dir = self.instances_dir().join(instance_name);
+        let instance_path = instance_dir.join("config.json");
+        let reader = BufReader::new(File::open(&instance_path).with_path(&instance_path)?);
+        let mut config = self.load_instance_configuration(reader, &instance_path)?;
+        if config.instance_name != instance_name {
+            let old_dir = self.instances_dir().join(&config.instance_name);
+            config.arguments = rewrite_instance_paths(&config.arguments, &old_dir, &instance_dir);
+            config.instance_name = instance_name.into();
+            self.write_instance_configuration(&config)?;
+        }
+        self.instance_map.insert(instance_name.into(), config);
+        Ok(())
+    }
+
+    /// Reads an instance config, migrating it to the current schema version. Migrated configs are
+    /// written back to disk, keeping a copy of the original as `config.json.bak`.
+    fn load_instance_configuration(
+        &self,
+        reader: BufReader<File>,
+        instance_path: &Path,
+    ) -> ManifestResult<InstanceConfiguration> {
+        let mut value = serde_json::from_reader::<BufReader<File>, Value>(reader)?;
+        let migrated = migrate_instance_configuration(&mut value)?;
+        let config = serde_json::from_value::<InstanceConfiguration>(value)?;
+        if migrated {
+            info!(
+                "Migrated instance config at {} to schema version {}",
+                instance_path.display(),
+                config.schema_version
+            );
+            fs::copy(instance_path, instance_path.with_extension("json.bak"))?;
+            self.write_instance_configuration(&config)?;
+        }
+        Ok(config)
+    }
+
     pub fn get_instance_names(&self) -> Vec<String> {
         self.instance_map
             .iter()
@@ -98,62 +590,1112 @@ impl InstanceManager {
             .collect()
     }
 
-    pub fn launch_instance(&mut self, instance_name: &str, active_account: &Account) {
+    /// Returns the loaded instances ordered by `sort`. `version_index` maps a version id to its position
+    /// in the vanilla manifest, which is ordered newest to oldest.
+    pub fn get_instance_entries(
+        &self,
+        sort: InstanceSort,
+        version_index: impl Fn(&str) -> Option<usize>,
+    ) -> Vec<InstanceEntry> {
+        let mut configs: Vec<&InstanceConfiguration> = self.instance_map.values().collect();
+        configs.sort_by_key(|config| config.instance_name.to_lowercase());
+        match sort {
+            InstanceSort::LastPlayed => {
+                configs.sort_by_key(|config| std::cmp::Reverse(config.last_played))
+            }
+            InstanceSort::Name => {}
+            InstanceSort::Version => configs.sort_by_key(|config| {
+                // Unknown versions go last.
+                version_index(&config.version_id).unwrap_or(usize::MAX)
+            }),
+        }
+        configs
+            .into_iter()
+            .map(|config| InstanceEntry {
+                instance_name: config.instance_name.clone(),
+                version_id: config.version_id.clone(),
+                last_played: config.last_played,
+                play_time: config.play_time,
+            })
+            .collect()
+    }
+
+    /// Returns an error if the instance does not exist or is currently running.
+    pub fn ensure_instance_idle(&self, instance_name: &str) -> InstanceResult<()> {
+        if !self.instance_map.contains_key(instance_name) {
+            return Err(InstanceError::UnknownInstance(instance_name.into()));
+        }
+        if self.is_instance_running(instance_name) {
+            return Err(InstanceError::InstanceRunning(instance_name.into()));
+        }
+        Ok(())
+    }
+
+    /// Locks an existing instance for `operation` until the returned lock is dropped.
+    pub fn lock_instance(
+        &self,
+        instance_name: &str,
+        operation: InstanceOperation,
+    ) -> InstanceResult<InstanceLock> {
+        if !self.instance_map.contains_key(instance_name) {
+            return Err(InstanceError::UnknownInstance(instance_name.into()));
+        }
+        self.locks.acquire(instance_name, operation)
+    }
+
+    /// Replaces the version specific parts of an instance's configuration and saves it.
+    pub fn update_instance_version(
+        &mut self,
+        instance_name: &str,
+        version_id: &str,
+        jvm_path: PathBuf,
+        arguments: Vec<String>,
+        feature_arguments: Vec<Argument>,
+    ) -> InstanceResult<()> {
+        let config = self
+            .instance_map
+            .get_mut(instance_name)
+            .ok_or_else(|| InstanceError::UnknownInstance(instance_name.into()))?;
+        config.version_id = version_id.into();
+        config.jvm_path = jvm_path;
+        config.arguments = arguments;
+        config.feature_arguments = feature_arguments;
+        Ok(self.write_instance_configuration(&self.instance_map[instance_name])?)
+    }
+
+    pub fn get_instance_configuration(
+        &self,
+        instance_name: &str,
+    ) -> InstanceResult<&InstanceConfiguration> {
+        self.instance_map
+            .get(instance_name)
+            .ok_or_else(|| InstanceError::UnknownInstance(instance_name.into()))
+    }
+
+    pub fn get_instance_settings(&self, instance_name: &str) -> InstanceResult<&LaunchSettings> {
+        self.instance_map
+            .get(instance_name)
+            .map(|config| &config.settings)
+            .ok_or_else(|| InstanceError::UnknownInstance(instance_name.into()))
+    }
+
+    /// Whether the instance has any mod jars, modded instances are recommended more memory.
+    pub fn is_instance_modded(&self, instance_name: &str) -> bool {
+        let mods_dir = self.instances_dir().join(instance_name).join("mods");
+        match fs::read_dir(mods_dir) {
+            Ok(entries) => entries.flatten().any(|entry| {
+                entry
+                    .path()
+                    .extension()
+                    .map_or(false, |extension| extension == "jar")
+            }),
+            Err(_) => false,
+        }
+    }
+
+    pub fn set_instance_settings(
+        &mut self,
+        instance_name: &str,
+        settings: LaunchSettings,
+    ) -> InstanceResult<()> {
+        let config = self
+            .instance_map
+            .get_mut(instance_name)
+            .ok_or_else(|| InstanceError::UnknownInstance(instance_name.into()))?;
+        config.settings = settings;
+        Ok(self.write_instance_configuration(&self.instance_map[instance_name])?)
+    }
+
+    pub fn get_instance_sync(&self, instance_name: &str) -> InstanceResult<Option<SyncSettings>> {
+        self.instance_map
+            .get(instance_name)
+            .map(|config| config.sync.clone())
+            .ok_or_else(|| InstanceError::UnknownInstance(instance_name.into()))
+    }
+
+    pub fn set_instance_sync(
+        &mut self,
+        instance_name: &str,
+        sync: Option<SyncSettings>,
+    ) -> InstanceResult<()> {
+        let config = self
+            .instance_map
+            .get_mut(instance_name)
+            .ok_or_else(|| InstanceError::UnknownInstance(instance_name.into()))?;
+        config.sync = sync;
+        Ok(self.write_instance_configuration(&self.instance_map[instance_name])?)
+    }
+
+    pub fn get_instance_notes(&self, instance_name: &str) -> InstanceResult<String> {
+        self.instance_map
+            .get(instance_name)
+            .map(|config| config.notes.clone())
+            .ok_or_else(|| InstanceError::UnknownInstance(instance_name.into()))
+    }
+
+    pub fn set_instance_notes(&mut self, instance_name: &str, notes: String) -> InstanceResult<()> {
+        let config = self
+            .instance_map
+            .get_mut(instance_name)
+            .ok_or_else(|| InstanceError::UnknownInstance(instance_name.into()))?;
+        config.notes = notes;
+        Ok(self.write_instance_configuration(&self.instance_map[instance_name])?)
+    }
+
+    pub fn get_instance_release_channel(
+        &self,
+        instance_name: &str,
+    ) -> InstanceResult<ReleaseChannel> {
+        self.instance_map
+            .get(instance_name)
+            .map(|config| config.release_channel)
+            .ok_or_else(|| InstanceError::UnknownInstance(instance_name.into()))
+    }
+
+    pub fn set_instance_release_channel(
+        &mut self,
+        instance_name: &str,
+        release_channel: ReleaseChannel,
+    ) -> InstanceResult<()> {
+        let config = self
+            .instance_map
+            .get_mut(instance_name)
+            .ok_or_else(|| InstanceError::UnknownInstance(instance_name.into()))?;
+        config.release_channel = release_channel;
+        Ok(self.write_instance_configuration(&self.instance_map[instance_name])?)
+    }
+
+    /// Returns the path to a folder inside of an instance, creating it if it does not exist yet.
+    pub fn get_instance_folder(
+        &self,
+        instance_name: &str,
+        folder: InstanceFolder,
+    ) -> InstanceResult<PathBuf> {
+        if !self.instance_map.contains_key(instance_name) {
+            return Err(InstanceError::UnknownInstance(instance_name.into()));
+        }
+        let instance_dir = self.instances_dir().join(instance_name);
+        let path = match folder {
+            InstanceFolder::Root => instance_dir,
+            InstanceFolder::Mods => instance_dir.join("mods"),
+            InstanceFolder::CrashReports => instance_dir.join("crash-reports"),
+            InstanceFolder::Saves => instance_dir.join("saves"),
+            InstanceFolder::Screenshots => instance_dir.join("screenshots"),
+            InstanceFolder::Logs => instance_dir.join("logs"),
+            InstanceFolder::Config => instance_dir.join("config"),
+        };
+        fs::create_dir_all(&path)?;
+        Ok(path)
+    }
+
+    /// Renames an instance, moving its directory and rewriting any paths in the persisted launch arguments.
+    pub fn rename_instance(
+        &mut self,
+        instance_name: &str,
+        new_instance_name: &str,
+    ) -> InstanceResult<()> {
+        validate_instance_name(new_instance_name)?;
+        self.ensure_instance_idle(instance_name)?;
+        let old_dir = self.instances_dir().join(instance_name);
+        let new_dir = self.instances_dir().join(new_instance_name);
+        if new_dir.exists() || self.instance_map.contains_key(new_instance_name) {
+            return Err(InstanceError::InstanceExists(new_instance_name.into()));
+        }
+
+        fs::rename(&old_dir, &new_dir)?;
+
+        let mut renamed_config = self.instance_map.get(instance_name).unwrap().clone();
+        renamed_config.instance_name = new_instance_name.into();
+        renamed_config.arguments =
+            rewrite_instance_paths(&renamed_config.arguments, &old_dir, &new_dir);
+        // Move the directory back if the new config could not be written so the instance is left untouched.
+        if let Err(error) = self.write_instance_configuration(&renamed_config) {
+            error!(
+                "Could not write config for renamed instance {}: {}",
+                new_instance_name, error
+            );
+            fs::rename(&new_dir, &old_dir)?;
+            return Err(error.into());
+        }
+
+        self.instance_map.remove(instance_name);
+        self.children.remove(instance_name);
+        self.process_groups.remove(instance_name);
+        self.content_watchers.remove(instance_name);
+        self.instance_map
+            .insert(new_instance_name.into(), renamed_config);
+        for group in self.groups.iter_mut() {
+            for name in group.instances.iter_mut() {
+                if name == instance_name {
+                    *name = new_instance_name.into();
+                }
+            }
+        }
+        self.serialize_groups()?;
+        // Backups stay with the instance, the renamed instance itself is fine if they can't be moved.
+        let old_backups_dir = self.backups_dir(instance_name);
+        if old_backups_dir.is_dir() {
+            if let Err(error) = fs::rename(&old_backups_dir, self.backups_dir(new_instance_name)) {
+                warn!("Could not move the backups of {}: {}", instance_name, error);
+            }
+        }
+        info!(
+            "Renamed instance {} to {}",
+            instance_name, new_instance_name
+        );
+        Ok(())
+    }
+
+    /// Creates a copy of an instance under a new name. Immutable files like mod jars are hard linked
+    /// where possible instead of being copied.
+    pub fn clone_instance(
+        &mut self,
+        instance_name: &str,
+        new_instance_name: &str,
+    ) -> InstanceResult<()> {
+        validate_instance_name(new_instance_name)?;
+        let config = match self.instance_map.get(instance_name) {
+            Some(config) => config,
+            None => return Err(InstanceError::UnknownInstance(instance_name.into())),
+        };
+        let source_dir = self.instances_dir().join(instance_name);
+        let new_dir = self.instances_dir().join(new_instance_name);
+        if new_dir.exists() || self.instance_map.contains_key(new_instance_name) {
+            return Err(InstanceError::InstanceExists(new_instance_name.into()));
+        }
+
+        let mut cloned_config = config.clone();
+        cloned_config.instance_name = new_instance_name.into();
+        cloned_config.arguments =
+            rewrite_instance_paths(&cloned_config.arguments, &source_dir, &new_dir);
+
+        fs::create_dir(&new_dir)?;
+        let result = copy_instance_contents(&source_dir, &new_dir)
+            .and_then(|_| self.write_instance_configuration(&cloned_config));
+        // Dont leave a half copied instance behind.
+        if let Err(error) = result {
+            error!(
+                "Could not clone instance {} into {}: {}",
+                instance_name, new_instance_name, error
+            );
+            fs::remove_dir_all(&new_dir)?;
+            return Err(error.into());
+        }
+
+        self.instance_map
+            .insert(new_instance_name.into(), cloned_config);
+        // Keep the clone next to the original instance.
+        if let Some(group) = self.find_group_mut(instance_name) {
+            let index = group
+                .instances
+                .iter()
+                .position(|name| name == instance_name)
+                .unwrap();
+            group.instances.insert(index + 1, new_instance_name.into());
+            self.serialize_groups()?;
+        }
+        info!(
+            "Cloned instance {} into {}",
+            instance_name, new_instance_name
+        );
+        Ok(())
+    }
+
+    /// Moves an instance into the launcher's trash directory instead of deleting it outright.
+    pub fn delete_instance(&mut self, instance_name: &str) -> InstanceResult<TrashEntry> {
+        self.ensure_instance_idle(instance_name)?;
+        let trash_entry = move_to_trash(
+            &self.trash_dir(),
+            &self.instances_dir().join(instance_name),
+            TrashKind::Instance,
+            instance_name,
+            Path::new(""),
+        )?;
+
+        self.instance_map.remove(instance_name);
+        self.children.remove(instance_name);
+        self.process_groups.remove(instance_name);
+        self.content_watchers.remove(instance_name);
+        if let Some(group) = self.find_group_mut(instance_name) {
+            group.instances.retain(|name| name != instance_name);
+            self.serialize_groups()?;
+        }
+        info!("Moved instance {} to the trash", instance_name);
+        Ok(trash_entry)
+    }
+
+    /// Moves a file or folder directly inside of one of the instance's folders into the trash, such
+    /// as a mod jar or a world.
+    pub fn trash_instance_file(
+        &self,
+        instance_name: &str,
+        folder: InstanceFolder,
+        kind: TrashKind,
+        file_name: &str,
+    ) -> InstanceResult<TrashEntry> {
+        self.ensure_instance_idle(instance_name)?;
+        let folder_path = self.get_instance_folder(instance_name, folder)?;
+        let path = folder_path.join(file_name);
+        let is_plain_name = Path::new(file_name).file_name() == Some(file_name.as_ref());
+        if !is_plain_name || !path.exists() {
+            return Err(InstanceError::UnknownFile(file_name.into()));
+        }
+        let instance_dir = self.instances_dir().join(instance_name);
+        let relative_path = path.strip_prefix(&instance_dir).unwrap_or(&path);
+        Ok(move_to_trash(
+            &self.trash_dir(),
+            &path,
+            kind,
+            instance_name,
+            relative_path,
+        )?)
+    }
+
+    /// Puts an entry of the trash back where it was deleted from. Mods and worlds go back into their
+    /// instance, which has to exist and be idle.
+    pub fn restore_trash_entry(&mut self, id: &str) -> ManifestResult<TrashEntry> {
+        let trash_dir = self.trash_dir();
+        let entry = get_trash_entry(&trash_dir, id)
+            .ok_or_else(|| InstanceError::UnknownTrashEntry(id.into()))?;
+        let destination = entry.original_path(&self.instances_dir());
+        match entry.kind {
+            TrashKind::Instance => {
+                if self.instance_map.contains_key(&entry.instance_name) || destination.exists() {
+                    return Err(InstanceError::InstanceExists(entry.instance_name).into());
+                }
+                restore_from_trash(&trash_dir, &entry, &destination)?;
+                self.reload_instance(&entry.instance_name)?;
+            }
+            TrashKind::Mod | TrashKind::World => {
+                if !self.instance_map.contains_key(&entry.instance_name) {
+                    return Err(InstanceError::UnknownInstance(entry.instance_name).into());
+                }
+                self.ensure_instance_idle(&entry.instance_name)?;
+                restore_from_trash(&trash_dir, &entry, &destination)?;
+            }
+        }
+        Ok(entry)
+    }
+
+    /// Deserialize the instance groups from `app_dir/instance_groups.json`
+    pub fn deserialize_groups(&mut self) -> Result<(), io::Error> {
+        let file = File::open(self.app_dir.join("instance_groups.json"))?;
+        let reader = BufReader::new(file);
+        self.groups = serde_json::from_reader::<BufReader<File>, Vec<InstanceGroup>>(reader)?;
+        Ok(())
+    }
+
+    /// Serialize the instance groups into `app_dir/instance_groups.json`
+    fn serialize_groups(&self) -> Result<(), io::Error> {
+        let json = serde_json::to_string(&self.groups)?;
+        let mut file = File::create(self.app_dir.join("instance_groups.json"))?;
+        file.write_all(json.as_bytes())
+    }
+
+    pub fn get_groups(&self) -> &[InstanceGroup] {
+        &self.groups
+    }
+
+    pub fn create_group(&mut self, group_name: &str) -> InstanceResult<()> {
+        if self.groups.iter().any(|group| group.name == group_name) {
+            return Err(InstanceError::GroupExists(group_name.into()));
+        }
+        self.groups.push(InstanceGroup {
+            name: group_name.into(),
+            instances: Vec::new(),
+        });
+        Ok(self.serialize_groups()?)
+    }
+
+    /// Deletes a group, the instances inside of it become ungrouped.
+    pub fn delete_group(&mut self, group_name: &str) -> InstanceResult<()> {
+        let index = self.group_index(group_name)?;
+        self.groups.remove(index);
+        Ok(self.serialize_groups()?)
+    }
+
+    /// Moves an instance into a group, or out of any group when `group_name` is None.
+    pub fn assign_instance_to_group(
+        &mut self,
+        instance_name: &str,
+        group_name: Option<&str>,
+    ) -> InstanceResult<()> {
+        if !self.instance_map.contains_key(instance_name) {
+            return Err(InstanceError::UnknownInstance(instance_name.into()));
+        }
+        let group_index = match group_name {
+            Some(group_name) => Some(self.group_index(group_name)?),
+            None => None,
+        };
+        if let Some(group) = self.find_group_mut(instance_name) {
+            group.instances.retain(|name| name != instance_name);
+        }
+        if let Some(index) = group_index {
+            self.groups[index].instances.push(instance_name.into());
+        }
+        Ok(self.serialize_groups()?)
+    }
+
+    /// Moves a group to `index` in the group order.
+    pub fn move_group(&mut self, group_name: &str, index: usize) -> InstanceResult<()> {
+        let current_index = self.group_index(group_name)?;
+        let group = self.groups.remove(current_index);
+        let index = index.min(self.groups.len());
+        self.groups.insert(index, group);
+        Ok(self.serialize_groups()?)
+    }
+
+    /// Moves an instance to `index` inside of the group it belongs to.
+    pub fn move_instance_in_group(
+        &mut self,
+        instance_name: &str,
+        index: usize,
+    ) -> InstanceResult<()> {
+        let group = match self.find_group_mut(instance_name) {
+            Some(group) => group,
+            None => return Err(InstanceError::UnknownInstance(instance_name.into())),
+        };
+        group.instances.retain(|name| name != instance_name);
+        let index = index.min(group.instances.len());
+        group.instances.insert(index, instance_name.into());
+        Ok(self.serialize_groups()?)
+    }
+
+    fn group_index(&self, group_name: &str) -> InstanceResult<usize> {
+        self.groups
+            .iter()
+            .position(|group| group.name == group_name)
+            .ok_or_else(|| InstanceError::UnknownGroup(group_name.into()))
+    }
+
+    /// Returns the group an instance belongs to, if any.
+    fn find_group_mut(&mut self, instance_name: &str) -> Option<&mut InstanceGroup> {
+        self.groups
+            .iter_mut()
+            .find(|group| group.instances.iter().any(|name| name == instance_name))
+    }
+
+    /// Launches an instance with the already resolved launch `settings`, after its files were verified
+    /// and its sync folder was pulled.
+    pub fn launch_instance(
+        &mut self,
+        instance_name: &str,
+        active_account: &Account,
+        settings: &LaunchSettings,
+        java: &LaunchJava,
+        authlib_injector: Option<&AuthlibInjector>,
+        mut timer: LaunchTimer,
+    ) -> InstanceResult<()> {
         debug!("Instance Name: {}", instance_name);
         let instance_config = self.instance_map.get(instance_name);
         match instance_config {
             Some(instance) => {
                 let working_dir = self.instances_dir().join(instance_name);
-                let mut args: Vec<String> = Vec::new();
-                for argument in &instance.arguments {
-                    args.push(
-                        match substitute_account_specific_arguments(argument, active_account) {
-                            Some(arg) => arg,
-                            None => argument.into(),
-                        },
-                    );
+                let args = launch_arguments(
+                    instance,
+                    &working_dir,
+                    active_account,
+                    settings,
+                    java,
+                    authlib_injector,
+                );
+                timer.end_phase(LaunchPhase::Arguments);
+                if let Some(hook) = &settings.pre_launch_hook {
+                    match run_hook(hook, &working_dir) {
+                        Ok(status) if !status.success() => {
+                            warn!("Pre launch hook exited with {}", status)
+                        }
+                        Ok(_) => {}
+                        Err(error) => warn!("Could not run pre launch hook: {}", error),
+                    }
                 }
-                let mut command = Command::new(&instance.jvm_path);
+                timer.end_phase(LaunchPhase::Hooks);
+                let mut command = Command::new(&java.path);
                 command
                     .current_dir(working_dir)
                     .args(args)
                     .stdout(Stdio::piped());
+                apply_gpu_preference(
+                    &mut command,
+                    &java.path,
+                    settings.discrete_gpu.unwrap_or_default(),
+                );
+                configure_process_group(&mut command);
+                configure_process_priority(
+                    &mut command,
+                    settings.process_priority.unwrap_or_default(),
+                );
                 debug!("Command: {:#?}", command);
-                let child = command.spawn().expect("Could not spawn instance.");
+                let child = command.spawn()?;
+                apply_process_priority(
+                    child.id(),
+                    settings.process_priority.unwrap_or_default(),
+                    settings.cpu_affinity.as_deref().unwrap_or_default(),
+                );
+                timer.end_phase(LaunchPhase::Spawn);
+                self.process_groups
+                    .insert(instance_name.into(), Arc::new(ProcessGroup::new(&child)));
                 self.children.insert(instance_name.into(), Arc::new(Mutex::new(child)));
+                self.launch_timers.insert(instance_name.into(), timer);
+                match &settings.post_exit_hook {
+                    Some(hook) => self.exit_hooks.insert(instance_name.into(), hook.clone()),
+                    None => self.exit_hooks.remove(instance_name),
+                };
+                self.record_last_played(instance_name);
+                Ok(())
             }
-            None => error!("Unknown instance name: {}", instance_name),
+            None => Err(InstanceError::UnknownInstance(instance_name.into())),
         }
     }
 
-    pub fn emit_logs_for_running_instance(&self, app_handle: AppHandle<Wry>) {
-        if let Some(instance) = self.get_running_instance() {
+    /// Assembles what launching an instance would run, without running any hooks or spawning the game.
+    pub fn launch_command(
+        &self,
+        instance_name: &str,
+        active_account: &Account,
+        settings: &LaunchSettings,
+        java: &LaunchJava,
+        authlib_injector: Option<&AuthlibInjector>,
+    ) -> InstanceResult<LaunchCommand> {
+        let config = self
+            .instance_map
+            .get(instance_name)
+            .ok_or_else(|| InstanceError::UnknownInstance(instance_name.into()))?;
+        let working_dir = self.instances_dir().join(instance_name);
+        let arguments = launch_arguments(
+            config,
+            &working_dir,
+            active_account,
+            settings,
+            java,
+            authlib_injector,
+        );
+        Ok(LaunchCommand {
+            java: java.path.clone(),
+            working_dir,
+            arguments,
+        })
+    }
+
+    /// Checks the java an instance is launched with, the java path of its settings or otherwise the
+    /// runtime it was installed with.
+    pub fn select_java(
+        &self,
+        instance_name: &str,
+        settings: &LaunchSettings,
+        required_java_version: Option<u32>,
+    ) -> InstanceResult<LaunchJava> {
+        let config = self
+            .instance_map
+            .get(instance_name)
+            .ok_or_else(|| InstanceError::UnknownInstance(instance_name.into()))?;
+        let path = settings
+            .java_path
+            .clone()
+            .unwrap_or_else(|| config.jvm_path.clone());
+        check_java(path, required_java_version)
+    }
+
+    /// Replaces the runtime an instance was installed with, e.g. after it was downloaded again.
+    pub fn set_instance_jvm_path(
+        &mut self,
+        instance_name: &str,
+        jvm_path: PathBuf,
+    ) -> InstanceResult<()> {
+        let config = self
+            .instance_map
+            .get_mut(instance_name)
+            .ok_or_else(|| InstanceError::UnknownInstance(instance_name.into()))?;
+        if config.jvm_path == jvm_path {
+            return Ok(());
+        }
+        config.jvm_path = jvm_path;
+        Ok(self.write_instance_configuration(&self.instance_map[instance_name])?)
+    }
+
+    pub fn get_instance_version_id(&self, instance_name: &str) -> InstanceResult<&str> {
+        self.instance_map
+            .get(instance_name)
+            .map(|config| config.version_id.as_str())
+            .ok_or_else(|| InstanceError::UnknownInstance(instance_name.into()))
+    }
+
+    /// Sets the last played time of an instance to now and saves it to the instance's config.json
+    fn record_last_played(&mut self, instance_name: &str) {
+        if let Some(config) = self.instance_map.get_mut(instance_name) {
+            config.last_played = Some(chrono::Local::now().timestamp());
+        }
+        if let Some(config) = self.instance_map.get(instance_name) {
+            if let Err(error) = self.write_instance_configuration(config) {
+                warn!(
+                    "Could not save last played time for {}: {}",
+                    instance_name, error
+                );
+            }
+        }
+    }
+
+    /// Adds `seconds` to the play time of an instance and saves it to the instance's config.json
+    fn record_play_time(&mut self, instance_name: &str, seconds: u64) {
+        if let Some(config) = self.instance_map.get_mut(instance_name) {
+            config.play_time += seconds;
+        }
+        if let Some(config) = self.instance_map.get(instance_name) {
+            if let Err(error) = self.write_instance_configuration(config) {
+                warn!("Could not save play time for {}: {}", instance_name, error);
+            }
+        }
+    }
+
+    /// Supervises the running game: forwards its output, records its play time and applies
+    /// `behavior` to the launcher window while it runs.
+    pub fn emit_logs_for_running_instance(
+        &mut self,
+        app_handle: AppHandle<Wry>,
+        behavior: LauncherBehavior,
+    ) {
+        if let Some((instance_name, instance)) = self.get_running_instance() {
+            let exit_hook = self.exit_hooks.get(instance_name).cloned();
+            let sync = self
+                .instance_map
+                .get(instance_name)
+                .and_then(|config| config.sync.clone());
+            let working_dir = self.instances_dir().join(instance_name);
+            let process_group = self.process_groups.get(instance_name).cloned();
 
             // FIXME: Save thread handle in a map and when and instance is exited, 'join' the thread handle to get its status.
             // https://doc.rust-lang.org/std/thread/
             // To learn when a thread completes, it is necessary to capture the JoinHandle object that is 
             // returned by the call to spawn, which provides a join method that allows the caller to 
             // wait for the completion of the spawned thread:
+            let instance_name = instance_name.clone();
+            let mut timer = self.launch_timers.remove(&instance_name);
+            let events = EventBus::new(app_handle.clone());
+            // Fatal error logs from before this launch belong to earlier crashes.
+            let started = SystemTime::now();
             thread::spawn(move || {
+                on_game_started(&app_handle, behavior);
                 if let Ok(mut child) = instance.lock() {
                     let stdout= child.stdout.as_mut().unwrap();
                     let reader = BufReader::new(stdout);
+                    let mut log_stream = Log4jStream::new();
                     for line in reader.lines() {
                         match line {
-                            Ok(l) => app_handle.emit_all("instance-logging", l).unwrap(),
+                            Ok(l) => {
+                                if is_window_open_line(&l) {
+                                    if let Some(mut timer) = timer.take() {
+                                        timer.end_phase(LaunchPhase::Window);
+                                        timer.record(&working_dir);
+                                    }
+                                }
+                                if let Some(record) = log_stream.push_line(&l) {
+                                    events.emit(LauncherEvent::InstanceLogging(record))
+                                }
+                            }
                             Err(error) => error!("Error reading child process's stdout: {}", error),
                         }
                     }
-                } 
+                    if let Some(record) = log_stream.finish() {
+                        events.emit(LauncherEvent::InstanceLogging(record))
+                    }
+                    // The window never opened, the launch is still recorded without it.
+                    if let Some(timer) = timer.take() {
+                        timer.record(&working_dir);
+                    }
+                    // Stdout closes when the game exits, wait for it before running the exit hook.
+                    match child.wait() {
+                        Ok(status) => {
+                            let mut fatal_error = None;
+                            let killed = process_group
+                                .as_ref()
+                                .map_or(false, |group| group.was_killed());
+                            if !status.success() && !killed {
+                                notify_game_crashed(&app_handle, &instance_name, status.code());
+                                fatal_error = find_fatal_error(&working_dir, started);
+                            }
+                            events.emit(LauncherEvent::GameExited(GameExited {
+                                instance_name: instance_name.clone(),
+                                exit_code: status.code(),
+                                success: status.success(),
+                                fatal_error,
+                            }))
+                        }
+                        Err(error) => error!("Error waiting for child process: {}", error),
+                    }
+                }
+                let play_time = started.elapsed().map(|elapsed| elapsed.as_secs());
+                let instance_state = app_handle.state::<InstanceState>();
+                tauri::async_runtime::block_on(instance_state.0.lock())
+                    .record_play_time(&instance_name, play_time.unwrap_or_default());
+                // Conflicts stay in place, they are reported before the next launch.
+                if let Some(sync) = sync {
+                    if let Err(error) =
+                        sync_instance(&working_dir, &sync, SyncDirection::Push, false)
+                    {
+                        warn!("Could not sync after the game exited: {}", error);
+                    }
+                }
+                if let Some(hook) = exit_hook {
+                    if let Err(error) = run_hook(&hook, &working_dir) {
+                        warn!("Could not run post exit hook: {}", error);
+                    }
+                }
+                // Last, the launcher may exit once no game is left running.
+                on_game_exited(&app_handle, behavior);
             });
         }
     }
 
+    /// Emits lines appended to the instance's `latest.log` as `instance-log-tail` events until
+    /// `stop_log_tail` is called. Complements the stdout capture for games the launcher didn't start.
+    pub fn start_log_tail(
+        &mut self,
+        instance_name: &str,
+        app_handle: AppHandle<Wry>,
+    ) -> InstanceResult<()> {
+        if self.log_tails.contains_key(instance_name) {
+            return Ok(());
+        }
+        let logs_dir = self.get_instance_folder(instance_name, InstanceFolder::Logs)?;
+        let name = instance_name.to_string();
+        let events = EventBus::new(app_handle);
+        let log_tail = LogTail::start(&logs_dir, move |lines| {
+            events.emit(LauncherEvent::InstanceLogTail(LogTailPayload {
+                instance_name: name.clone(),
+                lines,
+            }));
+        })?;
+        self.log_tails.insert(instance_name.into(), log_tail);
+        Ok(())
+    }
+
+    pub fn stop_log_tail(&mut self, instance_name: &str) {
+        self.log_tails.remove(instance_name);
+    }
+
+    /// Emits `instance-content-changed` events when files in the instance's mods, resource packs or
+    /// screenshots change, until `stop_content_watch` is called.
+    pub fn start_content_watch(
+        &mut self,
+        instance_name: &str,
+        app_handle: AppHandle<Wry>,
+    ) -> InstanceResult<()> {
+        if self.content_watchers.contains_key(instance_name) {
+            return Ok(());
+        }
+        let instance_dir = self.get_instance_folder(instance_name, InstanceFolder::Root)?;
+        let name = instance_name.to_string();
+        let events = EventBus::new(app_handle);
+        let watcher = ContentWatcher::start(&instance_dir, move |folder| {
+            events.emit(LauncherEvent::InstanceContentChanged(
+                ContentChangedPayload {
+                    instance_name: name.clone(),
+                    folder,
+                },
+            ));
+        })?;
+        self.content_watchers.insert(instance_name.into(), watcher);
+        Ok(())
+    }
+
+    pub fn stop_content_watch(&mut self, instance_name: &str) {
+        self.content_watchers.remove(instance_name);
+    }
+
+    /// Returns true if the instance has a child process that has not exited yet.
+    pub fn is_instance_running(&self, instance_name: &str) -> bool {
+        match self.children.get(instance_name) {
+            Some(child) => match child.try_lock() {
+                Ok(mut child) => matches!(child.try_wait(), Ok(None)),
+                // The logging thread holds the lock for as long as the game is writing to stdout.
+                Err(_) => true,
+            },
+            None => false,
+        }
+    }
+
+    pub fn has_running_instances(&self) -> bool {
+        self.children
+            .keys()
+            .any(|instance_name| self.is_instance_running(instance_name))
+    }
+
+    /// Kills the game of an instance and every process it started, for games that stopped responding.
+    pub fn kill_instance(&self, instance_name: &str) -> InstanceResult<()> {
+        let process_group = match self.process_groups.get(instance_name) {
+            Some(process_group) if self.is_instance_running(instance_name) => process_group,
+            _ => return Err(InstanceError::InstanceNotRunning(instance_name.into())),
+        };
+        info!("Killing instance {}", instance_name);
+        process_group.kill()?;
+        Ok(())
+    }
+
+    /// Asks every running game to exit, call before the launcher exits so no game is left without it.
+    pub fn terminate_running_instances(&self) {
+        for (instance_name, process_group) in self.process_groups.iter() {
+            if self.is_instance_running(instance_name) {
+                info!("Terminating instance {}", instance_name);
+                if let Err(error) = process_group.terminate() {
+                    warn!("Could not terminate instance {}: {}", instance_name, error);
+                }
+            }
+        }
+    }
+
     // FIXME: This is just getting a random running instance sine we only really support 1 running instance currently.
-    fn get_running_instance(&self) -> Option<Arc<Mutex<Child>>> {
+    fn get_running_instance(&self) -> Option<(&String, Arc<Mutex<Child>>)> {
         match self.children.iter().next() {
-            Some(entry) => Some(entry.1.clone()),
+            Some(entry) => Some((entry.0, entry.1.clone())),
             None => None,
         }
     }
 }
+
+/// Every argument the game is launched with: the jvm settings, the instance's arguments and the
+/// feature arguments enabled by the launch settings, with the account's details filled in.
+fn launch_arguments(
+    config: &InstanceConfiguration,
+    working_dir: &Path,
+    active_account: &Account,
+    settings: &LaunchSettings,
+    java: &LaunchJava,
+    authlib_injector: Option<&AuthlibInjector>,
+) -> Vec<String> {
+    let mut args: Vec<String> = jvm_setting_arguments(settings, java.major_version);
+    if let Some(authlib_injector) = authlib_injector {
+        args.extend(authlib_injector.java_arguments());
+    }
+    let feature_arguments = construct_feature_arguments(
+        &config.feature_arguments,
+        &LaunchFeatures::new(settings, active_account),
+        settings,
+        working_dir,
+    );
+    for argument in config.arguments.iter().chain(&feature_arguments) {
+        args.push(
+            match substitute_account_specific_arguments(argument, active_account) {
+                Some(arg) => arg,
+                None => argument.into(),
+            },
+        );
+    }
+    args
+}
+
+/// The java executable a launch uses.
+#[derive(Debug)]
+pub struct LaunchJava {
+    pub path: PathBuf,
+    /// None if the version's requirement is unknown, the java isn't run to check it then.
+    pub major_version: Option<u32>,
+}
+
+/// Checks that the java at `path` exists and, if the version has a requirement, is new enough.
+pub fn check_java(path: PathBuf, required_java_version: Option<u32>) -> InstanceResult<LaunchJava> {
+    if !path.is_file() {
+        return Err(InstanceError::JavaNotFound(path));
+    }
+    let major_version = match required_java_version {
+        Some(required) => {
+            let found = probe_java_major_version(&path).map_err(InstanceError::JavaProbeFailed)?;
+            // Newer javas can run older versions, only reject runtimes that are too old.
+            if found < required {
+                return Err(InstanceError::JavaVersionMismatch { required, found });
+            }
+            Some(found)
+        }
+        None => None,
+    };
+    Ok(LaunchJava {
+        path,
+        major_version,
+    })
+}
+
+/// Builds the memory, garbage collector and extra jvm arguments from the launch settings.
+fn jvm_setting_arguments(settings: &LaunchSettings, java_version: Option<u32>) -> Vec<String> {
+    let mut arguments = Vec::new();
+    if let Some(max_memory) = settings.max_memory {
+        arguments.push(format!("-Xmx{}M", max_memory));
+    }
+    if let Some(min_memory) = settings.min_memory {
+        arguments.push(format!("-Xms{}M", min_memory));
+    }
+    if let Some(gc_preset) = settings.gc_preset {
+        arguments.extend(
+            gc_preset
+                .arguments(java_version)
+                .iter()
+                .map(|&flag| flag.into()),
+        );
+    }
+    if let Some(java_arguments) = &settings.java_arguments {
+        arguments.extend(java_arguments.iter().cloned());
+    }
+    arguments
+}
+
+/// The Yggdrasil server api root to launch with. Accounts from a Yggdrasil server can only play through
+/// it, and instances set to a server need an account from it.
+pub fn launch_auth_server<'a>(
+    settings: &LaunchSettings,
+    account: &'a Account,
+) -> InstanceResult<Option<&'a str>> {
+    match (&settings.auth_server, &account.auth_server) {
+        (Some(required), Some(api_root)) if is_same_auth_server(required, api_root) => {
+            Ok(Some(api_root))
+        }
+        (Some(required), _) => Err(InstanceError::AuthServerMismatch {
+            required: required.clone(),
+            account: account.name.clone(),
+        }),
+        (None, api_root) => Ok(api_root.as_deref()),
+    }
+}
+
+/// Servers are entered by their website, e.g. `ely.by`, while accounts store the api root they resolved
+/// to, e.g. `https://authserver.ely.by/api/authlib-injector`, so only the domains are compared.
+fn is_same_auth_server(server: &str, api_root: &str) -> bool {
+    let host = |address: &str| {
+        let address = address.trim();
+        let url = if address.contains("://") {
+            Url::parse(address)
+        } else {
+            Url::parse(&format!("https://{}", address))
+        };
+        url.ok()
+            .and_then(|url| url.host_str().map(|host| host.to_lowercase()))
+    };
+    match (host(server), host(api_root)) {
+        (Some(server), Some(api_root)) => {
+            api_root == server || api_root.ends_with(&format!(".{}", server))
+        }
+        _ => false,
+    }
+}
+
+/// Runs a user provided hook through the platform's shell inside of `working_dir`.
+fn run_hook(hook: &str, working_dir: &Path) -> Result<ExitStatus, io::Error> {
+    debug!("Running hook: {}", hook);
+    #[cfg(target_os = "windows")]
+    let mut command = {
+        let mut command = Command::new("cmd");
+        command.args(["/C", hook]);
+        command
+    };
+    #[cfg(not(target_os = "windows"))]
+    let mut command = {
+        let mut command = Command::new("sh");
+        command.args(["-c", hook]);
+        command
+    };
+    command.current_dir(working_dir).status()
+}
+
+/// Opens `path` in the platform's file manager.
+pub fn open_in_file_manager(path: &Path) -> Result<(), io::Error> {
+    #[cfg(target_os = "windows")]
+    let program = "explorer";
+    #[cfg(target_os = "macos")]
+    let program = "open";
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let program = "xdg-open";
+
+    debug!("Opening {} with {}", path.display(), program);
+    let mut child = Command::new(program).arg(path).spawn()?;
+    // Reap the process once the file manager has been handed the path.
+    thread::spawn(move || child.wait());
+    Ok(())
+}
+
+/// Opens the folder containing `path` in the platform's file manager, selecting `path` where supported.
+pub fn reveal_in_file_manager(path: &Path) -> Result<(), io::Error> {
+    #[cfg(target_os = "windows")]
+    let mut command = {
+        let mut command = Command::new("explorer");
+        command.arg(format!("/select,{}", path.display()));
+        command
+    };
+    #[cfg(target_os = "macos")]
+    let mut command = {
+        let mut command = Command::new("open");
+        command.arg("-R").arg(path);
+        command
+    };
+    // There is no common way to select a file on linux, open the parent folder instead.
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let mut command = {
+        let mut command = Command::new("xdg-open");
+        command.arg(path.parent().unwrap_or(path));
+        command
+    };
+
+    debug!("Revealing {} with {:?}", path.display(), command);
+    let mut child = command.spawn()?;
+    thread::spawn(move || child.wait());
+    Ok(())
+}
+
+/// File extensions of files that are never modified in place. These are hard linked into clones.
+const IMMUTABLE_EXTENSIONS: [&str; 3] = ["jar", "zip", "litemod"];
+/// Top level instance directories that only relate to previous runs and are not carried over to clones.
+const UNCLONED_DIRECTORIES: [&str; 2] = ["logs", "crash-reports"];
+
+/// Copies everything but the config and run specific directories from one instance directory to another.
+fn copy_instance_contents(from: &Path, to: &Path) -> Result<(), io::Error> {
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let file_name = entry.file_name();
+        if file_name == "config.json" || UNCLONED_DIRECTORIES.iter().any(|dir| file_name == *dir) {
+            continue;
+        }
+        copy_entry(&entry.path(), &to.join(&file_name))?;
+    }
+    Ok(())
+}
+
+/// Recursively copies a file or directory, hard linking immutable files when the filesystem allows it.
+pub fn copy_entry(from: &Path, to: &Path) -> Result<(), io::Error> {
+    if from.is_dir() {
+        fs::create_dir_all(to)?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            copy_entry(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        return Ok(());
+    }
+    let is_immutable = from
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map_or(false, |extension| IMMUTABLE_EXTENSIONS.contains(&extension));
+    // Hard links fail across filesystems, fall back to a normal copy.
+    if !is_immutable || fs::hard_link(from, to).is_err() {
+        fs::copy(from, to)?;
+    }
+    Ok(())
+}
+
+/// Makes sure an instance name can safely be used as a directory name.
+fn validate_instance_name(instance_name: &str) -> InstanceResult<()> {
+    let trimmed = instance_name.trim();
+    if trimmed.is_empty()
+        || trimmed == "."
+        || trimmed == ".."
+        || trimmed.contains(|c: char| c == '/' || c == '\\')
+    {
+        return Err(InstanceError::InvalidInstanceName(instance_name.into()));
+    }
+    Ok(())
+}
+
+/// Replaces the old instance directory with the new one in every argument that references it.
+/// Used for the `${game_directory}` and `${natives_directory}` values baked into the arguments.
+fn rewrite_instance_paths(arguments: &[String], old_dir: &Path, new_dir: &Path) -> Vec<String> {
+    let old_str = old_dir.to_string_lossy();
+    let new_str = new_dir.to_string_lossy();
+    arguments
+        .iter()
+        .map(|argument| argument.replace(old_str.as_ref(), new_str.as_ref()))
+        .collect()
+}
@@ -3,36 +3,148 @@ use std::{
     io::{self, BufReader, Write},
     path::{Path, PathBuf},
     string::FromUtf8Error,
-    sync::Arc,
+    sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard},
 };
 
 use bytes::Bytes;
-use log::{info};
+use indexmap::IndexMap;
+use log::{info, warn};
 use serde::Serialize;
-use tauri::async_runtime::Mutex;
+use serde_json::Value;
+use thiserror::Error;
 use zip::result::ZipError;
 
 use crate::{
     commands::{VersionEntry, VersionFilter},
     consts::{VANILLA_MANIFEST_URL, FORGE_MANIFEST_URL},
+    events::InstallPhase,
+    state::{instance_manager::InstanceError, task_manager::TaskError},
     web_services::{
+        cache::{CacheSource, ResponseCache, MANIFEST_TTL},
         downloader::{download_bytes_from_url, validate_file_hash, validate_hash, DownloadError},
         manifest::{vanilla::{VanillaManifest, VanillaManifestVersion, VanillaVersion}, forge::ForgeManifest},
+        resources::{custom_resolution_argument, demo_argument},
     },
 };
 
 pub type ManifestResult<T> = Result<T, ManifestError>;
 
-#[derive(Debug)]
+#[derive(Debug, Error)]
 pub enum ManifestError {
-    HttpError(reqwest::Error),
-    SerializationFilesystemError(io::Error),
-    Utf8DeserializationError(FromUtf8Error),
-    JsonSerializationError(serde_json::Error),
+    #[error("Request failed: {0}")]
+    HttpError(#[from] reqwest::Error),
+    #[error("Filesystem error: {0}")]
+    SerializationFilesystemError(#[from] io::Error),
+    #[error("Filesystem error at {}: {source}", path.display())]
+    FileError {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error("Invalid utf8: {0}")]
+    Utf8DeserializationError(#[from] FromUtf8Error),
+    #[error("Invalid json: {0}")]
+    JsonSerializationError(#[from] serde_json::Error),
+    #[error("{0}")]
     VersionRetrievalError(String),
+    #[error("{0}")]
     ResourceError(String),
-    InvalidFileDownload(String),
-    FileExtractionError(ZipError),
+    #[error("Downloaded file from {url} has an invalid hash")]
+    InvalidFileDownload { url: String },
+    #[error("Could not extract archive: {0}")]
+    FileExtractionError(#[from] ZipError),
+    #[error("{}", serialized_message(.0))]
+    InstanceError(InstanceError),
+    #[error("{0}")]
+    SchemaMigrationError(String),
+    #[error("{}", serialized_message(.0))]
+    TaskError(TaskError),
+    /// Another launcher's files could not be imported.
+    #[error("{0}")]
+    ImportError(String),
+    /// The data directory could not be moved to the chosen location.
+    #[error("{0}")]
+    DataDirError(String),
+    /// A setting was rejected, e.g. a mirror url that doesn't parse.
+    #[error("{0}")]
+    InvalidSettingError(String),
+    /// The task this operation ran in was cancelled.
+    #[error("Cancelled")]
+    Cancelled,
+    /// An error that happened while installing a version, along with the step it happened in.
+    #[error("{source}")]
+    InstallError {
+        phase: InstallPhase,
+        #[source]
+        source: Box<ManifestError>,
+    },
+}
+
+impl ManifestError {
+    /// A stable identifier for the kind of error, so the frontend doesn't have to match on messages.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ManifestError::HttpError(_) => "http_error",
+            ManifestError::SerializationFilesystemError(_) | ManifestError::FileError { .. } => {
+                "filesystem_error"
+            }
+            ManifestError::Utf8DeserializationError(_) => "invalid_utf8",
+            ManifestError::JsonSerializationError(_) => "invalid_json",
+            ManifestError::VersionRetrievalError(_) => "version_unavailable",
+            ManifestError::ResourceError(_) => "manifest_not_loaded",
+            ManifestError::InvalidFileDownload { .. } => "invalid_file_hash",
+            ManifestError::FileExtractionError(_) => "extraction_failed",
+            ManifestError::InstanceError(_) => "instance_error",
+            ManifestError::SchemaMigrationError(_) => "schema_migration_failed",
+            ManifestError::TaskError(_) => "task_error",
+            ManifestError::ImportError(_) => "import_failed",
+            ManifestError::DataDirError(_) => "data_dir_error",
+            ManifestError::InvalidSettingError(_) => "invalid_setting",
+            ManifestError::Cancelled => "cancelled",
+            ManifestError::InstallError { source, .. } => source.code(),
+        }
+    }
+
+    pub fn url(&self) -> Option<String> {
+        match self {
+            ManifestError::HttpError(error) => error.url().map(|url| url.to_string()),
+            ManifestError::InvalidFileDownload { url } => Some(url.clone()),
+            ManifestError::InstallError { source, .. } => source.url(),
+            _ => None,
+        }
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            ManifestError::FileError { path, .. } => Some(path),
+            ManifestError::InstallError { source, .. } => source.path(),
+            _ => None,
+        }
+    }
+
+    pub fn in_phase(self, phase: InstallPhase) -> Self {
+        match self {
+            // Keep the phase the error originally happened in, cancelling isn't an error of a phase.
+            ManifestError::InstallError { .. } | ManifestError::Cancelled => self,
+            error => ManifestError::InstallError {
+                phase,
+                source: Box::new(error),
+            },
+        }
+    }
+}
+
+/// How a `ManifestError` is sent to the frontend.
+#[derive(Serialize)]
+struct ManifestErrorPayload<'a> {
+    code: &'static str,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path: Option<&'a Path>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    phase: Option<InstallPhase>,
 }
 
 impl Serialize for ManifestError {
@@ -40,92 +152,241 @@ impl Serialize for ManifestError {
     where
         S: serde::Serializer,
     {
-        match &self {
-            ManifestError::HttpError(error) => serializer.serialize_str(&error.to_string()),
-            ManifestError::SerializationFilesystemError(error) => {
-                serializer.serialize_str(&error.to_string())
-            }
-            ManifestError::Utf8DeserializationError(error) => {
-                serializer.serialize_str(&error.to_string())
-            }
-            ManifestError::JsonSerializationError(error) => {
-                serializer.serialize_str(&error.to_string())
-            }
-            ManifestError::VersionRetrievalError(error) => serializer.serialize_str(&error),
-            ManifestError::ResourceError(error) => serializer.serialize_str(&error),
-            ManifestError::InvalidFileDownload(error) => serializer.serialize_str(&error),
-            ManifestError::FileExtractionError(error) => {
-                serializer.serialize_str(&error.to_string())
-            }
+        let phase = match self {
+            ManifestError::InstallError { phase, .. } => Some(*phase),
+            _ => None,
+        };
+        ManifestErrorPayload {
+            code: self.code(),
+            message: self.to_string(),
+            url: self.url(),
+            path: self.path(),
+            phase,
         }
+        .serialize(serializer)
     }
 }
 
-impl From<reqwest::Error> for ManifestError {
-    fn from(e: reqwest::Error) -> Self {
-        ManifestError::HttpError(e)
+/// The message of an error that is only serialized as a string.
+fn serialized_message<T: Serialize>(error: &T) -> String {
+    match serde_json::to_value(error) {
+        Ok(Value::String(message)) => message,
+        _ => "Unknown error".into(),
     }
 }
 
-impl From<io::Error> for ManifestError {
-    fn from(error: io::Error) -> Self {
-        ManifestError::SerializationFilesystemError(error)
+impl From<DownloadError> for ManifestError {
+    fn from(error: DownloadError) -> Self {
+        match error {
+            DownloadError::RequestError(e) => ManifestError::HttpError(e),
+            DownloadError::Cancelled => ManifestError::Cancelled,
+            DownloadError::FileWriteError(e) => ManifestError::SerializationFilesystemError(e),
+            DownloadError::InvalidFileHashError { url } => {
+                ManifestError::InvalidFileDownload { url }
+            }
+        }
     }
 }
 
-impl From<FromUtf8Error> for ManifestError {
-    fn from(error: FromUtf8Error) -> Self {
-        ManifestError::Utf8DeserializationError(error)
+impl From<InstanceError> for ManifestError {
+    fn from(error: InstanceError) -> Self {
+        ManifestError::InstanceError(error)
     }
 }
 
-impl From<serde_json::Error> for ManifestError {
-    fn from(error: serde_json::Error) -> Self {
-        ManifestError::JsonSerializationError(error)
+impl From<TaskError> for ManifestError {
+    fn from(error: TaskError) -> Self {
+        ManifestError::TaskError(error)
     }
 }
 
-impl From<DownloadError> for ManifestError {
-    fn from(error: DownloadError) -> Self {
-        match error {
-            DownloadError::RequestError(e) => ManifestError::HttpError(e),
-            DownloadError::FileWriteError(e) => ManifestError::SerializationFilesystemError(e),
-            DownloadError::InvalidFileHashError(e) => ManifestError::InvalidFileDownload(e),
+/// Adds the path that an io operation failed on to its error.
+pub trait PathContext<T> {
+    fn with_path(self, path: &Path) -> ManifestResult<T>;
+}
+
+impl<T> PathContext<T> for io::Result<T> {
+    fn with_path(self, path: &Path) -> ManifestResult<T> {
+        self.map_err(|source| ManifestError::FileError {
+            path: path.into(),
+            source,
+        })
+    }
+}
+
+/// The schema version written to every instance's config.json. When `InstanceConfiguration` changes
+/// shape, bump this and append a migration to `INSTANCE_CONFIG_MIGRATIONS`.
+pub const INSTANCE_CONFIG_SCHEMA_VERSION: u32 = 5;
+
+/// Upgrades a raw instance config by a single schema version.
+type ConfigMigration = fn(&mut Value) -> ManifestResult<()>;
+
+/// The migration at index `n` upgrades a config from schema version `n` to `n + 1`.
+const INSTANCE_CONFIG_MIGRATIONS: [ConfigMigration; INSTANCE_CONFIG_SCHEMA_VERSION as usize] = [
+    migrate_instance_config_v0,
+    migrate_instance_config_v1,
+    migrate_instance_config_v2,
+    migrate_instance_config_v3,
+    migrate_instance_config_v4,
+];
+
+/// Version 0 configs predate the `schema_version` field and only contain the name, jvm path and arguments.
+fn migrate_instance_config_v0(config: &mut Value) -> ManifestResult<()> {
+    let object = config.as_object_mut().ok_or_else(|| {
+        ManifestError::SchemaMigrationError("Instance config is not a json object".into())
+    })?;
+    object.entry("version_id").or_insert_with(|| "".into());
+    object.entry("last_played").or_insert(Value::Null);
+    object.entry("notes").or_insert_with(|| "".into());
+    Ok(())
+}
+
+/// Version 1 configs have no per instance launch settings.
+fn migrate_instance_config_v1(config: &mut Value) -> ManifestResult<()> {
+    let object = config.as_object_mut().ok_or_else(|| {
+        ManifestError::SchemaMigrationError("Instance config is not a json object".into())
+    })?;
+    object
+        .entry("settings")
+        .or_insert_with(|| Value::Object(Default::default()));
+    Ok(())
+}
+
+/// Version 2 configs skipped every argument with feature rules and appended the resolution arguments
+/// at launch, so only those are restored.
+fn migrate_instance_config_v2(config: &mut Value) -> ManifestResult<()> {
+    let object = config.as_object_mut().ok_or_else(|| {
+        ManifestError::SchemaMigrationError("Instance config is not a json object".into())
+    })?;
+    let resolution_argument = serde_json::to_value(custom_resolution_argument())?;
+    object
+        .entry("feature_arguments")
+        .or_insert_with(|| Value::Array(vec![resolution_argument]));
+    Ok(())
+}
+
+/// Version 3 configs have the user type hardcoded to `mojang`, it is now substituted when launching.
+fn migrate_instance_config_v3(config: &mut Value) -> ManifestResult<()> {
+    let arguments = config
+        .get_mut("arguments")
+        .and_then(Value::as_array_mut)
+        .ok_or_else(|| {
+            ManifestError::SchemaMigrationError("Instance config has no arguments".into())
+        })?;
+    let mut follows_user_type = false;
+    for argument in arguments.iter_mut() {
+        if follows_user_type && *argument == "mojang" {
+            *argument = "${user_type}".into();
         }
+        follows_user_type = *argument == "--userType";
     }
+    Ok(())
 }
 
-impl From<ZipError> for ManifestError {
-    fn from(error: ZipError) -> Self {
-        ManifestError::FileExtractionError(error)
+/// Version 4 configs of versions <= 1.12 have no demo argument, newer versions already have one.
+fn migrate_instance_config_v4(config: &mut Value) -> ManifestResult<()> {
+    let feature_arguments = config
+        .get_mut("feature_arguments")
+        .and_then(Value::as_array_mut)
+        .ok_or_else(|| {
+            ManifestError::SchemaMigrationError("Instance config has no feature arguments".into())
+        })?;
+    let has_demo_argument = feature_arguments.iter().any(|argument| {
+        argument
+            .get("value")
+            .map_or(false, |value| value.to_string().contains("--demo"))
+    });
+    if !has_demo_argument {
+        feature_arguments.push(serde_json::to_value(demo_argument())?);
     }
+    Ok(())
 }
 
-pub struct ResourceState(pub Arc<Mutex<ResourceManager>>);
+/// Runs every migration needed to bring a raw instance config up to `INSTANCE_CONFIG_SCHEMA_VERSION`.
+/// Returns true if the config was changed and should be written back to disk.
+pub fn migrate_instance_configuration(config: &mut Value) -> ManifestResult<bool> {
+    let schema_version = config
+        .get("schema_version")
+        .and_then(Value::as_u64)
+        .unwrap_or(0) as u32;
+    if schema_version > INSTANCE_CONFIG_SCHEMA_VERSION {
+        return Err(ManifestError::SchemaMigrationError(format!(
+            "Instance config has schema version {} but only versions up to {} are supported",
+            schema_version, INSTANCE_CONFIG_SCHEMA_VERSION
+        )));
+    }
+    for migration in &INSTANCE_CONFIG_MIGRATIONS[schema_version as usize..] {
+        migration(config)?;
+    }
+    if let Some(object) = config.as_object_mut() {
+        object.insert(
+            "schema_version".into(),
+            INSTANCE_CONFIG_SCHEMA_VERSION.into(),
+        );
+    }
+    Ok(schema_version < INSTANCE_CONFIG_SCHEMA_VERSION)
+}
+
+/// Not behind a mutex, the `ResourceManager` locks each of its parts on its own. Installs take minutes
+/// and only read the manifests, so listing versions or launching never waits on them.
+pub struct ResourceState(pub Arc<ResourceManager>);
 
 impl ResourceState {
     pub fn new(app_dir: &PathBuf) -> Self {
-        Self(Arc::new(Mutex::new(ResourceManager::new(app_dir))))
+        Self(Arc::new(ResourceManager::new(app_dir)))
     }
 }
 
+#[derive(Debug, Default)]
+struct Manifests {
+    vanilla: Option<VanillaManifest>,
+    forge: Option<ForgeManifest>,
+    // TODO: Forge and Fabric manifests.
+    /// Set when the manifests could not be downloaded and were loaded from disk instead.
+    offline: bool,
+}
+
+/// The directories resources are downloaded into and the manifests of the versions that can be
+/// installed. The locks are never held across an await, a guard is only kept to copy out of it or
+/// to replace what it guards.
 #[derive(Debug)]
 pub struct ResourceManager {
     app_dir: PathBuf,
-    vanilla_manifest: Option<VanillaManifest>,
-    forge_manifest: Option<ForgeManifest>,
-    // TODO: Forge and Fabric manifests.
+    manifests: RwLock<Manifests>,
+    /// Versions imported from disk, see `import::version_bundle`. Used when the vanilla manifest
+    /// doesn't know a version or couldn't be loaded at all.
+    imported_versions: RwLock<IndexMap<String, VanillaManifestVersion>>,
 }
 
 impl ResourceManager {
     pub fn new(app_dir: &Path) -> Self {
         Self {
             app_dir: app_dir.into(),
-            vanilla_manifest: None,
-            forge_manifest: None,
+            manifests: RwLock::new(Manifests::default()),
+            imported_versions: RwLock::new(IndexMap::new()),
         }
     }
 
+    pub fn app_dir(&self) -> &Path {
+        &self.app_dir
+    }
+
+    /// Creates the directories resources are downloaded into, so missing permissions show up before
+    /// the first install.
+    pub fn create_directories(&self) -> ManifestResult<()> {
+        for dir in [
+            self.version_dir(),
+            self.libraries_dir(),
+            self.asset_objects_dir(),
+            self.java_dir(),
+            self.instances_dir(),
+            self.cache_dir(),
+        ] {
+            fs::create_dir_all(&dir).with_path(&dir)?;
+        }
+        Ok(())
+    }
+
     /// Returns the version directory at ${app_dir}/versions
     pub fn version_dir(&self) -> PathBuf {
         self.app_dir.join("versions")
@@ -151,34 +412,177 @@ impl ResourceManager {
         self.app_dir.join("java")
     }
 
+    /// Returns the authlib-injector directory at ${app_dir}/authlib-injector
+    pub fn authlib_injector_dir(&self) -> PathBuf {
+        self.app_dir.join("authlib-injector")
+    }
+
     /// Returns the java directory at ${app_dir}/instances
     pub fn instances_dir(&self) -> PathBuf {
         self.app_dir.join("instances")
     }
 
-    pub async fn download_manifests(&mut self) -> ManifestResult<()> {
+    /// Returns the cache directory at ${app_dir}/cache
+    pub fn cache_dir(&self) -> PathBuf {
+        self.app_dir.join("cache")
+    }
+
+    pub fn response_cache(&self) -> ResponseCache {
+        ResponseCache::new(&self.cache_dir())
+    }
+
+    /// Returns the path to the cached vanilla manifest at ${app_dir}/version_manifest_v2.json
+    fn vanilla_manifest_path(&self) -> PathBuf {
+        self.app_dir.join("version_manifest_v2.json")
+    }
+
+    /// Returns the path to the list of imported versions at ${app_dir}/imported_versions.json
+    fn imported_versions_path(&self) -> PathBuf {
+        self.app_dir.join("imported_versions.json")
+    }
+
+    fn manifests(&self) -> RwLockReadGuard<'_, Manifests> {
+        self.manifests
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn manifests_mut(&self) -> RwLockWriteGuard<'_, Manifests> {
+        self.manifests
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn imported_versions(&self) -> RwLockReadGuard<'_, IndexMap<String, VanillaManifestVersion>> {
+        self.imported_versions
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn imported_versions_mut(
+        &self,
+    ) -> RwLockWriteGuard<'_, IndexMap<String, VanillaManifestVersion>> {
+        self.imported_versions
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Returns true if the manifests were loaded from disk because the network was unreachable.
+    pub fn is_offline(&self) -> bool {
+        self.manifests().offline
+    }
+
+    /// Loads the manifests from the response cache, requesting them once the cached ones are older than
+    /// `MANIFEST_TTL`. Falls back to the cached vanilla manifest when the network is unreachable.
+    pub async fn download_manifests(&self) -> ManifestResult<()> {
+        // Imported versions have to be usable even when no manifest can be loaded.
+        if let Err(error) = self.load_imported_versions() {
+            warn!("Could not load the imported versions: {}", error);
+        }
         info!("Downloading manifests");
-        let client = reqwest::Client::new();
-        let vanilla_response = client.get(VANILLA_MANIFEST_URL).send().await?;
-        let vanilla_manifest = vanilla_response.json::<VanillaManifest>().await?;
-        self.vanilla_manifest = Some(vanilla_manifest);
+        match self.request_manifests().await {
+            Ok((vanilla, forge, offline)) => {
+                let mut manifests = self.manifests_mut();
+                manifests.vanilla = Some(vanilla);
+                manifests.forge = Some(forge);
+                manifests.offline = offline;
+                Ok(())
+            }
+            Err(ManifestError::HttpError(error)) => {
+                warn!(
+                    "Could not download manifests, falling back to the cached manifest: {}",
+                    error
+                );
+                let vanilla = self.load_cached_vanilla_manifest();
+                let mut manifests = self.manifests_mut();
+                manifests.offline = true;
+                manifests.vanilla = Some(vanilla?);
+                Ok(())
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    /// The offline flag is true if an outdated cached manifest had to be used because a request
+    /// failed.
+    async fn request_manifests(&self) -> ManifestResult<(VanillaManifest, ForgeManifest, bool)> {
+        let cache = self.response_cache();
+        let (vanilla_bytes, vanilla_source) =
+            cache.get_bytes(VANILLA_MANIFEST_URL, MANIFEST_TTL).await?;
+        let vanilla_manifest = serde_json::from_slice::<VanillaManifest>(&vanilla_bytes)?;
+
+        // Keep a copy of the manifest on disk so instances can be launched offline.
+        let mut file = File::create(self.vanilla_manifest_path())?;
+        file.write_all(&vanilla_bytes)?;
+
+        let (forge_manifest, forge_source) = cache
+            .get_json::<ForgeManifest>(FORGE_MANIFEST_URL, MANIFEST_TTL)
+            .await?;
+
+        let offline =
+            vanilla_source == CacheSource::StaleCache || forge_source == CacheSource::StaleCache;
+        Ok((vanilla_manifest, forge_manifest, offline))
+    }
+
+    /// Deserialize the vanilla manifest cached at ${app_dir}/version_manifest_v2.json
+    fn load_cached_vanilla_manifest(&self) -> ManifestResult<VanillaManifest> {
+        let file = File::open(self.vanilla_manifest_path())?;
+        let reader = BufReader::new(file);
+        let vanilla_manifest = serde_json::from_reader::<BufReader<File>, VanillaManifest>(reader)?;
+        info!("Loaded cached vanilla manifest from disk.");
+        Ok(vanilla_manifest)
+    }
 
-        let forge_response = client.get(FORGE_MANIFEST_URL).send().await?;
-        let forge_manifest = forge_response.json::<ForgeManifest>().await?;
-        self.forge_manifest = Some(forge_manifest);
+    fn load_imported_versions(&self) -> ManifestResult<()> {
+        let path = self.imported_versions_path();
+        if !path.exists() {
+            return Ok(());
+        }
+        let versions: Vec<VanillaManifestVersion> =
+            serde_json::from_slice(&fs::read(&path).with_path(&path)?)?;
+        let mut imported_versions = self.imported_versions_mut();
+        *imported_versions = versions
+            .into_iter()
+            .map(|version| (version.id.clone(), version))
+            .collect();
+        info!("Loaded {} imported versions", imported_versions.len());
+        Ok(())
+    }
 
+    /// Adds versions imported from disk, replacing earlier imports of the same versions.
+    pub fn register_imported_versions(
+        &self,
+        versions: Vec<VanillaManifestVersion>,
+    ) -> ManifestResult<()> {
+        let mut imported_versions = self.imported_versions_mut();
+        for version in versions {
+            imported_versions.insert(version.id.clone(), version);
+        }
+        let versions: Vec<&VanillaManifestVersion> = imported_versions.values().collect();
+        let path = self.imported_versions_path();
+        fs::write(&path, serde_json::to_vec_pretty(&versions)?).with_path(&path)?;
         Ok(())
     }
 
-    /// Gets a list of all vanilla versions
+    /// Gets a list of all vanilla versions, along with the imported ones the manifest doesn't know.
     pub fn get_vanilla_version_list(&self, filters: &[VersionFilter]) -> Vec<VersionEntry> {
         let mut result: Vec<VersionEntry> = Vec::new();
-        if let Some(manifest) = &self.vanilla_manifest {
-            for (version, version_info) in &manifest.versions {
-                for filter in filters {
-                    if filter.checked && version_info.version_type == filter.id {
-                        result.push(VersionEntry::new(version, version_info));
-                    }
+        let manifests = self.manifests();
+        let imported = self.imported_versions();
+        let manifest_versions = manifests
+            .vanilla
+            .iter()
+            .flat_map(|manifest| manifest.versions.iter());
+        let imported_versions = imported.iter().filter(|(version, _)| {
+            manifests
+                .vanilla
+                .as_ref()
+                .map_or(true, |manifest| !manifest.versions.contains_key(*version))
+        });
+        for (version, version_info) in manifest_versions.chain(imported_versions) {
+            for filter in filters {
+                if filter.checked && version_info.version_type == filter.id {
+                    result.push(VersionEntry::new(version, version_info));
                 }
             }
         }
@@ -192,11 +596,44 @@ impl ResourceManager {
     pub fn get_vanilla_manifest_from_version(
         &self,
         mc_version: &str,
-    ) -> Option<&VanillaManifestVersion> {
-        if let Some(manifest) = &self.vanilla_manifest {
-            manifest.versions.get(mc_version)
-        } else {
-            None
+    ) -> Option<VanillaManifestVersion> {
+        let manifest_version = self
+            .manifests()
+            .vanilla
+            .as_ref()
+            .and_then(|manifest| manifest.versions.get(mc_version))
+            .cloned();
+        manifest_version.or_else(|| self.imported_versions().get(mc_version).cloned())
+    }
+
+    /// Returns the position of a version in the vanilla manifest, newer versions come first.
+    pub fn get_vanilla_version_index(&self, version_id: &str) -> Option<usize> {
+        self.manifests()
+            .vanilla
+            .as_ref()?
+            .versions
+            .get_index_of(version_id)
+    }
+
+    /// Returns the newest version of a type, e.g. `release` or `snapshot`.
+    pub fn get_latest_vanilla_version(&self, version_type: &str) -> Option<String> {
+        self.manifests()
+            .vanilla
+            .as_ref()?
+            .versions
+            .values()
+            .find(|version| version.version_type == version_type)
+            .map(|version| version.id.clone())
+    }
+
+    /// The java major version required by a cached version json, versions without one run on java 8.
+    pub fn get_required_java_version(&self, version_id: &str) -> Option<u32> {
+        match self.deserialize_cached_vanilla_version(version_id) {
+            Ok(version) => Some(version.java_version.map_or(8, |java| java.major_version)),
+            Err(error) => {
+                warn!("Could not load version {}: {:?}", version_id, error);
+                None
+            }
         }
     }
 
@@ -204,8 +641,9 @@ impl ResourceManager {
         &self,
         version_id: &str,
     ) -> ManifestResult<VanillaVersion> {
-        if let Some(manifest) = &self.vanilla_manifest {
-            if let Some(manifest_version) = manifest.versions.get(version_id) {
+        let loaded = self.manifests().vanilla.is_some() || !self.imported_versions().is_empty();
+        if loaded {
+            if let Some(manifest_version) = self.get_vanilla_manifest_from_version(version_id) {
                 // If there is a version json cached and its hash matches the manifest hash, load it.
                 if validate_file_hash(
                     &self.get_version_file_path(version_id),
@@ -213,6 +651,11 @@ impl ResourceManager {
                 ) {
                     info!("Loading vanilla version `{}` from disk.", version_id);
                     self.deserialize_cached_vanilla_version(version_id)
+                } else if manifest_version.url.is_empty() {
+                    Err(ManifestError::VersionRetrievalError(format!(
+                        "The imported version {} is missing or was changed, import it again",
+                        version_id
+                    )))
                 } else {
                     info!("Requesting vanilla version from {}", &manifest_version.url);
                     let bytes = download_bytes_from_url(&manifest_version.url).await?;
@@ -242,7 +685,9 @@ impl ResourceManager {
 
     /// Gets the path to a version json given a `version_id`
     fn get_version_file_path(&self, version_id: &str) -> PathBuf {
-        self.version_dir().join(format!("{}.json", version_id))
+        self.version_dir()
+            .join(version_id)
+            .join(format!("{}.json", version_id))
     }
 
     /// Deserialize a cached vanilla version json from disk.
@@ -250,7 +695,7 @@ impl ResourceManager {
         &self,
         version_id: &str,
     ) -> ManifestResult<VanillaVersion> {
-        let path = self.version_dir().join(format!("{}.json", version_id));
+        let path = self.get_version_file_path(version_id);
         let file = File::open(path)?;
         let reader = BufReader::new(file);
         let version = serde_json::from_reader::<BufReader<File>, VanillaVersion>(reader)?;
@@ -0,0 +1,39 @@
+//! Keeps the secrets of the settings, like the CurseForge api key, out of `settings.json`. They are
+//! encrypted with the key of the accounts file, see `account_storage`, the settings only note which
+//! secrets are set.
+
+use std::{collections::BTreeMap, fs, io, path::Path};
+
+use super::account_storage::{self, write_private_file};
+
+pub const SECRETS_FILE: &str = "secrets.dat";
+pub const CURSEFORGE_API_KEY: &str = "curseforgeApiKey";
+pub const PROXY_PASSWORD: &str = "proxyPassword";
+
+fn read_secrets(app_dir: &Path) -> io::Result<BTreeMap<String, String>> {
+    let path = app_dir.join(SECRETS_FILE);
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+    let json = account_storage::decrypt(app_dir, &fs::read(&path)?)?;
+    Ok(serde_json::from_slice(&json)?)
+}
+
+pub fn load_secret(app_dir: &Path, name: &str) -> io::Result<Option<String>> {
+    Ok(read_secrets(app_dir)?.remove(name))
+}
+
+/// Stores a secret, `None` removes it.
+pub fn store_secret(app_dir: &Path, name: &str, value: Option<&str>) -> io::Result<()> {
+    // Clearing a secret that was never set doesn't need a key.
+    if value.is_none() && !app_dir.join(SECRETS_FILE).exists() {
+        return Ok(());
+    }
+    let mut secrets = read_secrets(app_dir)?;
+    match value {
+        Some(value) => secrets.insert(name.into(), value.into()),
+        None => secrets.remove(name),
+    };
+    let data = account_storage::encrypt(app_dir, &serde_json::to_vec(&secrets)?)?;
+    write_private_file(&app_dir.join(SECRETS_FILE), &data)
+}
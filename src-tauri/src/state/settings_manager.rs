@@ -0,0 +1,544 @@
+use std::{
+    fs::File,
+    io::{BufReader, Error, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use tauri::async_runtime::Mutex;
+
+use crate::{
+    i18n::{self, DEFAULT_LOCALE},
+    instance::process_priority::ProcessPriority,
+    launcher_behavior::LauncherBehavior,
+    system::{self, MemoryRecommendation},
+    web_services::{
+        curseforge::{CurseForgeClient, CurseForgeResult, CurseForgeSettings},
+        downloader::Verification,
+        mirrors::{self, MirrorSettings},
+        network::{self, NetworkSettings},
+    },
+    window_state::{UiState, WindowGeometry},
+};
+
+use super::secret_storage::{self, CURSEFORGE_API_KEY, PROXY_PASSWORD};
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct Resolution {
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Garbage collector flag presets, so users don't have to paste jvm flags from the internet.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum GcPreset {
+    /// Leaves the collector choice to the jvm.
+    Default,
+    /// The G1 flags the vanilla launcher uses.
+    G1,
+    /// Aikar's tuned G1 flags, see https://docs.papermc.io/paper/aikars-flags
+    Aikar,
+    /// Low pause collector, only production ready on java 17+.
+    Zgc,
+}
+
+const G1_FLAGS: &[&str] = &[
+    "-XX:+UnlockExperimentalVMOptions",
+    "-XX:+UseG1GC",
+    "-XX:G1NewSizePercent=20",
+    "-XX:G1ReservePercent=20",
+    "-XX:MaxGCPauseMillis=50",
+    "-XX:G1HeapRegionSize=32M",
+];
+
+const AIKAR_FLAGS: &[&str] = &[
+    "-XX:+UseG1GC",
+    "-XX:+ParallelRefProcEnabled",
+    "-XX:MaxGCPauseMillis=200",
+    "-XX:+UnlockExperimentalVMOptions",
+    "-XX:+DisableExplicitGC",
+    "-XX:+AlwaysPreTouch",
+    "-XX:G1NewSizePercent=30",
+    "-XX:G1MaxNewSizePercent=40",
+    "-XX:G1HeapRegionSize=8M",
+    "-XX:G1ReservePercent=20",
+    "-XX:G1HeapWastePercent=5",
+    "-XX:G1MixedGCCountTarget=4",
+    "-XX:InitiatingHeapOccupancyPercent=15",
+    "-XX:G1MixedGCLiveThresholdPercent=90",
+    "-XX:G1RSetUpdatingPauseTimePercent=5",
+    "-XX:SurvivorRatio=32",
+    "-XX:+PerfDisableSharedMem",
+    "-XX:MaxTenuringThreshold=1",
+];
+
+const ZGC_FLAGS: &[&str] = &["-XX:+UseZGC"];
+
+impl GcPreset {
+    /// The jvm flags for this preset. ZGC falls back to the G1 flags when the java version is known to
+    /// be older than 17.
+    pub fn arguments(&self, java_version: Option<u32>) -> &'static [&'static str] {
+        match self {
+            GcPreset::Default => &[],
+            GcPreset::G1 => G1_FLAGS,
+            GcPreset::Aikar => AIKAR_FLAGS,
+            GcPreset::Zgc => match java_version {
+                Some(version) if version < 17 => {
+                    warn!("ZGC requires java 17+, using G1 for java {}", version);
+                    G1_FLAGS
+                }
+                _ => ZGC_FLAGS,
+            },
+        }
+    }
+}
+
+/// Settings applied when launching an instance. Every field is optional so instance settings can be
+/// layered over the global settings, which are layered over `LaunchSettings::defaults`.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct LaunchSettings {
+    /// Maximum heap size in megabytes, passed as `-Xmx`.
+    pub max_memory: Option<u32>,
+    /// Initial heap size in megabytes, passed as `-Xms`.
+    pub min_memory: Option<u32>,
+    /// Extra jvm arguments added in front of the persisted arguments.
+    pub java_arguments: Option<Vec<String>>,
+    /// Garbage collector flags added before `java_arguments`.
+    pub gc_preset: Option<GcPreset>,
+    pub resolution: Option<Resolution>,
+    /// Shell command run in the instance directory before the game is started.
+    pub pre_launch_hook: Option<String>,
+    /// Shell command run in the instance directory after the game exits.
+    pub post_exit_hook: Option<String>,
+    /// Yggdrasil server to launch against through authlib-injector, e.g. `ely.by`. Launching then
+    /// requires an account from that server.
+    #[serde(default)]
+    pub auth_server: Option<String>,
+    /// Runs the game on the discrete gpu of laptops with hybrid graphics.
+    #[serde(default)]
+    pub discrete_gpu: Option<bool>,
+    /// Java executable used instead of the runtime the instance was installed with. The runtime is
+    /// used anyway when this one is missing or too old for the version.
+    #[serde(default)]
+    pub java_path: Option<PathBuf>,
+    /// Checks the game jar and natives before every launch, downloading them again when they are
+    /// corrupted.
+    #[serde(default)]
+    pub verify_on_launch: Option<bool>,
+    /// Scheduling priority of the game, so it can be put ahead of background tasks.
+    #[serde(default)]
+    pub process_priority: Option<ProcessPriority>,
+    /// Indices of the cpu cores the game may run on, every core when empty.
+    #[serde(default)]
+    pub cpu_affinity: Option<Vec<usize>>,
+}
+
+impl LaunchSettings {
+    /// The values used when neither the instance nor the global settings set a field.
+    pub fn defaults() -> Self {
+        Self {
+            max_memory: Some(2048),
+            min_memory: Some(512),
+            java_arguments: Some(Vec::new()),
+            gc_preset: Some(GcPreset::Default),
+            resolution: None,
+            pre_launch_hook: None,
+            post_exit_hook: None,
+            auth_server: None,
+            discrete_gpu: Some(false),
+            java_path: None,
+            verify_on_launch: Some(false),
+            process_priority: Some(ProcessPriority::Normal),
+            cpu_affinity: Some(Vec::new()),
+        }
+    }
+
+    /// Returns these settings with any unset fields taken from `fallback`.
+    pub fn layered_over(&self, fallback: &LaunchSettings) -> LaunchSettings {
+        LaunchSettings {
+            max_memory: self.max_memory.or(fallback.max_memory),
+            min_memory: self.min_memory.or(fallback.min_memory),
+            java_arguments: self
+                .java_arguments
+                .clone()
+                .or_else(|| fallback.java_arguments.clone()),
+            gc_preset: self.gc_preset.or(fallback.gc_preset),
+            resolution: self
+                .resolution
+                .clone()
+                .or_else(|| fallback.resolution.clone()),
+            pre_launch_hook: self
+                .pre_launch_hook
+                .clone()
+                .or_else(|| fallback.pre_launch_hook.clone()),
+            post_exit_hook: self
+                .post_exit_hook
+                .clone()
+                .or_else(|| fallback.post_exit_hook.clone()),
+            auth_server: self
+                .auth_server
+                .clone()
+                .or_else(|| fallback.auth_server.clone()),
+            discrete_gpu: self.discrete_gpu.or(fallback.discrete_gpu),
+            java_path: self
+                .java_path
+                .clone()
+                .or_else(|| fallback.java_path.clone()),
+            verify_on_launch: self.verify_on_launch.or(fallback.verify_on_launch),
+            process_priority: self.process_priority.or(fallback.process_priority),
+            cpu_affinity: self
+                .cpu_affinity
+                .clone()
+                .or_else(|| fallback.cpu_affinity.clone()),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct SettingsState(pub Arc<Mutex<SettingsManager>>);
+
+impl SettingsState {
+    pub fn new(app_dir: &PathBuf) -> Self {
+        let mut settings_manager = SettingsManager::new(app_dir);
+        // There are no saved settings on the first launch, the defaults are used instead.
+        if let Err(error) = settings_manager.deserialize_settings() {
+            info!("Using default settings: {}", error);
+        }
+        if let Err(error) = network::apply(settings_manager.get_network_settings()) {
+            warn!("Could not apply the network settings: {}", error);
+        }
+        mirrors::apply(settings_manager.get_mirror_settings());
+        i18n::set_locale(settings_manager.get_locale());
+        Self(Arc::new(Mutex::new(settings_manager)))
+    }
+}
+
+/// Whether cached libraries, assets and java files are hashed before they are reused. Hashing catches
+/// corrupted files but reads every file, otherwise files with the expected size are trusted.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct VerificationSettings {
+    #[serde(default, rename = "strictInstall")]
+    pub strict_install: bool,
+    #[serde(default, rename = "strictLaunch")]
+    pub strict_launch: bool,
+    /// Checks that aren't strict also hash the files whose size or modification time changed since they
+    /// were last verified, instead of only checking their size.
+    #[serde(default)]
+    pub incremental: bool,
+}
+
+impl VerificationSettings {
+    pub fn install(&self) -> Verification {
+        self.verification(self.strict_install)
+    }
+
+    pub fn launch(&self) -> Verification {
+        self.verification(self.strict_launch)
+    }
+
+    fn verification(&self, strict: bool) -> Verification {
+        if strict {
+            Verification::Strict
+        } else if self.incremental {
+            Verification::Incremental
+        } else {
+            Verification::Fast
+        }
+    }
+}
+
+/// When whole instances are backed up without being asked to, see `instance::backups`.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct BackupSettings {
+    /// Hours between scheduled backups of an instance, None turns scheduled backups off.
+    #[serde(default, rename = "intervalHours")]
+    pub interval_hours: Option<u32>,
+    /// Automatic backups kept per instance, older ones are removed. Manual backups are always kept.
+    #[serde(default = "default_max_backups", rename = "maxBackups")]
+    pub max_backups: u32,
+    /// Back up an instance before its minecraft version or modpack version changes.
+    #[serde(default = "default_true", rename = "beforeVersionChange")]
+    pub before_version_change: bool,
+}
+
+fn default_max_backups() -> u32 {
+    5
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for BackupSettings {
+    fn default() -> Self {
+        Self {
+            interval_hours: None,
+            max_backups: default_max_backups(),
+            before_version_change: true,
+        }
+    }
+}
+
+/// How launcher managed files are stored on disk.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct StorageSettings {
+    /// Store mod jars once in the shared mods library and link them into instances, see
+    /// `instance::mods_library`.
+    #[serde(default, rename = "sharedModsLibrary")]
+    pub shared_mods_library: bool,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct SettingsManager {
+    #[serde(skip)]
+    path: PathBuf,
+    #[serde(default)]
+    launch: LaunchSettings,
+    #[serde(default)]
+    curseforge: CurseForgeSettings,
+    #[serde(default)]
+    verification: VerificationSettings,
+    #[serde(default)]
+    backups: BackupSettings,
+    #[serde(default)]
+    storage: StorageSettings,
+    #[serde(default)]
+    network: NetworkSettings,
+    #[serde(default)]
+    mirrors: MirrorSettings,
+    /// Locale of the messages the backend sends, English if unset.
+    #[serde(default)]
+    locale: Option<String>,
+    #[serde(default)]
+    ui: UiState,
+    /// What happens to the launcher window when a game starts.
+    #[serde(default, rename = "launcherBehavior")]
+    launcher_behavior: LauncherBehavior,
+    /// Total system memory in megabytes, detected once on setup.
+    #[serde(skip)]
+    total_memory: Option<u64>,
+}
+
+impl SettingsManager {
+    /// Call on app setup.
+    pub fn new(app_dir: &Path) -> Self {
+        Self {
+            path: app_dir.into(),
+            launch: Default::default(),
+            curseforge: Default::default(),
+            verification: Default::default(),
+            backups: Default::default(),
+            storage: Default::default(),
+            network: Default::default(),
+            mirrors: Default::default(),
+            locale: None,
+            ui: Default::default(),
+            launcher_behavior: Default::default(),
+            total_memory: system::total_memory(),
+        }
+    }
+
+    /// Deserialize settings from `app_dir/settings.json`
+    pub fn deserialize_settings(&mut self) -> Result<(), Error> {
+        let path = &self.path.join("settings.json");
+        let file = File::open(&path)?;
+        let reader = BufReader::new(file);
+        let deserialized_settings_manager =
+            serde_json::from_reader::<BufReader<File>, SettingsManager>(reader)?;
+        self.launch = deserialized_settings_manager.launch;
+        self.curseforge = deserialized_settings_manager.curseforge;
+        self.verification = deserialized_settings_manager.verification;
+        self.backups = deserialized_settings_manager.backups;
+        self.storage = deserialized_settings_manager.storage;
+        self.network = deserialized_settings_manager.network;
+        self.mirrors = deserialized_settings_manager.mirrors;
+        self.locale = deserialized_settings_manager.locale;
+        self.ui = deserialized_settings_manager.ui;
+        self.launcher_behavior = deserialized_settings_manager.launcher_behavior;
+        self.load_secrets()
+    }
+
+    /// Reads the secrets of the settings from `secret_storage`, secrets earlier versions saved in
+    /// plaintext are moved there. The settings stay usable without them.
+    fn load_secrets(&mut self) -> Result<(), Error> {
+        if self.curseforge.api_key.is_some() {
+            self.set_curseforge_settings(self.curseforge.clone())?;
+            self.serialize_settings()?;
+        } else if self.curseforge.has_api_key {
+            match secret_storage::load_secret(&self.path, CURSEFORGE_API_KEY) {
+                Ok(api_key) => self.curseforge.api_key = api_key,
+                Err(error) => warn!("Could not read the CurseForge api key: {}", error),
+            }
+        }
+        if self.network.proxy_password.is_some() {
+            self.set_network_settings(self.network.clone())?;
+            self.serialize_settings()?;
+        } else if self.network.has_proxy_password {
+            match secret_storage::load_secret(&self.path, PROXY_PASSWORD) {
+                Ok(proxy_password) => self.network.proxy_password = proxy_password,
+                Err(error) => warn!("Could not read the proxy password: {}", error),
+            }
+        }
+        Ok(())
+    }
+
+    /// Serialize settings into `app_dir/settings.json`
+    pub fn serialize_settings(&self) -> Result<(), Error> {
+        let json = serde_json::to_string(&self)?;
+        let path = &self.path.join("settings.json");
+        let mut file = File::create(path)?;
+        // Secrets are kept in `secret_storage`, the rest is still private to the user.
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+        }
+        info!("Serialized settings manager.");
+        file.write_all(json.as_bytes())
+    }
+
+    /// Get the global launch settings, this does not include the built in defaults.
+    pub fn get_launch_settings(&self) -> &LaunchSettings {
+        &self.launch
+    }
+
+    pub fn set_launch_settings(&mut self, launch: LaunchSettings) {
+        self.launch = launch;
+    }
+
+    pub fn get_curseforge_settings(&self) -> &CurseForgeSettings {
+        &self.curseforge
+    }
+
+    /// Stores the api key in `secret_storage`, the settings only keep whether one is set.
+    pub fn set_curseforge_settings(&mut self, curseforge: CurseForgeSettings) -> Result<(), Error> {
+        secret_storage::store_secret(
+            &self.path,
+            CURSEFORGE_API_KEY,
+            curseforge.api_key.as_deref(),
+        )?;
+        self.curseforge = CurseForgeSettings {
+            has_api_key: curseforge.api_key.is_some(),
+            ..curseforge
+        };
+        Ok(())
+    }
+
+    pub fn get_verification_settings(&self) -> &VerificationSettings {
+        &self.verification
+    }
+
+    pub fn set_verification_settings(&mut self, verification: VerificationSettings) {
+        self.verification = verification;
+    }
+
+    pub fn get_backup_settings(&self) -> &BackupSettings {
+        &self.backups
+    }
+
+    pub fn set_backup_settings(&mut self, backups: BackupSettings) {
+        self.backups = backups;
+    }
+
+    pub fn get_storage_settings(&self) -> &StorageSettings {
+        &self.storage
+    }
+
+    pub fn set_storage_settings(&mut self, storage: StorageSettings) {
+        self.storage = storage;
+    }
+
+    pub fn get_network_settings(&self) -> &NetworkSettings {
+        &self.network
+    }
+
+    /// Stores the proxy password in `secret_storage`, the settings only keep whether one is set.
+    pub fn set_network_settings(&mut self, network: NetworkSettings) -> Result<(), Error> {
+        secret_storage::store_secret(
+            &self.path,
+            PROXY_PASSWORD,
+            network.proxy_password.as_deref(),
+        )?;
+        self.network = NetworkSettings {
+            has_proxy_password: network.proxy_password.is_some(),
+            ..network
+        };
+        Ok(())
+    }
+
+    pub fn get_mirror_settings(&self) -> &MirrorSettings {
+        &self.mirrors
+    }
+
+    pub fn set_mirror_settings(&mut self, mirrors: MirrorSettings) {
+        self.mirrors = mirrors;
+    }
+
+    pub fn get_locale(&self) -> &str {
+        self.locale.as_deref().unwrap_or(DEFAULT_LOCALE)
+    }
+
+    pub fn set_locale(&mut self, locale: String) {
+        self.locale = Some(locale);
+    }
+
+    pub fn get_launcher_behavior(&self) -> LauncherBehavior {
+        self.launcher_behavior
+    }
+
+    pub fn set_launcher_behavior(&mut self, launcher_behavior: LauncherBehavior) {
+        self.launcher_behavior = launcher_behavior;
+    }
+
+    pub fn get_ui_state(&self) -> &UiState {
+        &self.ui
+    }
+
+    /// Replaces what the frontend keeps track of, the window geometry is saved by the backend.
+    pub fn set_ui_preferences(
+        &mut self,
+        selected_instance: Option<String>,
+        last_tab: Option<String>,
+    ) {
+        self.ui.selected_instance = selected_instance;
+        self.ui.last_tab = last_tab;
+    }
+
+    pub fn set_window_geometry(&mut self, geometry: WindowGeometry) {
+        self.ui.window = Some(geometry);
+    }
+
+    /// A client for CurseForge features, fails with `MissingApiKey` when neither a key nor a proxy is set.
+    pub fn curseforge_client(&self) -> CurseForgeResult<CurseForgeClient> {
+        CurseForgeClient::new(&self.curseforge)
+    }
+
+    /// Resolves the settings used to launch an instance: instance overrides first, then the global
+    /// settings, then the built in defaults. Without a configured max memory the recommendation
+    /// for the system memory is used.
+    pub fn resolve_launch_settings(
+        &self,
+        instance_settings: &LaunchSettings,
+        modded: bool,
+    ) -> LaunchSettings {
+        let defaults = LaunchSettings {
+            max_memory: Some(system::recommended_max_memory(self.total_memory, modded)),
+            ..LaunchSettings::defaults()
+        };
+        instance_settings.layered_over(&self.launch.layered_over(&defaults))
+    }
+
+    /// The recommended max memory for an instance along with whether its configured max memory
+    /// is more than the system has.
+    pub fn memory_recommendation(
+        &self,
+        instance_settings: &LaunchSettings,
+        modded: bool,
+    ) -> MemoryRecommendation {
+        let max_memory = instance_settings.max_memory.or(self.launch.max_memory);
+        MemoryRecommendation::new(self.total_memory, modded, max_memory)
+    }
+}
@@ -0,0 +1,201 @@
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use log::info;
+use serde::Serialize;
+use tauri::async_runtime::Mutex;
+
+pub type TaskResult<T> = Result<T, TaskError>;
+
+#[derive(Debug)]
+pub enum TaskError {
+    /// A running task that can't run alongside the new one, e.g. another install into the same instance.
+    Conflict {
+        running: TaskKind,
+        target: String,
+    },
+    UnknownTask(u64),
+}
+
+impl Serialize for TaskError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match self {
+            TaskError::Conflict { running, target } => serializer.serialize_str(&format!(
+                "{} is busy with another task ({:?})",
+                target, running
+            )),
+            TaskError::UnknownTask(id) => {
+                serializer.serialize_str(&format!("Unknown task: {}", id))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TaskKind {
+    Install,
+    ChangeVersion,
+    Import,
+    MoveDataDir,
+    Verify,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TaskStatus {
+    Running,
+    Completed,
+    Cancelled,
+    Failed(String),
+}
+
+/// A snapshot of a task for the frontend.
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskInfo {
+    pub id: u64,
+    pub kind: TaskKind,
+    /// Name of the instance the task works on.
+    pub target: String,
+    pub status: TaskStatus,
+    pub completed: usize,
+    pub total: usize,
+}
+
+#[derive(Debug, Default)]
+struct TaskProgress {
+    cancelled: AtomicBool,
+    completed: AtomicUsize,
+    total: AtomicUsize,
+}
+
+/// Given to the code running a task to report progress and check for cancellation. It doesn't hold the
+/// task manager's lock, so it can be used from download callbacks.
+#[derive(Debug, Clone)]
+pub struct TaskHandle {
+    id: u64,
+    progress: Arc<TaskProgress>,
+}
+
+impl TaskHandle {
+    /// Tasks are cancelled cooperatively, long running operations should check this between steps.
+    pub fn is_cancelled(&self) -> bool {
+        self.progress.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub fn set_progress(&self, completed: usize, total: usize) {
+        self.progress.completed.store(completed, Ordering::Relaxed);
+        self.progress.total.store(total, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug)]
+struct Task {
+    kind: TaskKind,
+    target: String,
+    status: TaskStatus,
+    progress: Arc<TaskProgress>,
+}
+
+impl Task {
+    fn info(&self, id: u64) -> TaskInfo {
+        TaskInfo {
+            id,
+            kind: self.kind,
+            target: self.target.clone(),
+            status: self.status.clone(),
+            completed: self.progress.completed.load(Ordering::Relaxed),
+            total: self.progress.total.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct TaskState(pub Arc<Mutex<TaskManager>>);
+
+impl TaskState {
+    pub fn new() -> Self {
+        Self(Arc::new(Mutex::new(TaskManager::new())))
+    }
+}
+
+/// Keeps track of long running operations such as installs, so the frontend can show and cancel them.
+#[derive(Debug, Default)]
+pub struct TaskManager {
+    next_id: u64,
+    // <Task id, task>, ids are increasing so tasks are ordered by when they started.
+    tasks: BTreeMap<u64, Task>,
+}
+
+impl TaskManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a new running task. Fails if a running task already works on the same `target`, since
+    /// two tasks writing to one instance would leave it in an inconsistent state. Nothing can start
+    /// while the data directory is moved.
+    pub fn register(&mut self, kind: TaskKind, target: &str) -> TaskResult<TaskHandle> {
+        if let Some(running) = self.tasks.values().find(|task| {
+            task.status == TaskStatus::Running
+                && (task.target == target || task.kind == TaskKind::MoveDataDir)
+        }) {
+            return Err(TaskError::Conflict {
+                running: running.kind,
+                target: target.into(),
+            });
+        }
+        let id = self.next_id;
+        self.next_id += 1;
+        let progress = Arc::new(TaskProgress::default());
+        self.tasks.insert(
+            id,
+            Task {
+                kind,
+                target: target.into(),
+                status: TaskStatus::Running,
+                progress: progress.clone(),
+            },
+        );
+        info!("Started task {} ({:?} {})", id, kind, target);
+        Ok(TaskHandle { id, progress })
+    }
+
+    /// Returns the finished task, so callers can report on it.
+    pub fn finish(&mut self, handle: &TaskHandle, status: TaskStatus) -> Option<TaskInfo> {
+        let task = self.tasks.get_mut(&handle.id)?;
+        info!("Task {} finished: {:?}", handle.id, status);
+        task.status = status;
+        Some(task.info(handle.id))
+    }
+
+    pub fn cancel(&mut self, id: u64) -> TaskResult<()> {
+        let task = self.tasks.get(&id).ok_or(TaskError::UnknownTask(id))?;
+        task.progress.cancelled.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    pub fn get_tasks(&self) -> Vec<TaskInfo> {
+        self.tasks.iter().map(|(id, task)| task.info(*id)).collect()
+    }
+
+    pub fn has_running_tasks(&self) -> bool {
+        self.tasks
+            .values()
+            .any(|task| task.status == TaskStatus::Running)
+    }
+
+    /// Forgets every task that isn't running anymore.
+    pub fn clear_finished(&mut self) {
+        self.tasks
+            .retain(|_, task| task.status == TaskStatus::Running);
+    }
+}
@@ -0,0 +1,299 @@
+//! Reports how much disk space the shared stores save compared to every instance keeping its own copy,
+//! and finds large files that are duplicated between instances and could be linked instead.
+
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap, HashSet},
+    fs,
+    io::{self, BufReader},
+    path::{Path, PathBuf},
+};
+
+use log::{debug, warn};
+use serde::Serialize;
+
+use crate::web_services::{
+    downloader::Downloadable, hash_cache, manifest::vanilla::AssetObject,
+    resources::CLASSPATH_SEPARATOR,
+};
+
+/// Smaller files aren't worth linking.
+const DUPLICATE_MIN_SIZE: u64 = 1024 * 1024;
+/// Folders of an instance searched for duplicates.
+const DUPLICATE_FOLDERS: [&str; 3] = ["mods", "resourcepacks", "shaderpacks"];
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SharedStore {
+    /// Libraries and game jars.
+    Libraries,
+    Assets,
+    ModsLibrary,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StoreSavings {
+    pub store: SharedStore,
+    /// Size of the files in the store that instances use.
+    #[serde(rename = "storedBytes")]
+    pub stored_bytes: u64,
+    /// What the instances would take up with their own copies.
+    #[serde(rename = "referencedBytes")]
+    pub referenced_bytes: u64,
+    #[serde(rename = "savedBytes")]
+    pub saved_bytes: u64,
+}
+
+impl StoreSavings {
+    fn new(store: SharedStore, stored_bytes: u64, referenced_bytes: u64) -> Self {
+        Self {
+            store,
+            stored_bytes,
+            referenced_bytes,
+            saved_bytes: referenced_bytes.saturating_sub(stored_bytes),
+        }
+    }
+}
+
+/// The same file in several places that aren't linked to each other.
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicateFile {
+    pub sha1: String,
+    pub size: u64,
+    pub paths: Vec<PathBuf>,
+    /// Space freed if all copies were linked to one.
+    #[serde(rename = "reclaimableBytes")]
+    pub reclaimable_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StorageReport {
+    pub stores: Vec<StoreSavings>,
+    /// Largest savings first.
+    pub duplicates: Vec<DuplicateFile>,
+}
+
+/// Directories of the shared stores.
+#[derive(Debug)]
+pub struct StoreDirs {
+    pub libraries: PathBuf,
+    pub versions: PathBuf,
+    pub assets: PathBuf,
+    pub mods_library: PathBuf,
+}
+
+/// What an instance uses, taken from its persisted launch arguments.
+#[derive(Debug)]
+pub struct InstanceUsage {
+    pub instance_dir: PathBuf,
+    pub arguments: Vec<String>,
+}
+
+impl InstanceUsage {
+    fn argument_after(&self, name: &str) -> Option<&str> {
+        self.arguments
+            .iter()
+            .position(|argument| argument == name)
+            .and_then(|index| self.arguments.get(index + 1))
+            .map(String::as_str)
+    }
+
+    fn classpath(&self) -> Vec<PathBuf> {
+        self.argument_after("-cp")
+            .map(|classpath| {
+                classpath
+                    .split(CLASSPATH_SEPARATOR)
+                    .map(PathBuf::from)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+fn file_size(path: &Path) -> u64 {
+    fs::metadata(path).map_or(0, |metadata| metadata.len())
+}
+
+fn library_savings(dirs: &StoreDirs, instances: &[InstanceUsage]) -> StoreSavings {
+    let mut stored: HashMap<PathBuf, u64> = HashMap::new();
+    let mut referenced = 0;
+    for instance in instances {
+        let entries: HashSet<PathBuf> = instance
+            .classpath()
+            .into_iter()
+            .filter(|path| path.starts_with(&dirs.libraries) || path.starts_with(&dirs.versions))
+            .collect();
+        for path in entries {
+            let size = *stored
+                .entry(path)
+                .or_insert_with_key(|path| file_size(path));
+            referenced += size;
+        }
+    }
+    StoreSavings::new(SharedStore::Libraries, stored.values().sum(), referenced)
+}
+
+/// Sizes of the objects of an asset index by their hash.
+fn read_asset_index(path: &Path) -> io::Result<HashMap<String, u64>> {
+    let index: AssetObject = serde_json::from_reader(BufReader::new(fs::File::open(path)?))?;
+    Ok(index
+        .objects
+        .iter()
+        .map(|asset| (asset.hash().to_string(), asset.size()))
+        .collect())
+}
+
+fn asset_savings(dirs: &StoreDirs, instances: &[InstanceUsage]) -> StoreSavings {
+    let mut indexes: HashMap<&str, HashMap<String, u64>> = HashMap::new();
+    let mut stored: HashMap<&str, u64> = HashMap::new();
+    let mut referenced = 0;
+    for instance in instances {
+        let index_id = match instance.argument_after("--assetIndex") {
+            Some(index_id) => index_id,
+            None => continue,
+        };
+        if !indexes.contains_key(index_id) {
+            let path = dirs
+                .assets
+                .join("indexes")
+                .join(format!("{}.json", index_id));
+            match read_asset_index(&path) {
+                Ok(objects) => {
+                    indexes.insert(index_id, objects);
+                }
+                Err(error) => {
+                    warn!("Could not read asset index {}: {}", index_id, error);
+                    continue;
+                }
+            }
+        }
+        referenced += indexes[index_id].values().sum::<u64>();
+    }
+    for objects in indexes.values() {
+        for (hash, size) in objects {
+            stored.insert(hash, *size);
+        }
+    }
+    StoreSavings::new(SharedStore::Assets, stored.values().sum(), referenced)
+}
+
+/// Every instance linking a stored jar would otherwise have its own copy. The link count is only
+/// available on unix.
+#[cfg(unix)]
+fn mods_library_savings(dirs: &StoreDirs) -> io::Result<Option<StoreSavings>> {
+    use std::os::unix::fs::MetadataExt;
+
+    if !dirs.mods_library.is_dir() {
+        return Ok(None);
+    }
+    let mut stored = 0;
+    let mut referenced = 0;
+    for prefix in fs::read_dir(&dirs.mods_library)? {
+        let prefix = prefix?.path();
+        if !prefix.is_dir() {
+            continue;
+        }
+        for entry in fs::read_dir(&prefix)? {
+            let metadata = entry?.metadata()?;
+            stored += metadata.len();
+            // One of the links is the library's own.
+            referenced += metadata.len() * metadata.nlink().saturating_sub(1);
+        }
+    }
+    Ok(Some(StoreSavings::new(
+        SharedStore::ModsLibrary,
+        stored,
+        referenced,
+    )))
+}
+
+#[cfg(not(unix))]
+fn mods_library_savings(_dirs: &StoreDirs) -> io::Result<Option<StoreSavings>> {
+    Ok(None)
+}
+
+/// Identifies the file behind a path, links to the same file share it.
+#[cfg(unix)]
+fn file_identity(metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_identity(_metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// A file that may be a duplicate, along with the identity of the file behind it.
+type Candidate = (PathBuf, Option<(u64, u64)>);
+
+fn find_duplicates(instances: &[InstanceUsage]) -> io::Result<Vec<DuplicateFile>> {
+    // Only files of the same size can be the same, most files are never hashed.
+    let mut by_size: BTreeMap<u64, Vec<Candidate>> = BTreeMap::new();
+    for instance in instances {
+        for folder in DUPLICATE_FOLDERS {
+            let dir = instance.instance_dir.join(folder);
+            if !dir.is_dir() {
+                continue;
+            }
+            for entry in fs::read_dir(dir)? {
+                let entry = entry?;
+                let metadata = entry.metadata()?;
+                if metadata.is_file() && metadata.len() >= DUPLICATE_MIN_SIZE {
+                    by_size
+                        .entry(metadata.len())
+                        .or_default()
+                        .push((entry.path(), file_identity(&metadata)));
+                }
+            }
+        }
+    }
+
+    let mut duplicates = Vec::new();
+    for (size, candidates) in by_size {
+        if candidates.len() < 2 {
+            continue;
+        }
+        let mut by_hash: HashMap<String, Vec<Candidate>> = HashMap::new();
+        for (path, identity) in candidates {
+            let sha1 = hash_cache::file_sha1(&path)?;
+            by_hash.entry(sha1).or_default().push((path, identity));
+        }
+        for (sha1, copies) in by_hash {
+            // Paths that are links to one file only take up its space once.
+            let mut identities = HashSet::new();
+            let separate_copies = copies
+                .iter()
+                .filter(|(_, identity)| {
+                    identity.map_or(true, |identity| identities.insert(identity))
+                })
+                .count() as u64;
+            if separate_copies < 2 {
+                continue;
+            }
+            duplicates.push(DuplicateFile {
+                sha1,
+                size,
+                paths: copies.into_iter().map(|(path, _)| path).collect(),
+                reclaimable_bytes: size * (separate_copies - 1),
+            });
+        }
+    }
+    duplicates.sort_by_key(|duplicate| Reverse(duplicate.reclaimable_bytes));
+    debug!("Found {} duplicated files", duplicates.len());
+    Ok(duplicates)
+}
+
+/// Reads every file the report needs, run it off the async runtime.
+pub fn storage_report(dirs: &StoreDirs, instances: &[InstanceUsage]) -> io::Result<StorageReport> {
+    let mut stores = vec![
+        library_savings(dirs, instances),
+        asset_savings(dirs, instances),
+    ];
+    stores.extend(mods_library_savings(dirs)?);
+    Ok(StorageReport {
+        stores,
+        duplicates: find_duplicates(instances)?,
+    })
+}
@@ -0,0 +1,175 @@
+use std::path::Path;
+
+use log::warn;
+use serde::Serialize;
+
+/// Smallest heap the launcher recommends, older versions still run with less but modern ones struggle.
+const MIN_RECOMMENDED_MEMORY: u64 = 1024;
+/// Heap sizes beyond this mostly lengthen garbage collection pauses.
+const MAX_RECOMMENDED_VANILLA_MEMORY: u64 = 4096;
+const MAX_RECOMMENDED_MODDED_MEMORY: u64 = 8192;
+
+/// Memory the game could use on this system, returned to the frontend as a hint for the settings.
+#[derive(Debug, Clone, Serialize)]
+pub struct MemoryRecommendation {
+    /// Total physical memory in megabytes, None if it could not be detected.
+    #[serde(rename = "totalMemory")]
+    pub total_memory: Option<u64>,
+    #[serde(rename = "recommendedMaxMemory")]
+    pub recommended_max_memory: u32,
+    /// Set when the configured max memory is more than the system has.
+    #[serde(rename = "exceedsSystemMemory")]
+    pub exceeds_system_memory: bool,
+}
+
+impl MemoryRecommendation {
+    pub fn new(total_memory: Option<u64>, modded: bool, max_memory: Option<u32>) -> Self {
+        Self {
+            total_memory,
+            recommended_max_memory: recommended_max_memory(total_memory, modded),
+            exceeds_system_memory: exceeds_system_memory(total_memory, max_memory),
+        }
+    }
+}
+
+/// Picks a max heap size in megabytes for an instance. Vanilla gets up to a quarter of the system
+/// memory and modded instances up to half, the rest is left for the os and the jvm itself.
+pub fn recommended_max_memory(total_memory: Option<u64>, modded: bool) -> u32 {
+    let total_memory = match total_memory {
+        Some(total_memory) => total_memory,
+        // Matches the launcher default from before the memory was detected.
+        None => return 2048,
+    };
+    let (share, limit) = if modded {
+        (total_memory / 2, MAX_RECOMMENDED_MODDED_MEMORY)
+    } else {
+        (total_memory / 4, MAX_RECOMMENDED_VANILLA_MEMORY)
+    };
+    // Round down to a multiple of 512 so the value looks like something a user would pick.
+    let recommended = share.min(limit) / 512 * 512;
+    recommended.max(MIN_RECOMMENDED_MEMORY) as u32
+}
+
+/// Whether `max_memory` is more than the total memory of the system.
+pub fn exceeds_system_memory(total_memory: Option<u64>, max_memory: Option<u32>) -> bool {
+    match (total_memory, max_memory) {
+        (Some(total_memory), Some(max_memory)) => u64::from(max_memory) > total_memory,
+        _ => false,
+    }
+}
+
+/// Total physical memory of the system in megabytes.
+pub fn total_memory() -> Option<u64> {
+    let memory = detect_total_memory_bytes().map(|bytes| bytes / 1024 / 1024);
+    if memory.is_none() {
+        warn!("Could not detect the total system memory");
+    }
+    memory
+}
+
+/// Free space in megabytes on the drive `path` is on.
+pub fn available_disk_space(path: &Path) -> Option<u64> {
+    let space = detect_available_disk_space_bytes(path).map(|bytes| bytes / 1024 / 1024);
+    if space.is_none() {
+        warn!("Could not detect the free disk space of {}", path.display());
+    }
+    space
+}
+
+#[cfg(unix)]
+fn detect_available_disk_space_bytes(path: &Path) -> Option<u64> {
+    // `-P` keeps long device names on one line, `-k` reports 1024 byte blocks on every unix.
+    let output = command_output("df", &["-Pk", &path.to_string_lossy()])?;
+    let kilobytes = output
+        .lines()
+        .nth(1)?
+        .split_whitespace()
+        .nth(3)?
+        .parse::<u64>()
+        .ok()?;
+    Some(kilobytes * 1024)
+}
+
+#[cfg(windows)]
+fn detect_available_disk_space_bytes(path: &Path) -> Option<u64> {
+    let drive = path
+        .to_string_lossy()
+        .chars()
+        .next()
+        .filter(char::is_ascii_alphabetic)?;
+    command_output(
+        "powershell",
+        &[
+            "-NoProfile",
+            "-Command",
+            &format!("(Get-PSDrive -Name {}).Free", drive),
+        ],
+    )?
+    .trim()
+    .parse()
+    .ok()
+}
+
+#[cfg(not(any(unix, windows)))]
+fn detect_available_disk_space_bytes(_path: &Path) -> Option<u64> {
+    None
+}
+
+#[cfg(target_os = "linux")]
+fn detect_total_memory_bytes() -> Option<u64> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    parse_meminfo(&meminfo)
+}
+
+#[cfg(target_os = "macos")]
+fn detect_total_memory_bytes() -> Option<u64> {
+    command_output("sysctl", &["-n", "hw.memsize"])?
+        .trim()
+        .parse()
+        .ok()
+}
+
+#[cfg(target_os = "windows")]
+fn detect_total_memory_bytes() -> Option<u64> {
+    command_output(
+        "powershell",
+        &[
+            "-NoProfile",
+            "-Command",
+            "(Get-CimInstance Win32_ComputerSystem).TotalPhysicalMemory",
+        ],
+    )?
+    .trim()
+    .parse()
+    .ok()
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn detect_total_memory_bytes() -> Option<u64> {
+    None
+}
+
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = std::process::Command::new(program)
+        .args(args)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Reads the `MemTotal` line of `/proc/meminfo`, which is in kilobytes.
+#[cfg(target_os = "linux")]
+pub fn parse_meminfo(meminfo: &str) -> Option<u64> {
+    let line = meminfo.lines().find(|line| line.starts_with("MemTotal:"))?;
+    let kilobytes = line
+        .trim_start_matches("MemTotal:")
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    Some(kilobytes * 1024)
+}
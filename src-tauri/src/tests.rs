@@ -115,3 +115,577 @@
 // {
 //     (va.len() == vb.len()) && va.iter().zip(vb).all(|(a, b)| a == b)
 // }
+
+use std::{
+    env,
+    fs::{self, File},
+    io::BufReader,
+    path::{Path, PathBuf},
+    process,
+    sync::mpsc,
+    time::Duration,
+};
+
+use indexmap::IndexMap;
+use serde::Deserialize;
+
+use crate::{
+    data_dir::{check_data_dir, move_data_dir, resolve_data_dir},
+    deep_link::{
+        forward_to_running_launcher, listen_for_forwarded_requests, parse_import_request,
+        ImportRequest,
+    },
+    instance::{
+        crash_reports::parse_crash_report,
+        logs::{parse_log, read_log, LogFilter, LogLevel},
+    },
+    java::parse_java_major_version,
+    nbt::{read_nbt, read_nbt_file, write_nbt, write_nbt_file, NbtError, Tag},
+    state::task_manager::{TaskKind, TaskManager},
+    system::recommended_max_memory,
+    web_services::resources::{ClasspathBuilder, CLASSPATH_SEPARATOR},
+};
+
+/// A directory in the system's temp directory that is removed when the test ends.
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        let path = env::temp_dir().join(format!("mc-launcher-test-{}-{}", name, process::id()));
+        if path.exists() {
+            fs::remove_dir_all(&path).unwrap();
+        }
+        fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// A file from the `resources` folder.
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("resources")
+        .join(name)
+}
+
+#[test]
+fn test_classpath_unix_separator() {
+    let libraries = vec![
+        PathBuf::from("/data/libraries/a.jar"),
+        PathBuf::from("/data/libraries/b.jar"),
+    ];
+    let classpath = ClasspathBuilder::with_separator(':')
+        .entries(&libraries)
+        .entry(Path::new("/data/versions/1.19/1.19.jar"))
+        .build();
+    assert_eq!(
+        classpath,
+        "/data/libraries/a.jar:/data/libraries/b.jar:/data/versions/1.19/1.19.jar"
+    );
+}
+
+#[test]
+fn test_classpath_windows_separator() {
+    let libraries = vec![
+        PathBuf::from("C:\\data\\libraries\\a.jar"),
+        PathBuf::from("C:\\data\\libraries\\b.jar"),
+    ];
+    let classpath = ClasspathBuilder::with_separator(';')
+        .entries(&libraries)
+        .entry(Path::new("C:\\data\\versions\\1.19\\1.19.jar"))
+        .build();
+    // Drive letters contain ':' so windows has to use ';' to separate entries.
+    assert_eq!(
+        classpath,
+        "C:\\data\\libraries\\a.jar;C:\\data\\libraries\\b.jar;C:\\data\\versions\\1.19\\1.19.jar"
+    );
+}
+
+#[test]
+fn test_classpath_platform_separator() {
+    let expected = if cfg!(target_os = "windows") {
+        ';'
+    } else {
+        ':'
+    };
+    assert_eq!(CLASSPATH_SEPARATOR, expected);
+
+    let classpath = ClasspathBuilder::new()
+        .entry(Path::new("a.jar"))
+        .entry(Path::new("b.jar"))
+        .build();
+    assert_eq!(classpath, format!("a.jar{}b.jar", expected));
+}
+
+#[test]
+fn test_classpath_empty() {
+    assert_eq!(ClasspathBuilder::new().build(), "");
+    let classpath = ClasspathBuilder::new()
+        .entry(Path::new("client.jar"))
+        .build();
+    assert_eq!(classpath, "client.jar");
+}
+
+#[test]
+fn test_move_data_dir_back_to_default() {
+    let temp_dir = TempDir::new("data-dir");
+    let default_dir = temp_dir.0.join("default");
+    let moved_dir = temp_dir.0.join("moved");
+    let instance_dir = moved_dir.join("instances").join("Test");
+    fs::create_dir_all(&default_dir).unwrap();
+    fs::create_dir_all(&instance_dir).unwrap();
+    fs::write(
+        default_dir.join("data-dir.json"),
+        serde_json::to_vec(&serde_json::json!({ "dataDir": moved_dir })).unwrap(),
+    )
+    .unwrap();
+    let jvm_path = moved_dir.join("java").join("bin").join("java");
+    fs::write(
+        instance_dir.join("config.json"),
+        serde_json::to_vec(&serde_json::json!({ "jvm_path": jvm_path })).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(resolve_data_dir(&default_dir), moved_dir);
+
+    // The pointer doesn't make the default directory non-empty, anything else does.
+    check_data_dir(&default_dir, &moved_dir, &default_dir).unwrap();
+    fs::write(default_dir.join("other.txt"), "").unwrap();
+    assert!(check_data_dir(&default_dir, &moved_dir, &default_dir).is_err());
+    fs::remove_file(default_dir.join("other.txt")).unwrap();
+
+    let task = TaskManager::new()
+        .register(TaskKind::MoveDataDir, "data directory")
+        .unwrap();
+    move_data_dir(&default_dir, &moved_dir, &default_dir, &task).unwrap();
+
+    assert!(!default_dir.join("data-dir.json").exists());
+    assert_eq!(resolve_data_dir(&default_dir), default_dir);
+    let config: serde_json::Value = serde_json::from_slice(
+        &fs::read(
+            default_dir
+                .join("instances")
+                .join("Test")
+                .join("config.json"),
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        config["jvm_path"].as_str().map(PathBuf::from),
+        Some(default_dir.join("java").join("bin").join("java"))
+    );
+    assert!(fs::read_dir(&moved_dir).unwrap().next().is_none());
+}
+
+#[test]
+fn test_move_data_dir_rewrites_path_prefixes() {
+    let temp_dir = TempDir::new("data-dir-paths");
+    let default_dir = temp_dir.0.join("data");
+    let new_dir = temp_dir.0.join("moved");
+    let instances_dir = default_dir.join("instances");
+    fs::create_dir_all(instances_dir.join("Test")).unwrap();
+    fs::create_dir_all(instances_dir.join("Broken")).unwrap();
+    let (old, new) = (default_dir.to_str().unwrap(), new_dir.to_str().unwrap());
+    fs::write(
+        instances_dir.join("Test").join("config.json"),
+        serde_json::to_vec(&serde_json::json!({
+            "arguments": [
+                format!("-Djava.library.path={}/natives", old),
+                format!("{}/libraries/a.jar:{}2/b.jar", old, old),
+                format!("{}2", old),
+            ]
+        }))
+        .unwrap(),
+    )
+    .unwrap();
+    // Skipped, the move still succeeds.
+    fs::write(instances_dir.join("Broken").join("config.json"), "{").unwrap();
+
+    let task = TaskManager::new()
+        .register(TaskKind::MoveDataDir, "data directory")
+        .unwrap();
+    move_data_dir(&default_dir, &default_dir, &new_dir, &task).unwrap();
+
+    assert_eq!(resolve_data_dir(&default_dir), new_dir);
+    let config: serde_json::Value = serde_json::from_slice(
+        &fs::read(new_dir.join("instances").join("Test").join("config.json")).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        config["arguments"],
+        serde_json::json!([
+            format!("-Djava.library.path={}/natives", new),
+            format!("{}/libraries/a.jar:{}2/b.jar", new, old),
+            format!("{}2", old),
+        ])
+    );
+}
+
+fn nbt_compound(entries: Vec<(&str, Tag)>) -> Tag {
+    Tag::Compound(
+        entries
+            .into_iter()
+            .map(|(name, tag)| (name.to_string(), tag))
+            .collect::<IndexMap<String, Tag>>(),
+    )
+}
+
+fn nbt_test_root() -> Tag {
+    nbt_compound(vec![
+        ("byte", Tag::Byte(-1)),
+        ("short", Tag::Short(-300)),
+        ("int", Tag::Int(123_456)),
+        ("long", Tag::Long(-9_876_543_210)),
+        ("float", Tag::Float(0.5)),
+        ("double", Tag::Double(-1.25)),
+        ("byteArray", Tag::ByteArray(vec![-128, 0, 127])),
+        ("string", Tag::String("Grüße".into())),
+        ("emptyList", Tag::List(Vec::new())),
+        (
+            "servers",
+            Tag::List(vec![
+                nbt_compound(vec![("ip", Tag::String("localhost".into()))]),
+                nbt_compound(vec![("ip", Tag::String("example.com".into()))]),
+            ]),
+        ),
+        ("intArray", Tag::IntArray(vec![i32::MIN, 0, i32::MAX])),
+        ("longArray", Tag::LongArray(vec![i64::MIN, 0, i64::MAX])),
+        ("nested", nbt_compound(vec![("end", Tag::Byte(1))])),
+    ])
+}
+
+#[test]
+fn test_nbt_round_trip() {
+    let root = nbt_test_root();
+    let mut bytes = Vec::new();
+    write_nbt(&mut bytes, "Data", &root).unwrap();
+
+    let (name, read) = read_nbt(&mut bytes.as_slice()).unwrap();
+    assert_eq!(name, "Data");
+    assert_eq!(read, root);
+    // Compounds keep the order of their entries.
+    if let (Tag::Compound(read), Tag::Compound(root)) = (&read, &root) {
+        assert!(read.keys().eq(root.keys()));
+    }
+}
+
+#[test]
+fn test_nbt_file_round_trip() {
+    let temp_dir = TempDir::new("nbt");
+    let root = nbt_test_root();
+    for compressed in [true, false] {
+        let path = temp_dir.0.join(format!("level-{}.dat", compressed));
+        write_nbt_file(&path, "", &root, compressed).unwrap();
+        let magic = fs::read(&path).unwrap()[..2].to_vec();
+        assert_eq!(magic == [0x1f, 0x8b], compressed);
+        assert_eq!(read_nbt_file(&path).unwrap(), (String::new(), root.clone()));
+    }
+}
+
+#[test]
+fn test_nbt_rejects_invalid_input() {
+    let root = nbt_compound(vec![("int", Tag::Int(1))]);
+    let mut bytes = Vec::new();
+    write_nbt(&mut bytes, "", &root).unwrap();
+    bytes.truncate(bytes.len() - 2);
+    assert!(matches!(
+        read_nbt(&mut bytes.as_slice()),
+        Err(NbtError::IoError(_))
+    ));
+
+    assert!(matches!(
+        write_nbt(&mut Vec::new(), "", &Tag::Int(1)),
+        Err(NbtError::InvalidRoot(_))
+    ));
+    assert!(matches!(
+        read_nbt(&mut [8u8, 0, 0].as_slice()),
+        Err(NbtError::InvalidRoot(8))
+    ));
+}
+
+#[test]
+fn test_nbt_lengths_past_the_end_of_the_input() {
+    // Arrays and lists claiming i32::MAX elements, followed by a few bytes.
+    let mut header = vec![10u8, 0, 0];
+    for (tag_type, prefix) in [(7u8, vec![]), (9, vec![3u8]), (11, vec![]), (12, vec![])] {
+        let mut bytes = header.clone();
+        bytes.extend_from_slice(&[tag_type, 0, 1, b'a']);
+        bytes.extend_from_slice(&prefix);
+        bytes.extend_from_slice(&i32::MAX.to_be_bytes());
+        bytes.extend_from_slice(&[1, 2, 3, 4]);
+        assert!(matches!(
+            read_nbt(&mut bytes.as_slice()),
+            Err(NbtError::IoError(_))
+        ));
+    }
+
+    header.extend_from_slice(&[7, 0, 1, b'a']);
+    header.extend_from_slice(&(-1i32).to_be_bytes());
+    assert!(matches!(
+        read_nbt(&mut header.as_slice()),
+        Err(NbtError::NegativeLength(-1))
+    ));
+}
+
+#[test]
+fn test_nbt_nesting_depth() {
+    // A root compound holding `depth` lists nested in each other.
+    let nested_lists = |depth: usize| {
+        let mut bytes = vec![10u8, 0, 0, 9, 0, 1, b'a'];
+        for _ in 1..depth {
+            bytes.extend_from_slice(&[9, 0, 0, 0, 1]);
+        }
+        bytes.extend_from_slice(&[0, 0, 0, 0, 0, 0]);
+        bytes
+    };
+    assert!(read_nbt(&mut nested_lists(512).as_slice()).is_ok());
+    assert!(matches!(
+        read_nbt(&mut nested_lists(513).as_slice()),
+        Err(NbtError::TooDeep(512))
+    ));
+    assert!(matches!(
+        read_nbt(&mut nested_lists(100_000).as_slice()),
+        Err(NbtError::TooDeep(512))
+    ));
+}
+
+#[test]
+fn test_parse_crash_report() {
+    let contents = fs::read_to_string(fixture("crash_report_forge_1.19.2.txt")).unwrap();
+    let report = parse_crash_report(&contents);
+
+    assert_eq!(report.time.as_deref(), Some("2023-01-01 12:00:00"));
+    assert_eq!(report.description.as_deref(), Some("Ticking entity"));
+    let stacktrace: Vec<&str> = report.stacktrace.lines().collect();
+    assert_eq!(stacktrace.len(), 4);
+    assert!(stacktrace[0].starts_with("java.lang.NullPointerException"));
+
+    let titles: Vec<&str> = report
+        .sections
+        .iter()
+        .map(|section| section.title.as_str())
+        .collect();
+    assert_eq!(titles, ["Head", "Entity being ticked"]);
+    let head = &report.sections[0];
+    assert_eq!(head.details["Thread"], "Server thread");
+    assert_eq!(
+        head.details["Suspected Mod"].lines().next(),
+        Some("Create (create), Version: 0.5.0.i")
+    );
+    assert_eq!(
+        head.stacktrace.as_deref().map(|s| s.lines().count()),
+        Some(2)
+    );
+    let entity = &report.sections[1];
+    assert_eq!(
+        entity.details["Entity's Exact location"],
+        "10.50, 64.00, -3.25"
+    );
+
+    assert_eq!(report.system_details["Minecraft Version"], "1.19.2");
+    assert_eq!(report.system_details["FML"], "43.2");
+    assert_eq!(report.system_details["Mod List"].lines().count(), 2);
+
+    // The suspected mod comes first, followed by the mods in the stacktraces other than the game.
+    let affected: Vec<(&str, Option<&str>)> = report
+        .affected_mods
+        .iter()
+        .map(|affected_mod| (affected_mod.id.as_str(), affected_mod.version.as_deref()))
+        .collect();
+    assert_eq!(
+        affected,
+        [
+            ("create", Some("0.5.0.i")),
+            ("sodium", None),
+            ("flywheel", None)
+        ]
+    );
+    assert_eq!(report.probable_culprit.as_deref(), Some("create"));
+}
+
+#[test]
+fn test_parse_crash_report_without_sections() {
+    let report =
+        parse_crash_report("Time: 12:00\nDescription: Initializing game\n\njava.lang.Error\n");
+    assert_eq!(report.description.as_deref(), Some("Initializing game"));
+    assert_eq!(report.stacktrace, "java.lang.Error");
+    assert!(report.sections.is_empty());
+    assert!(report.system_details.is_empty());
+    assert!(report.probable_culprit.is_none());
+}
+
+#[test]
+fn test_parse_log() {
+    let file = File::open(fixture("latest_forge_1.19.2.log")).unwrap();
+    let lines = parse_log(BufReader::new(file)).unwrap();
+    assert_eq!(lines.len(), 7);
+
+    // Output from before log4j is set up has no level.
+    assert_eq!(lines[0].level, LogLevel::Unknown);
+    assert!(lines[0].time.is_none());
+    // Forge adds the logger name after the level.
+    assert_eq!(lines[1].time.as_deref(), Some("12:00:00"));
+    assert_eq!(lines[1].thread.as_deref(), Some("main"));
+    assert_eq!(lines[1].level, LogLevel::Info);
+    assert_eq!(
+        lines[1].message,
+        "ModLauncher running: args [--version, 1.19.2]"
+    );
+    assert_eq!(lines[2].thread.as_deref(), Some("Render thread"));
+    assert_eq!(lines[3].message, "Session ID is token:[redacted]");
+    assert_eq!(lines[4].level, LogLevel::Warn);
+    // Stacktraces continue the entry they belong to.
+    assert_eq!(lines[5].level, LogLevel::Error);
+    assert_eq!(lines[5].message.lines().count(), 3);
+    assert_eq!(lines[6].level, LogLevel::Fatal);
+}
+
+#[test]
+fn test_read_log_with_filter() {
+    let logs_dir = fixture("");
+    let filter = LogFilter {
+        levels: Some(vec![LogLevel::Error, LogLevel::Fatal]),
+        text: None,
+    };
+    let lines = read_log(&logs_dir, "latest_forge_1.19.2.log", &filter).unwrap();
+    assert_eq!(lines.len(), 2);
+
+    let filter = LogFilter {
+        levels: None,
+        text: Some("RENDER".into()),
+    };
+    let lines = read_log(&logs_dir, "latest_forge_1.19.2.log", &filter).unwrap();
+    assert_eq!(lines.len(), 4);
+
+    assert!(read_log(&logs_dir, "../Cargo.toml", &LogFilter::default()).is_err());
+}
+
+#[test]
+fn test_parse_java_major_version() {
+    // The fixtures are named after the major version of the java that printed them.
+    let mut checked = 0;
+    for entry in fs::read_dir(fixture("java_version")).unwrap() {
+        let path = entry.unwrap().path();
+        let file_name = path.file_name().unwrap().to_string_lossy().into_owned();
+        let expected: u32 = file_name.split('_').next().unwrap().parse().unwrap();
+        let output = fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            parse_java_major_version(&output),
+            Some(expected),
+            "{}",
+            file_name
+        );
+        checked += 1;
+    }
+    assert_eq!(checked, 5);
+
+    assert_eq!(
+        parse_java_major_version("Error: could not find libjava.so"),
+        None
+    );
+    assert_eq!(parse_java_major_version("java version \"\""), None);
+}
+
+#[derive(Debug, Deserialize)]
+struct DeepLinkFixture {
+    link: String,
+    request: Option<ImportRequest>,
+}
+
+#[test]
+fn test_parse_import_request() {
+    let fixtures: Vec<DeepLinkFixture> =
+        serde_json::from_slice(&fs::read(fixture("deep_links.json")).unwrap()).unwrap();
+    for fixture in fixtures {
+        assert_eq!(
+            parse_import_request(&fixture.link),
+            fixture.request,
+            "{}",
+            fixture.link
+        );
+    }
+}
+
+#[test]
+fn test_parse_import_request_mrpack() {
+    let temp_dir = TempDir::new("mrpack");
+    let path = temp_dir.0.join("Fabulously Optimized 5.4.1.mrpack");
+    fs::write(&path, "").unwrap();
+    assert_eq!(
+        parse_import_request(path.to_str().unwrap()),
+        Some(ImportRequest::Mrpack {
+            path: path.canonicalize().unwrap()
+        })
+    );
+    // Only existing files.
+    fs::remove_file(&path).unwrap();
+    assert_eq!(parse_import_request(path.to_str().unwrap()), None);
+}
+
+#[test]
+fn test_forward_import_request() {
+    let temp_dir = TempDir::new("deep-link");
+    let (sender, receiver) = mpsc::channel();
+    listen_for_forwarded_requests(&temp_dir.0, move |request| {
+        sender.send(request).unwrap();
+    })
+    .unwrap();
+
+    let link = "modrinth://modpack/fabulously-optimized";
+    let request = parse_import_request(link).unwrap();
+    assert!(forward_to_running_launcher(&temp_dir.0, link, &request));
+    assert_eq!(
+        receiver.recv_timeout(Duration::from_secs(5)).unwrap(),
+        request
+    );
+
+    // Anything that isn't a launch argument is dropped by the running launcher.
+    let bogus = ImportRequest::Modrinth {
+        project: "../../user".into(),
+        version: None,
+    };
+    assert!(forward_to_running_launcher(
+        &temp_dir.0,
+        "../../user",
+        &bogus
+    ));
+    assert!(receiver.recv_timeout(Duration::from_millis(500)).is_err());
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_parse_meminfo() {
+    use crate::system::parse_meminfo;
+
+    let meminfo = fs::read_to_string(fixture("meminfo.txt")).unwrap();
+    let total = parse_meminfo(&meminfo);
+    assert_eq!(total, Some(16_316_412 * 1024));
+
+    // 15933 megabytes.
+    let total_megabytes = total.map(|bytes| bytes / 1024 / 1024);
+    assert_eq!(recommended_max_memory(total_megabytes, false), 3584);
+    assert_eq!(recommended_max_memory(total_megabytes, true), 7680);
+
+    assert_eq!(parse_meminfo("MemFree: 2087156 kB"), None);
+    assert_eq!(parse_meminfo("MemTotal: unknown"), None);
+}
+
+#[test]
+fn test_recommended_max_memory() {
+    assert_eq!(recommended_max_memory(None, false), 2048);
+    assert_eq!(recommended_max_memory(None, true), 2048);
+    // A quarter for vanilla and half for modded, rounded down to a multiple of 512.
+    assert_eq!(recommended_max_memory(Some(8192), false), 2048);
+    assert_eq!(recommended_max_memory(Some(8192), true), 4096);
+    assert_eq!(recommended_max_memory(Some(3906), true), 1536);
+    // Capped on large systems.
+    assert_eq!(recommended_max_memory(Some(65536), false), 4096);
+    assert_eq!(recommended_max_memory(Some(65536), true), 8192);
+    // Never below the minimum on small systems.
+    assert_eq!(recommended_max_memory(Some(2048), false), 1024);
+}
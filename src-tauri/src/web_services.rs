@@ -1,4 +1,15 @@
 pub mod authentication;
+pub mod cache;
+pub mod curseforge;
 pub mod downloader;
+pub mod hash_cache;
 pub mod resources;
-pub mod manifest;
\ No newline at end of file
+pub mod manifest;
+pub mod mirrors;
+pub mod mod_details;
+pub mod modrinth;
+pub mod network;
+pub mod profile;
+pub mod rate_limit;
+pub mod skins;
+pub mod yggdrasil;
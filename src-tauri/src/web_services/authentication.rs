@@ -1,16 +1,24 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, fmt};
 
-use log::debug;
+use log::{debug, warn};
 use reqwest::{StatusCode, Url};
 use serde::{ser::SerializeStructVariant, Deserialize, Serialize};
 use serde_json::json;
 
-use crate::state::account_manager::Account;
+use crate::{
+    i18n::tr,
+    state::account_manager::{name_based_uuid, Account},
+    web_services::{
+        network,
+        rate_limit::{send, Api},
+        yggdrasil::validate_yggdrasil_account,
+    },
+};
 
 use crate::consts::{
     CLIENT_ID, MICROSOFT_TOKEN_URL, MINECRAFT_AUTHENTICATE_URL, MINECRAFT_LICENSE_URL,
-    MINECRAFT_PROFILE_URL, REDIRECT_URL, SCOPE, XBOX_LIVE_AUTHENTICATE_URL, XERR_HINTS,
-    XTXS_AUTHENTICATE_URL,
+    MINECRAFT_PROFILE_URL, MINECRAFT_RELYING_PARTY, REDIRECT_URL, SCOPE,
+    XBOX_LIVE_AUTHENTICATE_URL, XBOX_LIVE_RELYING_PARTY, XTXS_AUTHENTICATE_URL,
 };
 
 // REVIEW: Remove '_' prefix from unused fields when they're used. Just there to make the compilier happy. :)
@@ -62,6 +70,13 @@ impl XboxTokenSuccess {
         let uhs = xui.first()?.get("uhs")?;
         Some(uhs.into())
     }
+
+    /// Only tokens for the xbox live relying party contain the xuid.
+    pub fn get_xuid(&self) -> Option<String> {
+        let xui = self.display_claims.get("xui")?;
+        let xid = xui.first()?.get("xid")?;
+        Some(xid.into())
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -70,14 +85,14 @@ pub enum XboxTokenResponse {
     Success(XboxTokenSuccess),
     // TODO: Find a way to actually test error responses
     Failure {
-        #[serde(rename = "Identity")]
+        #[serde(rename = "Identity", default)]
         _identity: String,
         #[serde(rename = "XErr")]
         xerr: u32,
         #[serde(rename = "Message")]
         message: String,
         // Redirect is used for consoles.
-        #[serde(rename = "Redirect")]
+        #[serde(rename = "Redirect", default)]
         _redirect: String,
     },
 }
@@ -102,13 +117,23 @@ pub struct MinecraftProfileSkin {
     alias: String,
 }
 
+#[allow(unused)]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MinecraftProfileCape {
+    id: String,
+    state: String,
+    url: String,
+    alias: String,
+}
+
 #[allow(unused)]
 #[derive(Debug, Serialize, Deserialize)]
 pub struct MinecraftProfileSuccess {
     id: String,
     name: String,
     skins: Vec<MinecraftProfileSkin>,
-    // TODO: Missing capes, dont know what the response would look like.
+    #[serde(default)]
+    capes: Vec<MinecraftProfileCape>,
 }
 
 impl MinecraftProfileSuccess {
@@ -123,6 +148,11 @@ impl MinecraftProfileSuccess {
         // FIXME: Not sure if possible, but probably shouldnt unwrap here
         self.skins.get(0).unwrap()
     }
+
+    /// The cape the player has selected, None when they don't show one.
+    pub fn active_cape(&self) -> Option<&MinecraftProfileCape> {
+        self.capes.iter().find(|cape| cape.state == "ACTIVE")
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -161,8 +191,17 @@ enum MicrosoftGrantType {
     Refresh(String),
 }
 
+/// Name the vanilla launcher gives players of the demo.
+const DEMO_PLAYER_NAME: &str = "Player";
+
+/// XErr codes the Xbox Live endpoints answer with when the account itself can't sign in.
+const XERR_BANNED: u32 = 2148916227;
+const XERR_NO_XBOX_PROFILE: u32 = 2148916233;
+const XERR_REGION_UNAVAILABLE: u32 = 2148916235;
+const XERR_ADULT_VERIFICATION: [u32; 2] = [2148916236, 2148916237];
+const XERR_CHILD_ACCOUNT: u32 = 2148916238;
+
 #[derive(Debug)]
-// TODO: Implement Display for this error type.
 pub enum AuthenticationError {
     MicrosoftError {
         error_type: String,
@@ -177,11 +216,25 @@ pub enum AuthenticationError {
         error: String,
         error_message: String,
     },
+    /// An error returned by an authlib-injector Yggdrasil server.
+    YggdrasilError {
+        error: String,
+        error_message: String,
+    },
     UnknownQueryParameter(String),
     UrlParseError(url::ParseError),
     RequestError(reqwest::Error),
     WindowError(tauri::Error),
     HttpResponseError(StatusCode),
+    /// The Microsoft account never created an Xbox profile.
+    NoXboxProfile,
+    XboxBanned,
+    /// Xbox Live isn't available in the account's country.
+    XboxUnavailableInRegion,
+    /// The account has to verify its age on the Xbox website first. (South Korea)
+    AdultVerificationRequired,
+    /// The account is under 18 and has to be added to a Microsoft family by an adult.
+    ChildAccount,
 }
 
 pub type AuthResult<T> = core::result::Result<T, AuthenticationError>;
@@ -236,6 +289,20 @@ impl Serialize for AuthenticationError {
                 state.serialize_field("error_message", &error_message)?;
                 state.end()
             }
+            AuthenticationError::YggdrasilError {
+                error,
+                error_message,
+            } => {
+                let mut state = serializer.serialize_struct_variant(
+                    "AuthenticationError",
+                    3,
+                    "YggdrasilError",
+                    2,
+                )?;
+                state.serialize_field("error", &error)?;
+                state.serialize_field("error_message", &error_message)?;
+                state.end()
+            }
             AuthenticationError::UnknownQueryParameter(error) => serializer.serialize_str(&error),
             AuthenticationError::UrlParseError(error) => {
                 serializer.serialize_str(&error.to_string())
@@ -247,10 +314,124 @@ impl Serialize for AuthenticationError {
             AuthenticationError::HttpResponseError(status_code) => {
                 serializer.serialize_str(&format!("Status code: {}", status_code))
             }
+            AuthenticationError::NoXboxProfile => {
+                self.serialize_account_problem(serializer, 9, "NoXboxProfile")
+            }
+            AuthenticationError::XboxBanned => {
+                self.serialize_account_problem(serializer, 10, "XboxBanned")
+            }
+            AuthenticationError::XboxUnavailableInRegion => {
+                self.serialize_account_problem(serializer, 11, "XboxUnavailableInRegion")
+            }
+            AuthenticationError::AdultVerificationRequired => {
+                self.serialize_account_problem(serializer, 12, "AdultVerificationRequired")
+            }
+            AuthenticationError::ChildAccount => {
+                self.serialize_account_problem(serializer, 13, "ChildAccount")
+            }
         }?)
     }
 }
 
+impl fmt::Display for AuthenticationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthenticationError::MicrosoftError {
+                error_type,
+                error_description,
+            } => write!(f, "{}: {}", error_type, error_description),
+            AuthenticationError::XboxError { xerr, message, .. } => write!(
+                f,
+                "{}",
+                tr("error.auth.xbox", &[("xerr", xerr), ("message", message)])
+            ),
+            AuthenticationError::MinecraftProfileError {
+                error,
+                error_message,
+            }
+            | AuthenticationError::YggdrasilError {
+                error,
+                error_message,
+            } => write!(f, "{}: {}", error, error_message),
+            AuthenticationError::UnknownQueryParameter(parameter) => {
+                write!(f, "Unknown query parameter: {}", parameter)
+            }
+            AuthenticationError::UrlParseError(error) => write!(f, "{}", error),
+            AuthenticationError::RequestError(error) => write!(f, "{}", error),
+            AuthenticationError::WindowError(error) => write!(f, "{}", error),
+            AuthenticationError::HttpResponseError(status_code) => {
+                write!(f, "Status code: {}", status_code)
+            }
+            AuthenticationError::NoXboxProfile => {
+                write!(f, "{}", tr("error.auth.noXboxProfile", &[]))
+            }
+            AuthenticationError::XboxBanned => write!(f, "{}", tr("error.auth.xboxBanned", &[])),
+            AuthenticationError::XboxUnavailableInRegion => {
+                write!(f, "{}", tr("error.auth.xboxUnavailableInRegion", &[]))
+            }
+            AuthenticationError::AdultVerificationRequired => {
+                write!(f, "{}", tr("error.auth.adultVerificationRequired", &[]))
+            }
+            AuthenticationError::ChildAccount => {
+                write!(f, "{}", tr("error.auth.childAccount", &[]))
+            }
+        }
+    }
+}
+
+impl std::error::Error for AuthenticationError {}
+
+impl AuthenticationError {
+    /// Maps the XErr of a failed Xbox Live response to the problem the user has to fix.
+    fn from_xerr(xerr: u32, message: String) -> Self {
+        match xerr {
+            XERR_BANNED => AuthenticationError::XboxBanned,
+            XERR_NO_XBOX_PROFILE => AuthenticationError::NoXboxProfile,
+            XERR_REGION_UNAVAILABLE => AuthenticationError::XboxUnavailableInRegion,
+            XERR_CHILD_ACCOUNT => AuthenticationError::ChildAccount,
+            xerr if XERR_ADULT_VERIFICATION.contains(&xerr) => {
+                AuthenticationError::AdultVerificationRequired
+            }
+            xerr => AuthenticationError::XboxError {
+                xerr: xerr.to_string(),
+                hint: tr("error.auth.xboxHint", &[]),
+                message,
+            },
+        }
+    }
+
+    /// Account problems are sent with the translated message telling the user what to do.
+    fn serialize_account_problem<S>(
+        &self,
+        serializer: S,
+        variant_index: u32,
+        variant: &'static str,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut state = serializer.serialize_struct_variant(
+            "AuthenticationError",
+            variant_index,
+            variant,
+            1,
+        )?;
+        state.serialize_field("message", &self.to_string())?;
+        state.end()
+    }
+
+    /// Whether the account's tokens were rejected, as opposed to the servers being unreachable.
+    pub fn needs_login(&self) -> bool {
+        match self {
+            AuthenticationError::RequestError(_) | AuthenticationError::WindowError(_) => false,
+            AuthenticationError::HttpResponseError(status) => {
+                status.is_client_error() && *status != StatusCode::TOO_MANY_REQUESTS
+            }
+            _ => true,
+        }
+    }
+}
+
 impl From<url::ParseError> for AuthenticationError {
     fn from(e: url::ParseError) -> Self {
         AuthenticationError::UrlParseError(e)
@@ -308,7 +489,8 @@ pub async fn authenticate(auth_mode: AuthMode) -> AuthResult<Account> {
     debug!("Microsoft: {:#?}", microsoft_token);
     let xbl_auth_response = obtain_xbl_token(&microsoft_token.0).await?;
     debug!("Xbl Token: {:#?}", xbl_auth_response);
-    let xsts_auth_response = obtain_xsts_token(&xbl_auth_response.token).await?;
+    let xsts_auth_response =
+        obtain_xsts_token(&xbl_auth_response.token, MINECRAFT_RELYING_PARTY).await?;
     debug!("Xsts Token: {:#?}", xsts_auth_response);
     let user_hash = xsts_auth_response.get_user_hash().unwrap();
     let minecraft_auth_response =
@@ -320,23 +502,72 @@ pub async fn authenticate(auth_mode: AuthMode) -> AuthResult<Account> {
     // a user owns the game before attempting the next step, if it won't work for Xbox Game Pass users anyway?
     // let _ = check_license(&minecraft_auth_response.access_token).await?;
 
-    let minecraft_profile = obtain_minecraft_profile(&minecraft_auth_response.access_token).await?;
-    let active_skin = &minecraft_profile.active_skin();
+    // The xuid is only used for telemetry and chat reporting, the game still launches without it.
+    let xuid = match obtain_xsts_token(&xbl_auth_response.token, XBOX_LIVE_RELYING_PARTY).await {
+        Ok(response) => response.get_xuid().unwrap_or_default(),
+        Err(error) => {
+            warn!("Could not retrieve xuid: {:?}", error);
+            String::new()
+        }
+    };
+
+    let minecraft_profile =
+        match obtain_minecraft_profile(&minecraft_auth_response.access_token).await {
+            Ok(profile) => Some(profile),
+            // Accounts that don't own the game have no profile, they can still play the demo.
+            Err(AuthenticationError::HttpResponseError(StatusCode::NOT_FOUND)) => None,
+            Err(error) => return Err(error),
+        };
     debug!("minecraft_profile {:#?}", minecraft_profile);
+    let demo = minecraft_profile.is_none();
+    let (uuid, name, skin_url, skin_variant, cape_url) = match &minecraft_profile {
+        Some(profile) => {
+            let active_skin = profile.active_skin();
+            (
+                profile.id.clone(),
+                profile.name.clone(),
+                active_skin.url.clone(),
+                active_skin.variant.clone(),
+                profile.active_cape().map(|cape| cape.url.clone()),
+            )
+        }
+        // The user hash stays the same for an Xbox account, so the demo account keeps its uuid.
+        None => (
+            name_based_uuid(&format!("DemoPlayer:{}", user_hash)),
+            DEMO_PLAYER_NAME.into(),
+            String::new(),
+            String::new(),
+            None,
+        ),
+    };
     Ok(Account {
-        uuid: minecraft_profile.id.clone(),
-        name: minecraft_profile.name.clone(),
+        uuid,
+        name,
         // IDEA: Skin url for head?
-        skin_url: active_skin.url.clone(),
+        skin_url,
+        skin_variant,
+        cape_url,
         microsoft_access_token: microsoft_token.0,
         microsoft_access_token_expiry: microsoft_token.2,
         microsoft_refresh_token: microsoft_token.1,
         minecraft_access_token: minecraft_auth_response.access_token,
         minecraft_access_token_expiry: minecraft_auth_expiry,
+        xuid,
+        offline: false,
+        auth_server: None,
+        client_token: String::new(),
+        demo,
     })
 }
 
 pub async fn validate_account(account: &Account) -> AuthResult<Account> {
+    // Offline accounts have nothing to refresh.
+    if account.offline {
+        return Ok(account.clone());
+    }
+    if let Some(api_root) = &account.auth_server {
+        return validate_yggdrasil_account(api_root, account).await;
+    }
     let now = chrono::Local::now().timestamp();
     // Account expired.
     if account.minecraft_access_token_expiry <= now {
@@ -414,7 +645,7 @@ async fn obtain_microsoft_token(
     };
     form.insert(code.0, &code.1);
 
-    let client = reqwest::Client::new();
+    let client = network::client();
     // Send the post request with the body.
     let resp = client.post(MICROSOFT_TOKEN_URL).form(&form).send().await?;
 
@@ -439,7 +670,7 @@ async fn obtain_microsoft_token(
 
 /// Sends request to the XboxLive `/authenticate` endpoint using a Microsoft access token
 async fn obtain_xbl_token(access_token: &str) -> AuthResult<XboxTokenSuccess> {
-    let client = reqwest::Client::new();
+    let client = network::client();
     let response = client
         .post(XBOX_LIVE_AUTHENTICATE_URL)
         .header("Content-Type", "application/json")
@@ -463,8 +694,8 @@ async fn obtain_xbl_token(access_token: &str) -> AuthResult<XboxTokenSuccess> {
 }
 
 /// Sends request to the Xbox Secure Token Service `/authorize` endpoint using an XboxLive access token
-async fn obtain_xsts_token(xbl_token: &str) -> AuthResult<XboxTokenSuccess> {
-    let client = reqwest::Client::new();
+async fn obtain_xsts_token(xbl_token: &str, relying_party: &str) -> AuthResult<XboxTokenSuccess> {
+    let client = network::client();
     let response = client
         .post(XTXS_AUTHENTICATE_URL)
         .body(
@@ -475,7 +706,7 @@ async fn obtain_xsts_token(xbl_token: &str) -> AuthResult<XboxTokenSuccess> {
                         xbl_token
                     ]
                 },
-                "RelyingParty": "rp://api.minecraftservices.com/",
+                "RelyingParty": relying_party,
                 "TokenType": "JWT"
             })
             .to_string(),
@@ -491,8 +722,8 @@ async fn obtain_minecraft_token(
     xsts_token: &str,
     user_hash: &str,
 ) -> AuthResult<MinecraftTokenResponse> {
-    let client = reqwest::Client::new();
-    let response = client
+    let client = network::client();
+    let request = client
         .post(MINECRAFT_AUTHENTICATE_URL)
         .header("Content-Type", "application/json")
         .header("Accept", "application/json")
@@ -502,9 +733,8 @@ async fn obtain_minecraft_token(
                 "ensureLegacyEnabled": true
             })
             .to_string(),
-        )
-        .send()
-        .await?;
+        );
+    let response = send(Api::MinecraftServices, request).await?;
 
     if response.status().is_success() {
         let token_response = response.json::<MinecraftTokenResponse>().await?;
@@ -517,7 +747,7 @@ async fn obtain_minecraft_token(
 #[allow(unused)]
 /// Unused for now, currently cannot show if a Xbox Game Pass user owns the game so whats the point in checking...
 async fn check_license(access_token: &str) -> AuthResult<()> {
-    let client = reqwest::Client::new();
+    let client = network::client();
     let response = client
         .get(MINECRAFT_LICENSE_URL)
         .header("Content-Type", "application/json")
@@ -534,14 +764,13 @@ async fn check_license(access_token: &str) -> AuthResult<()> {
 
 // Obtains the Minecraft profile information like uuid, username, skins, and capes
 async fn obtain_minecraft_profile(access_token: &str) -> AuthResult<MinecraftProfileSuccess> {
-    let client = reqwest::Client::new();
-    let response = client
+    let client = network::client();
+    let request = client
         .get(MINECRAFT_PROFILE_URL)
         .header("Content-Type", "application/json")
         .header("Accept", "application/json")
-        .header("Authorization", format!("Bearer {}", access_token))
-        .send()
-        .await?;
+        .header("Authorization", format!("Bearer {}", access_token));
+    let response = send(Api::MinecraftServices, request).await?;
 
     if response.status().is_success() {
         debug!("obtain_minecraft_profile Response: {:#?}", response);
@@ -564,24 +793,21 @@ async fn obtain_minecraft_profile(access_token: &str) -> AuthResult<MinecraftPro
 
 /// Retrieves the successful response from a reqwest::Response from an XBL endpoint
 ///
-/// On error gather any XBL error hints and add them to the XboxError variant
+/// On error map the XErr to the account problem it stands for, see `AuthenticationError::from_xerr`
 ///
 /// On a failure response, return the status code of the error.
 async fn check_xbox_error(response: reqwest::Response) -> AuthResult<XboxTokenSuccess> {
-    if response.status().is_success() {
-        let token_response = response.json::<XboxTokenResponse>().await?;
-        match token_response {
-            XboxTokenResponse::Success(success) => Ok(success),
-            XboxTokenResponse::Failure { xerr, message, .. } => {
-                let hint = *XERR_HINTS.get(&xerr.to_string()).unwrap();
-                Err(AuthenticationError::XboxError {
-                    xerr: xerr.to_string(),
-                    message: message.into(),
-                    hint: hint.into(),
-                })
-            }
+    let status = response.status();
+    // Account problems are answered with a 401 carrying the XErr in its body.
+    if !status.is_success() && status != StatusCode::UNAUTHORIZED {
+        return Err(AuthenticationError::HttpResponseError(status));
+    }
+    match response.json::<XboxTokenResponse>().await {
+        Ok(XboxTokenResponse::Success(success)) => Ok(success),
+        Ok(XboxTokenResponse::Failure { xerr, message, .. }) => {
+            Err(AuthenticationError::from_xerr(xerr, message))
         }
-    } else {
-        Err(AuthenticationError::HttpResponseError(response.status()))
+        Err(_) if !status.is_success() => Err(AuthenticationError::HttpResponseError(status)),
+        Err(error) => Err(error.into()),
     }
 }
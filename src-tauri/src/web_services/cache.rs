@@ -0,0 +1,133 @@
+//! Keeps web api responses on disk, so the launcher starts offline and repeated operations don't request
+//! the same manifests again.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use bytes::Bytes;
+use crypto::{digest::Digest, sha1::Sha1};
+use log::{debug, warn};
+use serde::de::DeserializeOwned;
+
+use crate::{
+    state::resource_manager::{ManifestError, ManifestResult, PathContext},
+    web_services::downloader::download_bytes_from_url,
+};
+
+/// How long the version manifests are reused before asking for new versions.
+pub const MANIFEST_TTL: Duration = Duration::from_secs(60 * 60);
+/// How long api responses that rarely change, like the latest java builds, are reused.
+pub const API_RESPONSE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+/// How long mod descriptions and changelogs are reused.
+pub const MOD_DETAILS_TTL: Duration = Duration::from_secs(6 * 60 * 60);
+/// How long images like mod galleries are reused, they are hardly ever replaced under the same url.
+pub const MEDIA_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Where a cached response came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheSource {
+    Network,
+    /// Cached and still within its ttl.
+    Cache,
+    /// Cached but past its ttl, used because the request failed.
+    StaleCache,
+}
+
+#[derive(Debug, Clone)]
+pub struct ResponseCache {
+    dir: PathBuf,
+}
+
+impl ResponseCache {
+    pub fn new(dir: &Path) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Entries are named after the hash of their url, the file's modification time is when it was fetched.
+    fn entry_path(&self, url: &str) -> PathBuf {
+        let mut hasher = Sha1::new();
+        hasher.input_str(url);
+        self.dir.join(hasher.result_str())
+    }
+
+    /// Returns the response for `url`, requesting it only when there is no cached copy younger than `ttl`.
+    /// When the request fails an outdated copy is used instead.
+    pub async fn get_bytes(
+        &self,
+        url: &str,
+        ttl: Duration,
+    ) -> ManifestResult<(Bytes, CacheSource)> {
+        let (path, source) = self.get_file(url, ttl).await?;
+        Ok((fs::read(&path).with_path(&path)?.into(), source))
+    }
+
+    /// Like `get_bytes`, but returns the path of the cached copy for files that are handed to the
+    /// frontend as they are, like images.
+    pub async fn get_file(
+        &self,
+        url: &str,
+        ttl: Duration,
+    ) -> ManifestResult<(PathBuf, CacheSource)> {
+        let path = self.entry_path(url);
+        let age = entry_age(&path);
+        if let Some(age) = age {
+            if age < ttl {
+                debug!("Using cached response for {}", url);
+                return Ok((path, CacheSource::Cache));
+            }
+        }
+
+        match download_bytes_from_url(url).await {
+            Ok(bytes) => {
+                fs::create_dir_all(&self.dir).with_path(&self.dir)?;
+                fs::write(&path, &bytes).with_path(&path)?;
+                Ok((path, CacheSource::Network))
+            }
+            Err(error) if age.is_some() => {
+                warn!(
+                    "Request to {} failed, using the cached response: {}",
+                    url, error
+                );
+                Ok((path, CacheSource::StaleCache))
+            }
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    pub async fn get_json<T>(&self, url: &str, ttl: Duration) -> ManifestResult<(T, CacheSource)>
+    where
+        T: DeserializeOwned,
+    {
+        let (bytes, source) = self.get_bytes(url, ttl).await?;
+        match serde_json::from_slice(&bytes) {
+            Ok(value) => Ok((value, source)),
+            Err(error) => {
+                // Don't keep serving a response that can't be read.
+                self.remove(url)?;
+                Err(ManifestError::JsonSerializationError(error))
+            }
+        }
+    }
+
+    fn remove(&self, url: &str) -> io::Result<()> {
+        match fs::remove_file(self.entry_path(url)) {
+            Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn entry_age(path: &Path) -> Option<Duration> {
+    let modified = fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()?;
+    // A modification time in the future counts as fresh.
+    Some(
+        SystemTime::now()
+            .duration_since(modified)
+            .unwrap_or_default(),
+    )
+}
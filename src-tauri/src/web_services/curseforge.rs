@@ -0,0 +1,280 @@
+//! Access to the CurseForge api, which requires either an api key or a proxy that adds one.
+
+use std::io;
+
+use log::debug;
+use reqwest::{RequestBuilder, StatusCode};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    consts::{CURSEFORGE_API_URL, CURSEFORGE_MINECRAFT_GAME_ID},
+    web_services::{
+        network,
+        rate_limit::{send, Api},
+    },
+};
+
+pub type CurseForgeResult<T> = Result<T, CurseForgeError>;
+
+#[derive(Debug, Error)]
+pub enum CurseForgeError {
+    #[error("CurseForge features need an api key or a proxy, set one in the settings")]
+    MissingApiKey,
+    #[error("CurseForge rejected the api key")]
+    InvalidApiKey,
+    #[error("CurseForge responded with status {0}")]
+    HttpStatus(StatusCode),
+    #[error("Request failed: {0}")]
+    RequestError(#[from] reqwest::Error),
+    #[error("Could not save the settings: {0}")]
+    SettingsWriteError(#[from] io::Error),
+}
+
+impl CurseForgeError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            CurseForgeError::MissingApiKey => "curseforge_key_missing",
+            CurseForgeError::InvalidApiKey => "curseforge_key_invalid",
+            CurseForgeError::HttpStatus(_) => "http_status",
+            CurseForgeError::RequestError(_) => "http_error",
+            CurseForgeError::SettingsWriteError(_) => "filesystem_error",
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct CurseForgeErrorPayload {
+    code: &'static str,
+    message: String,
+}
+
+impl Serialize for CurseForgeError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        CurseForgeErrorPayload {
+            code: self.code(),
+            message: self.to_string(),
+        }
+        .serialize(serializer)
+    }
+}
+
+/// How the launcher reaches the CurseForge api. The api key is never sent to the frontend, nor
+/// written to the settings, it is kept in `secret_storage`.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct CurseForgeSettings {
+    /// Only read from settings of earlier versions, which stored the key in plaintext.
+    #[serde(default, skip_serializing)]
+    pub api_key: Option<String>,
+    /// Whether there is an api key in `secret_storage`.
+    #[serde(default)]
+    pub has_api_key: bool,
+    /// A server that forwards requests to the api with its own key, used instead of `api_key`.
+    #[serde(default)]
+    pub proxy_url: Option<String>,
+}
+
+/// What the frontend is told about the CurseForge settings.
+#[derive(Debug, Serialize)]
+pub struct CurseForgeStatus {
+    pub configured: bool,
+    /// The last characters of the api key so users can tell which key is set.
+    #[serde(rename = "maskedKey")]
+    pub masked_key: Option<String>,
+    #[serde(rename = "proxyUrl")]
+    pub proxy_url: Option<String>,
+}
+
+impl CurseForgeSettings {
+    pub fn status(&self) -> CurseForgeStatus {
+        CurseForgeStatus {
+            configured: self.api_key.is_some() || self.proxy_url.is_some(),
+            masked_key: self.api_key.as_deref().map(mask_api_key),
+            proxy_url: self.proxy_url.clone(),
+        }
+    }
+}
+
+fn mask_api_key(api_key: &str) -> String {
+    let visible: String = api_key
+        .chars()
+        .rev()
+        .take(4)
+        .collect::<Vec<char>>()
+        .into_iter()
+        .rev()
+        .collect();
+    format!("****{}", visible)
+}
+
+/// A file of a CurseForge project.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CurseForgeFile {
+    pub id: u32,
+    #[serde(rename = "displayName")]
+    pub display_name: String,
+    #[serde(rename = "fileName")]
+    pub file_name: String,
+    /// 1 for releases, 2 for betas and 3 for alphas.
+    #[serde(rename = "releaseType")]
+    pub release_type: u8,
+    #[serde(rename = "fileDate")]
+    pub file_date: String,
+    /// None when the author doesn't allow downloads outside of CurseForge.
+    #[serde(rename = "downloadUrl")]
+    pub download_url: Option<String>,
+    /// Minecraft versions mixed with the names of mod loaders and environments like `Fabric`.
+    #[serde(rename = "gameVersions", default)]
+    pub game_versions: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CurseForgeLogo {
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CurseForgeAuthor {
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CurseForgeCategory {
+    pub slug: String,
+}
+
+/// A project on CurseForge.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CurseForgeMod {
+    pub id: u32,
+    pub name: String,
+    pub summary: String,
+    /// A whole number, sent as a float.
+    #[serde(rename = "downloadCount")]
+    pub download_count: f64,
+    #[serde(default)]
+    pub logo: Option<CurseForgeLogo>,
+    #[serde(default)]
+    pub authors: Vec<CurseForgeAuthor>,
+    #[serde(default)]
+    pub categories: Vec<CurseForgeCategory>,
+    #[serde(rename = "dateModified")]
+    pub date_modified: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CurseForgePagination {
+    #[serde(rename = "totalCount")]
+    pub total_count: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CurseForgeSearchPage {
+    pub data: Vec<CurseForgeMod>,
+    pub pagination: CurseForgePagination,
+}
+
+/// Api responses wrap their payload in `data`.
+#[derive(Deserialize)]
+struct CurseForgeResponse<T> {
+    data: T,
+}
+
+/// A client for the CurseForge api. Every CurseForge feature should get one through `new`, so a
+/// missing key is reported the same way everywhere.
+#[derive(Debug, Clone)]
+pub struct CurseForgeClient {
+    base_url: String,
+    api_key: Option<String>,
+    client: reqwest::Client,
+}
+
+impl CurseForgeClient {
+    pub fn new(settings: &CurseForgeSettings) -> CurseForgeResult<Self> {
+        let (base_url, api_key) = match (&settings.proxy_url, &settings.api_key) {
+            (Some(proxy_url), _) => (proxy_url.trim_end_matches('/').to_string(), None),
+            (None, Some(api_key)) => (CURSEFORGE_API_URL.to_string(), Some(api_key.clone())),
+            (None, None) => return Err(CurseForgeError::MissingApiKey),
+        };
+        Ok(Self {
+            base_url,
+            api_key,
+            client: network::client(),
+        })
+    }
+
+    /// Starts a GET request to an api path like `/v1/mods/search`.
+    pub fn get(&self, path: &str) -> RequestBuilder {
+        let request = self.client.get(format!("{}{}", self.base_url, path));
+        match &self.api_key {
+            Some(api_key) => request.header("x-api-key", api_key),
+            None => request,
+        }
+    }
+
+    /// Makes a cheap request to check that the key or proxy is accepted.
+    pub async fn validate(&self) -> CurseForgeResult<()> {
+        let request = self.get(&format!("/v1/games/{}", CURSEFORGE_MINECRAFT_GAME_ID));
+        let response = send(Api::CurseForge, request).await?;
+        debug!("CurseForge validation responded with {}", response.status());
+        match response.status() {
+            status if status.is_success() => Ok(()),
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Err(CurseForgeError::InvalidApiKey),
+            status => Err(CurseForgeError::HttpStatus(status)),
+        }
+    }
+
+    /// Searches minecraft projects, `query` holds the parameters of `/v1/mods/search` besides the game.
+    pub async fn search_mods(
+        &self,
+        query: &[(&str, String)],
+    ) -> CurseForgeResult<CurseForgeSearchPage> {
+        let request = self
+            .get("/v1/mods/search")
+            .query(&[("gameId", CURSEFORGE_MINECRAFT_GAME_ID)])
+            .query(query);
+        let response = send(Api::CurseForge, request).await?;
+        match response.status() {
+            status if status.is_success() => Ok(response.json().await?),
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Err(CurseForgeError::InvalidApiKey),
+            status => Err(CurseForgeError::HttpStatus(status)),
+        }
+    }
+
+    /// Lists the files of a project, only the ones for the minecraft version and mod loader if they
+    /// are given. `loader_type` is CurseForge's id of the mod loader.
+    pub async fn mod_files(
+        &self,
+        mod_id: u32,
+        game_version: Option<&str>,
+        loader_type: Option<u32>,
+    ) -> CurseForgeResult<Vec<CurseForgeFile>> {
+        let mut query = vec![("pageSize", "50".to_string())];
+        if let Some(game_version) = game_version {
+            query.push(("gameVersion", game_version.to_string()));
+        }
+        if let Some(loader_type) = loader_type {
+            query.push(("modLoaderType", loader_type.to_string()));
+        }
+        let request = self
+            .get(&format!("/v1/mods/{}/files", mod_id))
+            .query(&query);
+        let response = send(Api::CurseForge, request).await?;
+        match response.status() {
+            status if status.is_success() => {
+                let files: CurseForgeResponse<Vec<CurseForgeFile>> = response.json().await?;
+                debug!(
+                    "Found {} files of CurseForge mod {}",
+                    files.data.len(),
+                    mod_id
+                );
+                Ok(files.data)
+            }
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Err(CurseForgeError::InvalidApiKey),
+            status => Err(CurseForgeError::HttpStatus(status)),
+        }
+    }
+}
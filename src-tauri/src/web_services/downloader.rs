@@ -1,103 +1,212 @@
 use std::{
-    fs::{self, File},
-    io::{self, Read},
+    fmt::Display,
+    fs, io,
     path::{Path, PathBuf},
+    sync::Arc,
 };
 
 use bytes::Bytes;
-use crypto::{digest::Digest, sha1::Sha1};
-use futures::StreamExt;
+use crypto::{digest::Digest, sha1::Sha1, sha2::Sha256};
+use futures::{channel::mpsc, StreamExt};
 use log::{debug, error, info};
 use serde::de::DeserializeOwned;
+use thiserror::Error;
+
+use super::{hash_cache, mirrors, network};
+use crate::state::task_manager::TaskHandle;
 
 const BUFFER_SIZE: usize = 8;
 
 pub type DownloadResult<T> = Result<T, DownloadError>;
 
-#[derive(Debug)]
+#[derive(Debug, Error)]
 pub enum DownloadError {
-    RequestError(reqwest::Error),
-    FileWriteError(io::Error),
-    InvalidFileHashError(String),
+    #[error("Request failed: {0}")]
+    RequestError(#[from] reqwest::Error),
+    #[error("Could not write downloaded file: {0}")]
+    FileWriteError(#[from] io::Error),
+    #[error("Downloaded file from {url} has an invalid hash")]
+    InvalidFileHashError { url: String },
+    #[error("Cancelled")]
+    Cancelled,
 }
 
-impl From<reqwest::Error> for DownloadError {
-    fn from(err: reqwest::Error) -> Self {
-        DownloadError::RequestError(err)
+impl DownloadError {
+    /// A stable identifier for the kind of error, matching the codes of `ManifestError`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            DownloadError::RequestError(_) => "http_error",
+            DownloadError::FileWriteError(_) => "filesystem_error",
+            DownloadError::InvalidFileHashError { .. } => "invalid_file_hash",
+            DownloadError::Cancelled => "cancelled",
+        }
     }
 }
 
-impl From<io::Error> for DownloadError {
-    fn from(error: io::Error) -> Self {
-        DownloadError::FileWriteError(error)
-    }
+/// How files already on disk are checked before they are reused instead of downloaded again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verification {
+    /// Hash every cached file, catches corrupted files at the cost of reading all of them.
+    Strict,
+    /// Check the size of every cached file, then its hash. Only files whose size or modification time
+    /// changed since they were last hashed are read, cheap enough to run on every launch.
+    Incremental,
+    /// Trust files that exist with the expected size.
+    Fast,
 }
 
 pub trait Downloadable {
     fn name(&self) -> &str;
     fn url(&self) -> String;
     fn hash(&self) -> &str;
+    fn size(&self) -> u64;
     fn path(&self, base_dir: &Path) -> PathBuf;
 }
 
-// FIXME: Dont bother checking file hash if the file is already downloaded. Assume that the file is valid.
+/// Downloads every item that isn't already in `base_dir` according to `verification`, calling `on_progress` with
+/// the number of finished and total items as each one completes. Items that are already present count as
+/// finished, so libraries and assets shared between instances are only downloaded once.
+///
+/// `task` is checked for cancellation between items, downloads already running are dropped.
+///
+/// Checking the files and `callback` run on the blocking thread pool, writing thousands of assets would
+/// otherwise stall the async runtime and every other command with it.
 pub async fn buffered_download_stream<T>(
     items: &[T],
     base_dir: &Path,
-    callback: impl Fn(&Bytes, &T) -> DownloadResult<()>,
+    verification: Verification,
+    task: Option<&TaskHandle>,
+    callback: impl Fn(&Bytes, &T) -> DownloadResult<()> + Send + Sync + 'static,
+    on_progress: impl Fn(usize, usize),
 ) -> DownloadResult<()>
 where
-    T: Downloadable,
+    T: Downloadable + Clone + Send + 'static,
 {
+    let missing: Vec<T> = {
+        let items = items.to_vec();
+        let base_dir = base_dir.to_path_buf();
+        run_blocking(move || {
+            items
+                .into_iter()
+                .filter(|item| {
+                    !is_file_valid(
+                        &item.path(&base_dir),
+                        item.hash(),
+                        item.size(),
+                        verification,
+                    )
+                })
+                .collect()
+        })
+        .await?
+    };
+    let present = items.len() - missing.len();
+    if present > 0 {
+        info!(
+            "Skipping {} of {} files that are already downloaded",
+            present,
+            items.len()
+        );
+        on_progress(present, items.len());
+    }
+
+    let callback = Arc::new(callback);
     let mut futures = Vec::new();
-    for item in items {
-        futures.push(download_single(item, &base_dir, &callback));
+    for item in missing {
+        futures.push(download_single(item, base_dir.into(), callback.clone()));
     }
-    let x = futures::stream::iter(futures)
-        .buffer_unordered(BUFFER_SIZE)
-        .collect::<Vec<DownloadResult<()>>>();
+    let mut stream = futures::stream::iter(futures).buffer_unordered(BUFFER_SIZE);
 
-    x.await;
+    let cancelled = || task.map_or(false, TaskHandle::is_cancelled);
+    let mut completed = present;
+    while stream.next().await.is_some() {
+        if cancelled() {
+            return Err(DownloadError::Cancelled);
+        }
+        completed += 1;
+        on_progress(completed, items.len());
+    }
     Ok(())
 }
 
-async fn download_single<T>(
-    item: &T,
-    base_dir: &Path,
-    callback: impl Fn(&Bytes, &T) -> DownloadResult<()>,
-) -> DownloadResult<()>
+async fn download_single<T, F>(item: T, base_dir: PathBuf, callback: Arc<F>) -> DownloadResult<()>
 where
-    T: Downloadable,
+    T: Downloadable + Send + 'static,
+    F: Fn(&Bytes, &T) -> DownloadResult<()> + Send + Sync + 'static,
 {
-    let path = &item.path(base_dir);
-    if !path.exists() {
-        debug!("Downloading file {}", item.name());
+    debug!("Downloading file {}", item.name());
+    let bytes = download_bytes_from_url(&item.url()).await?;
+    let x = run_blocking(move || {
+        let path = item.path(&base_dir);
         let dir_path = path.parent().unwrap();
         fs::create_dir_all(dir_path)?;
-
-        let bytes = download_bytes_from_url(&item.url()).await?;
-        let x = callback(&bytes, item);
-        if let Err(err) = x {
-            // TODO: Implmenet display for error.
-            error!("{:#?}", &err);
-        }
+        callback(&bytes, &item)
+    })
+    .await
+    .map_err(DownloadError::from)
+    .and_then(|result| result);
+    if let Err(err) = x {
+        error!("{} ({})", err, err.code());
     }
     Ok(())
 }
 
+/// Runs blocking filesystem work on the blocking thread pool and waits for it.
+pub async fn run_blocking<R>(work: impl FnOnce() -> R + Send + 'static) -> io::Result<R>
+where
+    R: Send + 'static,
+{
+    tauri::async_runtime::spawn_blocking(work)
+        .await
+        .map_err(worker_failed)
+}
+
+fn worker_failed(error: impl Display) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Other,
+        format!("Filesystem worker failed: {}", error),
+    )
+}
+
+/// Like `run_blocking`, for work on `total` items. `work` calls the function it is given once for every
+/// item it finished, `on_progress` is called with the finished and total items as they finish.
+pub async fn run_blocking_with_progress<R>(
+    total: usize,
+    work: impl FnOnce(&dyn Fn()) -> R + Send + 'static,
+    on_progress: impl Fn(usize, usize),
+) -> io::Result<R>
+where
+    R: Send + 'static,
+{
+    let (sender, mut finished) = mpsc::unbounded();
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        work(&|| {
+            let _ = sender.unbounded_send(());
+        })
+    });
+    let mut completed = 0;
+    on_progress(completed, total);
+    // Ends once `work` returns and drops the sender.
+    while finished.next().await.is_some() {
+        completed += 1;
+        on_progress(completed, total);
+    }
+    result.await.map_err(worker_failed)
+}
+
 pub async fn download_json_object<T>(url: &str) -> reqwest::Result<T>
 where
     T: DeserializeOwned,
 {
-    let client = reqwest::Client::new();
-    let response = client.get(url).send().await?;
+    let client = network::client();
+    let response = client.get(mirrors::mirrored(url)).send().await?;
     Ok(response.json().await?)
 }
 
 /// Download the bytes for a file at the specified `url`
 pub async fn download_bytes_from_url(url: &str) -> reqwest::Result<Bytes> {
-    let client = reqwest::Client::new();
-    let response = client.get(url).send().await?;
+    let client = network::client();
+    let response = client.get(mirrors::mirrored(url)).send().await?;
     Ok(response.bytes().await?)
 }
 
@@ -113,27 +222,32 @@ pub fn hash_bytes(bytes: &Bytes) -> String {
     hasher.result_str()
 }
 
-/// Validates that the `path` exists and that the hash of it matches `valid_hash`
-//TODO: Use this when a `strict` setting is enabled.
-pub fn validate_file_hash(path: &Path, valid_hash: &str) -> bool {
-    if !path.exists() {
-        return false;
-    }
-    let result = read_bytes_from_file(path);
-    if let Ok(bytes) = result {
-        let valid = validate_hash(&bytes, &valid_hash);
-        info!("REMOVEME: Is file valid: {}", valid);
-        valid
-    } else {
-        false
+/// Validates that the sha256 hash of `bytes` matches the `valid_hash`
+pub fn validate_sha256_hash(bytes: &Bytes, valid_hash: &str) -> bool {
+    let mut hasher = Sha256::new();
+    hasher.input(bytes);
+    hasher.result_str() == valid_hash
+}
+
+/// Whether the file at `path` can be reused as the file with `valid_hash` and `size`.
+pub fn is_file_valid(path: &Path, valid_hash: &str, size: u64, verification: Verification) -> bool {
+    match verification {
+        Verification::Strict => {
+            hash_cache::fresh_file_sha1(path).map_or(false, |sha1| sha1 == valid_hash)
+        }
+        Verification::Incremental => has_size(path, size) && validate_file_hash(path, valid_hash),
+        Verification::Fast => has_size(path, size),
     }
 }
 
-/// Reads and returns bytes from the file specified in `path`
-fn read_bytes_from_file(path: &Path) -> io::Result<Bytes> {
-    let mut file = File::open(&path)?;
-    let metadata = file.metadata()?;
-    let mut buffer = vec![0; metadata.len() as usize];
-    file.read(&mut buffer)?;
-    Ok(Bytes::from(buffer))
+fn has_size(path: &Path, size: u64) -> bool {
+    fs::metadata(path)
+        .map(|metadata| metadata.is_file() && metadata.len() == size)
+        .unwrap_or(false)
+}
+
+/// Validates that the `path` exists and that the hash of it matches `valid_hash`. Unchanged files are
+/// only hashed once, see `hash_cache`.
+pub fn validate_file_hash(path: &Path, valid_hash: &str) -> bool {
+    hash_cache::file_sha1(path).map_or(false, |sha1| sha1 == valid_hash)
 }
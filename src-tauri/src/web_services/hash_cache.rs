@@ -0,0 +1,148 @@
+//! Remembers the sha1 of files by their path, size and modification time, so verifying unchanged
+//! files doesn't read them again. The index is loaded on setup and kept at
+//! `${app_dir}/cache/file_hashes.json`. Filesystems with coarse modification times can miss a change
+//! that keeps the size and happens within the same tick.
+
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{Mutex, PoisonError},
+    time::UNIX_EPOCH,
+};
+
+use bytes::Bytes;
+use log::{debug, info, warn};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+use super::downloader::hash_bytes;
+
+/// What a cached hash is only valid for, a file with another size or modification time is hashed again.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+struct FileStamp {
+    size: u64,
+    /// Modification time since the unix epoch.
+    secs: u64,
+    nanos: u32,
+}
+
+impl FileStamp {
+    fn of(metadata: &fs::Metadata) -> Option<Self> {
+        let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+        Some(Self {
+            size: metadata.len(),
+            secs: modified.as_secs(),
+            nanos: modified.subsec_nanos(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+struct HashEntry {
+    #[serde(flatten)]
+    stamp: FileStamp,
+    sha1: String,
+}
+
+#[derive(Debug, Default)]
+struct HashCache {
+    /// Where the index is saved, None until it is loaded.
+    path: Option<PathBuf>,
+    entries: HashMap<PathBuf, HashEntry>,
+    /// Whether there are entries that aren't saved yet.
+    changed: bool,
+}
+
+static HASH_CACHE: Lazy<Mutex<HashCache>> = Lazy::new(|| Mutex::new(HashCache::default()));
+
+/// Loads the index at `path`, call on setup. A missing or unreadable index starts out empty.
+pub fn load(path: PathBuf) {
+    let entries = match fs::read(&path) {
+        Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|error| {
+            warn!("Discarding the unreadable hash cache: {}", error);
+            HashMap::new()
+        }),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => HashMap::new(),
+        Err(error) => {
+            warn!("Could not read the hash cache: {}", error);
+            HashMap::new()
+        }
+    };
+    info!("Loaded {} cached file hashes", entries.len());
+    let mut cache = HASH_CACHE.lock().unwrap_or_else(PoisonError::into_inner);
+    *cache = HashCache {
+        path: Some(path),
+        entries,
+        changed: false,
+    };
+}
+
+/// Saves the index if hashes were added since it was loaded, dropping the entries of deleted files.
+/// Call after operations that verify many files. Failures are only logged, the cache only saves time.
+pub fn save() {
+    let mut cache = HASH_CACHE.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Err(error) = write_cache(&mut cache) {
+        warn!("Could not save the hash cache: {}", error);
+    }
+}
+
+fn write_cache(cache: &mut HashCache) -> io::Result<()> {
+    let path = match &cache.path {
+        Some(path) if cache.changed => path.clone(),
+        _ => return Ok(()),
+    };
+    cache.entries.retain(|file, _| file.is_file());
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, serde_json::to_vec(&cache.entries)?)?;
+    cache.changed = false;
+    debug!("Saved {} cached file hashes", cache.entries.len());
+    Ok(())
+}
+
+/// The sha1 of the file at `path`, hashing it only if it changed since it was last hashed.
+pub fn file_sha1(path: &Path) -> io::Result<String> {
+    hash_file(path, true)
+}
+
+/// The sha1 of the file at `path`, always read from disk. Catches corruption that leaves the size and
+/// modification time alone, the result is cached for later calls to `file_sha1`.
+pub fn fresh_file_sha1(path: &Path) -> io::Result<String> {
+    hash_file(path, false)
+}
+
+fn hash_file(path: &Path, use_cached: bool) -> io::Result<String> {
+    let metadata = fs::metadata(path)?;
+    if !metadata.is_file() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is not a file", path.display()),
+        ));
+    }
+    let stamp = FileStamp::of(&metadata);
+    if let Some(stamp) = stamp.filter(|_| use_cached) {
+        let cache = HASH_CACHE.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(entry) = cache.entries.get(path) {
+            if entry.stamp == stamp {
+                return Ok(entry.sha1.clone());
+            }
+        }
+    }
+
+    let sha1 = hash_bytes(&Bytes::from(fs::read(path)?));
+    // Without a modification time a change couldn't be noticed, those files are always hashed.
+    if let Some(stamp) = stamp {
+        let mut cache = HASH_CACHE.lock().unwrap_or_else(PoisonError::into_inner);
+        cache.entries.insert(
+            path.into(),
+            HashEntry {
+                stamp,
+                sha1: sha1.clone(),
+            },
+        );
+        cache.changed = true;
+    }
+    Ok(sha1)
+}
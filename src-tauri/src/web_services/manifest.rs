@@ -1,2 +1,3 @@
 pub mod vanilla;
-pub mod forge;
\ No newline at end of file
+pub mod forge;
+pub mod adoptium;
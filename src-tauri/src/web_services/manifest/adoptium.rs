@@ -0,0 +1,22 @@
+use serde::Deserialize;
+
+/// A single release returned by the Adoptium `/assets/latest` endpoint.
+#[derive(Debug, Deserialize)]
+pub struct AdoptiumRelease {
+    pub binary: AdoptiumBinary,
+    pub release_name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AdoptiumBinary {
+    pub package: AdoptiumPackage,
+}
+
+/// The downloadable archive of a release, a `.tar.gz` on linux and macos and a `.zip` on windows.
+#[derive(Debug, Deserialize)]
+pub struct AdoptiumPackage {
+    pub name: String,
+    pub link: String,
+    /// Sha256 hash of the archive.
+    pub checksum: String,
+}
@@ -8,12 +8,12 @@ use indexmap::IndexMap;
 use log::{debug, warn, error};
 use serde::{
     de::{Error, SeqAccess, Visitor},
-    Deserialize, Deserializer,
+    Deserialize, Deserializer, Serialize,
 };
 
 use crate::{consts::VANILLA_ASSET_BASE_URL, web_services::downloader::Downloadable};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 /// The version metadata returned in the manifest request.
 pub struct VanillaManifestVersion {
     pub id: String,
@@ -28,6 +28,22 @@ pub struct VanillaManifestVersion {
     compliance_level: u32,
 }
 
+impl VanillaManifestVersion {
+    /// The entry for a version imported from disk, whose json has the hash `sha1`. Imported versions
+    /// can't be downloaded again so they have no url.
+    pub fn imported(version: &VanillaVersion, sha1: String) -> Self {
+        Self {
+            id: version.id.clone(),
+            version_type: version.version_type.clone(),
+            url: String::new(),
+            time: version.time.clone(),
+            release_time: version.release_time.clone(),
+            sha1,
+            compliance_level: version.compliance_level.unwrap_or_default(),
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 /// Struct holding everything returned in the vanilla manifest json.
 pub struct VanillaManifest {
@@ -52,7 +68,7 @@ where
     Ok(map)
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub enum RuleType {
     #[serde(rename = "features")]
     Features(HashMap<String, bool>),
@@ -60,14 +76,14 @@ pub enum RuleType {
     OperatingSystem(HashMap<String, String>),
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Rule {
     pub action: String,
     #[serde(flatten)]
     pub rule_type: Option<RuleType>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(untagged)]
 pub enum Argument {
     Arg(String),
@@ -145,7 +161,7 @@ impl DownloadMetadata {
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct Asset {
     path: String,
     hash: String,
@@ -170,6 +186,10 @@ impl Downloadable for Asset {
         &self.hash
     }
 
+    fn size(&self) -> u64 {
+        self.size as u64
+    }
+
     fn path(&self, base_dir: &Path) -> PathBuf {
         let first_two_chars = &self.hash.split_at(2);
         base_dir.join(format!("{}/{}", &first_two_chars.0, &self.hash))
@@ -180,6 +200,12 @@ impl Downloadable for Asset {
 pub struct AssetObject {
     #[serde(deserialize_with = "to_asset_vec")]
     pub objects: Vec<Asset>,
+    /// Versions older than 1.6 read assets from the `resources` dir inside the game directory.
+    #[serde(default)]
+    pub map_to_resources: bool,
+    /// Versions 1.6 to 1.7.2 read assets by path from `assets/virtual/<index id>`.
+    #[serde(default, rename = "virtual")]
+    pub is_virtual: bool,
 }
 
 fn to_asset_vec<'de, D>(deserializer: D) -> Result<Vec<Asset>, D::Error>
@@ -248,6 +274,10 @@ impl Downloadable for Artifact {
         &self.metadata.sha1
     }
 
+    fn size(&self) -> u64 {
+        self.metadata.size as u64
+    }
+
     fn path(&self, base_dir: &Path) -> PathBuf {
         base_dir.join(&self.path)
     }
@@ -272,6 +302,10 @@ impl Downloadable for DownloadableClassifier {
         self.classifier.hash()
     }
 
+    fn size(&self) -> u64 {
+        self.classifier.size()
+    }
+
     fn path(&self, base_dir: &Path) -> PathBuf {
         self.classifier.path(base_dir)
     }
@@ -299,6 +333,25 @@ pub struct Library {
 }
 
 impl Library {
+    /// Minecraft 1.19+ ships separate lwjgl natives for apple silicon, older versions only have x86_64 ones.
+    pub fn is_macos_arm64_natives(&self) -> bool {
+        self.name.ends_with(":natives-macos-arm64")
+    }
+
+    /// Minecraft 1.19+ also ships lwjgl natives for windows on arm, older versions only have x86 ones.
+    pub fn is_windows_arm64_natives(&self) -> bool {
+        self.name.ends_with(":natives-windows-arm64")
+    }
+
+    /// The artifact and every classifier of the library, for all systems.
+    pub fn artifacts(&self) -> Vec<&Artifact> {
+        let mut artifacts: Vec<&Artifact> = self.downloads.artifact.iter().collect();
+        if let Some(classifiers) = &self.downloads.classifiers {
+            artifacts.extend(classifiers.values());
+        }
+        artifacts
+    }
+
     pub fn determine_key_for_classifiers(&self) -> Option<String> {
         if let Some(map) = &self.natives {
             debug!("Has Some Natives: {:#?}", map);
@@ -310,7 +363,15 @@ impl Library {
                     "windows" => "windows",
                     _ => unreachable!("Unknown os key for classifiers: {}", os),
                 })?
-                .into(),
+                // Older versions ship 32 and 64 bit natives, e.g. `natives-windows-${arch}`.
+                .replace(
+                    "${arch}",
+                    if cfg!(target_pointer_width = "64") {
+                        "64"
+                    } else {
+                        "32"
+                    },
+                ),
             )
         } else {
             None
@@ -473,13 +534,13 @@ where
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 struct JavaRuntimeDownload {
     lzma: Option<DownloadMetadata>,
     raw: DownloadMetadata,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct JavaRuntimeFile {
     path: String,
     downloads: JavaRuntimeDownload,
@@ -500,6 +561,10 @@ impl Downloadable for JavaRuntimeFile {
         &self.downloads.raw.sha1
     }
 
+    fn size(&self) -> u64 {
+        self.downloads.raw.size as u64
+    }
+
     fn path(&self, base_dir: &Path) -> PathBuf {
         base_dir.join(&self.path)
     }
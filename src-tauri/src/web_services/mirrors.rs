@@ -0,0 +1,102 @@
+//! Mirrors for the Mojang download servers, for regions where the Mojang CDN is slow or unreachable.
+//! Official urls keep being used everywhere else, e.g. as cache keys and in lockfiles, and are only
+//! rewritten right before a request is made. Downloads are still checked against the hashes from
+//! the manifests, a mirror can't serve modified files.
+
+use std::sync::{PoisonError, RwLock};
+
+use log::info;
+use once_cell::sync::Lazy;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+
+/// Hosts of the version manifests, version jsons, asset indexes, game jars and java runtimes.
+const META_HOSTS: [&str; 4] = [
+    "piston-meta.mojang.com",
+    "launchermeta.mojang.com",
+    "piston-data.mojang.com",
+    "launcher.mojang.com",
+];
+const ASSETS_HOST: &str = "resources.download.minecraft.net";
+const LIBRARIES_HOST: &str = "libraries.minecraft.net";
+
+const BMCLAPI_URL: &str = "https://bmclapi2.bangbang93.com";
+
+/// Base urls that replace the Mojang servers, None uses the official server.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct MirrorSettings {
+    /// Replaces every `*.mojang.com` download server, they share their paths.
+    #[serde(default, rename = "metaUrl")]
+    pub meta_url: Option<String>,
+    /// Replaces `resources.download.minecraft.net`.
+    #[serde(default, rename = "assetsUrl")]
+    pub assets_url: Option<String>,
+    /// Replaces `libraries.minecraft.net`.
+    #[serde(default, rename = "librariesUrl")]
+    pub libraries_url: Option<String>,
+}
+
+impl MirrorSettings {
+    /// BMCLAPI mirrors all of the Mojang servers.
+    pub fn bmclapi() -> Self {
+        Self {
+            meta_url: Some(BMCLAPI_URL.into()),
+            assets_url: Some(format!("{}/assets", BMCLAPI_URL)),
+            libraries_url: Some(format!("{}/maven", BMCLAPI_URL)),
+        }
+    }
+
+    /// Returns the first base url that isn't an http or https url.
+    pub fn invalid_url(&self) -> Option<&str> {
+        [&self.meta_url, &self.assets_url, &self.libraries_url]
+            .into_iter()
+            .flatten()
+            .find(|url| {
+                Url::parse(url).map_or(true, |url| !matches!(url.scheme(), "http" | "https"))
+            })
+            .map(String::as_str)
+    }
+
+    fn base_url_for(&self, host: &str) -> Option<&str> {
+        if META_HOSTS.contains(&host) {
+            self.meta_url.as_deref()
+        } else if host == ASSETS_HOST {
+            self.assets_url.as_deref()
+        } else if host == LIBRARIES_HOST {
+            self.libraries_url.as_deref()
+        } else {
+            None
+        }
+    }
+}
+
+static MIRRORS: Lazy<RwLock<MirrorSettings>> = Lazy::new(|| RwLock::new(MirrorSettings::default()));
+
+/// Uses `settings` for every download from now on.
+pub fn apply(settings: &MirrorSettings) {
+    info!("Using download mirrors {:?}", settings);
+    *MIRRORS.write().unwrap_or_else(PoisonError::into_inner) = settings.clone();
+}
+
+/// The url to request `url` from, which is `url` itself unless its server is mirrored.
+pub fn mirrored(url: &str) -> String {
+    let parsed = match Url::parse(url) {
+        Ok(parsed) => parsed,
+        Err(_) => return url.into(),
+    };
+    let mirrors = MIRRORS.read().unwrap_or_else(PoisonError::into_inner);
+    match parsed
+        .host_str()
+        .and_then(|host| mirrors.base_url_for(host))
+    {
+        Some(base_url) => {
+            let mut mirrored = format!("{}{}", base_url.trim_end_matches('/'), parsed.path());
+            if let Some(query) = parsed.query() {
+                mirrored.push('?');
+                mirrored.push_str(query);
+            }
+            mirrored
+        }
+        None => url.into(),
+    }
+}
@@ -0,0 +1,113 @@
+//! Descriptions, changelogs and gallery images of Modrinth projects for the mod browser. Everything
+//! goes through the `ResponseCache`, so projects that were opened before can be shown offline.
+
+use std::path::PathBuf;
+
+use futures::future::join_all;
+use log::{debug, warn};
+use serde::Serialize;
+
+use crate::state::resource_manager::ManifestResult;
+
+use super::{
+    cache::{CacheSource, ResponseCache, MEDIA_TTL, MOD_DETAILS_TTL},
+    modrinth::{project_url, project_versions_url, ModrinthProject, ModrinthVersion},
+};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GalleryImage {
+    /// The cached image.
+    pub path: PathBuf,
+    pub title: Option<String>,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Changelog {
+    #[serde(rename = "versionNumber")]
+    pub version_number: String,
+    /// RFC 3339 timestamp.
+    pub published: String,
+    /// Markdown.
+    pub changelog: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ModDetails {
+    pub id: String,
+    pub title: String,
+    pub description: String,
+    /// The long description, in markdown.
+    pub body: String,
+    /// The cached icon, None if the project has none or it couldn't be downloaded.
+    #[serde(rename = "iconPath")]
+    pub icon_path: Option<PathBuf>,
+    /// Images that couldn't be downloaded are left out.
+    pub gallery: Vec<GalleryImage>,
+    /// Newest first, versions without a changelog are left out.
+    pub changelogs: Vec<Changelog>,
+    /// True when Modrinth couldn't be reached and older cached details are shown.
+    pub stale: bool,
+}
+
+/// Returns the details of a Modrinth project, reusing cached details for `MOD_DETAILS_TTL`.
+pub async fn get_mod_details(cache: &ResponseCache, project: &str) -> ManifestResult<ModDetails> {
+    let (details, details_source): (ModrinthProject, CacheSource) = cache
+        .get_json(&project_url(project), MOD_DETAILS_TTL)
+        .await?;
+    let (versions, versions_source): (Vec<ModrinthVersion>, CacheSource) = cache
+        .get_json(&project_versions_url(project), MOD_DETAILS_TTL)
+        .await?;
+
+    let icon_path = match &details.icon_url {
+        Some(url) => cached_image(cache, url).await,
+        None => None,
+    };
+    let gallery = join_all(details.gallery.into_iter().map(|image| async move {
+        Some(GalleryImage {
+            path: cached_image(cache, &image.url).await?,
+            title: image.title,
+            description: image.description,
+        })
+    }))
+    .await
+    .into_iter()
+    .flatten()
+    .collect();
+    let changelogs = versions
+        .into_iter()
+        .filter_map(|version| {
+            Some(Changelog {
+                changelog: version
+                    .changelog
+                    .filter(|changelog| !changelog.is_empty())?,
+                version_number: version.version_number,
+                published: version.date_published,
+            })
+        })
+        .collect();
+
+    debug!("Loaded the details of Modrinth project {}", project);
+    Ok(ModDetails {
+        id: details.id,
+        title: details.title,
+        description: details.description,
+        body: details.body,
+        icon_path,
+        gallery,
+        changelogs,
+        stale: details_source == CacheSource::StaleCache
+            || versions_source == CacheSource::StaleCache,
+    })
+}
+
+/// Images are only decoration, a missing one shouldn't fail the whole page.
+async fn cached_image(cache: &ResponseCache, url: &str) -> Option<PathBuf> {
+    match cache.get_file(url, MEDIA_TTL).await {
+        Ok((path, _)) => Some(path),
+        Err(error) => {
+            warn!("Could not load image {}: {}", url, error);
+            None
+        }
+    }
+}
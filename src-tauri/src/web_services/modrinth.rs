@@ -0,0 +1,221 @@
+//! Calls to the Modrinth api, which needs neither a key nor an account.
+
+use std::collections::HashMap;
+
+use log::debug;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{
+    consts::{LAUNCHER_NAME, LAUNCHER_VERSION, MODRINTH_API_URL},
+    web_services::{
+        network,
+        rate_limit::{get_json_cached, send, Api, API_CACHE_TTL},
+    },
+};
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ModrinthHashes {
+    pub sha1: String,
+    #[serde(default)]
+    pub sha512: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ModrinthFile {
+    pub url: String,
+    pub filename: String,
+    pub primary: bool,
+    pub hashes: ModrinthHashes,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ModrinthVersion {
+    pub id: String,
+    pub project_id: String,
+    pub version_number: String,
+    #[serde(default)]
+    pub name: String,
+    /// `release`, `beta` or `alpha`.
+    #[serde(default)]
+    pub version_type: String,
+    #[serde(default)]
+    pub game_versions: Vec<String>,
+    #[serde(default)]
+    pub loaders: Vec<String>,
+    #[serde(default)]
+    pub date_published: String,
+    /// Markdown.
+    #[serde(default)]
+    pub changelog: Option<String>,
+    pub files: Vec<ModrinthFile>,
+}
+
+impl ModrinthVersion {
+    /// The file to install, versions can have extra files like source jars.
+    pub fn primary_file(&self) -> Option<&ModrinthFile> {
+        self.files
+            .iter()
+            .find(|file| file.primary)
+            .or_else(|| self.files.first())
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ModrinthGalleryImage {
+    pub url: String,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ModrinthProject {
+    pub id: String,
+    pub title: String,
+    /// A one line summary.
+    pub description: String,
+    /// The long description, in markdown.
+    #[serde(default)]
+    pub body: String,
+    #[serde(default)]
+    pub icon_url: Option<String>,
+    #[serde(default)]
+    pub gallery: Vec<ModrinthGalleryImage>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ModrinthSearchHit {
+    pub project_id: String,
+    pub title: String,
+    pub description: String,
+    pub author: String,
+    pub downloads: u64,
+    #[serde(default)]
+    pub icon_url: Option<String>,
+    #[serde(default)]
+    pub categories: Vec<String>,
+    pub date_modified: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ModrinthSearchResponse {
+    pub hits: Vec<ModrinthSearchHit>,
+    pub total_hits: u64,
+}
+
+/// Api url of a project, for requests that go through the `ResponseCache`.
+pub fn project_url(project: &str) -> String {
+    format!("{}/project/{}", MODRINTH_API_URL, project)
+}
+
+/// Api url of a project's versions, newest first, for requests that go through the `ResponseCache`.
+pub fn project_versions_url(project: &str) -> String {
+    format!("{}/project/{}/version", MODRINTH_API_URL, project)
+}
+
+/// Modrinth asks clients to identify themselves.
+fn client() -> reqwest::Result<reqwest::Client> {
+    network::client_builder()
+        .user_agent(format!("{}/{}", LAUNCHER_NAME, LAUNCHER_VERSION))
+        .build()
+}
+
+/// Lists the versions of a project newest first, only the ones for `loader` and the minecraft
+/// version if they are given.
+pub async fn project_versions(
+    project: &str,
+    loader: Option<&str>,
+    game_version: Option<&str>,
+) -> reqwest::Result<Vec<ModrinthVersion>> {
+    let mut query = Vec::new();
+    if let Some(loader) = loader {
+        query.push(("loaders", format!("[\"{}\"]", loader)));
+    }
+    if let Some(game_version) = game_version {
+        query.push(("game_versions", format!("[\"{}\"]", game_version)));
+    }
+    let request = client()?.get(project_versions_url(project)).query(&query);
+    get_json_cached(Api::Modrinth, request, API_CACHE_TTL).await
+}
+
+/// Returns the newest version of a project for `loader` and the minecraft version, None if there
+/// is no such version.
+pub async fn latest_version(
+    project: &str,
+    loader: &str,
+    game_version: &str,
+) -> reqwest::Result<Option<ModrinthVersion>> {
+    let versions = project_versions(project, Some(loader), Some(game_version)).await?;
+    debug!(
+        "Found {} versions of {} for {} {}",
+        versions.len(),
+        project,
+        loader,
+        game_version
+    );
+    // Versions are listed newest first.
+    Ok(versions.into_iter().next())
+}
+
+/// Searches projects. `facets` are ANDed groups of ORed filters like `versions:1.20.1`, `index` is the
+/// sort order.
+pub async fn search(
+    query: &str,
+    facets: &[Vec<String>],
+    index: &str,
+    offset: u32,
+    limit: u32,
+) -> reqwest::Result<ModrinthSearchResponse> {
+    let facets = json!(facets).to_string();
+    let offset = offset.to_string();
+    let limit = limit.to_string();
+    let request = client()?
+        .get(format!("{}/search", MODRINTH_API_URL))
+        .query(&[
+            ("query", query),
+            ("facets", facets.as_str()),
+            ("index", index),
+            ("offset", offset.as_str()),
+            ("limit", limit.as_str()),
+        ]);
+    get_json_cached(Api::Modrinth, request, API_CACHE_TTL).await
+}
+
+/// Returns the version a file belongs to, None if the file isn't on Modrinth.
+pub async fn version_from_hash(sha1: &str) -> reqwest::Result<Option<ModrinthVersion>> {
+    let request = client()?
+        .get(format!("{}/version_file/{}", MODRINTH_API_URL, sha1))
+        .query(&[("algorithm", "sha1")]);
+    let response = send(Api::Modrinth, request).await?;
+    if response.status() == StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    Ok(Some(response.error_for_status()?.json().await?))
+}
+
+/// Looks up several files at once, returns the versions of the ones that are on Modrinth by their sha1.
+pub async fn versions_from_hashes(
+    hashes: &[String],
+) -> reqwest::Result<HashMap<String, ModrinthVersion>> {
+    if hashes.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let request = client()?
+        .post(format!("{}/version_files", MODRINTH_API_URL))
+        .json(&json!({ "hashes": hashes, "algorithm": "sha1" }));
+    send(Api::Modrinth, request)
+        .await?
+        .error_for_status()?
+        .json()
+        .await
+}
+
+/// Returns the project of a file hosted on the Modrinth cdn, whose urls look like
+/// `https://cdn.modrinth.com/data/<project>/versions/<version>/<file name>`.
+pub fn project_from_cdn_url(url: &str) -> Option<&str> {
+    let path = url.strip_prefix("https://cdn.modrinth.com/data/")?;
+    path.split('/').next().filter(|project| !project.is_empty())
+}
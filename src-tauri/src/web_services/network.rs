@@ -0,0 +1,231 @@
+//! Network configuration shared by every request the launcher makes: a proxy, optionally with
+//! credentials, and extra root certificates for networks that intercept TLS. Requests should get their
+//! client through `client` or `client_builder` so the configuration applies to them.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{PoisonError, RwLock},
+};
+
+use log::info;
+use once_cell::sync::Lazy;
+use reqwest::{Certificate, Proxy};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+const PEM_CERTIFICATE_START: &str = "-----BEGIN CERTIFICATE-----";
+const PEM_CERTIFICATE_END: &str = "-----END CERTIFICATE-----";
+
+pub type NetworkResult<T> = Result<T, NetworkError>;
+
+#[derive(Debug, Error)]
+pub enum NetworkError {
+    #[error("Invalid proxy url {0}, only http and https proxies are supported")]
+    InvalidProxy(String),
+    #[error("Could not read the certificate {path}: {source}")]
+    CertificateReadError { path: PathBuf, source: io::Error },
+    #[error("{0} does not contain a valid PEM or DER certificate")]
+    InvalidCertificate(PathBuf),
+    #[error("Request failed: {0}")]
+    RequestError(#[from] reqwest::Error),
+    #[error("Could not save the settings: {0}")]
+    SettingsWriteError(#[from] io::Error),
+}
+
+impl NetworkError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            NetworkError::InvalidProxy(_) => "invalid_proxy",
+            NetworkError::CertificateReadError { .. } => "filesystem_error",
+            NetworkError::InvalidCertificate(_) => "invalid_certificate",
+            NetworkError::RequestError(_) => "http_error",
+            NetworkError::SettingsWriteError(_) => "filesystem_error",
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct NetworkErrorPayload {
+    code: &'static str,
+    message: String,
+}
+
+impl Serialize for NetworkError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        NetworkErrorPayload {
+            code: self.code(),
+            message: self.to_string(),
+        }
+        .serialize(serializer)
+    }
+}
+
+/// How the launcher reaches the internet. The proxy password is never sent to the frontend, nor
+/// written to the settings, it is kept in `secret_storage`.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct NetworkSettings {
+    /// Proxy every request goes through, e.g. `http://proxy.example.com:3128`.
+    #[serde(default, rename = "proxyUrl")]
+    pub proxy_url: Option<String>,
+    #[serde(default, rename = "proxyUsername")]
+    pub proxy_username: Option<String>,
+    /// Only read from settings of earlier versions, which stored the password in plaintext.
+    #[serde(default, rename = "proxyPassword", skip_serializing)]
+    pub proxy_password: Option<String>,
+    /// Whether there is a proxy password in `secret_storage`.
+    #[serde(default, rename = "hasProxyPassword")]
+    pub has_proxy_password: bool,
+    /// PEM or DER files with root certificates trusted in addition to the system ones. A PEM file can
+    /// hold several certificates.
+    #[serde(default, rename = "caCertificates")]
+    pub ca_certificates: Vec<PathBuf>,
+}
+
+/// What the frontend is told about the network settings.
+#[derive(Debug, Serialize)]
+pub struct NetworkStatus {
+    #[serde(rename = "proxyUrl")]
+    pub proxy_url: Option<String>,
+    #[serde(rename = "proxyUsername")]
+    pub proxy_username: Option<String>,
+    #[serde(rename = "hasProxyPassword")]
+    pub has_proxy_password: bool,
+    #[serde(rename = "caCertificates")]
+    pub ca_certificates: Vec<PathBuf>,
+}
+
+impl NetworkSettings {
+    pub fn status(&self) -> NetworkStatus {
+        NetworkStatus {
+            proxy_url: self.proxy_url.clone(),
+            proxy_username: self.proxy_username.clone(),
+            has_proxy_password: self.proxy_password.is_some(),
+            ca_certificates: self.ca_certificates.clone(),
+        }
+    }
+
+    fn proxy(&self) -> NetworkResult<Option<Proxy>> {
+        let proxy_url = match &self.proxy_url {
+            Some(proxy_url) => proxy_url,
+            None => return Ok(None),
+        };
+        // Socks proxies need a reqwest feature that isn't enabled.
+        if !proxy_url.starts_with("http://") && !proxy_url.starts_with("https://") {
+            return Err(NetworkError::InvalidProxy(proxy_url.clone()));
+        }
+        let proxy =
+            Proxy::all(proxy_url).map_err(|_| NetworkError::InvalidProxy(proxy_url.clone()))?;
+        Ok(Some(match &self.proxy_username {
+            Some(username) => {
+                proxy.basic_auth(username, self.proxy_password.as_deref().unwrap_or_default())
+            }
+            None => proxy,
+        }))
+    }
+
+    fn certificates(&self) -> NetworkResult<Vec<Certificate>> {
+        let mut certificates = Vec::new();
+        for path in &self.ca_certificates {
+            certificates.extend(read_certificates(path)?);
+        }
+        Ok(certificates)
+    }
+}
+
+/// Reads every certificate in a PEM bundle, or the single certificate of a DER file.
+fn read_certificates(path: &Path) -> NetworkResult<Vec<Certificate>> {
+    let bytes = fs::read(path).map_err(|source| NetworkError::CertificateReadError {
+        path: path.into(),
+        source,
+    })?;
+    let text = String::from_utf8_lossy(&bytes);
+    if !text.contains(PEM_CERTIFICATE_START) {
+        return Certificate::from_der(&bytes)
+            .map(|certificate| vec![certificate])
+            .map_err(|_| NetworkError::InvalidCertificate(path.into()));
+    }
+
+    let mut certificates = Vec::new();
+    for block in text.split(PEM_CERTIFICATE_START).skip(1) {
+        let body = block.split(PEM_CERTIFICATE_END).next().unwrap_or_default();
+        let pem = format!("{}{}{}\n", PEM_CERTIFICATE_START, body, PEM_CERTIFICATE_END);
+        certificates.push(
+            Certificate::from_pem(pem.as_bytes())
+                .map_err(|_| NetworkError::InvalidCertificate(path.into()))?,
+        );
+    }
+    Ok(certificates)
+}
+
+/// The applied settings, kept apart from the client so clients with other options can be built.
+struct NetworkConfig {
+    proxy: Option<Proxy>,
+    certificates: Vec<Certificate>,
+    client: reqwest::Client,
+}
+
+static NETWORK_CONFIG: Lazy<RwLock<NetworkConfig>> = Lazy::new(|| {
+    RwLock::new(NetworkConfig {
+        proxy: None,
+        certificates: Vec::new(),
+        client: reqwest::Client::new(),
+    })
+});
+
+fn builder_for(proxy: &Option<Proxy>, certificates: &[Certificate]) -> reqwest::ClientBuilder {
+    let mut builder = reqwest::Client::builder();
+    if let Some(proxy) = proxy {
+        builder = builder.proxy(proxy.clone());
+    }
+    for certificate in certificates {
+        builder = builder.add_root_certificate(certificate.clone());
+    }
+    builder
+}
+
+/// Checks `settings` and uses them for every request from now on. The previous settings stay in use
+/// when they are invalid.
+pub fn apply(settings: &NetworkSettings) -> NetworkResult<()> {
+    let proxy = settings.proxy()?;
+    let certificates = settings.certificates()?;
+    let client = builder_for(&proxy, &certificates).build()?;
+    info!(
+        "Using {} with {} extra root certificates",
+        settings
+            .proxy_url
+            .as_deref()
+            .map_or("no proxy".to_string(), |url| format!("proxy {}", url)),
+        certificates.len()
+    );
+
+    // Nothing panics while holding the lock, a poisoned lock still holds a usable config.
+    let mut config = NETWORK_CONFIG
+        .write()
+        .unwrap_or_else(PoisonError::into_inner);
+    *config = NetworkConfig {
+        proxy,
+        certificates,
+        client,
+    };
+    Ok(())
+}
+
+/// A builder with the network settings applied, for clients that need other options like a user agent.
+pub fn client_builder() -> reqwest::ClientBuilder {
+    let config = NETWORK_CONFIG
+        .read()
+        .unwrap_or_else(PoisonError::into_inner);
+    builder_for(&config.proxy, &config.certificates)
+}
+
+/// The client for requests to anywhere. Clients share their connection pool, this is cheap to call.
+pub fn client() -> reqwest::Client {
+    let config = NETWORK_CONFIG
+        .read()
+        .unwrap_or_else(PoisonError::into_inner);
+    config.client.clone()
+}
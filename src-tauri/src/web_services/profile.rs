@@ -0,0 +1,141 @@
+//! Minecraft Services calls for changing the name of a Microsoft account's profile.
+
+use log::{debug, info};
+use reqwest::{RequestBuilder, StatusCode};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    consts::{MINECRAFT_NAME_CHANGE_URL, MINECRAFT_PROFILE_URL},
+    state::account_manager::Account,
+    web_services::{
+        authentication::{AuthResult, AuthenticationError},
+        network,
+        rate_limit::{send, Api},
+    },
+};
+
+/// Whether the account can change its name, names can only be changed every 30 days.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct NameChangeEligibility {
+    #[serde(rename = "nameChangeAllowed")]
+    pub allowed: bool,
+    /// When the name was changed last, None if it never was.
+    #[serde(rename = "changedAt", default)]
+    pub changed_at: Option<String>,
+    #[serde(rename = "createdAt", default)]
+    pub created_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum NameAvailability {
+    #[serde(rename(deserialize = "AVAILABLE"))]
+    Available,
+    /// Another player has the name.
+    #[serde(rename(deserialize = "DUPLICATE"))]
+    Duplicate,
+    /// The name is blocked, e.g. because of profanity.
+    #[serde(rename(deserialize = "NOT_ALLOWED"))]
+    NotAllowed,
+}
+
+#[derive(Debug, Deserialize)]
+struct NameAvailabilityResponse {
+    status: NameAvailability,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChangedProfile {
+    name: String,
+}
+
+fn name_change_error(error: &str, error_message: &str) -> AuthenticationError {
+    AuthenticationError::MinecraftProfileError {
+        error: error.into(),
+        error_message: error_message.into(),
+    }
+}
+
+/// Only Microsoft accounts have a profile on Minecraft Services.
+fn ensure_microsoft_account(account: &Account) -> AuthResult<()> {
+    if account.offline || account.auth_server.is_some() {
+        return Err(name_change_error(
+            "UnsupportedAccount",
+            "Only Microsoft accounts can change their name in the launcher",
+        ));
+    }
+    Ok(())
+}
+
+/// Names are 3 to 16 characters of letters, digits and underscores.
+pub fn is_valid_name(name: &str) -> bool {
+    (3..=16).contains(&name.len()) && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn authorized(request: RequestBuilder, account: &Account) -> RequestBuilder {
+    request.header("Accept", "application/json").header(
+        "Authorization",
+        format!("Bearer {}", account.minecraft_access_token),
+    )
+}
+
+pub async fn get_name_change_eligibility(account: &Account) -> AuthResult<NameChangeEligibility> {
+    ensure_microsoft_account(account)?;
+    let request = authorized(network::client().get(MINECRAFT_NAME_CHANGE_URL), account);
+    let response = send(Api::MinecraftServices, request).await?;
+    if !response.status().is_success() {
+        return Err(AuthenticationError::HttpResponseError(response.status()));
+    }
+    Ok(response.json().await?)
+}
+
+pub async fn check_name_availability(
+    account: &Account,
+    name: &str,
+) -> AuthResult<NameAvailability> {
+    ensure_microsoft_account(account)?;
+    // The api rejects invalid names with an error instead of a status.
+    if !is_valid_name(name) {
+        return Ok(NameAvailability::NotAllowed);
+    }
+    let url = format!("{}/name/{}/available", MINECRAFT_PROFILE_URL, name);
+    let request = authorized(network::client().get(url), account);
+    let response = send(Api::MinecraftServices, request).await?;
+    if !response.status().is_success() {
+        return Err(AuthenticationError::HttpResponseError(response.status()));
+    }
+    let availability: NameAvailabilityResponse = response.json().await?;
+    debug!("Name {} is {:?}", name, availability.status);
+    Ok(availability.status)
+}
+
+/// Changes the profile name of the account and returns the new name.
+pub async fn change_name(account: &Account, name: &str) -> AuthResult<String> {
+    ensure_microsoft_account(account)?;
+    if !is_valid_name(name) {
+        return Err(name_change_error(
+            "InvalidName",
+            "Names are 3 to 16 letters, digits or underscores",
+        ));
+    }
+    let url = format!("{}/name/{}", MINECRAFT_PROFILE_URL, name);
+    let request = authorized(network::client().put(url), account);
+    let response = send(Api::MinecraftServices, request).await?;
+    match response.status() {
+        status if status.is_success() => {
+            let profile: ChangedProfile = response.json().await?;
+            info!("Changed the name of {} to {}", account.uuid, profile.name);
+            Ok(profile.name)
+        }
+        StatusCode::BAD_REQUEST => Err(name_change_error("InvalidName", "The name is not valid")),
+        StatusCode::FORBIDDEN => Err(name_change_error(
+            "NameUnavailable",
+            "The name is taken or the name was changed in the last 30 days",
+        )),
+        StatusCode::TOO_MANY_REQUESTS => Err(name_change_error(
+            "TooManyRequests",
+            "Too many name changes were attempted, try again later",
+        )),
+        status => Err(AuthenticationError::HttpResponseError(status)),
+    }
+}
@@ -0,0 +1,226 @@
+//! Rate limits and a short lived response cache for the third party apis, so bulk operations like
+//! update checks don't get the launcher throttled. Requests to Modrinth, CurseForge and Minecraft
+//! Services should be sent through `send`, or `get_json_cached` for GETs whose answer can be reused.
+
+use std::{
+    any::Any,
+    collections::HashMap,
+    sync::{Arc, Mutex, PoisonError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use log::{debug, warn};
+use once_cell::sync::Lazy;
+use reqwest::{RequestBuilder, Response, StatusCode};
+use serde::de::DeserializeOwned;
+
+use super::downloader::run_blocking;
+
+/// How long `get_json_cached` reuses responses, long enough to cover one bulk operation.
+pub const API_CACHE_TTL: Duration = Duration::from_secs(5 * 60);
+/// Least recently fetched responses are dropped beyond this.
+const CACHE_CAPACITY: usize = 256;
+/// How often a request answered with 429 is sent again.
+const MAX_RETRIES: u32 = 3;
+/// Used when a 429 doesn't say how long to wait.
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Api {
+    Modrinth,
+    CurseForge,
+    MinecraftServices,
+}
+
+impl Api {
+    /// Modrinth documents 300 requests a minute, the others are kept well below what they tolerate.
+    fn requests_per_minute(self) -> f64 {
+        match self {
+            Api::Modrinth => 300.0,
+            Api::CurseForge => 120.0,
+            Api::MinecraftServices => 60.0,
+        }
+    }
+
+    /// Requests that can be sent at once before the rate applies.
+    fn burst(self) -> f64 {
+        match self {
+            Api::Modrinth => 20.0,
+            Api::CurseForge => 10.0,
+            Api::MinecraftServices => 5.0,
+        }
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    refilled: Instant,
+    /// Set when the api said it doesn't want requests until then.
+    blocked_until: Option<Instant>,
+}
+
+static BUCKETS: Lazy<Mutex<HashMap<Api, Bucket>>> = Lazy::new(Default::default);
+
+struct CachedResponse {
+    fetched: Instant,
+    value: Arc<dyn Any + Send + Sync>,
+}
+
+static RESPONSES: Lazy<Mutex<HashMap<String, CachedResponse>>> = Lazy::new(Default::default);
+
+/// Takes a token from the api's bucket and returns how long to wait before sending. Tokens can go
+/// negative, later requests then wait behind the earlier ones.
+fn reserve(api: Api) -> Duration {
+    let mut buckets = BUCKETS.lock().unwrap_or_else(PoisonError::into_inner);
+    let now = Instant::now();
+    let per_second = api.requests_per_minute() / 60.0;
+    let bucket = buckets.entry(api).or_insert_with(|| Bucket {
+        tokens: api.burst(),
+        refilled: now,
+        blocked_until: None,
+    });
+    let elapsed = now.duration_since(bucket.refilled).as_secs_f64();
+    bucket.tokens = (bucket.tokens + elapsed * per_second).min(api.burst());
+    bucket.refilled = now;
+    bucket.tokens -= 1.0;
+
+    let wait = if bucket.tokens >= 0.0 {
+        Duration::ZERO
+    } else {
+        Duration::from_secs_f64(-bucket.tokens / per_second)
+    };
+    let blocked = bucket
+        .blocked_until
+        .map(|until| until.saturating_duration_since(now))
+        .unwrap_or_default();
+    wait.max(blocked)
+}
+
+fn block(api: Api, duration: Duration) {
+    let mut buckets = BUCKETS.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some(bucket) = buckets.get_mut(&api) {
+        let until = Instant::now() + duration;
+        bucket.blocked_until = Some(
+            bucket
+                .blocked_until
+                .map_or(until, |blocked| blocked.max(until)),
+        );
+    }
+}
+
+fn header_seconds(response: &Response, name: &str) -> Option<Duration> {
+    let seconds = response
+        .headers()
+        .get(name)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+    Some(Duration::from_secs(seconds))
+}
+
+/// Pauses the api when it said it is out of requests.
+fn record_limits(api: Api, response: &Response) {
+    if response.status() == StatusCode::TOO_MANY_REQUESTS {
+        let retry_after = header_seconds(response, "retry-after").unwrap_or(DEFAULT_RETRY_AFTER);
+        block(api, retry_after);
+        return;
+    }
+    // Modrinth says how many requests are left in the current window.
+    let remaining = response
+        .headers()
+        .get("x-ratelimit-remaining")
+        .and_then(|remaining| remaining.to_str().ok());
+    if remaining == Some("0") {
+        if let Some(reset) = header_seconds(response, "x-ratelimit-reset") {
+            debug!("{:?} rate limit used up, pausing for {:?}", api, reset);
+            block(api, reset);
+        }
+    }
+}
+
+async fn pause(duration: Duration) {
+    if duration > Duration::ZERO {
+        let _ = run_blocking(move || thread::sleep(duration)).await;
+    }
+}
+
+/// Sends the request once the api's rate limit allows it. Requests the api answered with 429 are sent
+/// again after the time it asked for, the last 429 is returned like any other response.
+pub async fn send(api: Api, request: RequestBuilder) -> reqwest::Result<Response> {
+    let mut request = request;
+    let mut attempt = 0;
+    loop {
+        // Requests with streaming bodies can't be cloned and aren't retried.
+        let retry = request.try_clone();
+        pause(reserve(api)).await;
+        let response = request.send().await?;
+        record_limits(api, &response);
+        if response.status() != StatusCode::TOO_MANY_REQUESTS || attempt == MAX_RETRIES {
+            return Ok(response);
+        }
+        request = match retry {
+            Some(retry) => retry,
+            None => return Ok(response),
+        };
+        attempt += 1;
+        warn!(
+            "{:?} is rate limiting the launcher, retrying {} ({}/{})",
+            api,
+            response.url(),
+            attempt,
+            MAX_RETRIES
+        );
+    }
+}
+
+/// Sends a GET request and parses the json response, reusing the response to the same url for `ttl`.
+/// Only successful responses are cached.
+pub async fn get_json_cached<T>(
+    api: Api,
+    request: RequestBuilder,
+    ttl: Duration,
+) -> reqwest::Result<T>
+where
+    T: DeserializeOwned + Clone + Send + Sync + 'static,
+{
+    let key = request
+        .try_clone()
+        .and_then(|request| request.build().ok())
+        .map(|request| request.url().to_string());
+    if let Some(key) = &key {
+        let responses = RESPONSES.lock().unwrap_or_else(PoisonError::into_inner);
+        let cached = responses
+            .get(key)
+            .filter(|cached| cached.fetched.elapsed() < ttl)
+            .and_then(|cached| cached.value.downcast_ref::<T>());
+        if let Some(value) = cached {
+            debug!("Using cached api response for {}", key);
+            return Ok(value.clone());
+        }
+    }
+
+    let value: T = send(api, request).await?.error_for_status()?.json().await?;
+    if let Some(key) = key {
+        let mut responses = RESPONSES.lock().unwrap_or_else(PoisonError::into_inner);
+        if responses.len() >= CACHE_CAPACITY && !responses.contains_key(&key) {
+            let oldest = responses
+                .iter()
+                .min_by_key(|(_, cached)| cached.fetched)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                responses.remove(&oldest);
+            }
+        }
+        responses.insert(
+            key,
+            CachedResponse {
+                fetched: Instant::now(),
+                value: Arc::new(value.clone()),
+            },
+        );
+    }
+    Ok(value)
+}
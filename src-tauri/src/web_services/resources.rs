@@ -2,28 +2,47 @@ use std::{
     collections::HashMap,
     env,
     fs::{self, File},
-    io::{self, Write},
-    path::{Path, PathBuf},
+    io::{self, Cursor, Write},
+    path::{Component, Path, PathBuf},
+    sync::Mutex,
     time::Instant,
 };
 
 use bytes::Bytes;
+use flate2::read::GzDecoder;
+use futures::{channel::mpsc, stream::FuturesUnordered, StreamExt};
 use log::{debug, error, info, warn};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
 use tauri::{AppHandle, Manager, State, Wry};
-use xmltree::{Element, XMLNode};
 use zip::ZipArchive;
 
 use crate::{
-    consts::{JAVA_VERSION_MANIFEST, LAUNCHER_NAME, LAUNCHER_VERSION},
+    consts::{ADOPTIUM_API_URL, CLIENT_ID, JAVA_VERSION_MANIFEST, LAUNCHER_NAME, LAUNCHER_VERSION},
+    events::{estimate_remaining, DownloadKind, EventBus, InstallPhase},
+    instance::{
+        backups::{create_backup, prune_backups, BackupReason},
+        lockfile::LockedFile,
+    },
     state::{
         account_manager::Account,
-        resource_manager::{ManifestError, ManifestResult, ResourceState}, instance_manager::{InstanceConfiguration, InstanceState},
+        instance_lock::InstanceOperation,
+        instance_manager::{InstanceConfiguration, InstanceState},
+        resource_manager::{
+            ManifestError, ManifestResult, PathContext, ResourceManager, ResourceState,
+            INSTANCE_CONFIG_SCHEMA_VERSION,
+        },
+        settings_manager::{BackupSettings, LaunchSettings, SettingsState},
+        task_manager::TaskHandle,
     },
     web_services::{
+        cache::{ResponseCache, API_RESPONSE_TTL, MANIFEST_TTL},
         downloader::{
-            buffered_download_stream, download_bytes_from_url, download_json_object, validate_hash,
-            DownloadError, Downloadable,
+            buffered_download_stream, download_bytes_from_url, is_file_valid, run_blocking,
+            validate_file_hash, validate_hash, validate_sha256_hash, DownloadError, DownloadResult,
+            Downloadable, Verification,
         },
+        manifest::adoptium::AdoptiumRelease,
         manifest::vanilla::{
             Argument, Artifact, AssetObject, DownloadableClassifier, JavaRuntimeFile,
             JavaRuntimeManifest, JavaRuntimeType, VanillaVersion,
@@ -32,65 +51,124 @@ use crate::{
 };
 
 use super::{
-    downloader::{hash_bytes, validate_file_hash},
+    hash_cache,
     manifest::vanilla::{
-        AssetIndex, DownloadMetadata, JarType, JavaManifest, JavaRuntime, LaunchArguments,
-        LaunchArguments113, Library, Logging, Rule, RuleType, VanillaManifestVersion, JavaVersion,
+        AssetIndex, DownloadMetadata, JarType, JavaManifest, JavaRuntime, JavaVersion,
+        LaunchArguments, LaunchArguments113, Library, Logging, Rule, RuleType,
+        VanillaManifestVersion,
     },
 };
 
+/// The launcher features that `features` rules in conditional arguments are checked against.
+#[derive(Debug, Default, Clone)]
+pub struct LaunchFeatures {
+    pub is_demo_user: bool,
+    pub has_custom_resolution: bool,
+    pub has_quick_plays_support: bool,
+}
+
+impl LaunchFeatures {
+    pub fn new(settings: &LaunchSettings, account: &Account) -> Self {
+        Self {
+            is_demo_user: account.demo,
+            has_custom_resolution: settings.resolution.is_some(),
+            // The quick play log is always written to the instance directory, see `QUICK_PLAY_PATH`.
+            has_quick_plays_support: true,
+        }
+    }
+
+    /// Features this launcher doesn't know about (e.g. `is_quick_play_singleplayer`) are never enabled.
+    fn is_enabled(&self, feature: &str) -> bool {
+        match feature {
+            "is_demo_user" => self.is_demo_user,
+            "has_custom_resolution" => self.has_custom_resolution,
+            "has_quick_plays_support" => self.has_quick_plays_support,
+            _ => false,
+        }
+    }
+}
+
+/// Where the game writes its quick play log, relative to the instance directory.
+const QUICK_PLAY_PATH: &str = "quickPlay/log.json";
+
 /// Checks if a single rule matches every case.
 /// Returns true when an allow rule matches or a disallow rule does not match.
-fn rule_matches(rule: &Rule) -> bool {
+fn rule_matches(rule: &Rule, features: &LaunchFeatures) -> bool {
     let rule_type = &rule.rule_type;
     if rule_type.is_none() {
         return match rule.action.as_str() {
             "allow" => true,
             "disallow" => false,
-            _ => unimplemented!("Unknwon rule action: {}", rule.action),
+            _ => {
+                warn!("Unknown rule action: {}", rule.action);
+                false
+            }
         };
     }
     match rule_type.as_ref().unwrap() {
-        RuleType::Features(_feature_rules) => {
-            error!("Implement feature rules for arguments");
-            // FIXME: Currently just skipping these
-            false
+        RuleType::Features(feature_rules) => {
+            // Every listed feature has to be in the expected state.
+            let rule_matches = feature_rules
+                .iter()
+                .all(|(feature, expected)| features.is_enabled(feature) == *expected);
+            match rule.action.as_str() {
+                "allow" => rule_matches,
+                "disallow" => !rule_matches,
+                _ => {
+                    warn!("Unknown rule action: {}", rule.action);
+                    false
+                }
+            }
         }
         RuleType::OperatingSystem(os_rules) => {
             // Check if all the rules match the current system.
-            let mut rule_matches = false;
-            for (key, value) in os_rules {
-                match key.as_str() {
-                    "name" => {
-                        let os_type = env::consts::OS;
-                        if value == os_type || (os_type == "macos" && value == "osx") {
-                            rule_matches = true;
-                        }
-                    }
-                    "arch" => {
-                        let os_arch = env::consts::ARCH;
-                        if value == os_arch || (value == "x86" && os_arch == "x86_64") {
-                            rule_matches = true;
-                        }
-                    }
-                    "version" => { /*TODO: Check version of os to make sure it matches*/ }
-                    _ => unimplemented!("Unknown rule map key: {}", key),
+            let rule_matches = os_rules.iter().all(|(key, value)| match key.as_str() {
+                "name" => {
+                    let os_type = env::consts::OS;
+                    value == os_type || (os_type == "macos" && value == "osx")
                 }
-            }
+                "arch" => {
+                    let os_arch = env::consts::ARCH;
+                    value == os_arch
+                        || (value == "x86" && os_arch == "x86_64")
+                        || (value == "arm64" && os_arch == "aarch64")
+                }
+                "version" => os_version_matches(value),
+                _ => {
+                    warn!("Unknown rule map key: {}", key);
+                    false
+                }
+            });
             // Check if we allow or disallow this downloadable
             match rule.action.as_str() {
                 "allow" => rule_matches,
                 "disallow" => !rule_matches,
-                _ => unimplemented!("Unknwon rule action: {}", rule.action),
+                _ => {
+                    warn!("Unknown rule action: {}", rule.action);
+                    false
+                }
             }
         }
     }
 }
 
-fn rules_match(rules: &[Rule]) -> bool {
+/// Matches the regex from a rule's `version` key against the current os version, which is formatted
+/// like java's `os.version` property (e.g. `10.0` on windows 10, `10.15.7` on macos).
+fn os_version_matches(version_regex: &str) -> bool {
+    let version = os_info::get().version().to_string();
+    match Regex::new(version_regex) {
+        Ok(regex) => regex.is_match(&version),
+        Err(error) => {
+            warn!("Invalid os version regex {}: {}", version_regex, error);
+            false
+        }
+    }
+}
+
+fn rules_match(rules: &[Rule], features: &LaunchFeatures) -> bool {
     let mut result = false;
     for rule in rules {
-        if rule_matches(rule) {
+        if rule_matches(rule, features) {
             result = true;
         } else {
             return false;
@@ -99,57 +177,124 @@ fn rules_match(rules: &[Rule]) -> bool {
     result
 }
 
-// HACK: This key generation to get the java version is not optimal and could
-//       use to be redone. This uses architecture to map to known java manifest versions.
-//       If the manifest ever changes this function most likely needs to be updated.
-fn determine_key_for_java_manifest<'a>(
-    java_version_manifest_map: &HashMap<String, JavaManifest>,
-) -> &'a str {
-    let os = env::consts::OS;
-    let key = if os == "macos" { "mac-os" } else { os };
+/// Arguments with `features` rules depend on launch options and are only resolved when launching.
+fn has_feature_rules(rules: &[Rule]) -> bool {
+    rules
+        .iter()
+        .any(|rule| matches!(rule.rule_type, Some(RuleType::Features(_))))
+}
 
-    if java_version_manifest_map.contains_key(key) {
-        return key;
+/// The resolution arguments versions <= 1.12 don't provide, matching the ones newer versions declare.
+pub fn custom_resolution_argument() -> Argument {
+    Argument::ConditionalArg {
+        rules: vec![Rule {
+            action: "allow".into(),
+            rule_type: Some(RuleType::Features(HashMap::from([(
+                "has_custom_resolution".into(),
+                true,
+            )]))),
+        }],
+        values: vec![
+            "--width".into(),
+            "${resolution_width}".into(),
+            "--height".into(),
+            "${resolution_height}".into(),
+        ],
     }
-    let architecture = env::consts::ARCH;
-    match key {
-        "linux" => {
-            if architecture == "x86" {
-                "linux-i386"
-            } else {
-                key
-            }
-        }
-        "mac-os" => {
-            if architecture == "arm" {
-                "mac-os-arm64"
-            } else {
-                key
-            }
-        }
-        "windows" => {
-            if architecture == "x86" {
-                "windows-x86"
-            } else if architecture == "x86_64" {
-                "windows-x64"
-            } else {
-                unreachable!("Unexpected windows architecture: {}", architecture)
+}
+
+/// The demo argument versions <= 1.12 don't provide, newer versions declare it with an `is_demo_user` rule.
+pub fn demo_argument() -> Argument {
+    Argument::ConditionalArg {
+        rules: vec![Rule {
+            action: "allow".into(),
+            rule_type: Some(RuleType::Features(HashMap::from([(
+                "is_demo_user".into(),
+                true,
+            )]))),
+        }],
+        values: vec!["--demo".into()],
+    }
+}
+
+/// Resolves the feature dependent arguments persisted in an instance's config for a launch.
+/// Account specific placeholders are left for `substitute_account_specific_arguments`.
+pub fn construct_feature_arguments(
+    feature_arguments: &[Argument],
+    features: &LaunchFeatures,
+    settings: &LaunchSettings,
+    instance_path: &Path,
+) -> Vec<String> {
+    let mut formatted_arguments = Vec::new();
+    for argument in feature_arguments {
+        let (rules, values) = match argument {
+            Argument::ConditionalArg { rules, values } => (rules, values),
+            Argument::Arg(value) => {
+                formatted_arguments.push(value.clone());
+                continue;
             }
+        };
+        if !rules_match(rules, features) {
+            continue;
         }
-        _ => {
-            unreachable!(
-                "Unknown java version os: {}. Expected `linux`, `mac-os` or `windows`",
-                key
-            )
+        for value in values {
+            let substituted = get_arg_substring(value).and_then(|substr| match substr {
+                "${resolution_width}" => settings
+                    .resolution
+                    .as_ref()
+                    .map(|resolution| value.replace(substr, &resolution.width.to_string())),
+                "${resolution_height}" => settings
+                    .resolution
+                    .as_ref()
+                    .map(|resolution| value.replace(substr, &resolution.height.to_string())),
+                "${quickPlayPath}" => Some(value.replace(
+                    substr,
+                    path_to_utf8_str(&instance_path.join(QUICK_PLAY_PATH)),
+                )),
+                _ => None,
+            });
+            formatted_arguments.push(substituted.unwrap_or_else(|| value.clone()));
         }
     }
+    formatted_arguments
 }
+
+// HACK: This key generation to get the java version is not optimal and could
+//       use to be redone. This uses architecture to map to known java manifest versions.
+//       If the manifest ever changes this function most likely needs to be updated.
+/// Returns None when mojang doesn't ship java runtimes for this platform.
+fn determine_key_for_java_manifest(architecture: &str) -> Option<&'static str> {
+    match (env::consts::OS, architecture) {
+        ("linux", "x86_64") => Some("linux"),
+        ("linux", "x86") => Some("linux-i386"),
+        // The manifest has separate entries for intel and apple silicon macs.
+        ("macos", "aarch64") => Some("mac-os-arm64"),
+        ("macos", _) => Some("mac-os"),
+        ("windows", "aarch64") => Some("windows-arm64"),
+        ("windows", "x86_64") => Some("windows-x64"),
+        ("windows", "x86") => Some("windows-x86"),
+        _ => None,
+    }
+}
+
+/// The manifest key of the x86_64 runtimes an arm64 system can run through emulation, Rosetta on
+/// macos and the x64 emulation of windows 11.
+fn emulated_java_manifest_key(manifest_key: &str) -> Option<&'static str> {
+    match manifest_key {
+        "mac-os-arm64" => Some("mac-os"),
+        "windows-arm64" => Some("windows-x64"),
+        _ => None,
+    }
+}
+
 struct LaunchArgumentPaths {
     logging: (String, PathBuf),
     library_paths: Vec<PathBuf>,
     instance_path: PathBuf,
     jar_path: PathBuf,
     asset_dir_path: PathBuf,
+    /// The directory legacy versions read assets from, see `download_assets`.
+    game_assets_path: PathBuf,
 }
 
 // TODO: Add -Xmx and -Xms arguments for memory
@@ -171,7 +316,7 @@ fn construct_jvm_arguments113(
             }
             // For conditional args, check their rules before adding to formatted_arguments vec
             Argument::ConditionalArg { rules, values } => {
-                if !rules_match(&rules) {
+                if !rules_match(&rules, &LaunchFeatures::default()) {
                     continue;
                 }
                 for value in values {
@@ -207,9 +352,10 @@ fn construct_arguments(
     mc_version: &VanillaManifestVersion,
     asset_index: &str,
     argument_paths: LaunchArgumentPaths,
-) -> Vec<String> {
+) -> (Vec<String>, Vec<Argument>) {
     // IDEA: Vec could be 'with_capacity' if we calculate capacity first.
     let mut formatted_arguments: Vec<String> = Vec::new();
+    let mut feature_arguments: Vec<Argument> = Vec::new();
     let mut game_args: Vec<Argument> = Vec::new();
 
     // Create game arguments from the launch arguments.
@@ -219,6 +365,8 @@ fn construct_arguments(
         // Versions <= 1.12  use a string of game arguments and do not provide any jvm arguments.
         LaunchArguments::LaunchArguments112(game_args) => {
             formatted_arguments.append(&mut construct_jvm_arguments112(&argument_paths));
+            feature_arguments.push(custom_resolution_argument());
+            feature_arguments.push(demo_argument());
             // Split game arg string on whitespace to get individual args
             game_args
                 .split_ascii_whitespace()
@@ -258,26 +406,24 @@ fn construct_arguments(
             }
             // For conditional args, check their rules before adding to formatted_arguments vec
             Argument::ConditionalArg { rules, values } => {
-                if !rules_match(&rules) {
-                    continue;
-                }
-                for value in values {
-                    let sub_arg = substitute_game_arguments(
-                        &value,
-                        &mc_version,
-                        asset_index,
-                        &argument_paths,
-                    );
-                    formatted_arguments.push(match sub_arg {
-                        Some(argument) => argument,
-                        None => value.into(),
+                let values = values.iter().map(|value| {
+                    substitute_game_arguments(&value, &mc_version, asset_index, &argument_paths)
+                        .unwrap_or_else(|| value.into())
+                });
+                // Persist feature dependent arguments so they can be resolved at launch.
+                if has_feature_rules(&rules) {
+                    feature_arguments.push(Argument::ConditionalArg {
+                        rules: rules.clone(),
+                        values: values.collect(),
                     });
+                } else if rules_match(&rules, &LaunchFeatures::default()) {
+                    formatted_arguments.extend(values);
                 }
             }
         }
     }
     println!("HERE: {:#?}", formatted_arguments);
-    formatted_arguments
+    (formatted_arguments, feature_arguments)
 }
 
 // Returns the substring inside the argument if it exists, otherwise None
@@ -295,10 +441,6 @@ fn get_arg_substring(arg: &str) -> Option<&str> {
 // Returns a string with the substituted value in the jvm argument or None if it doesn't apply.
 fn substitute_jvm_arguments(arg: &str, argument_paths: &LaunchArgumentPaths) -> Option<String> {
     let substring = get_arg_substring(arg);
-    let classpath_strs: Vec<&str> = (&argument_paths.library_paths)
-        .into_iter()
-        .map(|path| path_to_utf8_str(&path))
-        .collect();
 
     if let Some(substr) = substring {
         info!("Substituting {} for jvm arguments", &substr);
@@ -312,18 +454,13 @@ fn substitute_jvm_arguments(arg: &str, argument_paths: &LaunchArgumentPaths) ->
             )),
             "${launcher_name}" => Some(arg.replace(substr, LAUNCHER_NAME)),
             "${launcher_version}" => Some(arg.replace(substr, LAUNCHER_VERSION)),
-            // FIXME: Linux and Windows use different classpath serperators. Windows uses ';' and Linux uses ':'
             "${classpath}" => {
-                debug!("Vec: {:#?}", classpath_strs);
-                debug!("Classpath: {} ", classpath_strs.join(":"));
-                Some(arg.replace(
-                    substr,
-                    &format!(
-                        "{}:{}",
-                        classpath_strs.join(":"),
-                        path_to_utf8_str(&argument_paths.jar_path)
-                    ),
-                ))
+                let classpath = ClasspathBuilder::new()
+                    .entries(&argument_paths.library_paths)
+                    .entry(&argument_paths.jar_path)
+                    .build();
+                debug!("Classpath: {} ", classpath);
+                Some(arg.replace(substr, &classpath))
             }
             _ => None,
         }
@@ -332,6 +469,54 @@ fn substitute_jvm_arguments(arg: &str, argument_paths: &LaunchArgumentPaths) ->
     }
 }
 
+/// Separates classpath entries, windows uses ';' while linux and macos use ':'.
+#[cfg(target_os = "windows")]
+pub const CLASSPATH_SEPARATOR: char = ';';
+#[cfg(not(target_os = "windows"))]
+pub const CLASSPATH_SEPARATOR: char = ':';
+
+/// Joins library and jar paths into the value passed to `-cp`.
+#[derive(Debug)]
+pub struct ClasspathBuilder {
+    separator: char,
+    entries: Vec<String>,
+}
+
+impl ClasspathBuilder {
+    /// Creates a builder using the separator of the current platform.
+    pub fn new() -> Self {
+        Self::with_separator(CLASSPATH_SEPARATOR)
+    }
+
+    pub fn with_separator(separator: char) -> Self {
+        Self {
+            separator,
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn entry(mut self, path: &Path) -> Self {
+        self.entries.push(path_to_utf8_str(path).into());
+        self
+    }
+
+    pub fn entries<P: AsRef<Path>>(self, paths: &[P]) -> Self {
+        paths
+            .iter()
+            .fold(self, |builder, path| builder.entry(path.as_ref()))
+    }
+
+    pub fn build(&self) -> String {
+        self.entries.join(&self.separator.to_string())
+    }
+}
+
+impl Default for ClasspathBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 fn substitute_game_arguments(
     arg: &str,
     mc_version: &VanillaManifestVersion,
@@ -353,7 +538,9 @@ fn substitute_game_arguments(
                 &format!("{}", path_to_utf8_str(&argument_paths.asset_dir_path)),
             )),
             "${assets_index_name}" => Some(arg.replace(substr, &asset_index)),
-            "${user_type}" => Some(arg.replace(substr, "mojang")), // TODO: Unknown but hardcoded to "mojang" as thats what the gdlauncher example shows
+            "${game_assets}" => {
+                Some(arg.replace(substr, path_to_utf8_str(&argument_paths.game_assets_path)))
+            }
             "${version_type}" => Some(arg.replace(substr, &mc_version.version_type)),
             "${resolution_width}" => None, // TODO: Launcher option specific
             "${resolution_height}" => None, // TODO: Launcher option specific
@@ -379,8 +566,17 @@ pub fn substitute_account_specific_arguments(
             "${auth_access_token}" => {
                 Some(arg.replace(substr, &active_account.minecraft_access_token))
             }
-            "${clientid}" => None,  // FIXME: Unknown
-            "${auth_xuid}" => None, // FIXME: Unknown
+            // Microsoft accounts are "msa", "legacy" tells the game there is no online account.
+            "${user_type}" => Some(arg.replace(
+                substr,
+                if active_account.offline {
+                    "legacy"
+                } else {
+                    "msa"
+                },
+            )),
+            "${clientid}" => Some(arg.replace(substr, CLIENT_ID)),
+            "${auth_xuid}" => Some(arg.replace(substr, &active_account.xuid)),
             _ => None,
         }
     } else {
@@ -403,6 +599,30 @@ fn path_to_utf8_str(path: &Path) -> &str {
     }
 }
 
+/// The natives classifier of `library` for this os, if it has one.
+fn native_classifier(library: &Library) -> Option<DownloadableClassifier> {
+    let classifier_key = library.determine_key_for_classifiers()?;
+    // Classifiers could be "missing" if the wrong key is used. An error is logged and downloads continue
+    library.get_classifier(&classifier_key)
+}
+
+fn save_library<T>(libraries_dir: &Path, bytes: &Bytes, artifact: &T) -> DownloadResult<()>
+where
+    T: Downloadable,
+{
+    // FIXME: Removing file hashing makes the downloads MUCH faster. Only because of a couple slow hashes, upwards of 1s each
+    if !validate_hash(bytes, artifact.hash()) {
+        return Err(DownloadError::InvalidFileHashError {
+            url: artifact.url(),
+        });
+    }
+    debug!("Downloading library: {}", artifact.name());
+    let path = artifact.path(libraries_dir);
+    let mut file = File::create(&path)?;
+    file.write_all(bytes)?;
+    Ok(())
+}
+
 #[derive(Debug)]
 struct LibraryData {
     library_paths: Vec<PathBuf>,
@@ -412,6 +632,9 @@ struct LibraryData {
 async fn download_libraries(
     libraries_dir: &Path,
     libraries: &[Library],
+    verification: Verification,
+    task: Option<&TaskHandle>,
+    on_progress: impl Fn(usize, usize),
 ) -> ManifestResult<LibraryData> {
     info!("Downloading {} libraries...", libraries.len());
     if !libraries_dir.exists() {
@@ -430,13 +653,7 @@ async fn download_libraries(
             downloadables.push(artifact.to_owned());
         }
         // If there is a natives json entry with an applicable (os dependent) classifier, get and append it
-        let key = library.determine_key_for_classifiers();
-        if let Some(classifier_key) = key {
-            // Classifiers could be "missing" if the wrong key is used. An error is logged and downloads continue
-            let classifier = match library.get_classifier(&classifier_key) {
-                Some(classifier) => classifier,
-                None => continue,
-            };
+        if let Some(classifier) = native_classifier(library) {
             classifiers.push(classifier.clone());
             downloadables.push(classifier.classifier);
         }
@@ -444,19 +661,15 @@ async fn download_libraries(
 
     let start = Instant::now();
     // Perform one buffered download for all libraries, including classifiers
-    buffered_download_stream(&downloadables, &libraries_dir, |bytes, artifact| {
-        // FIXME: Removing file hashing makes the downloads MUCH faster. Only because of a couple slow hashes, upwards of 1s each
-        if !validate_hash(&bytes, &artifact.hash()) {
-            let err = format!("Error downloading {}, invalid hash.", &artifact.url());
-            error!("{}", err);
-            return Err(DownloadError::InvalidFileHashError(err));
-        }
-        debug!("Downloading library: {}", artifact.name());
-        let path = artifact.path(&libraries_dir);
-        let mut file = File::create(&path)?;
-        file.write_all(&bytes)?;
-        Ok(())
-    })
+    let save_dir = libraries_dir.to_path_buf();
+    buffered_download_stream(
+        &downloadables,
+        &libraries_dir,
+        verification,
+        task,
+        move |bytes, artifact| save_library(&save_dir, bytes, artifact),
+        on_progress,
+    )
     .await?;
     info!(
         "Successfully downloaded libraries in {}ms",
@@ -478,6 +691,7 @@ async fn download_game_jar(
     jar_type: JarType,
     download: &DownloadMetadata,
     version_id: &str,
+    verification: Verification,
 ) -> ManifestResult<PathBuf> {
     let jar_str = match jar_type {
         JarType::Client => "client",
@@ -489,20 +703,18 @@ async fn download_game_jar(
 
     let path = dir_path.join(format!("{}.jar", &jar_str));
     let valid_hash = download.hash();
-    // Check if the file exists and the hash matches the download's sha1.
-    if !validate_file_hash(&path, valid_hash) {
+    if !is_file_valid(&path, valid_hash, download.size() as u64, verification) {
         info!("Downloading {} {} jar", version_id, jar_str);
         let bytes = download_bytes_from_url(download.url()).await?;
         if !validate_hash(&bytes, valid_hash) {
-            let err = format!(
-                "Error downloading {} {} jar, invalid hash.",
-                version_id, jar_str
-            );
-            error!("{}", err);
-            return Err(ManifestError::InvalidFileDownload(err));
+            return Err(ManifestError::InvalidFileDownload {
+                url: download.url().into(),
+            });
         }
-        let mut file = File::create(&path)?;
-        file.write_all(&bytes)?;
+        let jar_path = path.clone();
+        run_blocking(move || fs::write(jar_path, bytes))
+            .await?
+            .with_path(&path)?;
     }
     Ok(path)
 }
@@ -511,11 +723,17 @@ async fn download_game_jar(
 //        The correct order is important since it will create dirs before creating files in those dirs.
 async fn download_java_from_runtime_manifest(
     java_dir: &Path,
+    cache: &ResponseCache,
     manifest: &JavaRuntime,
+    verification: Verification,
+    task: Option<&TaskHandle>,
+    on_progress: impl Fn(usize, usize),
 ) -> ManifestResult<PathBuf> {
     info!("Downloading java runtime manifset");
-    let version_manifest: JavaRuntimeManifest =
-        download_json_object(&manifest.manifest.url()).await?;
+    // The runtime manifest url contains its hash, so a cached copy never goes out of date.
+    let (version_manifest, _): (JavaRuntimeManifest, _) = cache
+        .get_json(manifest.manifest.url(), API_RESPONSE_TTL)
+        .await?;
     let base_path = &java_dir.join(&manifest.version.name);
 
     let mut files: Vec<JavaRuntimeFile> = Vec::new();
@@ -537,31 +755,51 @@ async fn download_java_from_runtime_manifest(
     // FIXME: Currently downloading `raw` files, switch to lzma and decompress locally.
     info!("Downloading all java files.");
     let start = Instant::now();
-    buffered_download_stream(&files, &base_path, |bytes, jrt| {
-        if !validate_hash(&bytes, &jrt.hash()) {
-            let err = format!("Error downloading {}, invalid hash.", &jrt.url());
-            error!("{}", err);
-            return Err(DownloadError::InvalidFileHashError(err));
-        }
-        let path = jrt.path(&base_path);
-        let mut file = File::create(&path)?;
-        // TODO: Change from target_os ="linux" to unix
-        #[cfg(target_os = "linux")]
-        {
-            use std::os::unix::prelude::PermissionsExt;
-
-            // Markt the file as executable on unix os's
-            if jrt.executable {
-                let mut permissions = file.metadata()?.permissions();
-                permissions.set_mode(0o775);
-                file.set_permissions(permissions)?;
+    let save_path = base_path.clone();
+    buffered_download_stream(
+        &files,
+        &base_path,
+        verification,
+        task,
+        move |bytes, jrt| {
+            if !validate_hash(&bytes, &jrt.hash()) {
+                return Err(DownloadError::InvalidFileHashError { url: jrt.url() });
             }
-        }
-        file.write_all(&bytes)?;
-        Ok(())
-    })
+            let path = jrt.path(&save_path);
+            let mut file = File::create(&path)?;
+            #[cfg(unix)]
+            {
+                use std::os::unix::prelude::PermissionsExt;
+
+                // Mark the file as executable on linux and macos
+                if jrt.executable {
+                    let mut permissions = file.metadata()?.permissions();
+                    permissions.set_mode(0o775);
+                    file.set_permissions(permissions)?;
+                }
+            }
+            file.write_all(&bytes)?;
+            Ok(())
+        },
+        on_progress,
+    )
     .await?;
     info!("Downloaded java in {}ms", start.elapsed().as_millis());
+    // Files that were already downloaded are skipped above, runtimes downloaded on macos before
+    // permissions were set there still need them.
+    #[cfg(unix)]
+    {
+        use std::os::unix::prelude::PermissionsExt;
+
+        for jrt in files.iter().filter(|jrt| jrt.executable) {
+            let path = jrt.path(&base_path);
+            let mut permissions = fs::metadata(&path)?.permissions();
+            if permissions.mode() & 0o111 == 0 {
+                permissions.set_mode(0o775);
+                fs::set_permissions(&path, permissions)?;
+            }
+        }
+    }
 
     // Finally create links
     for link in links {
@@ -577,268 +815,686 @@ async fn download_java_from_runtime_manifest(
                     from.display(),
                     to.display()
                 );
-                #[cfg(target_os = "linux")]
-                {
-                    use std::os::unix::fs::symlink;
-
-                    // Create symlink FROM "target" TO "path"
-                    symlink(from, to)?;
-                }
+                // Create symlink FROM "target" TO "path"
+                link_runtime_dir(&from, to)?;
             } else {
                 debug!(
                     "Creating hard link between {} and {}",
                     from.display(),
                     to.display()
                 );
-                // Create hard link FROM "target" TO "path"
-                fs::hard_link(from, to)?;
+                // Create hard link FROM "target" TO "path", filesystems without hard links get a copy.
+                if let Err(error) = fs::hard_link(&from, to) {
+                    debug!(
+                        "Could not hard link {}, copying it: {}",
+                        from.display(),
+                        error
+                    );
+                    fs::copy(&from, to)?;
+                }
             }
         }
     }
 
-    let java_path = base_path.join("bin/java");
+    // Macos runtimes are packaged as an app bundle.
+    let java_path = if cfg!(target_os = "macos") {
+        base_path.join("jre.bundle/Contents/Home/bin/java")
+    } else if cfg!(windows) {
+        base_path.join("bin/java.exe")
+    } else {
+        base_path.join("bin/java")
+    };
     info!("Using java path: {:?}", java_path);
     Ok(java_path)
 }
 
+#[cfg(unix)]
+fn link_runtime_dir(from: &Path, to: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(from, to)
+}
+
+/// Symlinks need developer mode or admin rights on windows, the directory is copied without them.
+#[cfg(windows)]
+fn link_runtime_dir(from: &Path, to: &Path) -> io::Result<()> {
+    if let Err(error) = std::os::windows::fs::symlink_dir(from, to) {
+        debug!(
+            "Could not symlink {}, copying it: {}",
+            from.display(),
+            error
+        );
+        crate::state::instance_manager::copy_entry(from, to)?;
+    }
+    Ok(())
+}
+
+/// The architecture java has to run as for a version. Apple silicon macs and arm windows devices run
+/// versions without arm64 natives on an x86_64 java through emulation, an arm64 jvm can't load their
+/// x86_64 natives.
+fn java_architecture(libraries: &[Library]) -> &'static str {
+    let architecture = env::consts::ARCH;
+    if architecture != "aarch64" {
+        return architecture;
+    }
+    let has_arm64_natives = match env::consts::OS {
+        "macos" => libraries.iter().any(Library::is_macos_arm64_natives),
+        "windows" => libraries.iter().any(Library::is_windows_arm64_natives),
+        _ => true,
+    };
+    if !has_arm64_natives {
+        info!("No arm64 natives available, using an x86_64 java through emulation");
+        return "x86_64";
+    }
+    architecture
+}
+
+/// The runtime a version needs. Versions 1.6.4 and older don't name one, they get java 8.
+fn required_java(java_version: Option<JavaVersion>) -> JavaVersion {
+    java_version.unwrap_or_else(|| JavaVersion {
+        component: "jre-legacy".into(),
+        major_version: 8,
+    })
+}
+
+/// Downloads the runtime `version_id` is installed with, or checks it with `verification` if it was
+/// already downloaded. Returns the path of its java executable.
+pub async fn download_version_java(
+    resource_manager: &ResourceManager,
+    version_id: &str,
+    verification: Verification,
+) -> ManifestResult<PathBuf> {
+    let version = resource_manager
+        .download_vanilla_version(version_id)
+        .await?;
+    let libraries = applicable_libraries(version.libraries);
+    download_java_version(
+        &resource_manager.java_dir(),
+        &resource_manager.response_cache(),
+        required_java(version.java_version),
+        java_architecture(&libraries),
+        verification,
+        None,
+        |_, _| {},
+    )
+    .await
+}
+
 async fn download_java_version(
     java_dir: &Path,
+    cache: &ResponseCache,
     java: JavaVersion,
+    architecture: &str,
+    verification: Verification,
+    task: Option<&TaskHandle>,
+    on_progress: impl Fn(usize, usize),
 ) -> ManifestResult<PathBuf> {
     info!("Downloading java version manifest");
-    let java_version_manifest: HashMap<String, JavaManifest> =
-        download_json_object(JAVA_VERSION_MANIFEST).await?;
-    let manifest_key = determine_key_for_java_manifest(&java_version_manifest);
+    let (java_version_manifest, _): (HashMap<String, JavaManifest>, _) =
+        cache.get_json(JAVA_VERSION_MANIFEST, MANIFEST_TTL).await?;
+    let mut manifest_key = determine_key_for_java_manifest(architecture);
+    // Not every runtime is built for arm64, fall back to running the x86_64 one through emulation.
+    if let Some(key) = manifest_key {
+        let has_runtime = java_version_manifest.get(key).map_or(false, |manifest| {
+            get_java_runtime(manifest, &java.component).is_some()
+        });
+        if !has_runtime {
+            if let Some(emulated_key) = emulated_java_manifest_key(key) {
+                info!(
+                    "No {} runtime for component {}, using {}",
+                    key, &java.component, emulated_key
+                );
+                manifest_key = Some(emulated_key);
+            }
+        }
+    }
 
-    let java_manifest = &java_version_manifest.get(manifest_key).unwrap();
-    let runtime_opt = match java.component.as_str() {
-        "java-runtime-alpha" => &java_manifest.java_runtime_alpha,
-        "java-runtime-beta" => &java_manifest.java_runtime_beta,
-        "java-runtime-gamma" => &java_manifest.java_runtime_gamma,
-        "jre-legacy" => &java_manifest.jre_legacy,
-        "minecraft-java-exe" => &java_manifest.minecraft_java_exe,
-        _ => unreachable!(
-            "No such runtime found for java component: {}",
-            &java.component
-        ),
-    };
+    let runtime_opt = manifest_key
+        .and_then(|key| java_version_manifest.get(key))
+        .and_then(|java_manifest| get_java_runtime(java_manifest, &java.component).as_ref());
     info!("Downloading runtime: {:#?}", runtime_opt);
     match runtime_opt {
         Some(runtime) => {
             // let runtime_manifest = &runtime.manifest;
-            Ok(download_java_from_runtime_manifest(&java_dir, &runtime).await?)
+            Ok(download_java_from_runtime_manifest(
+                &java_dir,
+                cache,
+                &runtime,
+                verification,
+                task,
+                on_progress,
+            )
+            .await?)
         }
         None => {
-            let s = format!("Java runtime is empty for component {}", &java.component);
-            error!("{}", s);
-            //TODO: New error type?
-            return Err(ManifestError::VersionRetrievalError(s));
+            info!(
+                "No mojang runtime for component {} on {} {}, falling back to Temurin",
+                &java.component,
+                env::consts::OS,
+                architecture
+            );
+            download_temurin_java(java_dir, cache, java.major_version, architecture).await
         }
     }
 }
 
-type PatchingResult<T> = Result<T, PatchingError>;
+/// Downloads the latest Temurin jre for `major_version` from Adoptium into `java_dir`.
+async fn download_temurin_java(
+    java_dir: &Path,
+    cache: &ResponseCache,
+    major_version: u32,
+    architecture: &str,
+) -> ManifestResult<PathBuf> {
+    let os = match env::consts::OS {
+        "macos" => "mac",
+        os => os,
+    };
+    let adoptium_architecture = match architecture {
+        "x86_64" => "x64",
+        "x86" => "x32",
+        architecture => architecture,
+    };
+    let url = format!(
+        "{}/assets/latest/{}/hotspot?architecture={}&image_type=jre&os={}&vendor=eclipse",
+        ADOPTIUM_API_URL, major_version, adoptium_architecture, os
+    );
+    let (releases, _): (Vec<AdoptiumRelease>, _) = cache.get_json(&url, API_RESPONSE_TTL).await?;
+    let release = releases.into_iter().next().ok_or_else(|| {
+        ManifestError::VersionRetrievalError(format!(
+            "No Temurin {} build available for {} {}",
+            major_version, os, adoptium_architecture
+        ))
+    })?;
 
-#[derive(Debug)]
-enum PatchingError {
-    XmlParseError(xmltree::ParseError),
-    XmlWriteError(xmltree::Error),
-    XmlElementAccessError(String),
-}
+    let base_path = java_dir.join(format!("temurin-{}", release.release_name));
+    // Macos builds are packaged as an app bundle.
+    let java_path = if os == "mac" {
+        base_path.join("Contents/Home/bin/java")
+    } else {
+        base_path.join("bin/java")
+    };
+    if base_path.exists() {
+        info!("Using java path: {:?}", java_path);
+        return Ok(java_path);
+    }
 
-impl From<xmltree::ParseError> for PatchingError {
-    fn from(error: xmltree::ParseError) -> Self {
-        PatchingError::XmlParseError(error)
+    let package = &release.binary.package;
+    info!("Downloading Temurin from {}", &package.link);
+    let bytes = download_bytes_from_url(&package.link).await?;
+    if !validate_sha256_hash(&bytes, &package.checksum) {
+        return Err(ManifestError::InvalidFileDownload {
+            url: package.link.clone(),
+        });
     }
+    // Extract next to the final directory so a failed extraction isn't mistaken for an install.
+    let extraction_path = java_dir.join(format!("temurin-{}.tmp", release.release_name));
+    if extraction_path.exists() {
+        fs::remove_dir_all(&extraction_path).with_path(&extraction_path)?;
+    }
+    if package.name.ends_with(".zip") {
+        extract_zip_stripping_root(&bytes, &extraction_path)?;
+    } else {
+        extract_tar_gz_stripping_root(&bytes, &extraction_path)?;
+    }
+    fs::rename(&extraction_path, &base_path).with_path(&base_path)?;
+
+    info!("Using java path: {:?}", java_path);
+    Ok(java_path)
 }
 
-impl From<xmltree::Error> for PatchingError {
-    fn from(error: xmltree::Error) -> Self {
-        PatchingError::XmlWriteError(error)
+/// Removes the first component of an archive path, jre archives have everything in a single root dir.
+fn strip_archive_root(path: &Path) -> Option<PathBuf> {
+    let stripped: PathBuf = path.components().skip(1).collect();
+    let is_safe = stripped
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)));
+    if stripped.as_os_str().is_empty() || !is_safe {
+        None
+    } else {
+        Some(stripped)
     }
 }
 
-/// Patch the logging configurations to replace LegacyXMLLayout with PatternLayout
-fn patch_logging_configuration(bytes: &Bytes) -> PatchingResult<Bytes> {
-    info!("Patching logging configuration");
-    let mut root = Element::parse(&*bytes.to_vec())?;
-
-    let pattern_layout = root
-        .get_child("Appenders")
-        .and_then(|element| element.get_child("RollingRandomAccessFile"))
-        .and_then(|element| element.get_child("PatternLayout"))
-        .ok_or(PatchingError::XmlElementAccessError(
-            "Error trying to access PatternLayout in logging configuration".into(),
-        ))?
-        .clone();
-
-    let console = root
-        .get_mut_child("Appenders")
-        .and_then(|element| element.get_mut_child("Console"))
-        .ok_or(PatchingError::XmlElementAccessError(
-            "Error trying to access Console in logging configuration".into(),
-        ))?;
-
-    console.children.clear();
-    console.children.push(XMLNode::Element(pattern_layout));
+fn extract_zip_stripping_root(bytes: &Bytes, destination: &Path) -> ManifestResult<()> {
+    let mut archive = ZipArchive::new(Cursor::new(bytes))?;
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        let path = match file.enclosed_name().and_then(strip_archive_root) {
+            Some(path) => destination.join(path),
+            None => continue,
+        };
+        if file.is_dir() {
+            fs::create_dir_all(&path)?;
+            continue;
+        }
+        fs::create_dir_all(path.parent().unwrap())?;
+        let mut output = File::create(&path)?;
+        io::copy(&mut file, &mut output)?;
+    }
+    Ok(())
+}
 
-    let mut result: Vec<u8> = Vec::new();
-    root.write(&mut result)?;
+fn extract_tar_gz_stripping_root(bytes: &Bytes, destination: &Path) -> ManifestResult<()> {
+    let mut archive = tar::Archive::new(GzDecoder::new(Cursor::new(bytes)));
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = match strip_archive_root(&entry.path()?) {
+            Some(path) => destination.join(path),
+            None => continue,
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // Keeps the executable permissions of the binaries.
+        entry.unpack(&path)?;
+    }
+    Ok(())
+}
 
-    Ok(Bytes::from(result))
+fn get_java_runtime<'a>(
+    java_manifest: &'a JavaManifest,
+    component: &str,
+) -> &'a Option<JavaRuntime> {
+    match component {
+        "java-runtime-alpha" => &java_manifest.java_runtime_alpha,
+        "java-runtime-beta" => &java_manifest.java_runtime_beta,
+        "java-runtime-gamma" => &java_manifest.java_runtime_gamma,
+        "jre-legacy" => &java_manifest.jre_legacy,
+        "minecraft-java-exe" => &java_manifest.minecraft_java_exe,
+        _ => unreachable!("No such runtime found for java component: {}", component),
+    }
 }
 
-/// Downloads a logging configureation into ${app_dir}/assets/objects/**first two hash chars**/${logging_configuration.id}
+/// Downloads a logging configuration into `${asset_dir}/log_configs` like the official launcher does,
+/// so it can be imported from disk and isn't downloaded again. The game logs to stdout as log4j xml
+/// events with it, see `instance::log4j`.
 async fn download_logging_configurations(
-    asset_objects_dir: &Path,
+    asset_dir: &Path,
     logging: &Logging,
 ) -> ManifestResult<(String, PathBuf)> {
     let client_logger = &logging.client;
-    info!(
-        "Downloading logging configuration {}",
-        client_logger.file_id()
-    );
-    let original_bytes = download_bytes_from_url(&client_logger.file_url()).await?;
-
-    let patched_bytes = match patch_logging_configuration(&original_bytes) {
-        Ok(b) => b,
-        Err(e) => {
-            warn!("{:#?}", e);
-            original_bytes
+    let path = asset_dir.join("log_configs").join(client_logger.file_id());
+    if !validate_file_hash(&path, client_logger.file_hash()) {
+        info!(
+            "Downloading logging configuration {}",
+            client_logger.file_id()
+        );
+        let bytes = download_bytes_from_url(&client_logger.file_url()).await?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).with_path(parent)?;
         }
-    };
-    let hash = hash_bytes(&patched_bytes);
-    let first_two_chars = hash.split_at(2);
-    let objects_dir = &asset_objects_dir.join(first_two_chars.0);
-    fs::create_dir_all(&objects_dir)?;
-
-    let path = objects_dir.join(format!("{}", &client_logger.file_id()));
-    let mut file = File::create(&path)?;
-    file.write_all(&patched_bytes)?;
+        fs::write(&path, &bytes).with_path(&path)?;
+    }
     Ok((client_logger.argument.clone(), path))
 }
 
-//TODO: This probably needs to change a little to support "legacy" versions < 1.7
+/// Downloads the assets for an asset index. Returns the asset index id and the directory the game
+/// should read assets from, which is only different from `asset_dir` for legacy (< 1.7.3) versions.
 async fn download_assets(
     asset_dir: &Path,
     asset_objects_dir: &Path,
+    instance_dir: &Path,
     asset_index: &AssetIndex,
-) -> ManifestResult<String> {
+    verification: Verification,
+    task: Option<&TaskHandle>,
+    on_progress: impl Fn(usize, usize),
+) -> ManifestResult<(String, PathBuf)> {
     let metadata = &asset_index.metadata;
-    let asset_object: AssetObject = download_json_object(metadata.url()).await?;
     let asset_index_dir = asset_dir.join("indexes");
-    let index_bytes = download_bytes_from_url(metadata.url()).await?;
-    fs::create_dir_all(&asset_index_dir)?;
-
     info!("Asset Index ID: {:?}", &asset_index);
 
     let asset_index_name = format!("{}.json", asset_index.id);
     let index_path = &asset_index_dir.join(&asset_index_name);
-    let mut index_file = File::create(index_path)?;
-    index_file.write_all(&index_bytes)?;
+    // Indexes can be imported from disk, see `import::version_bundle`.
+    let index_bytes = if validate_file_hash(index_path, metadata.hash()) {
+        Bytes::from(fs::read(index_path).with_path(index_path)?)
+    } else {
+        let bytes = download_bytes_from_url(metadata.url()).await?;
+        fs::create_dir_all(&asset_index_dir)?;
+        let mut index_file = File::create(index_path)?;
+        index_file.write_all(&bytes)?;
+        bytes
+    };
+    let asset_object: AssetObject = serde_json::from_slice(&index_bytes)?;
     info!("Downloading {} assets", &asset_object.objects.len());
 
     let start = Instant::now();
 
     fs::create_dir_all(&asset_objects_dir)?;
 
-    let x = buffered_download_stream(&asset_object.objects, &asset_objects_dir, |bytes, asset| {
-        if !validate_hash(&bytes, &asset.hash()) {
-            let err = format!("Error downloading asset {}, invalid hash.", &asset.name());
-            error!("{}", err);
-            return Err(DownloadError::InvalidFileHashError(err));
-        }
-        fs::create_dir_all(&asset.path(&asset_objects_dir).parent().unwrap())?;
+    let save_dir = asset_objects_dir.to_path_buf();
+    let x = buffered_download_stream(
+        &asset_object.objects,
+        &asset_objects_dir,
+        verification,
+        task,
+        move |bytes, asset| {
+            if !validate_hash(&bytes, &asset.hash()) {
+                return Err(DownloadError::InvalidFileHashError { url: asset.url() });
+            }
+            let path = asset.path(&save_dir);
+            fs::create_dir_all(path.parent().unwrap())?;
 
-        debug!(
-            "Bulk Download asset path: {:#?}",
-            &asset.path(&asset_objects_dir)
-        );
-        let mut file = File::create(&asset.path(&asset_objects_dir))?;
-        file.write_all(&bytes)?;
-        Ok(())
-    })
+            debug!("Bulk Download asset path: {:#?}", path);
+            let mut file = File::create(path)?;
+            file.write_all(&bytes)?;
+            Ok(())
+        },
+        on_progress,
+    )
     .await;
     info!(
         "Finished downloading assets in {}ms - {:#?}",
         start.elapsed().as_millis(),
         &x
     );
-    Ok(asset_index.id.clone())
+
+    // Legacy versions can't read the hashed object store, lay the assets out by path for them.
+    let game_assets_path = if asset_object.map_to_resources {
+        instance_dir.join("resources")
+    } else if asset_object.is_virtual {
+        asset_dir.join("virtual").join(&asset_index.id)
+    } else {
+        return Ok((asset_index.id.clone(), asset_dir.into()));
+    };
+    info!(
+        "Reconstructing legacy assets in {}",
+        game_assets_path.display()
+    );
+    for asset in &asset_object.objects {
+        let destination = game_assets_path.join(asset.name());
+        if destination.exists() {
+            continue;
+        }
+        fs::create_dir_all(destination.parent().unwrap())?;
+        let source = asset.path(asset_objects_dir);
+        // Hard links fail across filesystems, fall back to a normal copy.
+        if fs::hard_link(&source, &destination).is_err() {
+            fs::copy(&source, &destination)?;
+        }
+    }
+    Ok((asset_index.id.clone(), game_assets_path))
 }
 
-fn extract_natives(
-    instance_dir: &Path,
+/// Written into the natives directory after extracting, records what the natives were extracted from.
+const NATIVES_MARKER_FILE: &str = ".natives.json";
+/// Natives of a version being installed into an existing instance, until they replace its natives.
+const NEW_NATIVES_DIR: &str = "natives.new";
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct NativesMarker {
+    os: String,
+    arch: String,
+    /// Sha1 hashes of the extracted classifier jars.
+    classifiers: Vec<String>,
+}
+
+impl NativesMarker {
+    fn new(classifiers: &[DownloadableClassifier]) -> Self {
+        let mut hashes: Vec<String> = classifiers
+            .iter()
+            .map(|classifier| classifier.hash().to_string())
+            .collect();
+        hashes.sort();
+        Self {
+            os: env::consts::OS.into(),
+            arch: env::consts::ARCH.into(),
+            classifiers: hashes,
+        }
+    }
+}
+
+/// Extracts every classifier jar into `natives_path`. Each jar is extracted on its own blocking worker,
+/// `on_progress` is called with the number of finished and total jars.
+async fn extract_natives(
+    natives_path: &Path,
     libraries_dir: &Path,
     classifiers: Vec<DownloadableClassifier>,
+    on_progress: impl Fn(usize, usize),
 ) -> ManifestResult<()> {
-    debug!("Extracting Natives");
-    for classifier in classifiers {
-        debug!("Classifier: {:#?}", classifier);
-        let classifier_path = classifier.path(libraries_dir);
-        let natives_path = instance_dir.join("natives");
-        let jar_file = File::open(&classifier_path);
-        debug!("Jar File: {:#?} at {}", jar_file, classifier_path.display());
-        let mut archive = ZipArchive::new(jar_file.unwrap())?;
-
-        'zip: for i in 0..archive.len() {
-            debug!("In loop");
-            if let Ok(mut file) = archive.by_index(i) {
-                debug!("File is ok");
-                if file.is_dir() {
-                    continue;
-                }
-                let zip_path = match file.enclosed_name() {
-                    Some(name) => name.to_owned(),
-                    None => continue,
-                };
-
-                debug!("ZipArchive Path: {}", zip_path.display());
-                // If the zip path starts with (or is) an excluded path, dont extract it.
-                if let Some(extraction_rule) = &classifier.extraction_rule {
-                    for exclusion in &extraction_rule.exclude {
-                        if zip_path.starts_with(exclusion) {
-                            debug!("Excluding {}", exclusion);
-                            continue 'zip;
-                        }
+    let start = Instant::now();
+    let natives_path = natives_path.to_path_buf();
+    let marker = NativesMarker::new(&classifiers);
+    let jar_count = classifiers.len();
+    let classifier_paths: Vec<PathBuf> = classifiers
+        .iter()
+        .map(|classifier| classifier.path(libraries_dir))
+        .collect();
+    // Progress is reported per entry, the jars of some versions hold dozens of libraries.
+    let total = {
+        let classifier_paths = classifier_paths.clone();
+        run_blocking(move || {
+            classifier_paths
+                .iter()
+                .map(|path| zip_entry_count(path))
+                .sum::<usize>()
+        })
+        .await?
+    };
+    let (entry_sender, mut extracted_entries) = mpsc::unbounded();
+    let mut extractions: FuturesUnordered<_> = classifiers
+        .into_iter()
+        .zip(classifier_paths)
+        .map(|(classifier, classifier_path)| {
+            let natives_path = natives_path.clone();
+            let entry_sender = entry_sender.clone();
+            tauri::async_runtime::spawn_blocking(move || {
+                extract_classifier(&classifier_path, &natives_path, &classifier, || {
+                    let _ = entry_sender.unbounded_send(());
+                })
+            })
+        })
+        .collect();
+    drop(entry_sender);
+
+    let mut completed = 0;
+    on_progress(completed, total);
+    // Ends once every extraction finished and dropped its sender.
+    while extracted_entries.next().await.is_some() {
+        completed += 1;
+        on_progress(completed, total);
+    }
+    while let Some(extraction) = extractions.next().await {
+        extraction.map_err(|error| {
+            io::Error::new(
+                io::ErrorKind::Other,
+                format!("Native extraction worker failed: {}", error),
+            )
+        })??;
+    }
+    fs::create_dir_all(&natives_path).with_path(&natives_path)?;
+    let marker_path = natives_path.join(NATIVES_MARKER_FILE);
+    fs::write(&marker_path, serde_json::to_vec(&marker)?).with_path(&marker_path)?;
+    info!(
+        "Extracted {} native jars in {}ms",
+        jar_count,
+        start.elapsed().as_millis()
+    );
+    Ok(())
+}
+
+/// Checks the instance's natives against the classifier jars of `version_id` for this os and architecture,
+/// extracting them again when they are missing or were extracted from other jars. Strict verification
+/// always checks the jars and extracts them again.
+pub async fn verify_natives(
+    resource_manager: &ResourceManager,
+    instance_dir: &Path,
+    version_id: &str,
+    verification: Verification,
+) -> ManifestResult<()> {
+    let version = resource_manager
+        .download_vanilla_version(version_id)
+        .await?;
+    let classifiers: Vec<DownloadableClassifier> = applicable_libraries(version.libraries)
+        .iter()
+        .filter_map(native_classifier)
+        .collect();
+
+    let natives_path = instance_dir.join("natives");
+    let libraries_dir = resource_manager.libraries_dir();
+    let extracted = fs::read(natives_path.join(NATIVES_MARKER_FILE))
+        .ok()
+        .and_then(|bytes| serde_json::from_slice::<NativesMarker>(&bytes).ok());
+    let up_to_date = extracted == Some(NativesMarker::new(&classifiers))
+        && match verification {
+            Verification::Strict => false,
+            Verification::Fast => true,
+            // The extracted files aren't checked, only the jars they were extracted from.
+            Verification::Incremental => classifiers.iter().all(|classifier| {
+                is_file_valid(
+                    &classifier.path(&libraries_dir),
+                    classifier.hash(),
+                    classifier.size(),
+                    verification,
+                )
+            }),
+        };
+    if up_to_date {
+        hash_cache::save();
+        debug!("Natives of {} are up to date", instance_dir.display());
+        return Ok(());
+    }
+
+    info!(
+        "Natives of {} are missing or stale, extracting them again",
+        instance_dir.display()
+    );
+    // The jars are shared between instances and could have been removed since the install.
+    let save_dir = libraries_dir.clone();
+    buffered_download_stream(
+        &classifiers,
+        &libraries_dir,
+        verification,
+        None,
+        move |bytes, classifier| save_library(&save_dir, bytes, classifier),
+        |_, _| {},
+    )
+    .await?;
+    if natives_path.exists() {
+        fs::remove_dir_all(&natives_path).with_path(&natives_path)?;
+    }
+    let result = extract_natives(&natives_path, &libraries_dir, classifiers, |_, _| {}).await;
+    hash_cache::save();
+    result
+}
+
+/// The quick check before a launch: downloads the game jar of `version_id` again when it is missing or
+/// corrupted and verifies the instance's natives.
+pub async fn verify_launch_files(
+    resource_manager: &ResourceManager,
+    instance_dir: &Path,
+    version_id: &str,
+    verification: Verification,
+) -> ManifestResult<()> {
+    let version = resource_manager
+        .download_vanilla_version(version_id)
+        .await?;
+    download_game_jar(
+        &resource_manager.version_dir(),
+        JarType::Client,
+        &version.downloads.client,
+        &version.id,
+        verification,
+    )
+    .await?;
+    verify_natives(resource_manager, instance_dir, version_id, verification).await
+}
+
+/// The game jar, relative to the versions folder, and the libraries of `version_id` for this os, for
+/// an instance's lockfile.
+pub async fn locked_version_files(
+    resource_manager: &ResourceManager,
+    version_id: &str,
+) -> ManifestResult<(LockedFile, Vec<LockedFile>)> {
+    let version = resource_manager
+        .download_vanilla_version(version_id)
+        .await?;
+    let client = &version.downloads.client;
+    let game_jar = LockedFile {
+        path: format!("{}/client.jar", version.id),
+        sha1: client.hash().into(),
+        url: Some(client.url().into()),
+    };
+
+    let mut libraries = Vec::new();
+    for library in applicable_libraries(version.libraries) {
+        let artifacts = library
+            .downloads
+            .artifact
+            .clone()
+            .into_iter()
+            .chain(native_classifier(&library).map(|classifier| classifier.classifier));
+        for artifact in artifacts {
+            libraries.push(LockedFile {
+                path: artifact.name().into(),
+                sha1: artifact.hash().into(),
+                url: Some(artifact.url()),
+            });
+        }
+    }
+    Ok((game_jar, libraries))
+}
+
+/// The number of entries in the zip at `path`, 0 if it can't be read.
+fn zip_entry_count(path: &Path) -> usize {
+    File::open(path)
+        .ok()
+        .and_then(|file| ZipArchive::new(file).ok())
+        .map_or(0, |archive| archive.len())
+}
+
+/// Extracts the natives of one classifier jar, calling `on_entry` as it gets to each entry of the jar.
+fn extract_classifier(
+    classifier_path: &Path,
+    natives_path: &Path,
+    classifier: &DownloadableClassifier,
+    on_entry: impl Fn(),
+) -> ManifestResult<()> {
+    debug!("Extracting natives from {}", classifier_path.display());
+    let jar_file = File::open(classifier_path).with_path(classifier_path)?;
+    let mut archive = ZipArchive::new(jar_file)?;
+
+    'zip: for i in 0..archive.len() {
+        on_entry();
+        if let Ok(mut file) = archive.by_index(i) {
+            if file.is_dir() {
+                continue;
+            }
+            let zip_path = match file.enclosed_name() {
+                Some(name) => name.to_owned(),
+                None => continue,
+            };
+
+            // If the zip path starts with (or is) an excluded path, dont extract it.
+            if let Some(extraction_rule) = &classifier.extraction_rule {
+                for exclusion in &extraction_rule.exclude {
+                    if zip_path.starts_with(exclusion) {
+                        debug!("Excluding {}", exclusion);
+                        continue 'zip;
                     }
                 }
-                let path = natives_path.join(zip_path);
-                if let Some(parent) = path.parent() {
-                    if !parent.exists() {
-                        fs::create_dir_all(parent)?;
-                    }
+            }
+            let path = natives_path.join(zip_path);
+            if let Some(parent) = path.parent() {
+                if !parent.exists() {
+                    fs::create_dir_all(parent)?;
                 }
-                debug!("Copy from {:#?} to {:#?}", file.name(), path.display());
-                let mut output_file = File::create(&path)?;
-                io::copy(&mut file, &mut output_file)?;
             }
+            debug!("Copy from {:#?} to {:#?}", file.name(), path.display());
+            let mut output_file = File::create(&path).with_path(&path)?;
+            io::copy(&mut file, &mut output_file)?;
         }
     }
     Ok(())
 }
 
-pub async fn create_instance(
-    selected: String,
-    instance_name: String,
-    app_handle: &AppHandle<Wry>,
-) -> ManifestResult<()> {
-    let resource_state: State<ResourceState> = app_handle
-        .try_state()
-        .expect("`ResourceState` should already be managed.");
-    let resource_manager = resource_state.0.lock().await;
-    let start = Instant::now();
-
-    let version: VanillaVersion = resource_manager.download_vanilla_version(&selected).await?;
-
-    let libraries: Vec<Library> = version
-        .libraries
+/// Removes the libraries whose rules don't match this system.
+fn applicable_libraries(libraries: Vec<Library>) -> Vec<Library> {
+    libraries
         .into_iter()
         .filter_map(|lib| {
             // If we have any rules...
             if let Some(rules) = &lib.rules {
                 // and the rules dont match
-                if !rules_match(&rules) {
+                if !rules_match(&rules, &LaunchFeatures::default()) {
                     // remove
                     None
                 } else {
@@ -850,38 +1506,136 @@ pub async fn create_instance(
                 Some(lib)
             }
         })
-        .collect();
+        .collect()
+}
 
-    let library_data = download_libraries(&resource_manager.libraries_dir(), &libraries).await?;
+/// The java path and persisted launch arguments produced by installing a version into an instance.
+struct InstalledVersion {
+    java_path: PathBuf,
+    arguments: Vec<String>,
+    feature_arguments: Vec<Argument>,
+}
 
+/// Downloads everything needed to run `selected` in `instance_dir` and extracts its natives into
+/// `natives_dir`. Errors are tagged with the phase of the install they happened in.
+#[allow(clippy::too_many_arguments)]
+async fn install_version(
+    resource_manager: &ResourceManager,
+    selected: &str,
+    instance_name: &str,
+    instance_dir: &Path,
+    natives_dir: &Path,
+    events: &EventBus,
+    task: &TaskHandle,
+    verification: Verification,
+) -> ManifestResult<InstalledVersion> {
+    let current_phase = Mutex::new(InstallPhase::VersionManifest);
+    let result = install_version_phases(
+        resource_manager,
+        selected,
+        instance_name,
+        instance_dir,
+        natives_dir,
+        events,
+        task,
+        verification,
+        &current_phase,
+    )
+    .await;
+    // Also saved after a failed install, a retry doesn't need to hash the same files again.
+    hash_cache::save();
+    result.map_err(|error| error.in_phase(*current_phase.lock().unwrap()))
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn install_version_phases(
+    resource_manager: &ResourceManager,
+    selected: &str,
+    instance_name: &str,
+    instance_dir: &Path,
+    natives_dir: &Path,
+    events: &EventBus,
+    task: &TaskHandle,
+    verification: Verification,
+    current_phase: &Mutex<InstallPhase>,
+) -> ManifestResult<InstalledVersion> {
+    let start = Instant::now();
+    let phase_start = Mutex::new(Instant::now());
+    // Cancellation is checked between phases and between the downloads of a phase.
+    let start_phase = |phase: InstallPhase| {
+        if task.is_cancelled() {
+            return Err(ManifestError::Cancelled);
+        }
+        *phase_start.lock().unwrap() = Instant::now();
+        *current_phase.lock().unwrap() = phase;
+        events.install_phase(instance_name, phase);
+        Ok(())
+    };
+    let download_progress = |kind: DownloadKind| {
+        let phase_start = &phase_start;
+        move |completed: usize, total: usize| {
+            task.set_progress(completed, total);
+            let elapsed = phase_start.lock().unwrap().elapsed();
+            events.download_progress(
+                instance_name,
+                kind,
+                completed,
+                total,
+                estimate_remaining(elapsed, completed, total),
+            )
+        }
+    };
+
+    start_phase(InstallPhase::VersionManifest)?;
+    let version: VanillaVersion = resource_manager.download_vanilla_version(selected).await?;
+
+    let libraries = applicable_libraries(version.libraries);
+
+    start_phase(InstallPhase::Libraries)?;
+    let library_data = download_libraries(
+        &resource_manager.libraries_dir(),
+        &libraries,
+        verification,
+        Some(task),
+        download_progress(DownloadKind::Libraries),
+    )
+    .await?;
+
+    start_phase(InstallPhase::GameJar)?;
     let game_jar_path = download_game_jar(
         &resource_manager.version_dir(),
         JarType::Client,
         &version.downloads.client,
         &version.id,
+        verification,
     )
     .await?;
 
-    // java versions is optional for versions 1.6.4 and older. We select java 8 for them by default. 
-    let java_version = match version.java_version {
-        Some(version) => version,
-        None => JavaVersion { component: "jre-legacy".into(), major_version: 8 },
-    };
-
+    start_phase(InstallPhase::Java)?;
     let java_path = download_java_version(
         &resource_manager.java_dir(),
-        java_version
+        &resource_manager.response_cache(),
+        required_java(version.java_version),
+        java_architecture(&libraries),
+        verification,
+        Some(task),
+        download_progress(DownloadKind::Java),
     )
     .await?;
 
+    start_phase(InstallPhase::LoggingConfiguration)?;
     let logging =
-        download_logging_configurations(&resource_manager.asset_objects_dir(), &version.logging)
-            .await?;
+        download_logging_configurations(&resource_manager.assets_dir(), &version.logging).await?;
 
-    let asset_index = download_assets(
+    start_phase(InstallPhase::Assets)?;
+    let (asset_index, game_assets_path) = download_assets(
         &resource_manager.assets_dir(),
         &resource_manager.asset_objects_dir(),
+        instance_dir,
         &version.asset_index,
+        verification,
+        Some(task),
+        download_progress(DownloadKind::Assets),
     )
     .await?;
     info!(
@@ -889,46 +1643,266 @@ pub async fn create_instance(
         start.elapsed().as_millis()
     );
 
-    let instance_dir = resource_manager.instances_dir().join(&instance_name);
-    fs::create_dir_all(&instance_dir)?;
+    fs::create_dir_all(instance_dir).with_path(instance_dir)?;
 
-    let mc_version_manifest = resource_manager.get_vanilla_manifest_from_version(&selected);
+    let mc_version_manifest = resource_manager.get_vanilla_manifest_from_version(selected);
     if mc_version_manifest.is_none() {
         warn!(
             "Could not retrieve manifest for unknown version: {}.",
-            &selected
+            selected
         );
     }
-    let persitent_arguments = construct_arguments(
+    let (persitent_arguments, feature_arguments) = construct_arguments(
         version.main_class,
         &version.arguments,
-        mc_version_manifest.unwrap(),
+        &mc_version_manifest.unwrap(),
         &asset_index,
         LaunchArgumentPaths {
             logging,
             library_paths: library_data.library_paths,
-            instance_path: instance_dir.clone(),
+            instance_path: instance_dir.into(),
             jar_path: game_jar_path,
             asset_dir_path: resource_manager.assets_dir(),
+            game_assets_path,
         },
     );
     debug!("Persistent Arguments: {}", &persitent_arguments.join(" "));
 
-    let instance_state: State<InstanceState> = app_handle
+    start_phase(InstallPhase::Natives)?;
+    extract_natives(
+        natives_dir,
+        &resource_manager.libraries_dir(),
+        library_data.classifiers,
+        download_progress(DownloadKind::Natives),
+    )
+    .await?;
+    events.install_phase(instance_name, InstallPhase::Finished);
+    Ok(InstalledVersion {
+        java_path,
+        arguments: persitent_arguments,
+        feature_arguments,
+    })
+}
+
+/// Installs `version_id` into an existing instance. Its natives are extracted next to the current ones
+/// and only replace them once the install succeeded, a failed install leaves the instance launchable.
+async fn reinstall_version(
+    resource_manager: &ResourceManager,
+    version_id: &str,
+    instance_name: &str,
+    instance_dir: &Path,
+    events: &EventBus,
+    task: &TaskHandle,
+    verification: Verification,
+) -> ManifestResult<InstalledVersion> {
+    let natives_dir = instance_dir.join("natives");
+    let new_natives_dir = instance_dir.join(NEW_NATIVES_DIR);
+    // Left behind if the launcher exited during a previous install.
+    if new_natives_dir.exists() {
+        fs::remove_dir_all(&new_natives_dir).with_path(&new_natives_dir)?;
+    }
+    let result = install_version(
+        resource_manager,
+        version_id,
+        instance_name,
+        instance_dir,
+        &new_natives_dir,
+        events,
+        task,
+        verification,
+    )
+    .await;
+    let installed = match result {
+        Ok(installed) => installed,
+        Err(error) => {
+            if new_natives_dir.exists() {
+                if let Err(error) = fs::remove_dir_all(&new_natives_dir) {
+                    warn!("Could not remove {}: {}", new_natives_dir.display(), error);
+                }
+            }
+            return Err(error);
+        }
+    };
+    if natives_dir.exists() {
+        fs::remove_dir_all(&natives_dir).with_path(&natives_dir)?;
+    }
+    fs::rename(&new_natives_dir, &natives_dir).with_path(&natives_dir)?;
+    Ok(installed)
+}
+
+/// How cached files are verified when installing, from the launcher settings.
+async fn install_verification(app_handle: &AppHandle<Wry>) -> Verification {
+    let settings_state: State<SettingsState> = app_handle
+        .try_state()
+        .expect("`SettingsState` should already be managed.");
+    let settings_manager = settings_state.0.lock().await;
+    settings_manager.get_verification_settings().install()
+}
+
+async fn backup_settings(app_handle: &AppHandle<Wry>) -> BackupSettings {
+    let settings_state: State<SettingsState> = app_handle
+        .try_state()
+        .expect("`SettingsState` should already be managed.");
+    let settings_manager = settings_state.0.lock().await;
+    settings_manager.get_backup_settings().clone()
+}
+
+pub async fn create_instance(
+    selected: String,
+    instance_name: String,
+    app_handle: &AppHandle<Wry>,
+    task: &TaskHandle,
+) -> ManifestResult<()> {
+    let verification = install_verification(app_handle).await;
+    let resource_state: State<ResourceState> = app_handle
         .try_state()
         .expect("`ResourceState` should already be managed.");
+    let resource_manager = &resource_state.0;
+
+    let instance_dir = resource_manager.instances_dir().join(&instance_name);
+    let installed = install_version(
+        resource_manager,
+        &selected,
+        &instance_name,
+        &instance_dir,
+        &instance_dir.join("natives"),
+        &EventBus::new(app_handle.clone()),
+        task,
+        verification,
+    )
+    .await?;
+
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
     let instance_manager = instance_state.0.lock().await;
 
     instance_manager.add_instance(InstanceConfiguration {
+        schema_version: INSTANCE_CONFIG_SCHEMA_VERSION,
         instance_name: instance_name.into(),
-        jvm_path: java_path,
-        arguments: persitent_arguments,
+        jvm_path: installed.java_path,
+        arguments: installed.arguments,
+        feature_arguments: installed.feature_arguments,
+        version_id: selected,
+        last_played: None,
+        play_time: 0,
+        notes: String::new(),
+        settings: Default::default(),
+        sync: None,
+        release_channel: Default::default(),
     })?;
-    debug!("After persistent args");
-    extract_natives(
+    Ok(())
+}
+
+/// Hashes every file of the instance's version, the libraries, game jar, java runtime, assets and
+/// natives, and downloads the ones that are missing or corrupted.
+pub async fn verify_instance(
+    instance_name: String,
+    app_handle: &AppHandle<Wry>,
+    task: &TaskHandle,
+) -> ManifestResult<()> {
+    let resource_state: State<ResourceState> = app_handle
+        .try_state()
+        .expect("`ResourceState` should already be managed.");
+    let resource_manager = &resource_state.0;
+
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    let (_lock, version_id) = {
+        let instance_manager = instance_state.0.lock().await;
+        instance_manager.ensure_instance_idle(&instance_name)?;
+        let lock = instance_manager.lock_instance(&instance_name, InstanceOperation::Repair)?;
+        let version_id = instance_manager
+            .get_instance_version_id(&instance_name)?
+            .to_string();
+        (lock, version_id)
+    };
+
+    let instance_dir = resource_manager.instances_dir().join(&instance_name);
+    // The natives are extracted again from the verified jars.
+    let installed = reinstall_version(
+        resource_manager,
+        &version_id,
+        &instance_name,
         &instance_dir,
-        &resource_manager.libraries_dir(),
-        library_data.classifiers,
+        &EventBus::new(app_handle.clone()),
+        task,
+        Verification::Strict,
+    )
+    .await?;
+
+    let mut instance_manager = instance_state.0.lock().await;
+    instance_manager.update_instance_version(
+        &instance_name,
+        &version_id,
+        installed.java_path,
+        installed.arguments,
+        installed.feature_arguments,
+    )?;
+    info!("Verified the files of {}", instance_name);
+    Ok(())
+}
+
+/// Switches an existing instance to a different minecraft version. Saves, options and mods are left
+/// untouched, only the natives and the persisted launch configuration are regenerated.
+pub async fn change_instance_version(
+    instance_name: String,
+    version_id: String,
+    app_handle: &AppHandle<Wry>,
+    task: &TaskHandle,
+) -> ManifestResult<()> {
+    let verification = install_verification(app_handle).await;
+    let resource_state: State<ResourceState> = app_handle
+        .try_state()
+        .expect("`ResourceState` should already be managed.");
+    let resource_manager = &resource_state.0;
+
+    let instance_state: State<InstanceState> = app_handle
+        .try_state()
+        .expect("`InstanceState` should already be managed.");
+    // Held until the new version is saved, so the instance can't be launched or edited halfway.
+    let (_lock, backups_dir) = {
+        let instance_manager = instance_state.0.lock().await;
+        instance_manager.ensure_instance_idle(&instance_name)?;
+        let lock =
+            instance_manager.lock_instance(&instance_name, InstanceOperation::ChangeVersion)?;
+        (lock, instance_manager.backups_dir(&instance_name))
+    };
+
+    let instance_dir = resource_manager.instances_dir().join(&instance_name);
+    let backup_settings = backup_settings(app_handle).await;
+    // Worlds opened in a newer version can't go back, keep a way to undo the change.
+    if backup_settings.before_version_change {
+        create_backup(
+            &instance_dir,
+            &backups_dir,
+            BackupReason::BeforeVersionChange,
+        )?;
+        prune_backups(&backups_dir, backup_settings.max_backups as usize)?;
+    }
+    let installed = reinstall_version(
+        resource_manager,
+        &version_id,
+        &instance_name,
+        &instance_dir,
+        &EventBus::new(app_handle.clone()),
+        task,
+        verification,
+    )
+    .await?;
+
+    let mut instance_manager = instance_state.0.lock().await;
+    instance_manager.update_instance_version(
+        &instance_name,
+        &version_id,
+        installed.java_path,
+        installed.arguments,
+        installed.feature_arguments,
     )?;
+    info!(
+        "Changed instance {} to version {}",
+        instance_name, version_id
+    );
     Ok(())
 }
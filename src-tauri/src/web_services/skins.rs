@@ -0,0 +1,445 @@
+//! Skin and cape textures of the active account. They are kept on disk for the 3D preview and
+//! rendered into flat avatars shown next to the account name. New skins are checked here before
+//! they are sent to Minecraft Services.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crypto::{digest::Digest, sha1::Sha1};
+use image::{
+    imageops::{self, FilterType},
+    ImageError, ImageFormat, RgbaImage,
+};
+use log::{debug, info};
+use reqwest::{
+    multipart::{Form, Part},
+    RequestBuilder, StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use thiserror::Error;
+
+use crate::{
+    consts::MINECRAFT_SKINS_URL,
+    state::account_manager::Account,
+    web_services::{
+        authentication::AuthenticationError,
+        network,
+        rate_limit::{send, Api},
+    },
+};
+
+/// Largest scale an avatar is rendered at, a head is `8 * scale` pixels wide.
+const MAX_AVATAR_SCALE: u32 = 32;
+
+/// The only sizes Minecraft Services accepts, HD skins are rejected.
+const UPLOAD_SIZES: [(u32, u32); 2] = [(64, 64), (64, 32)];
+
+pub type SkinResult<T> = Result<T, SkinError>;
+
+#[derive(Debug, Error)]
+pub enum SkinError {
+    #[error("The account doesn't have a skin")]
+    NoSkin,
+    #[error("Could not download the texture: {0}")]
+    DownloadError(#[from] reqwest::Error),
+    #[error("Could not read the skin: {0}")]
+    ImageError(#[from] ImageError),
+    #[error("The skin has an unsupported size of {0}x{1}")]
+    InvalidSize(u32, u32),
+    #[error("Could not save the avatar: {0}")]
+    FilesystemError(#[from] io::Error),
+    #[error("The skin has to be a png image")]
+    NotPng,
+    #[error("Skins have to be 64x64 or 64x32 pixels but this one is {0}x{1}")]
+    UploadSize(u32, u32),
+    #[error("Only Microsoft accounts that own the game can change their skin")]
+    UnsupportedAccount,
+    #[error("Could not change the skin: {0}")]
+    UploadError(reqwest::Error),
+    #[error("Minecraft Services rejected the skin with status {0}")]
+    UploadRejected(StatusCode),
+    #[error("{0}")]
+    AuthenticationError(#[from] AuthenticationError),
+}
+
+impl SkinError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            SkinError::NoSkin => "no_skin",
+            SkinError::DownloadError(_) => "http_error",
+            SkinError::ImageError(_) | SkinError::InvalidSize(..) => "invalid_skin",
+            SkinError::FilesystemError(_) => "filesystem_error",
+            SkinError::NotPng => "not_png",
+            SkinError::UploadSize(..) => "invalid_size",
+            SkinError::UnsupportedAccount => "unsupported_account",
+            SkinError::UploadError(_) | SkinError::UploadRejected(_) => "upload_failed",
+            SkinError::AuthenticationError(_) => "authentication_error",
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct SkinErrorPayload {
+    code: &'static str,
+    message: String,
+}
+
+impl Serialize for SkinError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        SkinErrorPayload {
+            code: self.code(),
+            message: self.to_string(),
+        }
+        .serialize(serializer)
+    }
+}
+
+/// Which part of the skin an avatar shows.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AvatarKind {
+    Head,
+    /// The whole player seen from the front.
+    Body,
+}
+
+/// The player model a skin is made for.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SkinModel {
+    Classic,
+    /// Alex style model with 3 pixel wide arms.
+    Slim,
+}
+
+impl SkinModel {
+    /// Reads the `variant` of a skin in the minecraft profile, anything unknown is the classic model.
+    pub fn from_variant(variant: &str) -> Self {
+        if variant.eq_ignore_ascii_case("slim") {
+            SkinModel::Slim
+        } else {
+            SkinModel::Classic
+        }
+    }
+
+    /// The `variant` Minecraft Services expects when changing the skin.
+    fn variant(self) -> &'static str {
+        match self {
+            SkinModel::Classic => "classic",
+            SkinModel::Slim => "slim",
+        }
+    }
+}
+
+/// The skin of a profile after changing it.
+#[derive(Debug, Clone)]
+pub struct AppliedSkin {
+    pub url: String,
+    /// As the profile reports it, see `Account::skin_variant`.
+    pub variant: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProfileSkin {
+    state: String,
+    url: String,
+    variant: String,
+}
+
+/// Minecraft Services answers a skin change with the whole profile, only its skins are needed.
+#[derive(Debug, Deserialize)]
+struct ChangedProfile {
+    skins: Vec<ProfileSkin>,
+}
+
+/// What the frontend needs to render the 3D preview of the active account.
+#[derive(Debug, Clone, Serialize)]
+pub struct SkinPreview {
+    pub skin: PathBuf,
+    pub model: SkinModel,
+    pub cape: Option<PathBuf>,
+}
+
+/// Returns the path of the texture at `url` in `textures_dir`, downloading it the first time it is
+/// used. Texture urls point at the hash of the texture, so a downloaded texture never changes.
+pub async fn cache_texture(textures_dir: &Path, url: &str) -> SkinResult<PathBuf> {
+    let mut hasher = Sha1::new();
+    hasher.input_str(url);
+    let texture_path = textures_dir.join(format!("{}.png", hasher.result_str()));
+    if texture_path.is_file() {
+        return Ok(texture_path);
+    }
+    debug!("Downloading texture {}", url);
+    let bytes = reqwest::get(url).await?.error_for_status()?.bytes().await?;
+    // Don't keep anything that isn't an image, it would be served from the cache forever.
+    image::load_from_memory(&bytes)?;
+    fs::create_dir_all(textures_dir)?;
+    fs::write(&texture_path, &bytes)?;
+    Ok(texture_path)
+}
+
+/// Returns the skin texture, downloading it the first time it is used.
+pub async fn fetch_skin(textures_dir: &Path, skin_url: &str) -> SkinResult<RgbaImage> {
+    if skin_url.is_empty() {
+        return Err(SkinError::NoSkin);
+    }
+    let skin_path = cache_texture(textures_dir, skin_url).await?;
+    Ok(image::open(skin_path)?.to_rgba8())
+}
+
+/// Caches the skin and cape textures of an account for the 3D preview.
+pub async fn get_skin_preview(
+    textures_dir: &Path,
+    skin_url: &str,
+    model: SkinModel,
+    cape_url: Option<&str>,
+) -> SkinResult<SkinPreview> {
+    if skin_url.is_empty() {
+        return Err(SkinError::NoSkin);
+    }
+    let skin = cache_texture(textures_dir, skin_url).await?;
+    let cape = match cape_url {
+        Some(cape_url) => Some(cache_texture(textures_dir, cape_url).await?),
+        None => None,
+    };
+    Ok(SkinPreview { skin, model, cape })
+}
+
+/// Renders an avatar of the skin at `skin_url` into `avatars_dir` and returns its path. Avatars are
+/// named after the skin and scale, so one is only rendered again when the skin changes.
+pub async fn get_avatar(
+    textures_dir: &Path,
+    avatars_dir: &Path,
+    skin_url: &str,
+    model: SkinModel,
+    kind: AvatarKind,
+    scale: u32,
+) -> SkinResult<PathBuf> {
+    let scale = scale.clamp(1, MAX_AVATAR_SCALE);
+    let mut hasher = Sha1::new();
+    hasher.input_str(skin_url);
+    let file_name = match kind {
+        AvatarKind::Head => format!("{}-head-{}.png", hasher.result_str(), scale),
+        AvatarKind::Body => format!(
+            "{}-body-{}-{}.png",
+            hasher.result_str(),
+            if model == SkinModel::Slim {
+                "slim"
+            } else {
+                "classic"
+            },
+            scale
+        ),
+    };
+    let avatar_path = avatars_dir.join(file_name);
+    if avatar_path.is_file() {
+        return Ok(avatar_path);
+    }
+
+    let skin = fetch_skin(textures_dir, skin_url).await?;
+    debug!("Rendering {:?} avatar for {}", kind, skin_url);
+    let avatar = match kind {
+        AvatarKind::Head => render_head(&skin)?,
+        AvatarKind::Body => render_body(&skin, model)?,
+    };
+    let (width, height) = avatar.dimensions();
+    let avatar = imageops::resize(&avatar, width * scale, height * scale, FilterType::Nearest);
+    fs::create_dir_all(avatars_dir)?;
+    avatar.save(&avatar_path)?;
+    Ok(avatar_path)
+}
+
+/// Size of one skin pixel in texture pixels, 1 for regular 64x64 and legacy 64x32 skins.
+fn skin_unit(skin: &RgbaImage) -> SkinResult<u32> {
+    let (width, height) = skin.dimensions();
+    if width == 0 || width % 64 != 0 || (height != width && height != width / 2) {
+        return Err(SkinError::InvalidSize(width, height));
+    }
+    Ok(width / 64)
+}
+
+/// Skins from before 1.8 are 64x32, they have no second layer besides the hat and mirror the right
+/// limbs for the left ones.
+fn is_legacy(skin: &RgbaImage) -> bool {
+    skin.height() * 2 == skin.width()
+}
+
+/// Copies a region given in skin pixels, scaled down to one pixel per skin pixel.
+fn part(skin: &RgbaImage, unit: u32, x: u32, y: u32, width: u32, height: u32) -> RgbaImage {
+    let region =
+        imageops::crop_imm(skin, x * unit, y * unit, width * unit, height * unit).to_image();
+    if unit == 1 {
+        region
+    } else {
+        imageops::resize(&region, width, height, FilterType::Nearest)
+    }
+}
+
+/// Legacy skins often fill the hat layer with an opaque color, the game ignores such a hat.
+fn is_usable_overlay(skin: &RgbaImage, overlay: &RgbaImage) -> bool {
+    !is_legacy(skin) || overlay.pixels().any(|pixel| pixel[3] < 255)
+}
+
+/// The front of the head with the hat layer on top, 8x8 pixels.
+pub fn render_head(skin: &RgbaImage) -> SkinResult<RgbaImage> {
+    let unit = skin_unit(skin)?;
+    let mut head = part(skin, unit, 8, 8, 8, 8);
+    let hat = part(skin, unit, 40, 8, 8, 8);
+    if is_usable_overlay(skin, &hat) {
+        imageops::overlay(&mut head, &hat, 0, 0);
+    }
+    Ok(head)
+}
+
+/// The front of the whole player with every second layer on top, 16x32 pixels.
+pub fn render_body(skin: &RgbaImage, model: SkinModel) -> SkinResult<RgbaImage> {
+    let unit = skin_unit(skin)?;
+    let legacy = is_legacy(skin);
+    let arm = match model {
+        SkinModel::Classic => 4,
+        SkinModel::Slim => 3,
+    };
+    let mut body = RgbaImage::new(16, 32);
+
+    // (base layer, second layer, x and y on the avatar)
+    let right_arm = part(skin, unit, 44, 20, arm, 12);
+    let right_leg = part(skin, unit, 4, 20, 4, 12);
+    let mut layers = vec![
+        (render_head(skin)?, None, 4, 0),
+        (part(skin, unit, 20, 20, 8, 12), Some((20, 36, 8)), 4, 8),
+        (right_arm.clone(), Some((44, 36, arm)), 4 - arm, 8),
+        (right_leg.clone(), Some((4, 36, 4)), 4, 20),
+    ];
+    if legacy {
+        layers.push((imageops::flip_horizontal(&right_arm), None, 12, 8));
+        layers.push((imageops::flip_horizontal(&right_leg), None, 8, 20));
+    } else {
+        layers.push((
+            part(skin, unit, 36, 52, arm, 12),
+            Some((52, 52, arm)),
+            12,
+            8,
+        ));
+        layers.push((part(skin, unit, 20, 52, 4, 12), Some((4, 52, 4)), 8, 20));
+    }
+
+    for (base, second_layer, x, y) in layers {
+        imageops::overlay(&mut body, &base, x.into(), y.into());
+        // Legacy skins only have the hat, which is part of the head already.
+        if let Some((layer_x, layer_y, width)) = second_layer.filter(|_| !legacy) {
+            let layer = part(skin, unit, layer_x, layer_y, width, 12);
+            imageops::overlay(&mut body, &layer, x.into(), y.into());
+        }
+    }
+    Ok(body)
+}
+
+/// Checks that `bytes` are a skin Minecraft Services accepts, so a bad file fails before anything is
+/// sent.
+pub fn validate_skin(bytes: &[u8]) -> SkinResult<RgbaImage> {
+    if image::guess_format(bytes).ok() != Some(ImageFormat::Png) {
+        return Err(SkinError::NotPng);
+    }
+    let skin = image::load_from_memory_with_format(bytes, ImageFormat::Png)?.to_rgba8();
+    let (width, height) = skin.dimensions();
+    if !UPLOAD_SIZES.contains(&(width, height)) {
+        return Err(SkinError::UploadSize(width, height));
+    }
+    Ok(skin)
+}
+
+/// Guesses the model a skin is made for. The back of the right arm ends two pixels earlier on slim
+/// skins, so those pixels are transparent. Legacy skins predate the slim model.
+pub fn detect_model(skin: &RgbaImage) -> SkinModel {
+    let unit = match skin_unit(skin) {
+        Ok(unit) => unit,
+        Err(_) => return SkinModel::Classic,
+    };
+    if is_legacy(skin) {
+        return SkinModel::Classic;
+    }
+    let arm_end = part(skin, unit, 54, 20, 2, 12);
+    if arm_end.pixels().all(|pixel| pixel[3] == 0) {
+        SkinModel::Slim
+    } else {
+        SkinModel::Classic
+    }
+}
+
+/// Skins can only be changed on the Minecraft profile of a Microsoft account.
+fn ensure_skin_changeable(account: &Account) -> SkinResult<()> {
+    if account.offline || account.demo || account.auth_server.is_some() {
+        return Err(SkinError::UnsupportedAccount);
+    }
+    Ok(())
+}
+
+/// Sends a skin change and returns the skin the profile has now.
+async fn change_skin(account: &Account, request: RequestBuilder) -> SkinResult<AppliedSkin> {
+    let request = request.header("Accept", "application/json").header(
+        "Authorization",
+        format!("Bearer {}", account.minecraft_access_token),
+    );
+    let response = send(Api::MinecraftServices, request)
+        .await
+        .map_err(SkinError::UploadError)?;
+    if !response.status().is_success() {
+        return Err(SkinError::UploadRejected(response.status()));
+    }
+    let profile: ChangedProfile = response.json().await.map_err(SkinError::UploadError)?;
+    let skin = profile
+        .skins
+        .into_iter()
+        .find(|skin| skin.state == "ACTIVE")
+        .ok_or(SkinError::NoSkin)?;
+    info!("Changed the skin of {}", account.uuid);
+    Ok(AppliedSkin {
+        url: skin.url,
+        variant: skin.variant,
+    })
+}
+
+/// Uploads the png in `bytes` as the account's skin. Without a `model` it is detected from the skin.
+pub async fn upload_skin(
+    account: &Account,
+    bytes: Vec<u8>,
+    model: Option<SkinModel>,
+) -> SkinResult<AppliedSkin> {
+    ensure_skin_changeable(account)?;
+    let skin = validate_skin(&bytes)?;
+    let model = model.unwrap_or_else(|| detect_model(&skin));
+    let file = Part::bytes(bytes)
+        .file_name("skin.png")
+        .mime_str("image/png")
+        .map_err(SkinError::UploadError)?;
+    let form = Form::new()
+        .text("variant", model.variant())
+        .part("file", file);
+    let request = network::client().post(MINECRAFT_SKINS_URL).multipart(form);
+    change_skin(account, request).await
+}
+
+/// Changes the account's skin to the texture at `url`. The texture is downloaded first so it can be
+/// checked, and without a `model` it is detected from the skin.
+pub async fn apply_skin_url(
+    account: &Account,
+    textures_dir: &Path,
+    url: &str,
+    model: Option<SkinModel>,
+) -> SkinResult<AppliedSkin> {
+    ensure_skin_changeable(account)?;
+    let texture_path = cache_texture(textures_dir, url).await?;
+    let skin = validate_skin(&fs::read(texture_path)?)?;
+    let model = model.unwrap_or_else(|| detect_model(&skin));
+    let request = network::client()
+        .post(MINECRAFT_SKINS_URL)
+        .header("Content-Type", "application/json")
+        .body(json!({ "variant": model.variant(), "url": url }).to_string());
+    change_skin(account, request).await
+}
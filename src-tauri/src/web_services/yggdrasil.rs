@@ -0,0 +1,234 @@
+//! Accounts from Yggdrasil servers other than Mojang's, like ely.by or Blessing Skin, which the game
+//! is pointed at through the authlib-injector agent.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use log::{debug, info, warn};
+use reqwest::{StatusCode, Url};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Value};
+
+use crate::{
+    consts::AUTHLIB_INJECTOR_LATEST_URL,
+    state::{
+        account_manager::Account,
+        resource_manager::{ManifestError, ManifestResult, PathContext},
+    },
+    web_services::{
+        authentication::{AuthResult, AuthenticationError},
+        downloader::{download_bytes_from_url, download_json_object, validate_sha256_hash},
+        network,
+    },
+};
+
+/// Header a server's website can use to point at its actual api root.
+const API_LOCATION_HEADER: &str = "X-Authlib-Injector-API-Location";
+
+#[derive(Debug, Deserialize)]
+struct AuthlibInjectorArtifact {
+    version: String,
+    download_url: String,
+    checksums: AuthlibInjectorChecksums,
+}
+
+#[derive(Debug, Deserialize)]
+struct AuthlibInjectorChecksums {
+    sha256: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct YggdrasilProfile {
+    id: String,
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct YggdrasilAuthResponse {
+    #[serde(rename = "accessToken")]
+    access_token: String,
+    #[serde(rename = "clientToken")]
+    client_token: String,
+    #[serde(rename = "selectedProfile")]
+    selected_profile: Option<YggdrasilProfile>,
+    #[serde(rename = "availableProfiles", default)]
+    available_profiles: Vec<YggdrasilProfile>,
+}
+
+#[derive(Debug, Deserialize)]
+struct YggdrasilErrorResponse {
+    error: String,
+    #[serde(rename = "errorMessage", default)]
+    error_message: String,
+}
+
+/// The agent jar and the server the game should authenticate against.
+#[derive(Debug)]
+pub struct AuthlibInjector {
+    pub jar_path: PathBuf,
+    pub api_root: String,
+}
+
+impl AuthlibInjector {
+    pub fn java_arguments(&self) -> Vec<String> {
+        vec![format!(
+            "-javaagent:{}={}",
+            self.jar_path.display(),
+            self.api_root
+        )]
+    }
+}
+
+/// Downloads the latest authlib-injector into `dir`, returning the path to the jar. Falls back to a
+/// previously downloaded jar when the latest version can't be retrieved.
+pub async fn download_authlib_injector(dir: &Path) -> ManifestResult<PathBuf> {
+    let artifact =
+        match download_json_object::<AuthlibInjectorArtifact>(AUTHLIB_INJECTOR_LATEST_URL).await {
+            Ok(artifact) => artifact,
+            Err(error) => {
+                warn!("Could not retrieve the latest authlib-injector: {}", error);
+                return find_downloaded_jar(dir).ok_or(ManifestError::HttpError(error));
+            }
+        };
+
+    let jar_path = dir.join(format!("authlib-injector-{}.jar", artifact.version));
+    if jar_path.exists() {
+        return Ok(jar_path);
+    }
+    info!("Downloading authlib-injector {}", artifact.version);
+    let bytes = download_bytes_from_url(&artifact.download_url).await?;
+    if !validate_sha256_hash(&bytes, &artifact.checksums.sha256) {
+        return Err(ManifestError::InvalidFileDownload {
+            url: artifact.download_url,
+        });
+    }
+    fs::create_dir_all(dir).with_path(dir)?;
+    fs::write(&jar_path, &bytes).with_path(&jar_path)?;
+    Ok(jar_path)
+}
+
+/// The newest jar in `dir`, compared by the version in their names, e.g. 1.10 is newer than 1.9.
+fn find_downloaded_jar(dir: &Path) -> Option<PathBuf> {
+    fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension() == Some("jar".as_ref()))
+        .filter_map(|path| jar_version(&path).map(|version| (version, path)))
+        .max_by(|(a, _), (b, _)| a.cmp(b))
+        .map(|(_, path)| path)
+}
+
+/// Parses the version out of a jar named `authlib-injector-${version}.jar`.
+fn jar_version(path: &Path) -> Option<Vec<u32>> {
+    let version = path
+        .file_stem()?
+        .to_str()?
+        .strip_prefix("authlib-injector-")?;
+    version
+        .split('.')
+        .map(|component| component.parse().ok())
+        .collect()
+}
+
+/// Finds the api root of a server from the address a user entered, following the api location
+/// header servers send from their website.
+pub async fn resolve_api_root(server: &str) -> AuthResult<String> {
+    let server = server.trim();
+    let url = if server.contains("://") {
+        Url::parse(server)?
+    } else {
+        Url::parse(&format!("https://{}", server))?
+    };
+    let response = network::client().get(url.clone()).send().await?;
+    let api_root = match response
+        .headers()
+        .get(API_LOCATION_HEADER)
+        .and_then(|location| location.to_str().ok())
+    {
+        Some(location) => url.join(location)?,
+        None => url,
+    };
+    debug!("Yggdrasil api root: {}", api_root);
+    Ok(api_root.as_str().trim_end_matches('/').into())
+}
+
+/// Logs into a Yggdrasil server with a username or email and password.
+pub async fn authenticate_yggdrasil(
+    server: &str,
+    username: &str,
+    password: &str,
+) -> AuthResult<Account> {
+    let api_root = resolve_api_root(server).await?;
+    let body = json!({
+        "agent": { "name": "Minecraft", "version": 1 },
+        "username": username,
+        "password": password,
+        "requestUser": false,
+    });
+    let response: YggdrasilAuthResponse =
+        post_yggdrasil(&format!("{}/authserver/authenticate", api_root), &body).await?;
+    // Servers that allow several profiles per account don't select one on login.
+    let profile = response
+        .selected_profile
+        .or_else(|| response.available_profiles.into_iter().next())
+        .ok_or_else(|| AuthenticationError::YggdrasilError {
+            error: "NoProfile".into(),
+            error_message: "The account doesn't have a minecraft profile".into(),
+        })?;
+    Ok(Account {
+        uuid: profile.id,
+        name: profile.name,
+        minecraft_access_token: response.access_token,
+        // Yggdrasil tokens don't expire at a known time, they are validated with the server instead.
+        minecraft_access_token_expiry: i64::MAX,
+        client_token: response.client_token,
+        auth_server: Some(api_root),
+        ..Default::default()
+    })
+}
+
+/// Checks the account's access token with its server, refreshing it if it isn't valid anymore.
+pub async fn validate_yggdrasil_account(api_root: &str, account: &Account) -> AuthResult<Account> {
+    let tokens = json!({
+        "accessToken": account.minecraft_access_token,
+        "clientToken": account.client_token,
+    });
+    let response = network::client()
+        .post(format!("{}/authserver/validate", api_root))
+        .json(&tokens)
+        .send()
+        .await?;
+    if response.status() == StatusCode::NO_CONTENT {
+        debug!("Yggdrasil token valid.");
+        return Ok(account.clone());
+    }
+
+    debug!("Yggdrasil token invalid, refreshing.");
+    let response: YggdrasilAuthResponse =
+        post_yggdrasil(&format!("{}/authserver/refresh", api_root), &tokens).await?;
+    let mut account = account.clone();
+    account.minecraft_access_token = response.access_token;
+    account.client_token = response.client_token;
+    if let Some(profile) = response.selected_profile {
+        account.name = profile.name;
+    }
+    Ok(account)
+}
+
+async fn post_yggdrasil<T: DeserializeOwned>(url: &str, body: &Value) -> AuthResult<T> {
+    let response = network::client().post(url).json(body).send().await?;
+    if response.status().is_success() {
+        return Ok(response.json().await?);
+    }
+    let status = response.status();
+    match response.json::<YggdrasilErrorResponse>().await {
+        Ok(error) => Err(AuthenticationError::YggdrasilError {
+            error: error.error,
+            error_message: error.error_message,
+        }),
+        Err(_) => Err(AuthenticationError::HttpResponseError(status)),
+    }
+}
@@ -0,0 +1,122 @@
+//! Where the user left the launcher: the size and position of its window, the selected instance and
+//! the open tab. Kept in the settings so the launcher opens the way it was closed.
+
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use tauri::{Manager, PhysicalPosition, PhysicalSize, Position, Size, Window, Wry};
+
+use crate::state::settings_manager::SettingsState;
+
+/// Windows smaller than this were most likely saved while minimized.
+const MIN_WINDOW_SIZE: u32 = 200;
+
+/// Size and position of the window in physical pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct WindowGeometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub maximized: bool,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct UiState {
+    /// Only saved by the backend when the window closes.
+    #[serde(default)]
+    pub window: Option<WindowGeometry>,
+    #[serde(default, rename = "selectedInstance")]
+    pub selected_instance: Option<String>,
+    #[serde(default, rename = "lastTab")]
+    pub last_tab: Option<String>,
+}
+
+/// The current geometry of `window`. A maximized window keeps the size and position of `previous`,
+/// so it is restored to them when it is unmaximized again.
+fn capture(
+    window: &Window<Wry>,
+    previous: Option<WindowGeometry>,
+) -> tauri::Result<WindowGeometry> {
+    if window.is_maximized()? {
+        if let Some(previous) = previous {
+            return Ok(WindowGeometry {
+                maximized: true,
+                ..previous
+            });
+        }
+    }
+    let position = window.outer_position()?;
+    let size = window.inner_size()?;
+    Ok(WindowGeometry {
+        x: position.x,
+        y: position.y,
+        width: size.width,
+        height: size.height,
+        maximized: window.is_maximized()?,
+    })
+}
+
+/// Moves and resizes `window` to `geometry`. Geometries off every monitor, e.g. one that was
+/// unplugged, only restore the size.
+fn apply_geometry(window: &Window<Wry>, geometry: &WindowGeometry) -> tauri::Result<()> {
+    if geometry.width < MIN_WINDOW_SIZE || geometry.height < MIN_WINDOW_SIZE {
+        debug!("Ignoring the saved window geometry {:?}", geometry);
+        return Ok(());
+    }
+    window.set_size(Size::Physical(PhysicalSize {
+        width: geometry.width,
+        height: geometry.height,
+    }))?;
+    let on_screen = window.available_monitors()?.iter().any(|monitor| {
+        let position = monitor.position();
+        let size = monitor.size();
+        geometry.x >= position.x
+            && geometry.y >= position.y
+            && geometry.x < position.x + size.width as i32
+            && geometry.y < position.y + size.height as i32
+    });
+    if on_screen {
+        window.set_position(Position::Physical(PhysicalPosition {
+            x: geometry.x,
+            y: geometry.y,
+        }))?;
+    } else {
+        warn!("The saved window position is off screen, centering the window instead");
+        window.center()?;
+    }
+    if geometry.maximized {
+        window.maximize()?;
+    }
+    Ok(())
+}
+
+/// Restores the saved geometry of `window`, call on setup once the settings are managed.
+pub fn restore_window(window: &Window<Wry>) {
+    let settings_state = window.state::<SettingsState>();
+    let geometry = tauri::async_runtime::block_on(settings_state.0.lock())
+        .get_ui_state()
+        .window;
+    if let Some(geometry) = geometry {
+        if let Err(error) = apply_geometry(window, &geometry) {
+            warn!("Could not restore the window geometry: {}", error);
+        }
+    }
+}
+
+/// Saves the geometry of `window`, call when it is about to close.
+pub fn save_window(window: &Window<Wry>) {
+    let settings_state = window.state::<SettingsState>();
+    let mut settings_manager = tauri::async_runtime::block_on(settings_state.0.lock());
+    let previous = settings_manager.get_ui_state().window;
+    let geometry = match capture(window, previous) {
+        Ok(geometry) => geometry,
+        Err(error) => {
+            warn!("Could not read the window geometry: {}", error);
+            return;
+        }
+    };
+    settings_manager.set_window_geometry(geometry);
+    if let Err(error) = settings_manager.serialize_settings() {
+        warn!("Could not save the window geometry: {}", error);
+    }
+}